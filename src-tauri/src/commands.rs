@@ -1,10 +1,12 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::Utc;
-use tauri::Manager;
+use tauri::{Emitter, Manager, State};
 use rusqlite::{Connection, params, OptionalExtension};
+use rusqlite::functions::FunctionFlags;
+use r2d2_sqlite::SqliteConnectionManager;
 use aes_gcm::{
-    aead::{Aead, KeyInit},
+    aead::{Aead, AeadCore, KeyInit, OsRng},
     Aes256Gcm, Nonce,
 };
 use sha2::{Sha256, Digest};
@@ -13,24 +15,215 @@ use serde_yaml;
 use git2::{Repository, Signature};
 use std::path::PathBuf;
 use std::collections::HashMap;
-use std::sync::{Mutex, OnceLock};
+use std::sync::{mpsc, Mutex, OnceLock};
+use std::io::Read;
+use std::process::{Child, Stdio};
+use std::time::{Duration, Instant};
 
 fn terminal_cwds() -> &'static Mutex<HashMap<String, String>> {
     static CWD_MAP: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
     CWD_MAP.get_or_init(|| Mutex::new(HashMap::new()))
 }
 
+// Child processes of in-flight execute_shell_command calls, keyed by command id
+fn running_commands() -> &'static Mutex<HashMap<String, Child>> {
+    static RUNNING: OnceLock<Mutex<HashMap<String, Child>>> = OnceLock::new();
+    RUNNING.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// Exit codes recorded in command_history for commands that never exited on their own
+const EXIT_CODE_TIMED_OUT: i32 = -2;
+const EXIT_CODE_KILLED: i32 = -3;
+
+const PIPE_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Cap on the output stored in command_history per command
+const DEFAULT_MAX_OUTPUT_BYTES: usize = 256 * 1024;
+const TRUNCATED_MARKER: &str = "\n...[truncated]";
+
+fn truncate_output(output: &str, max_bytes: usize) -> String {
+    if output.len() <= max_bytes {
+        return output.to_string();
+    }
+    let mut end = max_bytes;
+    while !output.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{}", &output[..end], TRUNCATED_MARKER)
+}
+
+fn spawn_pipe_reader<R: Read + Send + 'static>(pipe: Option<R>) -> mpsc::Receiver<Vec<u8>> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        let _ = tx.send(buf);
+    });
+    rx
+}
+
+// Starting directory for a project's terminal: its configured working_dir, else $HOME when
+// none is set or it no longer exists. A missing project or a failed query is an error.
+fn default_terminal_cwd(conn: &Connection, project_id: &str) -> Result<String, AppError> {
+    let working_dir: Option<String> = conn.query_row(
+        "SELECT working_dir FROM projects WHERE id = ?1",
+        params![project_id],
+        |row| row.get(0),
+    ).optional().map_err(|e| AppError::database("Failed to look up project working directory", e))?
+        .ok_or_else(|| AppError::NotFound(format!("Project '{}' not found", project_id)))?;
+
+    Ok(working_dir
+        .filter(|dir| std::path::Path::new(dir).is_dir())
+        .unwrap_or_else(home_dir))
+}
+
 fn shell_escape(s: &str) -> String {
     format!("'{}'", s.replace('\'', "'\\''"))
 }
 
+fn powershell_escape(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+fn home_dir() -> String {
+    std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .unwrap_or_else(|_| "/".to_string())
+}
+
+// Wraps a user command so it runs in `cwd` and prints `cwd_marker` followed by the
+// final working directory, letting `cd` persist between invocations.
+fn build_shell_command(cwd: &str, command: &str, cwd_marker: &str) -> std::process::Command {
+    use std::process::Command as StdCommand;
+
+    if cfg!(target_os = "windows") {
+        let wrapped = format!(
+            "Set-Location -LiteralPath {}; {}; $__ec = $LASTEXITCODE; Write-Output \"`n{}\"; (Get-Location).Path; exit $__ec",
+            powershell_escape(cwd),
+            command,
+            cwd_marker
+        );
+
+        let mut cmd = StdCommand::new("powershell.exe");
+        cmd.arg("-NoLogo")
+            .arg("-NoProfile")
+            .arg("-NonInteractive")
+            .arg("-Command")
+            .arg(wrapped);
+
+        // Keep a console window from flashing up for every command
+        #[cfg(target_os = "windows")]
+        {
+            use std::os::windows::process::CommandExt;
+            const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+            cmd.creation_flags(CREATE_NO_WINDOW);
+        }
+
+        cmd
+    } else {
+        let user_shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string());
+        let wrapped = format!(
+            "cd {} 2>/dev/null && {{ {}; }}; __ec=$?; printf '\\n{}'; pwd; exit $__ec",
+            shell_escape(cwd),
+            command,
+            cwd_marker
+        );
+
+        let mut cmd = StdCommand::new(user_shell);
+        cmd.arg("-l")
+            .arg("-i")
+            .arg("-c")
+            .arg(wrapped)
+            .env("TERM", "xterm-256color")
+            .env("CLICOLOR", "1")
+            .env("CLICOLOR_FORCE", "1")
+            .env("LSCOLORS", "Gxfxcxdxbxegedabagacad");
+        cmd
+    }
+}
+
+// Structured error for commands that need to tell the UI what went wrong, not just that it did.
+// Serializes as {"kind": "not_found", "message": "..."}.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum AppError {
+    NotFound(String),
+    Validation(String),
+    Database(String),
+    Conflict(String),
+    Io(String),
+    Internal(String),
+    // A shell command refused by the command policy, as opposed to one that failed to run
+    PolicyViolation(String),
+}
+
+impl AppError {
+    // Unique and primary-key violations become Conflict, e.g. a framework id that is already taken
+    fn database(context: &str, e: rusqlite::Error) -> Self {
+        let message = format!("{}: {}", context, e);
+        let is_conflict = matches!(
+            &e,
+            rusqlite::Error::SqliteFailure(err, _)
+                if err.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE
+                    || err.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_PRIMARYKEY
+        );
+        if is_conflict {
+            AppError::Conflict(message)
+        } else {
+            AppError::Database(message)
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::NotFound(message)
+            | AppError::Validation(message)
+            | AppError::Database(message)
+            | AppError::Conflict(message)
+            | AppError::Io(message)
+            | AppError::Internal(message)
+            | AppError::PolicyViolation(message) => message,
+        }
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::Io(e.to_string())
+    }
+}
+
+// Lets commands still returning Result<_, String> call migrated ones with `?`
+impl From<AppError> for String {
+    fn from(e: AppError) -> Self {
+        e.message().to_string()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Project {
     pub id: String,
     pub name: String,
     pub description: Option<String>,
+    pub working_dir: Option<String>,
+    pub deleted_at: Option<i64>,
+    #[serde(default)]
+    pub sort_order: i32,
     pub created_at: i64,
     pub updated_at: i64,
+    // Set when the project is opened; unlike updated_at it says nothing about edits
+    #[serde(default)]
+    pub last_accessed_at: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -41,6 +234,10 @@ pub struct Conversation {
     pub model: String,
     pub total_tokens: i32,
     pub total_cost: f64,
+    #[serde(default)]
+    pub is_archived: bool,
+    #[serde(default)]
+    pub is_pinned: bool,
     pub created_at: i64,
     pub updated_at: i64,
 }
@@ -55,6 +252,14 @@ pub struct Message {
     pub created_at: i64,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageInput {
+    pub role: String,
+    pub content: String,
+    #[serde(default)]
+    pub tokens: i32,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Settings {
     pub id: String,
@@ -74,6 +279,8 @@ pub struct Settings {
     pub jira_project_key: Option<String>,
     pub notion_api_token_encrypted: Option<String>,
     pub notion_parent_page_id: Option<String>,
+    pub default_model: Option<String>,
+    pub has_api_key: bool,
     pub created_at: i64,
     pub updated_at: i64,
 }
@@ -96,10 +303,11 @@ pub struct SettingsUpdate {
     pub jira_project_key: Option<String>,
     pub notion_api_token: Option<String>,
     pub notion_parent_page_id: Option<String>,
+    pub default_model: Option<String>,
 }
 
 // Encryption helpers
-fn get_encryption_key(_app: &tauri::AppHandle) -> Result<[u8; 32], String> {
+fn get_encryption_key() -> Result<[u8; 32], String> {
     // Derive a key from the app's unique identifier and machine ID
     let app_id = "com.dsotiriou.ai-pm-ide";
     let machine_id = machine_uid::get().unwrap_or_else(|_| "default-machine-id".to_string());
@@ -114,57 +322,203 @@ fn get_encryption_key(_app: &tauri::AppHandle) -> Result<[u8; 32], String> {
     Ok(key)
 }
 
+const NONCE_LEN: usize = 12;
+
 fn encrypt_string(plaintext: &str, key: &[u8; 32]) -> Result<String, String> {
     let cipher = Aes256Gcm::new(key.into());
-    let nonce_bytes = [0u8; 12]; // For production, use OsRng to generate random nonce
-    let nonce = Nonce::from_slice(&nonce_bytes);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
 
     let ciphertext = cipher
-        .encrypt(nonce, plaintext.as_bytes())
+        .encrypt(&nonce, plaintext.as_bytes())
         .map_err(|e| format!("Encryption failed: {}", e))?;
 
-    Ok(general_purpose::STANDARD.encode(ciphertext))
+    // Stored format: 12-byte nonce followed by the ciphertext + tag
+    let mut blob = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    blob.extend_from_slice(&nonce);
+    blob.extend_from_slice(&ciphertext);
+
+    Ok(general_purpose::STANDARD.encode(blob))
 }
 
 fn decrypt_string(encrypted: &str, key: &[u8; 32]) -> Result<String, String> {
     let cipher = Aes256Gcm::new(key.into());
-    let nonce_bytes = [0u8; 12];
-    let nonce = Nonce::from_slice(&nonce_bytes);
 
-    let ciphertext = general_purpose::STANDARD
+    let blob = general_purpose::STANDARD
         .decode(encrypted)
         .map_err(|e| format!("Base64 decode failed: {}", e))?;
 
-    let plaintext = cipher
-        .decrypt(nonce, ciphertext.as_ref())
-        .map_err(|e| format!("Decryption failed: {}", e))?;
+    // Try the nonce-prefixed format first, then fall back to the legacy
+    // zero-nonce format written by older versions. GCM authentication
+    // guarantees the wrong interpretation fails rather than returning garbage.
+    let prefixed = if blob.len() > NONCE_LEN {
+        let (nonce_bytes, ciphertext) = blob.split_at(NONCE_LEN);
+        cipher.decrypt(Nonce::from_slice(nonce_bytes), ciphertext).ok()
+    } else {
+        None
+    };
+
+    let plaintext = match prefixed {
+        Some(p) => p,
+        None => {
+            let legacy_nonce = [0u8; NONCE_LEN];
+            cipher
+                .decrypt(Nonce::from_slice(&legacy_nonce), blob.as_ref())
+                .map_err(|e| format!("Decryption failed: {}", e))?
+        }
+    };
 
     String::from_utf8(plaintext).map_err(|e| format!("UTF-8 conversion failed: {}", e))
 }
 
+// OS keychain helpers
+const KEYRING_SERVICE: &str = "com.dsotiriou.ai-pm-ide";
+const KEYRING_API_KEY_USER: &str = "api_key";
+
+fn keyring_entry() -> Option<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_API_KEY_USER).ok()
+}
+
+// Returns Err only when the keychain itself is unavailable; a missing entry is Ok(None)
+fn keyring_get_api_key() -> Result<Option<String>, String> {
+    let entry = keyring_entry().ok_or("Keychain unavailable")?;
+    match entry.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read keychain: {}", e)),
+    }
+}
+
+fn keyring_set_api_key(api_key: &str) -> Result<(), String> {
+    let entry = keyring_entry().ok_or("Keychain unavailable")?;
+    entry.set_password(api_key)
+        .map_err(|e| format!("Failed to write keychain: {}", e))
+}
+
+fn keyring_delete_api_key() -> Result<(), String> {
+    let entry = keyring_entry().ok_or("Keychain unavailable")?;
+    match entry.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to delete keychain entry: {}", e)),
+    }
+}
+
+const KEYRING_DOCUMENT_KEY_USER: &str = "document_key";
+
+// Random data key for encrypted context documents, generated on first use. It only lives in
+// the keychain, so a copied database can't be opened with the machine-derived key alone.
+fn keyring_document_key() -> Result<[u8; 32], String> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_DOCUMENT_KEY_USER)
+        .map_err(|e| format!("Keychain unavailable: {}", e))?;
+    let encoded = match entry.get_password() {
+        Ok(encoded) => encoded,
+        Err(keyring::Error::NoEntry) => {
+            let encoded = general_purpose::STANDARD.encode(Aes256Gcm::generate_key(&mut OsRng));
+            entry.set_password(&encoded)
+                .map_err(|e| format!("Failed to write keychain: {}", e))?;
+            encoded
+        }
+        Err(e) => return Err(format!("Failed to read keychain: {}", e)),
+    };
+    let bytes = general_purpose::STANDARD.decode(&encoded)
+        .map_err(|e| format!("Document key in keychain is corrupt: {}", e))?;
+    <[u8; 32]>::try_from(bytes.as_slice())
+        .map_err(|_| "Document key in keychain has the wrong length".to_string())
+}
+
 // Database connection helper
-fn get_db_connection(app: &tauri::AppHandle) -> Result<Connection, String> {
+pub type DbPool = r2d2::Pool<SqliteConnectionManager>;
+type DbConnection = r2d2::PooledConnection<SqliteConnectionManager>;
+
+const DB_POOL_MAX_SIZE: u32 = 8;
+const DB_POOL_CHECKOUT_TIMEOUT: Duration = Duration::from_secs(10);
+const DB_BUSY_TIMEOUT_MS: u32 = 5000;
+
+// Builds the shared connection pool; setup runs init_db on it and then registers it as managed state
+fn db_file_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     let app_dir = app.path().app_data_dir()
         .map_err(|e| format!("Failed to get app directory: {}", e))?;
 
     std::fs::create_dir_all(&app_dir)
         .map_err(|e| format!("Failed to create app directory: {}", e))?;
 
-    let db_path = app_dir.join("pm-ide.db");
-    let conn = Connection::open(db_path)
+    Ok(app_dir.join("pm-ide.db"))
+}
+
+pub fn create_db_pool(app: &tauri::AppHandle) -> Result<DbPool, String> {
+    let db_path = db_file_path(app)?;
+
+    // Foreign keys are required for CASCADE deletes; WAL plus a busy timeout lets concurrent
+    // commands read while another writes instead of failing with "database is locked"
+    let manager = SqliteConnectionManager::file(db_path)
+        .with_init(|conn| {
+            conn.execute_batch(&format!(
+                "PRAGMA foreign_keys = ON; PRAGMA busy_timeout = {};",
+                DB_BUSY_TIMEOUT_MS
+            ))?;
+            conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))
+                .map(|_| ())
+        });
+
+    let pool = r2d2::Pool::builder()
+        .max_size(DB_POOL_MAX_SIZE)
+        .connection_timeout(DB_POOL_CHECKOUT_TIMEOUT)
+        .build(manager)
         .map_err(|e| format!("Failed to open database: {}", e))?;
 
-    // Enable foreign key constraints (required for CASCADE deletes)
-    conn.execute("PRAGMA foreign_keys = ON", [])
-        .map_err(|e| format!("Failed to enable foreign keys: {}", e))?;
+    let conn = pool.get()
+        .map_err(|e| format!("Failed to get database connection: {}", e))?;
+    let journal_mode: String = conn.pragma_query_value(None, "journal_mode", |row| row.get(0))
+        .map_err(|e| format!("Failed to read journal mode: {}", e))?;
+    if !journal_mode.eq_ignore_ascii_case("wal") {
+        return Err(format!("Failed to enable WAL journal mode (database reports '{}')", journal_mode));
+    }
+    drop(conn);
+
+    Ok(pool)
+}
+
+fn get_db_connection(pool: &DbPool) -> Result<DbConnection, AppError> {
+    pool.get().map_err(|e| AppError::Database(format!("Failed to get database connection: {}", e)))
+}
 
-    Ok(conn)
+// Runs `f` inside a transaction: commits when it returns Ok, and rolls back (the transaction
+// is dropped uncommitted) on any Err so multi-statement writes land all-or-nothing
+fn with_transaction<T, E, F>(conn: &Connection, f: F) -> Result<T, E>
+where
+    E: From<AppError>,
+    F: FnOnce(&Connection) -> Result<T, E>,
+{
+    let tx = conn.unchecked_transaction()
+        .map_err(|e| E::from(AppError::database("Failed to start transaction", e)))?;
+    let value = f(&tx)?;
+    tx.commit()
+        .map_err(|e| E::from(AppError::database("Failed to commit transaction", e)))?;
+    Ok(value)
 }
 
 // Initialize database tables (called on startup)
-pub fn init_db(app: &tauri::AppHandle) -> Result<(), String> {
-    let conn = get_db_connection(app)?;
+pub fn init_db(pool: &DbPool) -> Result<(), String> {
+    let conn = get_db_connection(pool)?;
+    init_schema(&conn)?;
+
+    // Only touch the keychain when there is something to migrate. Failures are not fatal:
+    // legacy documents stay readable through the machine-key fallback and are retried next launch.
+    let has_encrypted: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM context_documents WHERE is_encrypted = 1)",
+        [],
+        |row| row.get(0),
+    ).map_err(|e| format!("Failed to check encrypted documents: {}", e))?;
+    if has_encrypted {
+        let keys = get_document_keys()?;
+        if keys.data.is_ok() {
+            let _ = reseal_legacy_documents(&conn, &keys);
+        }
+    }
+    Ok(())
+}
 
+// Creates, migrates and seeds the schema on any connection, so tests can run it in memory
+fn init_schema(conn: &Connection) -> Result<(), String> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS projects (
             id TEXT PRIMARY KEY NOT NULL,
@@ -195,23 +549,6 @@ pub fn init_db(app: &tauri::AppHandle) -> Result<(), String> {
         [],
     ).map_err(|e| format!("Failed to create documents index: {}", e))?;
 
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS document_embeddings (
-            id TEXT PRIMARY KEY NOT NULL,
-            document_id TEXT NOT NULL,
-            chunk_text TEXT NOT NULL,
-            chunk_index INTEGER NOT NULL,
-            embedding BLOB,
-            FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
-        )",
-        [],
-    ).map_err(|e| format!("Failed to create document_embeddings table: {}", e))?;
-
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_embeddings_document_id ON document_embeddings(document_id)",
-        [],
-    ).map_err(|e| format!("Failed to create embeddings index: {}", e))?;
-
     conn.execute(
         "CREATE TABLE IF NOT EXISTS conversations (
             id TEXT PRIMARY KEY NOT NULL,
@@ -269,17 +606,6 @@ pub fn init_db(app: &tauri::AppHandle) -> Result<(), String> {
         [],
     ).map_err(|e| format!("Failed to create settings table: {}", e))?;
 
-    // Add username column if it doesn't exist (migration)
-    let _ = conn.execute(
-        "ALTER TABLE settings ADD COLUMN username TEXT",
-        [],
-    );
-    let _ = conn.execute("ALTER TABLE settings ADD COLUMN jira_url TEXT", []);
-    let _ = conn.execute("ALTER TABLE settings ADD COLUMN jira_email TEXT", []);
-    let _ = conn.execute("ALTER TABLE settings ADD COLUMN jira_api_token_encrypted TEXT", []);
-    let _ = conn.execute("ALTER TABLE settings ADD COLUMN jira_project_key TEXT", []);
-    let _ = conn.execute("ALTER TABLE settings ADD COLUMN notion_api_token_encrypted TEXT", []);
-    let _ = conn.execute("ALTER TABLE settings ADD COLUMN notion_parent_page_id TEXT", []);
 
     // Create token usage tracking table
     conn.execute(
@@ -387,18 +713,6 @@ pub fn init_db(app: &tauri::AppHandle) -> Result<(), String> {
         [],
     ).map_err(|e| format!("Failed to create folders parent index: {}", e))?;
 
-    // Migrations: add folder_id, tags, is_favorite, sort_order to context_documents
-    let _ = conn.execute("ALTER TABLE context_documents ADD COLUMN folder_id TEXT", []);
-    let _ = conn.execute("ALTER TABLE context_documents ADD COLUMN tags TEXT DEFAULT '[]'", []);
-    let _ = conn.execute("ALTER TABLE context_documents ADD COLUMN is_favorite INTEGER NOT NULL DEFAULT 0", []);
-    let _ = conn.execute("ALTER TABLE context_documents ADD COLUMN sort_order INTEGER NOT NULL DEFAULT 0", []);
-
-    // Migrations: add folder_id, tags, is_favorite, sort_order to framework_outputs
-    let _ = conn.execute("ALTER TABLE framework_outputs ADD COLUMN folder_id TEXT", []);
-    let _ = conn.execute("ALTER TABLE framework_outputs ADD COLUMN tags TEXT DEFAULT '[]'", []);
-    let _ = conn.execute("ALTER TABLE framework_outputs ADD COLUMN is_favorite INTEGER NOT NULL DEFAULT 0", []);
-    let _ = conn.execute("ALTER TABLE framework_outputs ADD COLUMN sort_order INTEGER NOT NULL DEFAULT 0", []);
-
     // Create command_history table
     conn.execute(
         "CREATE TABLE IF NOT EXISTS command_history (
@@ -598,37 +912,545 @@ pub fn init_db(app: &tauri::AppHandle) -> Result<(), String> {
         ).map_err(|e| format!("Failed to migrate workflows table: {}", e))?;
     }
 
-    seed_frameworks(&conn)?;
-    seed_prompts(&conn)?;
-    seed_workflows(&conn)?;
+    run_migrations(conn)?;
+    backfill_content_hashes(conn)?;
+    recover_streaming_messages(conn)?;
+    purge_expired_trash(conn, TRASH_RETENTION_DAYS)?;
 
-    // Create default settings if none exist
-    let count: i64 = conn.query_row("SELECT COUNT(*) FROM settings", [], |row| row.get(0))
-        .map_err(|e| format!("Failed to count settings: {}", e))?;
+    seed_frameworks(conn)?;
+    seed_prompts(conn)?;
+    normalize_stored_prompt_variables(conn)?;
+    seed_workflows(conn)?;
+    seed_model_pricing(conn)?;
 
-    if count == 0 {
-        let now = Utc::now().timestamp();
-        conn.execute(
-            "INSERT INTO settings (id, created_at, updated_at) VALUES (?1, ?2, ?3)",
-            params!["default", &now, &now],
-        ).map_err(|e| format!("Failed to create default settings: {}", e))?;
+    ensure_default_settings(conn)?;
+
+    Ok(())
+}
+
+// Create the default settings row if it is missing; a no-op when it already exists
+fn ensure_default_settings(conn: &Connection) -> Result<(), String> {
+    let now = Utc::now().timestamp();
+    conn.execute(
+        "INSERT OR IGNORE INTO settings (id, created_at, updated_at) VALUES (?1, ?2, ?3)",
+        params!["default", &now, &now],
+    ).map_err(|e| format!("Failed to create default settings: {}", e))?;
+    Ok(())
+}
+
+// Ordered schema migrations. Append new entries with the next version number;
+// never renumber or edit a migration that has already shipped.
+fn migrations() -> Vec<(u32, &'static [&'static str])> {
+    vec![
+        (1, &["ALTER TABLE settings ADD COLUMN username TEXT"]),
+        (2, &["ALTER TABLE settings ADD COLUMN jira_url TEXT"]),
+        (3, &["ALTER TABLE settings ADD COLUMN jira_email TEXT"]),
+        (4, &["ALTER TABLE settings ADD COLUMN jira_api_token_encrypted TEXT"]),
+        (5, &["ALTER TABLE settings ADD COLUMN jira_project_key TEXT"]),
+        (6, &["ALTER TABLE settings ADD COLUMN notion_api_token_encrypted TEXT"]),
+        (7, &["ALTER TABLE settings ADD COLUMN notion_parent_page_id TEXT"]),
+        (8, &["ALTER TABLE context_documents ADD COLUMN folder_id TEXT"]),
+        (9, &["ALTER TABLE context_documents ADD COLUMN tags TEXT DEFAULT '[]'"]),
+        (10, &["ALTER TABLE context_documents ADD COLUMN is_favorite INTEGER NOT NULL DEFAULT 0"]),
+        (11, &["ALTER TABLE context_documents ADD COLUMN sort_order INTEGER NOT NULL DEFAULT 0"]),
+        (12, &["ALTER TABLE framework_outputs ADD COLUMN folder_id TEXT"]),
+        (13, &["ALTER TABLE framework_outputs ADD COLUMN tags TEXT DEFAULT '[]'"]),
+        (14, &["ALTER TABLE framework_outputs ADD COLUMN is_favorite INTEGER NOT NULL DEFAULT 0"]),
+        (15, &["ALTER TABLE framework_outputs ADD COLUMN sort_order INTEGER NOT NULL DEFAULT 0"]),
+        (16, &["ALTER TABLE projects ADD COLUMN working_dir TEXT"]),
+        // External-content index keyed by the messages rowid, so the delete and update triggers
+        // remove entries by rowid instead of scanning for an unindexed message_id
+        (17, &[
+            "CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(content, content='messages', content_rowid='rowid')",
+            "CREATE TRIGGER IF NOT EXISTS messages_fts_insert AFTER INSERT ON messages BEGIN
+                  INSERT INTO messages_fts (rowid, content) VALUES (new.rowid, new.content);
+              END;",
+            "CREATE TRIGGER IF NOT EXISTS messages_fts_delete AFTER DELETE ON messages BEGIN
+                  INSERT INTO messages_fts (messages_fts, rowid, content) VALUES ('delete', old.rowid, old.content);
+              END;",
+            "CREATE TRIGGER IF NOT EXISTS messages_fts_update AFTER UPDATE OF content ON messages BEGIN
+                  INSERT INTO messages_fts (messages_fts, rowid, content) VALUES ('delete', old.rowid, old.content);
+                  INSERT INTO messages_fts (rowid, content) VALUES (new.rowid, new.content);
+              END;",
+            "INSERT INTO messages_fts (messages_fts) VALUES ('rebuild')",
+        ]),
+        // document_embeddings was never written to; re-point it at context_documents
+        (18, &[
+            "DROP TABLE IF EXISTS document_embeddings",
+            "CREATE TABLE document_embeddings (
+                  id TEXT PRIMARY KEY NOT NULL,
+                  document_id TEXT NOT NULL,
+                  chunk_text TEXT NOT NULL,
+                  chunk_index INTEGER NOT NULL,
+                  embedding BLOB,
+                  model TEXT NOT NULL DEFAULT '',
+                  created_at INTEGER NOT NULL DEFAULT 0,
+                  FOREIGN KEY (document_id) REFERENCES context_documents(id) ON DELETE CASCADE
+              )",
+            "CREATE INDEX IF NOT EXISTS idx_embeddings_document_id ON document_embeddings(document_id)",
+        ]),
+        (19, &[
+            "CREATE TABLE IF NOT EXISTS framework_output_versions (
+                  id TEXT PRIMARY KEY NOT NULL,
+                  output_id TEXT NOT NULL,
+                  version_no INTEGER NOT NULL,
+                  content TEXT NOT NULL,
+                  created_at INTEGER NOT NULL,
+                  UNIQUE (output_id, version_no),
+                  FOREIGN KEY (output_id) REFERENCES framework_outputs(id) ON DELETE CASCADE
+              )",
+            "CREATE INDEX IF NOT EXISTS idx_output_versions_output ON framework_output_versions(output_id)",
+        ]),
+        (20, &["ALTER TABLE projects ADD COLUMN deleted_at INTEGER"]),
+        // seq orders messages sharing a created_at, since rowid is not stable across VACUUM on a
+        // table without an INTEGER PRIMARY KEY. The page index serves the paged query's filter and
+        // ORDER BY; the seq index serves the next-seq lookup on insert.
+        (21, &[
+            "ALTER TABLE messages ADD COLUMN seq INTEGER NOT NULL DEFAULT 0",
+            "UPDATE messages SET seq = ranked.seq
+              FROM (SELECT id, ROW_NUMBER() OVER (PARTITION BY conversation_id ORDER BY created_at, rowid) AS seq
+                    FROM messages) AS ranked
+              WHERE messages.id = ranked.id",
+            "CREATE INDEX IF NOT EXISTS idx_messages_conversation_seq ON messages(conversation_id, seq)",
+            "CREATE INDEX IF NOT EXISTS idx_messages_conversation_page ON messages(conversation_id, created_at, seq)",
+        ]),
+        (22, &[
+            "CREATE TABLE IF NOT EXISTS model_pricing (
+                  id TEXT PRIMARY KEY NOT NULL,
+                  model TEXT NOT NULL,
+                  input_price_per_mtok REAL NOT NULL,
+                  output_price_per_mtok REAL NOT NULL,
+                  effective_date TEXT NOT NULL,
+                  created_at INTEGER NOT NULL,
+                  updated_at INTEGER NOT NULL,
+                  UNIQUE(model, effective_date)
+              )",
+            "CREATE INDEX IF NOT EXISTS idx_model_pricing_model ON model_pricing(model, effective_date)",
+        ]),
+        (23, &["CREATE TABLE IF NOT EXISTS token_usage_rollup (
+                  id TEXT PRIMARY KEY NOT NULL,
+                  input_tokens INTEGER NOT NULL DEFAULT 0,
+                  output_tokens INTEGER NOT NULL DEFAULT 0,
+                  total_tokens INTEGER NOT NULL DEFAULT 0,
+                  cost REAL NOT NULL DEFAULT 0.0,
+                  record_count INTEGER NOT NULL DEFAULT 0,
+                  updated_at INTEGER NOT NULL
+              )"]),
+        (24, &["ALTER TABLE projects ADD COLUMN sort_order INTEGER NOT NULL DEFAULT 0"]),
+        (25, &[
+            "CREATE TABLE IF NOT EXISTS command_policy (
+                  id TEXT PRIMARY KEY NOT NULL,
+                  kind TEXT NOT NULL CHECK (kind IN ('deny', 'allow')),
+                  pattern TEXT NOT NULL,
+                  created_at INTEGER NOT NULL,
+                  UNIQUE(kind, pattern)
+              )",
+            "INSERT OR IGNORE INTO command_policy (id, kind, pattern, created_at) VALUES
+                  ('builtin-deny-rm-root', 'deny', 'rm -rf /', 0),
+                  ('builtin-deny-rm-root-star', 'deny', 'rm -rf /*', 0),
+                  ('builtin-deny-rm-home', 'deny', 'rm -rf ~', 0),
+                  ('builtin-deny-mkfs', 'deny', 'mkfs', 0),
+                  ('builtin-deny-rm-root-fr', 'deny', 'rm -fr /', 0),
+                  ('builtin-deny-dd-zero', 'deny', 'dd if=/dev/zero', 0),
+                  ('builtin-deny-dd-random', 'deny', 'dd if=/dev/random', 0),
+                  ('builtin-deny-fork-bomb', 'deny', ':(){ :|:& };:', 0),
+                  ('builtin-deny-chmod-root', 'deny', 'chmod -R 777 /', 0),
+                  ('builtin-deny-format', 'deny', 'format c:', 0)",
+        ]),
+        (26, &["ALTER TABLE settings ADD COLUMN command_allowlist_enabled INTEGER NOT NULL DEFAULT 0"]),
+        (27, &["CREATE TABLE IF NOT EXISTS api_keys (
+                  provider TEXT PRIMARY KEY NOT NULL,
+                  key_encrypted TEXT NOT NULL,
+                  created_at INTEGER NOT NULL,
+                  updated_at INTEGER NOT NULL
+              )"]),
+        (28, &["ALTER TABLE conversations ADD COLUMN is_archived INTEGER NOT NULL DEFAULT 0"]),
+        (29, &["CREATE INDEX IF NOT EXISTS idx_conversations_project_archived ON conversations(project_id, is_archived, updated_at)"]),
+        (30, &["ALTER TABLE context_documents ADD COLUMN content_hash TEXT"]),
+        (31, &["CREATE INDEX IF NOT EXISTS idx_context_documents_hash ON context_documents(project_id, content_hash)"]),
+        (32, &["ALTER TABLE framework_definitions ADD COLUMN seed_hash TEXT"]),
+        (33, &["ALTER TABLE saved_prompts ADD COLUMN seed_hash TEXT"]),
+        (34, &["CREATE TABLE IF NOT EXISTS seed_versions (
+                  kind TEXT PRIMARY KEY NOT NULL,
+                  version TEXT NOT NULL,
+                  updated_at INTEGER NOT NULL
+              )"]),
+        (35, &["ALTER TABLE framework_outputs ADD COLUMN conversation_id TEXT REFERENCES conversations(id) ON DELETE SET NULL"]),
+        (36, &["CREATE INDEX IF NOT EXISTS idx_framework_outputs_conversation ON framework_outputs(conversation_id)"]),
+        // Vectors are little-endian f32, so existing rows get their dimension from the blob length
+        (37, &[
+            "ALTER TABLE document_embeddings ADD COLUMN dimension INTEGER",
+            "UPDATE document_embeddings SET dimension = length(embedding) / 4 WHERE embedding IS NOT NULL",
+        ]),
+        (38, &["ALTER TABLE conversations ADD COLUMN is_pinned INTEGER NOT NULL DEFAULT 0"]),
+        // app_word_count is registered by run_migrations and counts words the same way as the app
+        (39, &[
+            "ALTER TABLE framework_outputs ADD COLUMN word_count INTEGER",
+            "UPDATE framework_outputs SET word_count = app_word_count(generated_content) WHERE word_count IS NULL",
+        ]),
+        (40, &["CREATE TABLE IF NOT EXISTS message_chunks (
+                  message_id TEXT NOT NULL,
+                  seq INTEGER NOT NULL,
+                  delta TEXT NOT NULL,
+                  PRIMARY KEY (message_id, seq),
+                  FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE CASCADE
+              )"]),
+        (41, &["ALTER TABLE framework_outputs ADD COLUMN author TEXT"]),
+        (42, &["ALTER TABLE projects ADD COLUMN last_accessed_at INTEGER"]),
+        // Existing outputs have no measurement and stay NULL
+        (43, &["ALTER TABLE framework_outputs ADD COLUMN generation_ms INTEGER"]),
+        // Prompts used before this column existed have no known last use and stay NULL
+        (44, &["ALTER TABLE saved_prompts ADD COLUMN last_used_at INTEGER"]),
+        (45, &["ALTER TABLE context_documents ADD COLUMN is_encrypted INTEGER NOT NULL DEFAULT 0"]),
+        // NULL keeps a prompt in the shared library; a project id scopes it to that project
+        (46, &["ALTER TABLE saved_prompts ADD COLUMN project_id TEXT REFERENCES projects(id) ON DELETE CASCADE"]),
+        (47, &["ALTER TABLE settings ADD COLUMN default_model TEXT"]),
+        (48, &[
+            "ALTER TABLE context_documents ADD COLUMN deleted_at INTEGER",
+            "ALTER TABLE framework_outputs ADD COLUMN deleted_at INTEGER",
+        ]),
+        // Cost of each conversation's pruned token_usage rows; only prune_token_usage writes to it
+        (49, &[
+            "CREATE TABLE IF NOT EXISTS conversation_cost_rollup (
+                  conversation_id TEXT PRIMARY KEY NOT NULL,
+                  cost REAL NOT NULL DEFAULT 0.0,
+                  updated_at INTEGER NOT NULL,
+                  FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE
+              )",
+        ]),
+        // Plaintext hashes of encrypted documents leaked which of them share content
+        (50, &["UPDATE context_documents SET content_hash = NULL WHERE is_encrypted = 1"]),
+    ]
+}
+
+fn run_migrations(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY NOT NULL,
+            applied_at INTEGER NOT NULL
+        )",
+        [],
+    ).map_err(|e| format!("Failed to create schema_migrations table: {}", e))?;
+
+    let current: u32 = conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_migrations", [], |row| row.get(0)
+    ).map_err(|e| format!("Failed to read schema version: {}", e))?;
+
+    // Lets backfill statements compute derived values exactly as the Rust code does
+    conn.create_scalar_function(
+        "app_word_count",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| Ok(ctx.get::<Option<String>>(0)?.map(|text| word_count(&text))),
+    ).map_err(|e| format!("Failed to register migration functions: {}", e))?;
+
+    for (version, statements) in migrations() {
+        if version <= current {
+            continue;
+        }
+
+        with_transaction(conn, |tx| {
+            // Databases created before versioning may already have a column, so additions of an
+            // existing column are skipped and the rest of the migration still runs
+            for sql in statements {
+                if let Some((table, column)) = added_column(sql) {
+                    let exists = column_exists(tx, table, column)
+                        .map_err(|e| format!("Migration {} failed to inspect {}: {}", version, table, e))?;
+                    if exists {
+                        continue;
+                    }
+                }
+                tx.execute_batch(sql)
+                    .map_err(|e| format!("Migration {} failed: {}", version, e))?;
+            }
+
+            tx.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (?1, ?2)",
+                params![version, Utc::now().timestamp()],
+            ).map_err(|e| format!("Failed to record migration {}: {}", version, e))?;
+            Ok::<_, String>(())
+        })?;
     }
 
     Ok(())
 }
 
-fn seed_frameworks(conn: &Connection) -> Result<(), String> {
-    let fw_count: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM framework_definitions WHERE is_builtin = 1", [], |row| row.get(0)
-    ).unwrap_or(0);
+// Table and column of an "ALTER TABLE <table> ADD COLUMN <column> ..." statement
+fn added_column(sql: &str) -> Option<(&str, &str)> {
+    let words: Vec<&str> = sql.split_whitespace().take(6).collect();
+    match words.as_slice() {
+        [alter, table_kw, table, add, column_kw, column]
+            if alter.eq_ignore_ascii_case("ALTER")
+                && table_kw.eq_ignore_ascii_case("TABLE")
+                && add.eq_ignore_ascii_case("ADD")
+                && column_kw.eq_ignore_ascii_case("COLUMN") => Some((table, column)),
+        _ => None,
+    }
+}
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+        params![table, column],
+        |row| row.get(0),
+    )
+}
+
+const BUILTIN_CATEGORIES_JSON: &str = include_str!("../../src/frameworks/categories.json");
+
+const BUILTIN_FRAMEWORK_FILES: &[&str] = &[
+    // Strategy (8)
+    include_str!("../../src/frameworks/strategy/business-model-canvas.json"),
+    include_str!("../../src/frameworks/strategy/swot.json"),
+    include_str!("../../src/frameworks/strategy/porters-five-forces.json"),
+    include_str!("../../src/frameworks/strategy/lean-canvas.json"),
+    include_str!("../../src/frameworks/strategy/value-proposition-canvas.json"),
+    include_str!("../../src/frameworks/strategy/blue-ocean-strategy.json"),
+    include_str!("../../src/frameworks/strategy/ansoff-matrix.json"),
+    include_str!("../../src/frameworks/strategy/strategic-planning.json"),
+    // Prioritization (6)
+    include_str!("../../src/frameworks/prioritization/rice.json"),
+    include_str!("../../src/frameworks/prioritization/moscow.json"),
+    include_str!("../../src/frameworks/prioritization/kano-model.json"),
+    include_str!("../../src/frameworks/prioritization/ice-scoring.json"),
+    include_str!("../../src/frameworks/prioritization/value-effort-matrix.json"),
+    include_str!("../../src/frameworks/prioritization/weighted-scoring.json"),
+    // Discovery (8)
+    include_str!("../../src/frameworks/discovery/jtbd.json"),
+    include_str!("../../src/frameworks/discovery/customer-journey-map.json"),
+    include_str!("../../src/frameworks/discovery/user-personas.json"),
+    include_str!("../../src/frameworks/discovery/empathy-map.json"),
+    include_str!("../../src/frameworks/discovery/problem-statement.json"),
+    include_str!("../../src/frameworks/discovery/competitive-analysis.json"),
+    include_str!("../../src/frameworks/discovery/survey-design.json"),
+    include_str!("../../src/frameworks/discovery/feature-audit.json"),
+    // Development (5)
+    include_str!("../../src/frameworks/development/sprint-planning.json"),
+    include_str!("../../src/frameworks/development/technical-spec.json"),
+    include_str!("../../src/frameworks/development/architecture-decision-record.json"),
+    include_str!("../../src/frameworks/development/definition-of-done.json"),
+    include_str!("../../src/frameworks/development/release-plan.json"),
+    // Execution (6)
+    include_str!("../../src/frameworks/execution/okrs.json"),
+    include_str!("../../src/frameworks/execution/north-star-metric.json"),
+    include_str!("../../src/frameworks/execution/kpi-dashboard.json"),
+    include_str!("../../src/frameworks/execution/retrospective.json"),
+    include_str!("../../src/frameworks/execution/roadmap-template.json"),
+    include_str!("../../src/frameworks/execution/success-metrics.json"),
+    // Decision Making (5)
+    include_str!("../../src/frameworks/decision/decision-matrix.json"),
+    include_str!("../../src/frameworks/decision/raci.json"),
+    include_str!("../../src/frameworks/decision/pre-mortem.json"),
+    include_str!("../../src/frameworks/decision/opportunity-assessment.json"),
+    include_str!("../../src/frameworks/decision/trade-off-analysis.json"),
+    // Communication (7)
+    include_str!("../../src/frameworks/communication/prd.json"),
+    include_str!("../../src/frameworks/communication/user-stories.json"),
+    include_str!("../../src/frameworks/communication/stakeholder-update.json"),
+    include_str!("../../src/frameworks/communication/launch-plan.json"),
+    include_str!("../../src/frameworks/communication/feature-brief.json"),
+    include_str!("../../src/frameworks/communication/product-vision.json"),
+    include_str!("../../src/frameworks/communication/changelog.json"),
+];
+
+const BUILTIN_PROMPT_FILES: &[&str] = &[
+    // PRD (5)
+    include_str!("../../src/prompts/prd/prd-from-jtbd.json"),
+    include_str!("../../src/prompts/prd/technical-prd.json"),
+    include_str!("../../src/prompts/prd/one-pager.json"),
+    include_str!("../../src/prompts/prd/feature-spec.json"),
+    include_str!("../../src/prompts/prd/api-specification.json"),
+    // Analysis (5)
+    include_str!("../../src/prompts/analysis/competitive-analysis.json"),
+    include_str!("../../src/prompts/analysis/feature-comparison.json"),
+    include_str!("../../src/prompts/analysis/market-positioning.json"),
+    include_str!("../../src/prompts/analysis/feedback-synthesis.json"),
+    include_str!("../../src/prompts/analysis/churn-analysis.json"),
+    // Stories (5)
+    include_str!("../../src/prompts/stories/jtbd-to-stories.json"),
+    include_str!("../../src/prompts/stories/epic-breakdown.json"),
+    include_str!("../../src/prompts/stories/invest-criteria.json"),
+    include_str!("../../src/prompts/stories/acceptance-criteria.json"),
+    include_str!("../../src/prompts/stories/story-estimation.json"),
+    // Communication (5)
+    include_str!("../../src/prompts/communication/stakeholder-email.json"),
+    include_str!("../../src/prompts/communication/executive-summary.json"),
+    include_str!("../../src/prompts/communication/product-announcement.json"),
+    include_str!("../../src/prompts/communication/release-notes.json"),
+    include_str!("../../src/prompts/communication/team-update.json"),
+    // Data (4)
+    include_str!("../../src/prompts/data/metrics-analysis.json"),
+    include_str!("../../src/prompts/data/ab-test-analysis.json"),
+    include_str!("../../src/prompts/data/kpi-review.json"),
+    include_str!("../../src/prompts/data/funnel-analysis.json"),
+    // Prioritization (3)
+    include_str!("../../src/prompts/prioritization/quarterly-priorities.json"),
+    include_str!("../../src/prompts/prioritization/feature-scoring.json"),
+    include_str!("../../src/prompts/prioritization/resource-allocation.json"),
+    // Strategy (3)
+    include_str!("../../src/prompts/strategy/okr-drafting.json"),
+    include_str!("../../src/prompts/strategy/strategic-initiative.json"),
+    include_str!("../../src/prompts/strategy/vision-alignment.json"),
+];
+
+// Fingerprint of everything bundled for a seed kind; the upgrade pass only runs when it changes
+fn bundled_seed_version(files: &[&str]) -> String {
+    content_hash(&files.concat())
+}
+
+fn stored_seed_version(conn: &Connection, kind: &str) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT version FROM seed_versions WHERE kind = ?1",
+        params![kind],
+        |row| row.get(0),
+    ).optional().map_err(|e| format!("Failed to read seed version: {}", e))
+}
+
+fn record_seed_version(conn: &Connection, kind: &str, version: &str) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO seed_versions (kind, version, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(kind) DO UPDATE SET version = excluded.version, updated_at = excluded.updated_at",
+        params![kind, version, Utc::now().timestamp()],
+    ).map_err(|e| format!("Failed to record seed version: {}", e))?;
+    Ok(())
+}
+
+// The user-editable fields of a built-in framework, in their stored form
+struct BuiltinFrameworkFields {
+    category: String,
+    name: String,
+    description: String,
+    icon: String,
+    example_output: String,
+    system_prompt: String,
+    guiding_questions: String,
+    supports_visuals: bool,
+    visual_instructions: Option<String>,
+}
+
+impl BuiltinFrameworkFields {
+    fn from_seed(fw: &serde_json::Value) -> Self {
+        let text = |key: &str| fw[key].as_str().unwrap_or("").to_string();
+        BuiltinFrameworkFields {
+            category: text("category"),
+            name: text("name"),
+            description: text("description"),
+            icon: text("icon"),
+            example_output: text("example_output"),
+            system_prompt: text("system_prompt"),
+            guiding_questions: fw["guiding_questions"].to_string(),
+            supports_visuals: fw["supports_visuals"].as_bool().unwrap_or(false),
+            visual_instructions: fw["visual_instructions"].as_str().map(str::to_string),
+        }
+    }
+
+    // Compared against seed_hash (the bundled content last written to the row) to tell
+    // untouched built-ins from customized ones
+    fn seed_hash(&self) -> String {
+        content_hash(&[
+            self.category.as_str(), self.name.as_str(), self.description.as_str(), self.icon.as_str(),
+            self.example_output.as_str(), self.system_prompt.as_str(), self.guiding_questions.as_str(),
+            if self.supports_visuals { "1" } else { "0" },
+            self.visual_instructions.as_deref().unwrap_or(""),
+        ].join("\u{1f}"))
+    }
+}
+
+struct BuiltinPromptFields {
+    name: String,
+    description: String,
+    category: String,
+    prompt_text: String,
+    variables: String,
+    framework_id: Option<String>,
+}
+
+impl BuiltinPromptFields {
+    fn from_seed(p: &serde_json::Value) -> Self {
+        let raw_variables = p["variables"].to_string();
+        BuiltinPromptFields {
+            name: p["name"].as_str().unwrap_or("").to_string(),
+            description: p["description"].as_str().unwrap_or("").to_string(),
+            category: p["category"].as_str().unwrap_or("general").to_string(),
+            prompt_text: p["prompt_text"].as_str().unwrap_or("").to_string(),
+            // Stored in normalized form so the startup normalization pass doesn't look like a user edit
+            variables: normalize_prompt_variables(&raw_variables).unwrap_or(raw_variables),
+            framework_id: p["framework_id"].as_str().map(str::to_string),
+        }
+    }
+
+    fn seed_hash(&self) -> String {
+        content_hash(&[
+            self.name.as_str(), self.description.as_str(), self.category.as_str(),
+            self.prompt_text.as_str(), self.variables.as_str(), self.framework_id.as_deref().unwrap_or(""),
+        ].join("\u{1f}"))
+    }
+}
+
+// Overwrites a built-in framework's editable fields with bundled content
+fn apply_builtin_framework(conn: &Connection, id: &str, fields: &BuiltinFrameworkFields, now: i64) -> Result<usize, AppError> {
+    conn.execute(
+        "UPDATE framework_definitions SET category = ?2, name = ?3, description = ?4, icon = ?5, example_output = ?6,
+             system_prompt = ?7, guiding_questions = ?8, supports_visuals = ?9, visual_instructions = ?10,
+             updated_at = ?11, seed_hash = ?12
+         WHERE id = ?1 AND is_builtin = 1",
+        params![
+            id, &fields.category, &fields.name, &fields.description, &fields.icon,
+            &fields.example_output, &fields.system_prompt, &fields.guiding_questions,
+            fields.supports_visuals, &fields.visual_instructions, &now, &fields.seed_hash(),
+        ],
+    ).map_err(|e| AppError::database("Failed to apply built-in framework", e))
+}
 
-    if fw_count >= 45 {
+fn apply_builtin_prompt(conn: &Connection, id: &str, fields: &BuiltinPromptFields, now: i64) -> Result<usize, AppError> {
+    conn.execute(
+        "UPDATE saved_prompts SET name = ?2, description = ?3, category = ?4, prompt_text = ?5, variables = ?6,
+             framework_id = ?7, updated_at = ?8, seed_hash = ?9
+         WHERE id = ?1 AND is_builtin = 1",
+        params![
+            id, &fields.name, &fields.description, &fields.category, &fields.prompt_text,
+            &fields.variables, &fields.framework_id, &now, &fields.seed_hash(),
+        ],
+    ).map_err(|e| AppError::database("Failed to apply built-in prompt", e))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum SeedAction {
+    Insert,
+    Upgrade,
+    // Content already matches the bundle; only seed_hash needs recording
+    Adopt,
+    Skip,
+}
+
+// `existing` is (is_builtin, seed_hash, hash of current content) for a row with the bundled id.
+// Built-ins are upgraded only while their content still equals what was last seeded.
+fn seed_action(existing: Option<(bool, Option<String>, String)>, bundled_hash: &str) -> SeedAction {
+    match existing {
+        None => SeedAction::Insert,
+        Some((false, _, _)) => SeedAction::Skip,
+        Some((true, seed_hash, current)) => {
+            if current == bundled_hash {
+                if seed_hash.as_deref() == Some(bundled_hash) { SeedAction::Skip } else { SeedAction::Adopt }
+            } else if seed_hash.as_deref() == Some(current.as_str()) {
+                SeedAction::Upgrade
+            } else {
+                SeedAction::Skip
+            }
+        }
+    }
+}
+
+fn seed_frameworks(conn: &Connection) -> Result<(), String> {
+    let mut bundled = vec![BUILTIN_CATEGORIES_JSON];
+    bundled.extend_from_slice(BUILTIN_FRAMEWORK_FILES);
+    let version = bundled_seed_version(&bundled);
+    if stored_seed_version(conn, "frameworks")?.as_deref() == Some(version.as_str()) {
         return Ok(());
     }
 
     let now = Utc::now().timestamp();
-    let categories_json = include_str!("../../src/frameworks/categories.json");
-    let categories: Vec<serde_json::Value> = serde_json::from_str(categories_json)
+    let categories: Vec<serde_json::Value> = serde_json::from_str(BUILTIN_CATEGORIES_JSON)
         .map_err(|e| format!("Failed to parse seed categories: {}", e))?;
 
     for (i, cat) in categories.iter().enumerate() {
@@ -647,165 +1469,139 @@ fn seed_frameworks(conn: &Connection) -> Result<(), String> {
         ).map_err(|e| format!("Failed to seed category: {}", e))?;
     }
 
-    let framework_files: &[&str] = &[
-        // Strategy (8)
-        include_str!("../../src/frameworks/strategy/business-model-canvas.json"),
-        include_str!("../../src/frameworks/strategy/swot.json"),
-        include_str!("../../src/frameworks/strategy/porters-five-forces.json"),
-        include_str!("../../src/frameworks/strategy/lean-canvas.json"),
-        include_str!("../../src/frameworks/strategy/value-proposition-canvas.json"),
-        include_str!("../../src/frameworks/strategy/blue-ocean-strategy.json"),
-        include_str!("../../src/frameworks/strategy/ansoff-matrix.json"),
-        include_str!("../../src/frameworks/strategy/strategic-planning.json"),
-        // Prioritization (6)
-        include_str!("../../src/frameworks/prioritization/rice.json"),
-        include_str!("../../src/frameworks/prioritization/moscow.json"),
-        include_str!("../../src/frameworks/prioritization/kano-model.json"),
-        include_str!("../../src/frameworks/prioritization/ice-scoring.json"),
-        include_str!("../../src/frameworks/prioritization/value-effort-matrix.json"),
-        include_str!("../../src/frameworks/prioritization/weighted-scoring.json"),
-        // Discovery (8)
-        include_str!("../../src/frameworks/discovery/jtbd.json"),
-        include_str!("../../src/frameworks/discovery/customer-journey-map.json"),
-        include_str!("../../src/frameworks/discovery/user-personas.json"),
-        include_str!("../../src/frameworks/discovery/empathy-map.json"),
-        include_str!("../../src/frameworks/discovery/problem-statement.json"),
-        include_str!("../../src/frameworks/discovery/competitive-analysis.json"),
-        include_str!("../../src/frameworks/discovery/survey-design.json"),
-        include_str!("../../src/frameworks/discovery/feature-audit.json"),
-        // Development (5)
-        include_str!("../../src/frameworks/development/sprint-planning.json"),
-        include_str!("../../src/frameworks/development/technical-spec.json"),
-        include_str!("../../src/frameworks/development/architecture-decision-record.json"),
-        include_str!("../../src/frameworks/development/definition-of-done.json"),
-        include_str!("../../src/frameworks/development/release-plan.json"),
-        // Execution (6)
-        include_str!("../../src/frameworks/execution/okrs.json"),
-        include_str!("../../src/frameworks/execution/north-star-metric.json"),
-        include_str!("../../src/frameworks/execution/kpi-dashboard.json"),
-        include_str!("../../src/frameworks/execution/retrospective.json"),
-        include_str!("../../src/frameworks/execution/roadmap-template.json"),
-        include_str!("../../src/frameworks/execution/success-metrics.json"),
-        // Decision Making (5)
-        include_str!("../../src/frameworks/decision/decision-matrix.json"),
-        include_str!("../../src/frameworks/decision/raci.json"),
-        include_str!("../../src/frameworks/decision/pre-mortem.json"),
-        include_str!("../../src/frameworks/decision/opportunity-assessment.json"),
-        include_str!("../../src/frameworks/decision/trade-off-analysis.json"),
-        // Communication (7)
-        include_str!("../../src/frameworks/communication/prd.json"),
-        include_str!("../../src/frameworks/communication/user-stories.json"),
-        include_str!("../../src/frameworks/communication/stakeholder-update.json"),
-        include_str!("../../src/frameworks/communication/launch-plan.json"),
-        include_str!("../../src/frameworks/communication/feature-brief.json"),
-        include_str!("../../src/frameworks/communication/product-vision.json"),
-        include_str!("../../src/frameworks/communication/changelog.json"),
-    ];
-
-    for (i, fw_json) in framework_files.iter().enumerate() {
+    for (i, fw_json) in BUILTIN_FRAMEWORK_FILES.iter().enumerate() {
         let fw: serde_json::Value = serde_json::from_str(fw_json)
             .map_err(|e| format!("Failed to parse seed framework: {}", e))?;
+        let id = fw["id"].as_str().unwrap_or("");
+        let fields = BuiltinFrameworkFields::from_seed(&fw);
+        let bundled_hash = fields.seed_hash();
 
-        let guiding_questions = fw["guiding_questions"].to_string();
-        let supports_visuals = fw["supports_visuals"].as_bool().unwrap_or(false);
-
-        conn.execute(
-            "INSERT OR IGNORE INTO framework_definitions (id, category, name, description, icon, example_output, system_prompt, guiding_questions, supports_visuals, visual_instructions, is_builtin, sort_order, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, 1, ?11, ?12, ?13)",
-            params![
-                fw["id"].as_str().unwrap_or(""),
-                fw["category"].as_str().unwrap_or(""),
-                fw["name"].as_str().unwrap_or(""),
-                fw["description"].as_str().unwrap_or(""),
-                fw["icon"].as_str().unwrap_or(""),
-                fw["example_output"].as_str().unwrap_or(""),
-                fw["system_prompt"].as_str().unwrap_or(""),
-                &guiding_questions,
-                supports_visuals,
-                fw["visual_instructions"].as_str(),
-                i as i32,
-                &now,
-                &now,
-            ],
-        ).map_err(|e| format!("Failed to seed framework: {}", e))?;
+        let existing = conn.query_row(
+            "SELECT is_builtin, seed_hash, category, name, description, icon, example_output, system_prompt, guiding_questions, supports_visuals, visual_instructions
+             FROM framework_definitions WHERE id = ?1",
+            params![id],
+            |row| {
+                let current = BuiltinFrameworkFields {
+                    category: row.get(2)?,
+                    name: row.get(3)?,
+                    description: row.get(4)?,
+                    icon: row.get(5)?,
+                    example_output: row.get(6)?,
+                    system_prompt: row.get(7)?,
+                    guiding_questions: row.get(8)?,
+                    supports_visuals: row.get::<_, i32>(9)? != 0,
+                    visual_instructions: row.get(10)?,
+                };
+                Ok((row.get::<_, i32>(0)? != 0, row.get::<_, Option<String>>(1)?, current.seed_hash()))
+            },
+        ).optional().map_err(|e| format!("Failed to inspect seeded framework: {}", e))?;
+
+        match seed_action(existing, &bundled_hash) {
+            SeedAction::Skip => {}
+            SeedAction::Adopt => {
+                conn.execute(
+                    "UPDATE framework_definitions SET seed_hash = ?1 WHERE id = ?2",
+                    params![&bundled_hash, id],
+                ).map_err(|e| format!("Failed to record framework seed hash: {}", e))?;
+            }
+            SeedAction::Insert => {
+                conn.execute(
+                    "INSERT INTO framework_definitions (id, category, name, description, icon, example_output, system_prompt, guiding_questions, supports_visuals, visual_instructions, is_builtin, sort_order, created_at, updated_at, seed_hash)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, 1, ?11, ?12, ?12, ?13)",
+                    params![
+                        id, &fields.category, &fields.name, &fields.description, &fields.icon,
+                        &fields.example_output, &fields.system_prompt, &fields.guiding_questions,
+                        fields.supports_visuals, &fields.visual_instructions, i as i32, &now, &bundled_hash,
+                    ],
+                ).map_err(|e| format!("Failed to seed framework: {}", e))?;
+            }
+            SeedAction::Upgrade => {
+                apply_builtin_framework(conn, id, &fields, now)?;
+            }
+        }
     }
 
-    Ok(())
+    record_seed_version(conn, "frameworks", &version)
 }
 
 fn seed_prompts(conn: &Connection) -> Result<(), String> {
-    let count: i64 = conn.query_row("SELECT COUNT(*) FROM saved_prompts", [], |row| row.get(0))
-        .map_err(|e| format!("Failed to count saved_prompts: {}", e))?;
-
-    if count > 0 {
+    let version = bundled_seed_version(BUILTIN_PROMPT_FILES);
+    if stored_seed_version(conn, "prompts")?.as_deref() == Some(version.as_str()) {
         return Ok(());
     }
 
     let now = Utc::now().timestamp();
-    let prompt_files: &[&str] = &[
-        // PRD (5)
-        include_str!("../../src/prompts/prd/prd-from-jtbd.json"),
-        include_str!("../../src/prompts/prd/technical-prd.json"),
-        include_str!("../../src/prompts/prd/one-pager.json"),
-        include_str!("../../src/prompts/prd/feature-spec.json"),
-        include_str!("../../src/prompts/prd/api-specification.json"),
-        // Analysis (5)
-        include_str!("../../src/prompts/analysis/competitive-analysis.json"),
-        include_str!("../../src/prompts/analysis/feature-comparison.json"),
-        include_str!("../../src/prompts/analysis/market-positioning.json"),
-        include_str!("../../src/prompts/analysis/feedback-synthesis.json"),
-        include_str!("../../src/prompts/analysis/churn-analysis.json"),
-        // Stories (5)
-        include_str!("../../src/prompts/stories/jtbd-to-stories.json"),
-        include_str!("../../src/prompts/stories/epic-breakdown.json"),
-        include_str!("../../src/prompts/stories/invest-criteria.json"),
-        include_str!("../../src/prompts/stories/acceptance-criteria.json"),
-        include_str!("../../src/prompts/stories/story-estimation.json"),
-        // Communication (5)
-        include_str!("../../src/prompts/communication/stakeholder-email.json"),
-        include_str!("../../src/prompts/communication/executive-summary.json"),
-        include_str!("../../src/prompts/communication/product-announcement.json"),
-        include_str!("../../src/prompts/communication/release-notes.json"),
-        include_str!("../../src/prompts/communication/team-update.json"),
-        // Data (4)
-        include_str!("../../src/prompts/data/metrics-analysis.json"),
-        include_str!("../../src/prompts/data/ab-test-analysis.json"),
-        include_str!("../../src/prompts/data/kpi-review.json"),
-        include_str!("../../src/prompts/data/funnel-analysis.json"),
-        // Prioritization (3)
-        include_str!("../../src/prompts/prioritization/quarterly-priorities.json"),
-        include_str!("../../src/prompts/prioritization/feature-scoring.json"),
-        include_str!("../../src/prompts/prioritization/resource-allocation.json"),
-        // Strategy (3)
-        include_str!("../../src/prompts/strategy/okr-drafting.json"),
-        include_str!("../../src/prompts/strategy/strategic-initiative.json"),
-        include_str!("../../src/prompts/strategy/vision-alignment.json"),
-    ];
-
-    for (i, prompt_json) in prompt_files.iter().enumerate() {
+    for (i, prompt_json) in BUILTIN_PROMPT_FILES.iter().enumerate() {
         let p: serde_json::Value = serde_json::from_str(prompt_json)
             .map_err(|e| format!("Failed to parse seed prompt: {}", e))?;
 
-        let variables = p["variables"].to_string();
+        let id = p["id"].as_str().unwrap_or("");
+        let fields = BuiltinPromptFields::from_seed(&p);
+        let bundled_hash = fields.seed_hash();
 
-        conn.execute(
-            "INSERT OR IGNORE INTO saved_prompts (id, name, description, category, prompt_text, variables, framework_id, is_builtin, is_favorite, usage_count, sort_order, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 1, 0, 0, ?8, ?9, ?10)",
-            params![
-                p["id"].as_str().unwrap_or(""),
-                p["name"].as_str().unwrap_or(""),
-                p["description"].as_str().unwrap_or(""),
-                p["category"].as_str().unwrap_or("general"),
-                p["prompt_text"].as_str().unwrap_or(""),
-                &variables,
-                p["framework_id"].as_str(),
-                i as i32,
-                &now,
-                &now,
-            ],
-        ).map_err(|e| format!("Failed to seed prompt: {}", e))?;
+        let existing = conn.query_row(
+            "SELECT is_builtin, seed_hash, name, description, category, prompt_text, variables, framework_id
+             FROM saved_prompts WHERE id = ?1",
+            params![id],
+            |row| {
+                let current = BuiltinPromptFields {
+                    name: row.get(2)?,
+                    description: row.get(3)?,
+                    category: row.get(4)?,
+                    prompt_text: row.get(5)?,
+                    variables: row.get(6)?,
+                    framework_id: row.get(7)?,
+                };
+                Ok((row.get::<_, i32>(0)? != 0, row.get::<_, Option<String>>(1)?, current.seed_hash()))
+            },
+        ).optional().map_err(|e| format!("Failed to inspect seeded prompt: {}", e))?;
+
+        match seed_action(existing, &bundled_hash) {
+            SeedAction::Skip => {}
+            SeedAction::Adopt => {
+                conn.execute(
+                    "UPDATE saved_prompts SET seed_hash = ?1 WHERE id = ?2",
+                    params![&bundled_hash, id],
+                ).map_err(|e| format!("Failed to record prompt seed hash: {}", e))?;
+            }
+            SeedAction::Insert => {
+                conn.execute(
+                    "INSERT INTO saved_prompts (id, name, description, category, prompt_text, variables, framework_id, is_builtin, is_favorite, usage_count, sort_order, created_at, updated_at, seed_hash)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 1, 0, 0, ?8, ?9, ?9, ?10)",
+                    params![
+                        id, &fields.name, &fields.description, &fields.category, &fields.prompt_text,
+                        &fields.variables, &fields.framework_id, i as i32, &now, &bundled_hash,
+                    ],
+                ).map_err(|e| format!("Failed to seed prompt: {}", e))?;
+            }
+            SeedAction::Upgrade => {
+                apply_builtin_prompt(conn, id, &fields, now)?;
+            }
+        }
     }
 
+    record_seed_version(conn, "prompts", &version)
+}
+
+// Rewrites stored variable schemas into the typed shape. Rows that can't be parsed are left
+// untouched so a bad row never blocks startup; render_prompt reports them instead.
+fn normalize_stored_prompt_variables(conn: &Connection) -> Result<(), String> {
+    let mut stmt = conn.prepare("SELECT id, variables FROM saved_prompts")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("Failed to query prompt variables: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect prompt variables: {}", e))?;
+
+    for (id, variables) in rows {
+        let Ok(normalized) = normalize_prompt_variables(&variables) else { continue };
+        if normalized != variables {
+            conn.execute(
+                "UPDATE saved_prompts SET variables = ?1 WHERE id = ?2",
+                params![&normalized, &id],
+            ).map_err(|e| format!("Failed to normalize prompt variables: {}", e))?;
+        }
+    }
     Ok(())
 }
 
@@ -824,6 +1620,8 @@ fn row_to_saved_prompt(row: &rusqlite::Row) -> rusqlite::Result<SavedPromptRow>
         sort_order: row.get(10)?,
         created_at: row.get(11)?,
         updated_at: row.get(12)?,
+        last_used_at: row.get(13)?,
+        project_id: row.get(14)?,
     })
 }
 
@@ -861,30 +1659,68 @@ fn row_to_framework_def(row: &rusqlite::Row) -> rusqlite::Result<FrameworkDefRow
 
 const FRAMEWORK_DEF_COLUMNS: &str = "id, category, name, description, icon, example_output, system_prompt, guiding_questions, supports_visuals, visual_instructions, is_builtin, sort_order, created_at, updated_at";
 
+const SLUG_MAX_LEN: usize = 64;
+
+// Lowercase ASCII letters, digits and single hyphens only; anything else becomes a separator.
+// Names with nothing usable ("...", emoji only) fall back to a random id.
+fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.truncate(SLUG_MAX_LEN);
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        Uuid::new_v4().to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+// Ids are slugs of the name, so a second "Launch Plan" becomes launch-plan-2 rather than colliding
+fn unique_slug_id(conn: &Connection, table: &'static str, base: &str) -> Result<String, AppError> {
+    let mut stmt = conn.prepare(&format!("SELECT EXISTS(SELECT 1 FROM {} WHERE id = ?1)", table))
+        .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+    let mut candidate = base.to_string();
+    let mut suffix = 2;
+    while stmt.query_row(params![&candidate], |row| row.get::<_, bool>(0))
+        .map_err(|e| AppError::database("Failed to check id", e))?
+    {
+        candidate = format!("{}-{}", base, suffix);
+        suffix += 1;
+    }
+    Ok(candidate)
+}
+
 #[tauri::command]
-pub async fn list_framework_categories(app: tauri::AppHandle) -> Result<Vec<FrameworkCategoryRow>, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn list_framework_categories(pool: State<'_, DbPool>) -> Result<Vec<FrameworkCategoryRow>, AppError> {
+    let conn = get_db_connection(&pool)?;
     let mut stmt = conn.prepare(
         "SELECT id, name, description, icon, is_builtin, sort_order, created_at, updated_at
          FROM framework_categories ORDER BY sort_order ASC"
-    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    ).map_err(|e| AppError::database("Failed to prepare statement", e))?;
 
     let rows = stmt.query_map([], row_to_category)
-        .map_err(|e| format!("Failed to query categories: {}", e))?;
+        .map_err(|e| AppError::database("Failed to query categories", e))?;
     let result: Result<Vec<_>, _> = rows.collect();
-    result.map_err(|e| format!("Failed to collect categories: {}", e))
+    result.map_err(|e| AppError::database("Failed to collect categories", e))
 }
 
 #[tauri::command]
-pub async fn get_framework_category(id: String, app: tauri::AppHandle) -> Result<Option<FrameworkCategoryRow>, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn get_framework_category(id: String, pool: State<'_, DbPool>) -> Result<Option<FrameworkCategoryRow>, AppError> {
+    let conn = get_db_connection(&pool)?;
     let mut stmt = conn.prepare(
         "SELECT id, name, description, icon, is_builtin, sort_order, created_at, updated_at
          FROM framework_categories WHERE id = ?1"
-    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    ).map_err(|e| AppError::database("Failed to prepare statement", e))?;
 
     let cat = stmt.query_row(params![&id], row_to_category).optional()
-        .map_err(|e| format!("Failed to get category: {}", e))?;
+        .map_err(|e| AppError::database("Failed to get category", e))?;
     Ok(cat)
 }
 
@@ -893,21 +1729,21 @@ pub async fn create_framework_category(
     name: String,
     description: String,
     icon: String,
-    app: tauri::AppHandle,
-) -> Result<FrameworkCategoryRow, String> {
-    let conn = get_db_connection(&app)?;
-    let id = name.to_lowercase().replace(' ', "-");
+    pool: State<'_, DbPool>,
+) -> Result<FrameworkCategoryRow, AppError> {
+    let conn = get_db_connection(&pool)?;
+    let id = unique_slug_id(&conn, "framework_categories", &slugify(&name))?;
     let now = Utc::now().timestamp();
 
     let max_order: i32 = conn.query_row(
         "SELECT COALESCE(MAX(sort_order), -1) FROM framework_categories", [], |row| row.get(0)
-    ).map_err(|e| format!("Failed to get max sort_order: {}", e))?;
+    ).map_err(|e| AppError::database("Failed to get max sort_order", e))?;
 
     conn.execute(
         "INSERT INTO framework_categories (id, name, description, icon, is_builtin, sort_order, created_at, updated_at)
          VALUES (?1, ?2, ?3, ?4, 0, ?5, ?6, ?7)",
         params![&id, &name, &description, &icon, max_order + 1, &now, &now],
-    ).map_err(|e| format!("Failed to create category: {}", e))?;
+    ).map_err(|e| AppError::database("Failed to create category", e))?;
 
     Ok(FrameworkCategoryRow { id, name, description, icon, is_builtin: false, sort_order: max_order + 1, created_at: now, updated_at: now })
 }
@@ -918,74 +1754,117 @@ pub async fn update_framework_category(
     name: String,
     description: String,
     icon: String,
-    app: tauri::AppHandle,
-) -> Result<FrameworkCategoryRow, String> {
-    let conn = get_db_connection(&app)?;
+    pool: State<'_, DbPool>,
+) -> Result<FrameworkCategoryRow, AppError> {
+    let conn = get_db_connection(&pool)?;
     let now = Utc::now().timestamp();
 
     conn.execute(
         "UPDATE framework_categories SET name = ?1, description = ?2, icon = ?3, updated_at = ?4 WHERE id = ?5",
         params![&name, &description, &icon, &now, &id],
-    ).map_err(|e| format!("Failed to update category: {}", e))?;
+    ).map_err(|e| AppError::database("Failed to update category", e))?;
+
+    get_framework_category(id, pool).await?
+        .ok_or_else(|| AppError::NotFound("Category not found after update".to_string()))
+}
+
+// Moves every framework in `from_category` to `to_category`, returning how many moved
+fn reassign_category_frameworks(conn: &Connection, from_category: &str, to_category: &str) -> Result<usize, AppError> {
+    if from_category == to_category {
+        return Err(AppError::Validation("Source and target category are the same".to_string()));
+    }
+
+    let target_exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM framework_categories WHERE id = ?1)", params![to_category], |row| row.get(0)
+    ).map_err(|e| AppError::database("Failed to look up target category", e))?;
+    if !target_exists {
+        return Err(AppError::NotFound(format!("Category '{}' not found", to_category)));
+    }
+
+    let now = Utc::now().timestamp();
+    conn.execute(
+        "UPDATE framework_definitions SET category = ?1, updated_at = ?2 WHERE category = ?3",
+        params![to_category, &now, from_category],
+    ).map_err(|e| AppError::database("Failed to reassign frameworks", e))
+}
 
-    get_framework_category(id, app).await?
-        .ok_or_else(|| "Category not found after update".to_string())
+#[tauri::command]
+pub async fn reassign_frameworks_category(
+    from_category: String,
+    to_category: String,
+    pool: State<'_, DbPool>,
+) -> Result<usize, AppError> {
+    let conn = get_db_connection(&pool)?;
+    with_transaction(&conn, |tx| reassign_category_frameworks(tx, &from_category, &to_category))
 }
 
+// With `reassign_to`, the category's frameworks are moved there first; returns how many moved
 #[tauri::command]
-pub async fn delete_framework_category(id: String, app: tauri::AppHandle) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
+pub async fn delete_framework_category(
+    id: String,
+    reassign_to: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<usize, AppError> {
+    let conn = get_db_connection(&pool)?;
 
     let is_builtin: i32 = conn.query_row(
         "SELECT is_builtin FROM framework_categories WHERE id = ?1", params![&id], |row| row.get(0)
-    ).map_err(|e| format!("Category not found: {}", e))?;
+    ).optional().map_err(|e| AppError::database("Failed to look up category", e))?
+        .ok_or_else(|| AppError::NotFound(format!("Category '{}' not found", id)))?;
 
     if is_builtin != 0 {
-        return Err("Cannot delete built-in category".to_string());
+        return Err(AppError::Validation("Cannot delete built-in category".to_string()));
     }
 
-    let fw_count: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM framework_definitions WHERE category = ?1", params![&id], |row| row.get(0)
-    ).map_err(|e| format!("Failed to count frameworks: {}", e))?;
+    with_transaction(&conn, |tx| {
+        let moved = match reassign_to {
+            Some(ref target) => reassign_category_frameworks(tx, &id, target)?,
+            None => 0,
+        };
 
-    if fw_count > 0 {
-        return Err("Cannot delete category with frameworks. Delete or move frameworks first.".to_string());
-    }
+        let fw_count: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM framework_definitions WHERE category = ?1", params![&id], |row| row.get(0)
+        ).map_err(|e| AppError::database("Failed to count frameworks", e))?;
 
-    conn.execute("DELETE FROM framework_categories WHERE id = ?1", params![&id])
-        .map_err(|e| format!("Failed to delete category: {}", e))?;
-    Ok(())
+        if fw_count > 0 {
+            return Err(AppError::Conflict("Cannot delete category with frameworks. Delete or move frameworks first.".to_string()));
+        }
+
+        tx.execute("DELETE FROM framework_categories WHERE id = ?1", params![&id])
+            .map_err(|e| AppError::database("Failed to delete category", e))?;
+        Ok(moved)
+    })
 }
 
 #[tauri::command]
-pub async fn list_framework_defs(category: Option<String>, app: tauri::AppHandle) -> Result<Vec<FrameworkDefRow>, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn list_framework_defs(category: Option<String>, pool: State<'_, DbPool>) -> Result<Vec<FrameworkDefRow>, AppError> {
+    let conn = get_db_connection(&pool)?;
 
     if let Some(ref cat) = category {
         let q = format!("SELECT {} FROM framework_definitions WHERE category = ?1 ORDER BY sort_order ASC", FRAMEWORK_DEF_COLUMNS);
-        let mut stmt = conn.prepare(&q).map_err(|e| format!("Failed to prepare: {}", e))?;
+        let mut stmt = conn.prepare(&q).map_err(|e| AppError::database("Failed to prepare", e))?;
         let rows = stmt.query_map(params![cat], row_to_framework_def)
-            .map_err(|e| format!("Failed to query: {}", e))?;
+            .map_err(|e| AppError::database("Failed to query", e))?;
         let r: Result<Vec<_>, _> = rows.collect();
-        r.map_err(|e| format!("Failed to collect: {}", e))
+        r.map_err(|e| AppError::database("Failed to collect", e))
     } else {
         let q = format!("SELECT {} FROM framework_definitions ORDER BY sort_order ASC", FRAMEWORK_DEF_COLUMNS);
-        let mut stmt = conn.prepare(&q).map_err(|e| format!("Failed to prepare: {}", e))?;
+        let mut stmt = conn.prepare(&q).map_err(|e| AppError::database("Failed to prepare", e))?;
         let rows = stmt.query_map([], row_to_framework_def)
-            .map_err(|e| format!("Failed to query: {}", e))?;
+            .map_err(|e| AppError::database("Failed to query", e))?;
         let r: Result<Vec<_>, _> = rows.collect();
-        r.map_err(|e| format!("Failed to collect: {}", e))
+        r.map_err(|e| AppError::database("Failed to collect", e))
     }
 }
 
 #[tauri::command]
-pub async fn get_framework_def(id: String, app: tauri::AppHandle) -> Result<Option<FrameworkDefRow>, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn get_framework_def(id: String, pool: State<'_, DbPool>) -> Result<Option<FrameworkDefRow>, AppError> {
+    let conn = get_db_connection(&pool)?;
     let q = format!("SELECT {} FROM framework_definitions WHERE id = ?1", FRAMEWORK_DEF_COLUMNS);
-    let mut stmt = conn.prepare(&q).map_err(|e| format!("Failed to prepare: {}", e))?;
+    let mut stmt = conn.prepare(&q).map_err(|e| AppError::database("Failed to prepare", e))?;
 
     let fw = stmt.query_row(params![&id], row_to_framework_def).optional()
-        .map_err(|e| format!("Failed to get framework: {}", e))?;
+        .map_err(|e| AppError::database("Failed to get framework", e))?;
     Ok(fw)
 }
 
@@ -1000,20 +1879,20 @@ pub async fn create_framework_def(
     example_output: String,
     supports_visuals: bool,
     visual_instructions: Option<String>,
-    app: tauri::AppHandle,
-) -> Result<FrameworkDefRow, String> {
-    let conn = get_db_connection(&app)?;
-    let id = name.to_lowercase().replace(' ', "-").replace('(', "").replace(')', "");
+    pool: State<'_, DbPool>,
+) -> Result<FrameworkDefRow, AppError> {
+    let conn = get_db_connection(&pool)?;
+    let id = unique_slug_id(&conn, "framework_definitions", &slugify(&name))?;
     let now = Utc::now().timestamp();
 
     let max_order: i32 = conn.query_row(
         "SELECT COALESCE(MAX(sort_order), -1) FROM framework_definitions WHERE category = ?1", params![&category], |row| row.get(0)
-    ).map_err(|e| format!("Failed to get max sort_order: {}", e))?;
+    ).map_err(|e| AppError::database("Failed to get max sort_order", e))?;
 
     conn.execute(
         &format!("INSERT INTO framework_definitions ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, 0, ?11, ?12, ?13)", FRAMEWORK_DEF_COLUMNS),
         params![&id, &category, &name, &description, &icon, &example_output, &system_prompt, &guiding_questions, supports_visuals, &visual_instructions, max_order + 1, &now, &now],
-    ).map_err(|e| format!("Failed to create framework: {}", e))?;
+    ).map_err(|e| AppError::database("Failed to create framework", e))?;
 
     Ok(FrameworkDefRow {
         id, category, name, description, icon, example_output, system_prompt, guiding_questions,
@@ -1034,9 +1913,9 @@ pub async fn update_framework_def(
     example_output: Option<String>,
     supports_visuals: Option<bool>,
     visual_instructions: Option<String>,
-    app: tauri::AppHandle,
-) -> Result<FrameworkDefRow, String> {
-    let conn = get_db_connection(&app)?;
+    pool: State<'_, DbPool>,
+) -> Result<FrameworkDefRow, AppError> {
+    let conn = get_db_connection(&pool)?;
     let now = Utc::now().timestamp();
 
     conn.execute(
@@ -1058,141 +1937,164 @@ pub async fn update_framework_def(
             supports_visuals.map(|v| if v { 1 } else { 0 }),
             &visual_instructions, &now, &id
         ],
-    ).map_err(|e| format!("Failed to update framework: {}", e))?;
+    ).map_err(|e| AppError::database("Failed to update framework", e))?;
 
-    get_framework_def(id, app).await?
-        .ok_or_else(|| "Framework not found after update".to_string())
+    get_framework_def(id, pool).await?
+        .ok_or_else(|| AppError::NotFound("Framework not found after update".to_string()))
 }
 
 #[tauri::command]
-pub async fn delete_framework_def(id: String, app: tauri::AppHandle) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
+pub async fn delete_framework_def(id: String, pool: State<'_, DbPool>) -> Result<(), AppError> {
+    let conn = get_db_connection(&pool)?;
 
     let is_builtin: i32 = conn.query_row(
         "SELECT is_builtin FROM framework_definitions WHERE id = ?1", params![&id], |row| row.get(0)
-    ).map_err(|e| format!("Framework not found: {}", e))?;
+    ).optional().map_err(|e| AppError::database("Failed to look up framework", e))?
+        .ok_or_else(|| AppError::NotFound(format!("Framework '{}' not found", id)))?;
 
     if is_builtin != 0 {
-        return Err("Cannot delete built-in framework".to_string());
+        return Err(AppError::Validation("Cannot delete built-in framework".to_string()));
     }
 
     conn.execute("DELETE FROM framework_definitions WHERE id = ?1", params![&id])
-        .map_err(|e| format!("Failed to delete framework: {}", e))?;
+        .map_err(|e| AppError::database("Failed to delete framework", e))?;
     Ok(())
 }
 
 #[tauri::command]
-pub async fn reset_framework_def(id: String, app: tauri::AppHandle) -> Result<FrameworkDefRow, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn reset_framework_def(id: String, pool: State<'_, DbPool>) -> Result<FrameworkDefRow, AppError> {
+    let conn = get_db_connection(&pool)?;
 
     let is_builtin: i32 = conn.query_row(
         "SELECT is_builtin FROM framework_definitions WHERE id = ?1", params![&id], |row| row.get(0)
-    ).map_err(|e| format!("Framework not found: {}", e))?;
+    ).optional().map_err(|e| AppError::database("Failed to look up framework", e))?
+        .ok_or_else(|| AppError::NotFound(format!("Framework '{}' not found", id)))?;
 
     if is_builtin == 0 {
-        return Err("Can only reset built-in frameworks".to_string());
-    }
-
-    let framework_files: &[&str] = &[
-        // Strategy (8)
-        include_str!("../../src/frameworks/strategy/business-model-canvas.json"),
-        include_str!("../../src/frameworks/strategy/swot.json"),
-        include_str!("../../src/frameworks/strategy/porters-five-forces.json"),
-        include_str!("../../src/frameworks/strategy/lean-canvas.json"),
-        include_str!("../../src/frameworks/strategy/value-proposition-canvas.json"),
-        include_str!("../../src/frameworks/strategy/blue-ocean-strategy.json"),
-        include_str!("../../src/frameworks/strategy/ansoff-matrix.json"),
-        include_str!("../../src/frameworks/strategy/strategic-planning.json"),
-        // Prioritization (6)
-        include_str!("../../src/frameworks/prioritization/rice.json"),
-        include_str!("../../src/frameworks/prioritization/moscow.json"),
-        include_str!("../../src/frameworks/prioritization/kano-model.json"),
-        include_str!("../../src/frameworks/prioritization/ice-scoring.json"),
-        include_str!("../../src/frameworks/prioritization/value-effort-matrix.json"),
-        include_str!("../../src/frameworks/prioritization/weighted-scoring.json"),
-        // Discovery (8)
-        include_str!("../../src/frameworks/discovery/jtbd.json"),
-        include_str!("../../src/frameworks/discovery/customer-journey-map.json"),
-        include_str!("../../src/frameworks/discovery/user-personas.json"),
-        include_str!("../../src/frameworks/discovery/empathy-map.json"),
-        include_str!("../../src/frameworks/discovery/problem-statement.json"),
-        include_str!("../../src/frameworks/discovery/competitive-analysis.json"),
-        include_str!("../../src/frameworks/discovery/survey-design.json"),
-        include_str!("../../src/frameworks/discovery/feature-audit.json"),
-        // Development (5)
-        include_str!("../../src/frameworks/development/sprint-planning.json"),
-        include_str!("../../src/frameworks/development/technical-spec.json"),
-        include_str!("../../src/frameworks/development/architecture-decision-record.json"),
-        include_str!("../../src/frameworks/development/definition-of-done.json"),
-        include_str!("../../src/frameworks/development/release-plan.json"),
-        // Execution (6)
-        include_str!("../../src/frameworks/execution/okrs.json"),
-        include_str!("../../src/frameworks/execution/north-star-metric.json"),
-        include_str!("../../src/frameworks/execution/kpi-dashboard.json"),
-        include_str!("../../src/frameworks/execution/retrospective.json"),
-        include_str!("../../src/frameworks/execution/roadmap-template.json"),
-        include_str!("../../src/frameworks/execution/success-metrics.json"),
-        // Decision Making (5)
-        include_str!("../../src/frameworks/decision/decision-matrix.json"),
-        include_str!("../../src/frameworks/decision/raci.json"),
-        include_str!("../../src/frameworks/decision/pre-mortem.json"),
-        include_str!("../../src/frameworks/decision/opportunity-assessment.json"),
-        include_str!("../../src/frameworks/decision/trade-off-analysis.json"),
-        // Communication (7)
-        include_str!("../../src/frameworks/communication/prd.json"),
-        include_str!("../../src/frameworks/communication/user-stories.json"),
-        include_str!("../../src/frameworks/communication/stakeholder-update.json"),
-        include_str!("../../src/frameworks/communication/launch-plan.json"),
-        include_str!("../../src/frameworks/communication/feature-brief.json"),
-        include_str!("../../src/frameworks/communication/product-vision.json"),
-        include_str!("../../src/frameworks/communication/changelog.json"),
-    ];
+        return Err(AppError::Validation("Can only reset built-in frameworks".to_string()));
+    }
 
     let now = Utc::now().timestamp();
-    for fw_json in framework_files {
+    for fw_json in BUILTIN_FRAMEWORK_FILES {
         let fw: serde_json::Value = serde_json::from_str(fw_json)
-            .map_err(|e| format!("Failed to parse framework: {}", e))?;
+            .map_err(|e| AppError::Internal(format!("Failed to parse framework: {}", e)))?;
         if fw["id"].as_str() == Some(id.as_str()) {
+            let fields = BuiltinFrameworkFields::from_seed(&fw);
+            // seed_hash marks the prompt fields as bundled again, so future seed upgrades reach
+            // this framework unless other fields are still customized
             conn.execute(
-                "UPDATE framework_definitions SET system_prompt = ?1, guiding_questions = ?2, example_output = ?3, visual_instructions = ?4, updated_at = ?5 WHERE id = ?6",
+                "UPDATE framework_definitions SET system_prompt = ?1, guiding_questions = ?2, example_output = ?3, visual_instructions = ?4, updated_at = ?5, seed_hash = ?6 WHERE id = ?7",
                 params![
-                    fw["system_prompt"].as_str().unwrap_or(""),
-                    fw["guiding_questions"].to_string(),
-                    fw["example_output"].as_str().unwrap_or(""),
-                    fw["visual_instructions"].as_str(),
+                    &fields.system_prompt,
+                    &fields.guiding_questions,
+                    &fields.example_output,
+                    &fields.visual_instructions,
                     &now,
+                    &fields.seed_hash(),
                     &id,
                 ],
-            ).map_err(|e| format!("Failed to reset framework: {}", e))?;
+            ).map_err(|e| AppError::database("Failed to reset framework", e))?;
 
-            return get_framework_def(id, app).await?
-                .ok_or_else(|| "Framework not found after reset".to_string());
+            return get_framework_def(id, pool).await?
+                .ok_or_else(|| AppError::NotFound("Framework not found after reset".to_string()));
         }
     }
 
-    Err(format!("No seed data found for framework '{}'", id))
+    Err(AppError::NotFound(format!("No seed data found for framework '{}'", id)))
+}
+
+// Re-applies bundled content to every built-in framework; user-created frameworks are untouched
+#[tauri::command]
+pub async fn reset_all_frameworks(pool: State<'_, DbPool>) -> Result<usize, AppError> {
+    let conn = get_db_connection(&pool)?;
+    with_transaction(&conn, |tx| {
+        let now = Utc::now().timestamp();
+        let mut reset = 0;
+        for fw_json in BUILTIN_FRAMEWORK_FILES {
+            let fw: serde_json::Value = serde_json::from_str(fw_json)
+                .map_err(|e| AppError::Internal(format!("Failed to parse framework: {}", e)))?;
+            let id = fw["id"].as_str().unwrap_or("");
+            reset += apply_builtin_framework(tx, id, &BuiltinFrameworkFields::from_seed(&fw), now)?;
+        }
+
+        Ok(reset)
+    })
+}
+
+#[tauri::command]
+pub async fn reset_all_prompts(pool: State<'_, DbPool>) -> Result<usize, AppError> {
+    let conn = get_db_connection(&pool)?;
+    with_transaction(&conn, |tx| {
+        let now = Utc::now().timestamp();
+        let mut reset = 0;
+        for prompt_json in BUILTIN_PROMPT_FILES {
+            let p: serde_json::Value = serde_json::from_str(prompt_json)
+                .map_err(|e| AppError::Internal(format!("Failed to parse prompt: {}", e)))?;
+            let id = p["id"].as_str().unwrap_or("");
+            reset += apply_builtin_prompt(tx, id, &BuiltinPromptFields::from_seed(&p), now)?;
+        }
+
+        Ok(reset)
+    })
 }
 
 #[tauri::command]
-pub async fn search_framework_defs(query: String, app: tauri::AppHandle) -> Result<Vec<FrameworkDefRow>, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn search_framework_defs(query: String, pool: State<'_, DbPool>) -> Result<Vec<FrameworkDefRow>, AppError> {
+    let conn = get_db_connection(&pool)?;
     let search = format!("%{}%", query);
     let q = format!("SELECT {} FROM framework_definitions WHERE name LIKE ?1 OR description LIKE ?1 ORDER BY sort_order ASC", FRAMEWORK_DEF_COLUMNS);
-    let mut stmt = conn.prepare(&q).map_err(|e| format!("Failed to prepare: {}", e))?;
+    let mut stmt = conn.prepare(&q).map_err(|e| AppError::database("Failed to prepare", e))?;
 
     let rows = stmt.query_map(params![&search], row_to_framework_def)
-        .map_err(|e| format!("Failed to search: {}", e))?;
+        .map_err(|e| AppError::database("Failed to search", e))?;
+    let result: Result<Vec<_>, _> = rows.collect();
+    result.map_err(|e| AppError::database("Failed to collect", e))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FrameworkUsageStat {
+    pub framework_id: String,
+    pub name: String,
+    pub category: String,
+    pub usage_count: i64,
+    pub last_used_at: Option<i64>,
+    // Averaged over outputs that recorded a generation time; None when none did
+    pub avg_generation_ms: Option<f64>,
+}
+
+// Starts from framework_definitions so frameworks that were never used show up with a zero count
+#[tauri::command]
+pub async fn get_framework_usage_stats(pool: State<'_, DbPool>) -> Result<Vec<FrameworkUsageStat>, AppError> {
+    let conn = get_db_connection(&pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT d.id, d.name, d.category, COUNT(o.id), MAX(o.created_at), AVG(o.generation_ms)
+         FROM framework_definitions d
+         LEFT JOIN framework_outputs o ON o.framework_id = d.id AND o.deleted_at IS NULL
+         GROUP BY d.id
+         ORDER BY COUNT(o.id) DESC, MAX(o.created_at) DESC, d.name ASC"
+    ).map_err(|e| AppError::database("Failed to prepare", e))?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok(FrameworkUsageStat {
+            framework_id: row.get(0)?,
+            name: row.get(1)?,
+            category: row.get(2)?,
+            usage_count: row.get(3)?,
+            last_used_at: row.get(4)?,
+            avg_generation_ms: row.get(5)?,
+        })
+    }).map_err(|e| AppError::database("Failed to query framework usage", e))?;
     let result: Result<Vec<_>, _> = rows.collect();
-    result.map_err(|e| format!("Failed to collect: {}", e))
+    result.map_err(|e| AppError::database("Failed to collect", e))
 }
 
 #[tauri::command]
-pub async fn duplicate_framework_def(id: String, new_name: String, app: tauri::AppHandle) -> Result<FrameworkDefRow, String> {
-    let original = get_framework_def(id.clone(), app.clone()).await?
-        .ok_or_else(|| format!("Framework '{}' not found", id))?;
+pub async fn duplicate_framework_def(id: String, new_name: String, pool: State<'_, DbPool>) -> Result<FrameworkDefRow, AppError> {
+    let original = get_framework_def(id.clone(), pool.clone()).await?
+        .ok_or_else(|| AppError::NotFound(format!("Framework '{}' not found", id)))?;
 
-    let conn = get_db_connection(&app)?;
-    let new_id = new_name.to_lowercase().replace(' ', "-").replace('(', "").replace(')', "");
+    let conn = get_db_connection(&pool)?;
+    let new_id = unique_slug_id(&conn, "framework_definitions", &slugify(&new_name))?;
     let now = Utc::now().timestamp();
 
     conn.execute(
@@ -1202,19 +2104,19 @@ pub async fn duplicate_framework_def(id: String, new_name: String, app: tauri::A
             &original.example_output, &original.system_prompt, &original.guiding_questions,
             original.supports_visuals, &original.visual_instructions, original.sort_order + 1, &now, &now
         ],
-    ).map_err(|e| format!("Failed to duplicate framework: {}", e))?;
+    ).map_err(|e| AppError::database("Failed to duplicate framework", e))?;
 
-    get_framework_def(new_id, app).await?
-        .ok_or_else(|| "Framework not found after duplicate".to_string())
+    get_framework_def(new_id, pool).await?
+        .ok_or_else(|| AppError::NotFound("Framework not found after duplicate".to_string()))
 }
 
 #[tauri::command]
 pub async fn create_project(
     name: String,
     description: Option<String>,
-    app: tauri::AppHandle,
+    pool: State<'_, DbPool>,
 ) -> Result<Project, String> {
-    let conn = get_db_connection(&app)?;
+    let conn = get_db_connection(&pool)?;
     let id = Uuid::new_v4().to_string();
     let now = Utc::now().timestamp();
 
@@ -1222,8 +2124,12 @@ pub async fn create_project(
         id: id.clone(),
         name: name.clone(),
         description: description.clone(),
+        working_dir: None,
+        deleted_at: None,
+        sort_order: 0,
         created_at: now,
         updated_at: now,
+        last_accessed_at: None,
     };
 
     conn.execute(
@@ -1234,62 +2140,99 @@ pub async fn create_project(
     Ok(project)
 }
 
+const PROJECT_COLUMNS: &str = "id, name, description, working_dir, deleted_at, sort_order, created_at, updated_at, last_accessed_at";
+
+fn row_to_project(row: &rusqlite::Row) -> rusqlite::Result<Project> {
+    Ok(Project {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: {
+            let desc: String = row.get(2)?;
+            if desc.is_empty() { None } else { Some(desc) }
+        },
+        working_dir: row.get(3)?,
+        deleted_at: row.get(4)?,
+        sort_order: row.get(5)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+        last_accessed_at: row.get(8)?,
+    })
+}
+
 #[tauri::command]
-pub async fn list_projects(app: tauri::AppHandle) -> Result<Vec<Project>, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn list_projects(pool: State<'_, DbPool>) -> Result<Vec<Project>, String> {
+    let conn = get_db_connection(&pool)?;
 
-    let mut stmt = conn.prepare("SELECT id, name, description, created_at, updated_at FROM projects ORDER BY updated_at DESC")
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM projects WHERE deleted_at IS NULL ORDER BY sort_order ASC, updated_at DESC", PROJECT_COLUMNS))
         .map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
-    let projects = stmt.query_map([], |row| {
-        Ok(Project {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            description: {
-                let desc: String = row.get(2)?;
-                if desc.is_empty() { None } else { Some(desc) }
-            },
-            created_at: row.get(3)?,
-            updated_at: row.get(4)?,
-        })
-    }).map_err(|e| format!("Failed to query projects: {}", e))?;
+    let projects = stmt.query_map([], row_to_project)
+        .map_err(|e| format!("Failed to query projects: {}", e))?;
 
     let result: Result<Vec<Project>, _> = projects.collect();
     result.map_err(|e| format!("Failed to collect projects: {}", e))
 }
 
 #[tauri::command]
-pub async fn get_project(id: String, app: tauri::AppHandle) -> Result<Option<Project>, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn get_project(id: String, pool: State<'_, DbPool>) -> Result<Option<Project>, String> {
+    let conn = get_db_connection(&pool)?;
 
-    let mut stmt = conn.prepare("SELECT id, name, description, created_at, updated_at FROM projects WHERE id = ?1")
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM projects WHERE id = ?1", PROJECT_COLUMNS))
         .map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
-    let project = stmt.query_row(params![&id], |row| {
-        Ok(Project {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            description: {
-                let desc: String = row.get(2)?;
-                if desc.is_empty() { None } else { Some(desc) }
-            },
-            created_at: row.get(3)?,
-            updated_at: row.get(4)?,
-        })
-    }).optional()
+    let project = stmt.query_row(params![&id], row_to_project).optional()
         .map_err(|e| format!("Failed to get project: {}", e))?;
 
     Ok(project)
 }
 
+// Records that the project was opened; deliberately leaves updated_at alone
+#[tauri::command]
+pub async fn touch_project(id: String, pool: State<'_, DbPool>) -> Result<(), String> {
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
+
+    let rows = conn.execute(
+        "UPDATE projects SET last_accessed_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+        params![&now, &id],
+    ).map_err(|e| format!("Failed to touch project: {}", e))?;
+    if rows == 0 {
+        return Err("Project not found".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_recent_projects(
+    limit: Option<i32>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<Project>, String> {
+    let conn = get_db_connection(&pool)?;
+    let limit = limit.unwrap_or(10);
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM projects
+         WHERE deleted_at IS NULL AND last_accessed_at IS NOT NULL
+         ORDER BY last_accessed_at DESC
+         LIMIT ?1",
+        PROJECT_COLUMNS
+    )).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let projects = stmt.query_map(params![&limit], row_to_project)
+        .map_err(|e| format!("Failed to query recent projects: {}", e))?;
+
+    let result: Result<Vec<Project>, _> = projects.collect();
+    result.map_err(|e| format!("Failed to collect recent projects: {}", e))
+}
+
 #[tauri::command]
 pub async fn update_project(
     id: String,
     name: String,
     description: Option<String>,
-    app: tauri::AppHandle,
+    pool: State<'_, DbPool>,
 ) -> Result<Project, String> {
-    let conn = get_db_connection(&app)?;
+    let conn = get_db_connection(&pool)?;
     let now = Utc::now().timestamp();
 
     conn.execute(
@@ -1298,19 +2241,254 @@ pub async fn update_project(
     ).map_err(|e| format!("Failed to update project: {}", e))?;
 
     // Fetch the updated project
-    get_project(id, app).await?
+    get_project(id, pool).await?
         .ok_or_else(|| "Project not found after update".to_string())
 }
 
 #[tauri::command]
-pub async fn delete_project(id: String, app: tauri::AppHandle) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
+pub async fn set_project_working_dir(
+    id: String,
+    working_dir: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<Project, String> {
+    let working_dir = match working_dir {
+        Some(dir) if !dir.trim().is_empty() => {
+            let expanded = expand_home(dir.trim());
+            if !std::path::Path::new(&expanded).is_dir() {
+                return Err(format!("Directory does not exist: {}", expanded));
+            }
+            Some(expanded)
+        }
+        _ => None,
+    };
+
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
 
     conn.execute(
-        "DELETE FROM projects WHERE id = ?1",
-        params![&id],
-    ).map_err(|e| format!("Failed to delete project: {}", e))?;
+        "UPDATE projects SET working_dir = ?1, updated_at = ?2 WHERE id = ?3",
+        params![&working_dir, &now, &id],
+    ).map_err(|e| format!("Failed to set project working directory: {}", e))?;
+
+    // Drop the tracked terminal cwd so the next command starts in the new directory
+    {
+        let mut map = terminal_cwds().lock().map_err(|e| format!("Lock error: {}", e))?;
+        map.remove(&id);
+    }
+
+    get_project(id, pool).await?
+        .ok_or_else(|| "Project not found after update".to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectStats {
+    pub project_id: String,
+    pub conversation_count: i64,
+    pub message_count: i64,
+    pub context_document_count: i64,
+    pub framework_output_count: i64,
+    pub folder_count: i64,
+    pub total_tokens: i64,
+    pub total_cost: f64,
+}
+
+#[tauri::command]
+pub async fn get_project_stats(project_id: String, pool: State<'_, DbPool>) -> Result<ProjectStats, String> {
+    let conn = get_db_connection(&pool)?;
+
+    let count = |sql: &str| -> Result<i64, String> {
+        conn.query_row(sql, params![&project_id], |row| row.get(0))
+            .map_err(|e| format!("Failed to compute project stats: {}", e))
+    };
+
+    let conversation_count = count("SELECT COUNT(*) FROM conversations WHERE project_id = ?1")?;
+    let message_count = count(
+        "SELECT COUNT(*) FROM messages m JOIN conversations c ON c.id = m.conversation_id WHERE c.project_id = ?1"
+    )?;
+    let context_document_count = count("SELECT COUNT(*) FROM context_documents WHERE project_id = ?1 AND deleted_at IS NULL")?;
+    let framework_output_count = count("SELECT COUNT(*) FROM framework_outputs WHERE project_id = ?1 AND deleted_at IS NULL")?;
+    let folder_count = count("SELECT COUNT(*) FROM folders WHERE project_id = ?1")?;
+
+    let (total_tokens, total_cost): (i64, f64) = conn.query_row(
+        "SELECT COALESCE(SUM(t.total_tokens), 0), COALESCE(SUM(t.cost), 0.0)
+         FROM token_usage t
+         JOIN conversations c ON c.id = t.conversation_id
+         WHERE c.project_id = ?1",
+        params![&project_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).map_err(|e| format!("Failed to compute project token usage: {}", e))?;
+
+    Ok(ProjectStats {
+        project_id,
+        conversation_count,
+        message_count,
+        context_document_count,
+        framework_output_count,
+        folder_count,
+        total_tokens,
+        total_cost,
+    })
+}
+
+#[tauri::command]
+pub async fn reorder_projects(ordered_ids: Vec<String>, pool: State<'_, DbPool>) -> Result<(), String> {
+    let conn = get_db_connection(&pool)?;
+    with_transaction(&conn, |tx| {
+        for (index, id) in ordered_ids.iter().enumerate() {
+            tx.execute(
+                "UPDATE projects SET sort_order = ?1 WHERE id = ?2",
+                params![&(index as i32), id],
+            ).map_err(|e| format!("Failed to reorder projects: {}", e))?;
+        }
+
+        Ok(())
+    })
+}
+
+// Moves the project to the trash; use permanently_delete_project to remove it and its contents.
+// Returns the number of rows affected, so 0 means no live project had that id.
+#[tauri::command]
+pub async fn delete_project(id: String, pool: State<'_, DbPool>) -> Result<usize, String> {
+    let conn = get_db_connection(&pool)?;
+    trash_row(&conn, "projects", &id)
+        .map_err(|e| format!("Failed to delete project: {}", e))
+}
+
+// Sets deleted_at on a live row; returns 0 when the id is missing or already trashed
+fn trash_row(conn: &Connection, table: &'static str, id: &str) -> rusqlite::Result<usize> {
+    conn.execute(
+        &format!("UPDATE {} SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL", table),
+        params![Utc::now().timestamp(), id],
+    )
+}
+
+#[tauri::command]
+pub async fn list_trashed_projects(pool: State<'_, DbPool>) -> Result<Vec<Project>, String> {
+    let conn = get_db_connection(&pool)?;
+
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM projects WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC", PROJECT_COLUMNS))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let projects = stmt.query_map([], row_to_project)
+        .map_err(|e| format!("Failed to query trashed projects: {}", e))?;
+
+    let result: Result<Vec<Project>, _> = projects.collect();
+    result.map_err(|e| format!("Failed to collect trashed projects: {}", e))
+}
+
+#[tauri::command]
+pub async fn restore_project(id: String, pool: State<'_, DbPool>) -> Result<Project, String> {
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
+
+    conn.execute(
+        "UPDATE projects SET deleted_at = NULL, updated_at = ?1 WHERE id = ?2",
+        params![&now, &id],
+    ).map_err(|e| format!("Failed to restore project: {}", e))?;
+
+    get_project(id, pool).await?
+        .ok_or_else(|| "Project not found after restore".to_string())
+}
+
+// Everything that hangs off a project, leaves first, as (table, filter on ?1 = project id).
+// Deleting in this order means each DELETE finds nothing left to cascade into.
+const PROJECT_CHILD_TABLES: &[(&str, &str)] = &[
+    ("document_embeddings", "document_id IN (SELECT id FROM context_documents WHERE project_id = ?1)"),
+    ("context_documents", "project_id = ?1"),
+    ("framework_output_versions", "output_id IN (SELECT id FROM framework_outputs WHERE project_id = ?1)"),
+    ("framework_outputs", "project_id = ?1"),
+    ("token_usage", "conversation_id IN (SELECT id FROM conversations WHERE project_id = ?1)"),
+    ("message_chunks", "message_id IN (SELECT m.id FROM messages m JOIN conversations c ON c.id = m.conversation_id WHERE c.project_id = ?1)"),
+    ("messages", "conversation_id IN (SELECT id FROM conversations WHERE project_id = ?1)"),
+    ("conversation_cost_rollup", "conversation_id IN (SELECT id FROM conversations WHERE project_id = ?1)"),
+    ("conversations", "project_id = ?1"),
+    ("workflow_run_steps", "run_id IN (SELECT id FROM workflow_runs WHERE project_id = ?1)"),
+    ("workflow_runs", "project_id = ?1"),
+    ("project_insights", "project_id = ?1"),
+    ("command_history", "project_id = ?1"),
+    ("documents", "project_id = ?1"),
+    ("saved_prompts", "project_id = ?1"),
+    ("folders", "project_id = ?1"),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectChildCount {
+    pub table: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeleteProgressEvent {
+    pub project_id: String,
+    pub table: String,
+    pub deleted: usize,
+    pub completed_stages: usize,
+    pub total_stages: usize,
+}
+
+// Lets the UI warn before permanently deleting a project with a lot of content
+#[tauri::command]
+pub async fn count_project_children(id: String, pool: State<'_, DbPool>) -> Result<Vec<ProjectChildCount>, String> {
+    let conn = get_db_connection(&pool)?;
+
+    let mut counts = Vec::with_capacity(PROJECT_CHILD_TABLES.len());
+    for (table, filter) in PROJECT_CHILD_TABLES {
+        let count: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM {} WHERE {}", table, filter),
+            params![&id],
+            |row| row.get(0),
+        ).map_err(|e| format!("Failed to count {}: {}", table, e))?;
+        counts.push(ProjectChildCount { table: table.to_string(), count });
+    }
+    Ok(counts)
+}
+
+// Deletes children table by table inside one transaction, emitting `delete-progress` after each
+// stage, so large projects report progress while the delete stays all-or-nothing.
+#[tauri::command]
+pub async fn permanently_delete_project(id: String, app: tauri::AppHandle, pool: State<'_, DbPool>) -> Result<(), String> {
+    let conn = get_db_connection(&pool)?;
+    let total_stages = PROJECT_CHILD_TABLES.len() + 1;
+    let deleted = with_transaction(&conn, |tx| {
+        for (stage, (table, filter)) in PROJECT_CHILD_TABLES.iter().enumerate() {
+            let deleted = tx.execute(&format!("DELETE FROM {} WHERE {}", table, filter), params![&id])
+                .map_err(|e| format!("Failed to delete {}: {}", table, e))?;
+            let _ = app.emit("delete-progress", DeleteProgressEvent {
+                project_id: id.clone(),
+                table: table.to_string(),
+                deleted,
+                completed_stages: stage + 1,
+                total_stages,
+            });
+        }
+
+        tx.execute(
+            "DELETE FROM projects WHERE id = ?1",
+            params![&id],
+        ).map_err(|e| format!("Failed to permanently delete project: {}", e))
+    })?;
+    let _ = app.emit("delete-progress", DeleteProgressEvent {
+        project_id: id,
+        table: "projects".to_string(),
+        deleted,
+        completed_stages: total_stages,
+        total_stages,
+    });
+
+    Ok(())
+}
 
+// Checked before inserting a project's children so a bad id reports NotFound rather than
+// SQLite's bare foreign-key failure. Trashed projects count as missing.
+fn ensure_project_exists(conn: &Connection, project_id: &str) -> Result<(), AppError> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM projects WHERE id = ?1 AND deleted_at IS NULL)",
+        params![project_id],
+        |row| row.get(0),
+    ).map_err(|e| AppError::database("Failed to look up project", e))?;
+    if !exists {
+        return Err(AppError::NotFound(format!("Project '{}' not found", project_id)));
+    }
     Ok(())
 }
 
@@ -1321,9 +2499,16 @@ pub async fn create_conversation(
     project_id: String,
     title: Option<String>,
     model: String,
-    app: tauri::AppHandle,
-) -> Result<Conversation, String> {
-    let conn = get_db_connection(&app)?;
+    pool: State<'_, DbPool>,
+) -> Result<Conversation, AppError> {
+    let conn = get_db_connection(&pool)?;
+    ensure_project_exists(&conn, &project_id)?;
+    let model = if model.trim().is_empty() {
+        stored_default_model(&conn)?
+            .unwrap_or_else(|| DEFAULT_CONVERSATION_MODEL.to_string())
+    } else {
+        model
+    };
     let id = Uuid::new_v4().to_string();
     let now = Utc::now().timestamp();
 
@@ -1334,6 +2519,8 @@ pub async fn create_conversation(
         model: model.clone(),
         total_tokens: 0,
         total_cost: 0.0,
+        is_archived: false,
+        is_pinned: false,
         created_at: now,
         updated_at: now,
     };
@@ -1342,40 +2529,56 @@ pub async fn create_conversation(
         "INSERT INTO conversations (id, project_id, title, model, total_tokens, total_cost, created_at, updated_at)
          VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
         params![&id, &project_id, &title.unwrap_or_default(), &model, &0, &0.0, &now, &now],
-    ).map_err(|e| format!("Failed to create conversation: {}", e))?;
+    ).map_err(|e| AppError::database("Failed to create conversation", e))?;
 
     Ok(conversation)
 }
 
+// Used when neither the caller nor settings name a model
+const DEFAULT_CONVERSATION_MODEL: &str = "gpt-5";
+
+fn stored_default_model(conn: &Connection) -> Result<Option<String>, AppError> {
+    let model: Option<String> = conn.query_row(
+        "SELECT default_model FROM settings WHERE id = ?1", params!["default"], |row| row.get(0)
+    ).optional().map_err(|e| AppError::database("Failed to read default model", e))?.flatten();
+    Ok(model.filter(|m| !m.trim().is_empty()))
+}
+
+const CONVERSATION_COLUMNS: &str = "id, project_id, title, model, total_tokens, total_cost, created_at, updated_at, is_archived, is_pinned";
+
+fn row_to_conversation(row: &rusqlite::Row) -> rusqlite::Result<Conversation> {
+    Ok(Conversation {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        title: {
+            let title: Option<String> = row.get(2)?;
+            title.filter(|t| !t.is_empty())
+        },
+        model: row.get(3)?,
+        total_tokens: row.get(4)?,
+        total_cost: row.get(5)?,
+        is_archived: row.get::<_, i32>(8)? != 0,
+        is_pinned: row.get::<_, i32>(9)? != 0,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+    })
+}
+
 #[tauri::command]
 pub async fn list_conversations(
     project_id: String,
-    app: tauri::AppHandle,
+    include_archived: Option<bool>,
+    pool: State<'_, DbPool>,
 ) -> Result<Vec<Conversation>, String> {
-    let conn = get_db_connection(&app)?;
+    let conn = get_db_connection(&pool)?;
 
-    let mut stmt = conn.prepare(
-        "SELECT id, project_id, title, model, total_tokens, total_cost, created_at, updated_at
-         FROM conversations
-         WHERE project_id = ?1
-         ORDER BY updated_at DESC"
-    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM conversations WHERE project_id = ?1 AND (?2 OR is_archived = 0) ORDER BY is_pinned DESC, updated_at DESC",
+        CONVERSATION_COLUMNS
+    )).map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
-    let conversations = stmt.query_map(params![&project_id], |row| {
-        Ok(Conversation {
-            id: row.get(0)?,
-            project_id: row.get(1)?,
-            title: {
-                let title: String = row.get(2)?;
-                if title.is_empty() { None } else { Some(title) }
-            },
-            model: row.get(3)?,
-            total_tokens: row.get(4)?,
-            total_cost: row.get(5)?,
-            created_at: row.get(6)?,
-            updated_at: row.get(7)?,
-        })
-    }).map_err(|e| format!("Failed to query conversations: {}", e))?;
+    let conversations = stmt.query_map(params![&project_id, &include_archived.unwrap_or(false)], row_to_conversation)
+        .map_err(|e| format!("Failed to query conversations: {}", e))?;
 
     let result: Result<Vec<Conversation>, _> = conversations.collect();
     result.map_err(|e| format!("Failed to collect conversations: {}", e))
@@ -1384,45 +2587,59 @@ pub async fn list_conversations(
 #[tauri::command]
 pub async fn get_conversation(
     id: String,
-    app: tauri::AppHandle,
+    pool: State<'_, DbPool>,
 ) -> Result<Option<Conversation>, String> {
-    let conn = get_db_connection(&app)?;
+    let conn = get_db_connection(&pool)?;
 
-    let mut stmt = conn.prepare(
-        "SELECT id, project_id, title, model, total_tokens, total_cost, created_at, updated_at
-         FROM conversations
-         WHERE id = ?1"
-    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM conversations WHERE id = ?1", CONVERSATION_COLUMNS))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
-    let conversation = stmt.query_row(params![&id], |row| {
-        Ok(Conversation {
-            id: row.get(0)?,
-            project_id: row.get(1)?,
-            title: {
-                let title: String = row.get(2)?;
-                if title.is_empty() { None } else { Some(title) }
-            },
-            model: row.get(3)?,
-            total_tokens: row.get(4)?,
-            total_cost: row.get(5)?,
-            created_at: row.get(6)?,
-            updated_at: row.get(7)?,
-        })
-    }).optional()
+    let conversation = stmt.query_row(params![&id], row_to_conversation).optional()
         .map_err(|e| format!("Failed to get conversation: {}", e))?;
 
     Ok(conversation)
 }
 
+#[tauri::command]
+pub async fn update_conversation(
+    id: String,
+    title: Option<String>,
+    model: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<Conversation, String> {
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
+
+    // An empty title clears it; None leaves it untouched
+    let clear_title = matches!(title.as_deref(), Some(t) if t.trim().is_empty());
+    let title = title.filter(|t| !t.trim().is_empty());
+
+    let rows = conn.execute(
+        "UPDATE conversations
+         SET title = CASE WHEN ?1 THEN NULL ELSE COALESCE(?2, title) END,
+             model = COALESCE(?3, model),
+             updated_at = ?4
+         WHERE id = ?5",
+        params![&clear_title, &title, &model, &now, &id],
+    ).map_err(|e| format!("Failed to update conversation: {}", e))?;
+
+    if rows == 0 {
+        return Err("Conversation not found".to_string());
+    }
+
+    get_conversation(id, pool).await?
+        .ok_or_else(|| "Conversation not found after update".to_string())
+}
+
 #[tauri::command]
 pub async fn add_message(
     conversation_id: String,
     role: String,
     content: String,
     tokens: i32,
-    app: tauri::AppHandle,
+    pool: State<'_, DbPool>,
 ) -> Result<Message, String> {
-    let conn = get_db_connection(&app)?;
+    let conn = get_db_connection(&pool)?;
     let id = Uuid::new_v4().to_string();
     let now = Utc::now().timestamp();
 
@@ -1436,2716 +2653,8300 @@ pub async fn add_message(
     };
 
     conn.execute(
-        "INSERT INTO messages (id, conversation_id, role, content, tokens, created_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        "INSERT INTO messages (id, conversation_id, role, content, tokens, created_at, seq)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, (SELECT COALESCE(MAX(seq), 0) + 1 FROM messages WHERE conversation_id = ?2))",
         params![&id, &conversation_id, &role, &content, &tokens, &now],
     ).map_err(|e| format!("Failed to add message: {}", e))?;
 
     Ok(message)
 }
 
-#[tauri::command]
-pub async fn get_messages(
-    conversation_id: String,
-    app: tauri::AppHandle,
-) -> Result<Vec<Message>, String> {
-    let conn = get_db_connection(&app)?;
+fn row_to_message(row: &rusqlite::Row) -> rusqlite::Result<Message> {
+    Ok(Message {
+        id: row.get(0)?,
+        conversation_id: row.get(1)?,
+        role: row.get(2)?,
+        content: row.get(3)?,
+        tokens: row.get(4)?,
+        created_at: row.get(5)?,
+    })
+}
 
+fn load_conversation_messages(conn: &Connection, conversation_id: &str) -> Result<Vec<Message>, String> {
     let mut stmt = conn.prepare(
         "SELECT id, conversation_id, role, content, tokens, created_at
          FROM messages
          WHERE conversation_id = ?1
-         ORDER BY created_at ASC"
+         ORDER BY created_at ASC, seq ASC"
     ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
-    let messages = stmt.query_map(params![&conversation_id], |row| {
-        Ok(Message {
-            id: row.get(0)?,
-            conversation_id: row.get(1)?,
-            role: row.get(2)?,
-            content: row.get(3)?,
-            tokens: row.get(4)?,
-            created_at: row.get(5)?,
-        })
-    }).map_err(|e| format!("Failed to query messages: {}", e))?;
+    let messages = stmt.query_map(params![conversation_id], row_to_message)
+        .map_err(|e| format!("Failed to query messages: {}", e))?;
 
     let result: Result<Vec<Message>, _> = messages.collect();
     result.map_err(|e| format!("Failed to collect messages: {}", e))
 }
 
-#[tauri::command]
-pub async fn update_conversation_stats(
-    id: String,
-    tokens: i32,
-    cost: f64,
-    app: tauri::AppHandle,
-) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
-    let now = Utc::now().timestamp();
+// Streaming replies are persisted chunk by chunk. Appending to messages.content directly would
+// rewrite the whole row and re-run the FTS trigger on every delta, so deltas go to the
+// append-only message_chunks table and are folded into the message once, on finalize (or on the
+// next startup if the app died mid-stream).
+fn fold_message_chunks(conn: &Connection, message_id: &str) -> Result<(), String> {
+    let mut stmt = conn.prepare("SELECT delta FROM message_chunks WHERE message_id = ?1 ORDER BY seq ASC")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let deltas = stmt.query_map(params![message_id], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to read message chunks: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect message chunks: {}", e))?;
+    if deltas.is_empty() {
+        return Ok(());
+    }
 
-    conn.execute(
-        "UPDATE conversations
-         SET total_tokens = total_tokens + ?1,
-             total_cost = total_cost + ?2,
-             updated_at = ?3
-         WHERE id = ?4",
-        params![&tokens, &cost, &now, &id],
-    ).map_err(|e| format!("Failed to update conversation stats: {}", e))?;
+    with_transaction(conn, |tx| {
+        tx.execute(
+            "UPDATE messages SET content = content || ?1 WHERE id = ?2",
+            params![deltas.concat(), message_id],
+        ).map_err(|e| format!("Failed to write message content: {}", e))?;
+        tx.execute("DELETE FROM message_chunks WHERE message_id = ?1", params![message_id])
+            .map_err(|e| format!("Failed to clear message chunks: {}", e))?;
+        Ok(())
+    })
+}
+
+// Folds chunks left behind by a stream that never finalized, so the partial reply is visible
+fn recover_streaming_messages(conn: &Connection) -> Result<(), String> {
+    let mut stmt = conn.prepare("SELECT DISTINCT message_id FROM message_chunks")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let message_ids = stmt.query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to query unfinished messages: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect unfinished messages: {}", e))?;
 
+    for message_id in message_ids {
+        fold_message_chunks(conn, &message_id)?;
+    }
     Ok(())
 }
 
 #[tauri::command]
-pub async fn delete_conversation(
-    id: String,
-    app: tauri::AppHandle,
+pub async fn begin_streaming_message(
+    conversation_id: String,
+    role: String,
+    pool: State<'_, DbPool>,
+) -> Result<Message, String> {
+    add_message(conversation_id, role, String::new(), 0, pool).await
+}
+
+#[tauri::command]
+pub async fn append_to_message(
+    message_id: String,
+    delta: String,
+    pool: State<'_, DbPool>,
 ) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
+    if delta.is_empty() {
+        return Ok(());
+    }
+    let conn = get_db_connection(&pool)?;
 
     conn.execute(
-        "DELETE FROM conversations WHERE id = ?1",
-        params![&id],
-    ).map_err(|e| format!("Failed to delete conversation: {}", e))?;
+        "INSERT INTO message_chunks (message_id, seq, delta)
+         VALUES (?1, (SELECT COALESCE(MAX(seq), -1) + 1 FROM message_chunks WHERE message_id = ?1), ?2)",
+        params![&message_id, &delta],
+    ).map_err(|e| format!("Failed to append to message: {}", e))?;
 
     Ok(())
 }
 
-// Token usage tracking commands
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TokenUsage {
-    pub id: String,
-    pub conversation_id: String,
-    pub model: String,
-    pub input_tokens: i32,
-    pub output_tokens: i32,
-    pub total_tokens: i32,
-    pub cost: f64,
-    pub created_at: i64,
-    pub date: String,
-}
+#[tauri::command]
+pub async fn finalize_message(
+    message_id: String,
+    tokens: i32,
+    pool: State<'_, DbPool>,
+) -> Result<Message, String> {
+    let conn = get_db_connection(&pool)?;
+    fold_message_chunks(&conn, &message_id)?;
+
+    let rows = conn.execute(
+        "UPDATE messages SET tokens = ?1 WHERE id = ?2",
+        params![&tokens, &message_id],
+    ).map_err(|e| format!("Failed to finalize message: {}", e))?;
+    if rows == 0 {
+        return Err("Message not found".to_string());
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TokenUsageAggregate {
-    pub date: String,
-    pub total_tokens: i32,
-    pub input_tokens: i32,
-    pub output_tokens: i32,
-    pub cost: f64,
-    pub conversation_count: i32,
+    conn.query_row(
+        "SELECT id, conversation_id, role, content, tokens, created_at FROM messages WHERE id = ?1",
+        params![&message_id],
+        row_to_message,
+    ).map_err(|e| format!("Failed to read finalized message: {}", e))
 }
 
+// Inserts many messages in one transaction; rows sharing a timestamp keep their insertion order via seq
 #[tauri::command]
-pub async fn record_token_usage(
+pub async fn add_messages_batch(
     conversation_id: String,
-    model: String,
-    input_tokens: i32,
-    output_tokens: i32,
-    cost: f64,
-    app: tauri::AppHandle,
-) -> Result<String, String> {
-    let conn = get_db_connection(&app)?;
-    let id = Uuid::new_v4().to_string();
-    let now = Utc::now();
-    let timestamp = now.timestamp();
-    let date = now.format("%Y-%m-%d").to_string();
-    let total_tokens = input_tokens + output_tokens;
+    messages: Vec<MessageInput>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<Message>, String> {
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
 
-    conn.execute(
-        "INSERT INTO token_usage (id, conversation_id, model, input_tokens, output_tokens, total_tokens, cost, created_at, date)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        params![&id, &conversation_id, &model, &input_tokens, &output_tokens, &total_tokens, &cost, &timestamp, &date],
-    ).map_err(|e| format!("Failed to record token usage: {}", e))?;
+    with_transaction(&conn, |tx| {
+        let mut created = Vec::with_capacity(messages.len());
+        let mut stmt = tx.prepare(
+            "INSERT INTO messages (id, conversation_id, role, content, tokens, created_at, seq)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, (SELECT COALESCE(MAX(seq), 0) + 1 FROM messages WHERE conversation_id = ?2))"
+        ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        for input in messages {
+            let id = Uuid::new_v4().to_string();
+            stmt.execute(params![&id, &conversation_id, &input.role, &input.content, &input.tokens, &now])
+                .map_err(|e| format!("Failed to add message: {}", e))?;
+
+            created.push(Message {
+                id,
+                conversation_id: conversation_id.clone(),
+                role: input.role,
+                content: input.content,
+                tokens: input.tokens,
+                created_at: now,
+            });
+        }
 
-    Ok(id)
+        Ok(created)
+    })
 }
 
-#[tauri::command]
-pub async fn get_token_usage_by_date_range(
-    start_date: String,
-    end_date: String,
-    view_type: String, // "daily" or "monthly"
-    app: tauri::AppHandle,
-) -> Result<Vec<TokenUsageAggregate>, String> {
-    let conn = get_db_connection(&app)?;
+const DEFAULT_MESSAGE_PAGE_SIZE: i64 = 200;
 
-    let date_format = if view_type == "monthly" {
-        "%Y-%m"
-    } else {
-        "%Y-%m-%d"
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessagePage {
+    pub messages: Vec<Message>,
+    pub has_more: bool,
+}
+
+// Newest first. The cursor is the (created_at, seq) position of a message; messages sharing a
+// timestamp are told apart by seq, so no message on a page boundary is skipped.
+const MESSAGE_PAGE_SQL: &str =
+    "SELECT id, conversation_id, role, content, tokens, created_at
+     FROM messages
+     WHERE conversation_id = ?1
+       AND (?2 IS NULL OR created_at < ?2 OR (created_at = ?2 AND seq < ?3))
+     ORDER BY created_at DESC, seq DESC
+     LIMIT ?4";
+
+fn load_message_page(
+    conn: &Connection,
+    conversation_id: &str,
+    limit: i64,
+    before: Option<(i64, &str)>,
+) -> Result<MessagePage, String> {
+    let (before_created_at, before_seq) = match before {
+        Some((created_at, id)) => {
+            let seq: i64 = conn.query_row(
+                "SELECT seq FROM messages WHERE id = ?1 AND conversation_id = ?2 AND created_at = ?3",
+                params![id, conversation_id, created_at],
+                |row| row.get(0),
+            ).optional()
+                .map_err(|e| format!("Failed to resolve message cursor: {}", e))?
+                .ok_or_else(|| "Message cursor does not match a message in this conversation".to_string())?;
+            (Some(created_at), Some(seq))
+        }
+        None => (None, None),
     };
 
-    let query = format!(
-        "SELECT
-            strftime('{}', date) as period,
-            SUM(total_tokens) as total_tokens,
-            SUM(input_tokens) as input_tokens,
-            SUM(output_tokens) as output_tokens,
-            SUM(cost) as cost,
-            COUNT(DISTINCT conversation_id) as conversation_count
-         FROM token_usage
-         WHERE date >= ?1 AND date <= ?2
-         GROUP BY period
-         ORDER BY period ASC",
-        date_format
-    );
+    let mut stmt = conn.prepare(MESSAGE_PAGE_SQL)
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let mut messages: Vec<Message> = stmt.query_map(
+        params![conversation_id, &before_created_at, &before_seq, &(limit + 1)],
+        row_to_message,
+    ).map_err(|e| format!("Failed to query messages: {}", e))?
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("Failed to collect messages: {}", e))?;
 
-    let mut stmt = conn.prepare(&query)
-        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+    let has_more = messages.len() as i64 > limit;
+    messages.truncate(limit as usize);
 
-    let aggregates = stmt.query_map(params![&start_date, &end_date], |row| {
-        Ok(TokenUsageAggregate {
-            date: row.get(0)?,
-            total_tokens: row.get(1)?,
-            input_tokens: row.get(2)?,
-            output_tokens: row.get(3)?,
-            cost: row.get(4)?,
-            conversation_count: row.get(5)?,
-        })
-    }).map_err(|e| format!("Failed to query token usage: {}", e))?;
+    Ok(MessagePage { messages, has_more })
+}
 
-    let result: Result<Vec<TokenUsageAggregate>, _> = aggregates.collect();
-    result.map_err(|e| format!("Failed to collect token usage: {}", e))
+// Newest page first, DEFAULT_MESSAGE_PAGE_SIZE messages unless `limit` says otherwise. Pass the
+// oldest returned message's created_at and id together to fetch the page before it.
+#[tauri::command]
+pub async fn get_messages(
+    conversation_id: String,
+    limit: Option<i64>,
+    before_created_at: Option<i64>,
+    before_id: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<MessagePage, String> {
+    let before = match (before_created_at, before_id.as_deref()) {
+        (Some(created_at), Some(id)) => Some((created_at, id)),
+        (None, None) => None,
+        _ => return Err("before_created_at and before_id must be given together".to_string()),
+    };
+    let limit = limit.unwrap_or(DEFAULT_MESSAGE_PAGE_SIZE).max(1);
+
+    let conn = get_db_connection(&pool)?;
+    load_message_page(&conn, &conversation_id, limit, before)
 }
 
+// Messages and token_usage rows reference the conversation rather than the project,
+// so both follow the move and per-project cost attribution updates with it.
 #[tauri::command]
-pub async fn get_all_token_usage(
-    app: tauri::AppHandle,
-) -> Result<Vec<TokenUsage>, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn move_conversation(
+    conversation_id: String,
+    target_project_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Conversation, String> {
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
 
-    let mut stmt = conn.prepare(
-        "SELECT id, conversation_id, model, input_tokens, output_tokens, total_tokens, cost, created_at, date
-         FROM token_usage
-         ORDER BY created_at DESC"
-    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
+    let target_exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM projects WHERE id = ?1 AND deleted_at IS NULL)",
+        params![&target_project_id],
+        |row| row.get(0),
+    ).map_err(|e| format!("Failed to look up target project: {}", e))?;
+    if !target_exists {
+        return Err(format!("Target project '{}' not found", target_project_id));
+    }
 
-    let usage_records = stmt.query_map([], |row| {
-        Ok(TokenUsage {
-            id: row.get(0)?,
-            conversation_id: row.get(1)?,
-            model: row.get(2)?,
-            input_tokens: row.get(3)?,
-            output_tokens: row.get(4)?,
-            total_tokens: row.get(5)?,
-            cost: row.get(6)?,
-            created_at: row.get(7)?,
-            date: row.get(8)?,
-        })
-    }).map_err(|e| format!("Failed to query token usage: {}", e))?;
+    let source_project_id: String = conn.query_row(
+        "SELECT project_id FROM conversations WHERE id = ?1",
+        params![&conversation_id],
+        |row| row.get(0),
+    ).optional().map_err(|e| format!("Failed to find conversation: {}", e))?
+        .ok_or_else(|| "Conversation not found".to_string())?;
+
+    if source_project_id != target_project_id {
+        with_transaction(&conn, |tx| {
+            tx.execute(
+                "UPDATE conversations SET project_id = ?1, updated_at = ?2 WHERE id = ?3",
+                params![&target_project_id, &now, &conversation_id],
+            ).map_err(|e| format!("Failed to move conversation: {}", e))?;
+
+            tx.execute(
+                "UPDATE projects SET updated_at = ?1 WHERE id IN (?2, ?3)",
+                params![&now, &source_project_id, &target_project_id],
+            ).map_err(|e| format!("Failed to update projects: {}", e))?;
+            Ok::<_, String>(())
+        })?;
+    }
+    drop(conn);
 
-    let result: Result<Vec<TokenUsage>, _> = usage_records.collect();
-    result.map_err(|e| format!("Failed to collect token usage: {}", e))
+    get_conversation(conversation_id, pool).await?
+        .ok_or_else(|| "Conversation not found after move".to_string())
 }
 
-// Settings commands
+fn set_conversation_archived(pool: &DbPool, id: &str, archived: bool) -> Result<(), String> {
+    let conn = get_db_connection(pool)?;
+    let now = Utc::now().timestamp();
+
+    let rows = conn.execute(
+        "UPDATE conversations SET is_archived = ?1, updated_at = ?2 WHERE id = ?3",
+        params![&archived, &now, id],
+    ).map_err(|e| format!("Failed to update conversation archive state: {}", e))?;
+
+    if rows == 0 {
+        return Err("Conversation not found".to_string());
+    }
+    Ok(())
+}
 
 #[tauri::command]
-pub async fn get_settings(app: tauri::AppHandle) -> Result<Settings, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn archive_conversation(id: String, pool: State<'_, DbPool>) -> Result<(), String> {
+    set_conversation_archived(&pool, &id, true)
+}
 
-    let mut stmt = conn.prepare(
-        "SELECT id, api_key_encrypted, username, name, surname, job_title, company, company_url,
-                profile_pic, about_me, about_role, jira_url, jira_email, jira_api_token_encrypted,
-                jira_project_key, notion_api_token_encrypted, notion_parent_page_id,
-                created_at, updated_at
-         FROM settings WHERE id = ?1"
-    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+#[tauri::command]
+pub async fn unarchive_conversation(id: String, pool: State<'_, DbPool>) -> Result<(), String> {
+    set_conversation_archived(&pool, &id, false)
+}
 
-    let settings = stmt.query_row(params!["default"], |row| {
-        Ok(Settings {
-            id: row.get(0)?,
-            api_key_encrypted: row.get(1)?,
-            username: row.get(2)?,
-            name: row.get(3)?,
-            surname: row.get(4)?,
-            job_title: row.get(5)?,
-            company: row.get(6)?,
-            company_url: row.get(7)?,
-            profile_pic: row.get(8)?,
-            about_me: row.get(9)?,
-            about_role: row.get(10)?,
-            jira_url: row.get(11)?,
-            jira_email: row.get(12)?,
-            jira_api_token_encrypted: row.get(13)?,
-            jira_project_key: row.get(14)?,
-            notion_api_token_encrypted: row.get(15)?,
-            notion_parent_page_id: row.get(16)?,
-            created_at: row.get(17)?,
-            updated_at: row.get(18)?,
-        })
-    }).map_err(|e| format!("Failed to get settings: {}", e))?;
+// Pinning only changes list order, so updated_at is left alone
+#[tauri::command]
+pub async fn toggle_conversation_pin(id: String, pinned: bool, pool: State<'_, DbPool>) -> Result<(), String> {
+    let conn = get_db_connection(&pool)?;
 
-    Ok(settings)
+    let rows = conn.execute(
+        "UPDATE conversations SET is_pinned = ?1 WHERE id = ?2",
+        params![&pinned, &id],
+    ).map_err(|e| format!("Failed to update conversation pin: {}", e))?;
+
+    if rows == 0 {
+        return Err("Conversation not found".to_string());
+    }
+    Ok(())
+}
+
+fn recompute_conversation_tokens(conn: &Connection, conversation_id: &str) -> Result<(), String> {
+    conn.execute(
+        "UPDATE conversations
+         SET total_tokens = (SELECT COALESCE(SUM(tokens), 0) FROM messages WHERE conversation_id = ?1)
+         WHERE id = ?1",
+        params![conversation_id],
+    ).map_err(|e| format!("Failed to recompute conversation tokens: {}", e))?;
+    Ok(())
 }
 
 #[tauri::command]
-pub async fn update_settings(
-    settings: SettingsUpdate,
-    app: tauri::AppHandle,
-) -> Result<Settings, String> {
-    let conn = get_db_connection(&app)?;
-    let now = Utc::now().timestamp();
+pub async fn update_message(
+    id: String,
+    content: String,
+    recompute_tokens: Option<bool>,
+    pool: State<'_, DbPool>,
+) -> Result<Message, String> {
+    let conn = get_db_connection(&pool)?;
 
-    let enc_key = get_encryption_key(&app)?;
+    with_transaction(&conn, |tx| {
+        let rows = tx.execute(
+            "UPDATE messages SET content = ?1 WHERE id = ?2",
+            params![&content, &id],
+        ).map_err(|e| format!("Failed to update message: {}", e))?;
 
-    let api_key_encrypted = if let Some(ref api_key) = settings.api_key {
-        if api_key.is_empty() { None } else { Some(encrypt_string(api_key, &enc_key)?) }
-    } else {
-        None
-    };
+        if rows == 0 {
+            return Err("Message not found".to_string());
+        }
 
-    let jira_token_encrypted = if let Some(ref token) = settings.jira_api_token {
-        if token.is_empty() { None } else { Some(encrypt_string(token, &enc_key)?) }
-    } else {
-        None
-    };
+        let message = tx.query_row(
+            "SELECT id, conversation_id, role, content, tokens, created_at FROM messages WHERE id = ?1",
+            params![&id],
+            row_to_message,
+        ).map_err(|e| format!("Failed to load message: {}", e))?;
 
-    let notion_token_encrypted = if let Some(ref token) = settings.notion_api_token {
-        if token.is_empty() { None } else { Some(encrypt_string(token, &enc_key)?) }
-    } else {
-        None
-    };
+        if recompute_tokens.unwrap_or(false) {
+            recompute_conversation_tokens(tx, &message.conversation_id)?;
+        }
 
-    conn.execute(
-        "UPDATE settings
-         SET api_key_encrypted = COALESCE(?1, api_key_encrypted),
-             username = COALESCE(?2, username),
-             name = COALESCE(?3, name),
-             surname = COALESCE(?4, surname),
-             job_title = COALESCE(?5, job_title),
-             company = COALESCE(?6, company),
-             company_url = COALESCE(?7, company_url),
-             profile_pic = COALESCE(?8, profile_pic),
-             about_me = COALESCE(?9, about_me),
-             about_role = COALESCE(?10, about_role),
-             jira_url = COALESCE(?11, jira_url),
-             jira_email = COALESCE(?12, jira_email),
-             jira_api_token_encrypted = COALESCE(?13, jira_api_token_encrypted),
-             jira_project_key = COALESCE(?14, jira_project_key),
-             notion_api_token_encrypted = COALESCE(?15, notion_api_token_encrypted),
-             notion_parent_page_id = COALESCE(?16, notion_parent_page_id),
-             updated_at = ?17
-         WHERE id = ?18",
-        params![
-            &api_key_encrypted,
-            &settings.username,
-            &settings.name,
-            &settings.surname,
-            &settings.job_title,
-            &settings.company,
-            &settings.company_url,
-            &settings.profile_pic,
-            &settings.about_me,
-            &settings.about_role,
-            &settings.jira_url,
-            &settings.jira_email,
-            &jira_token_encrypted,
-            &settings.jira_project_key,
-            &notion_token_encrypted,
-            &settings.notion_parent_page_id,
-            &now,
-            "default"
-        ],
-    ).map_err(|e| format!("Failed to update settings: {}", e))?;
+        Ok(message)
+    })
+}
+
+fn remove_message(conn: &Connection, id: &str, recompute_tokens: bool) -> Result<(), String> {
+    with_transaction(conn, |tx| {
+        let conversation_id: String = tx.query_row(
+            "SELECT conversation_id FROM messages WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        ).optional().map_err(|e| format!("Failed to find message: {}", e))?
+            .ok_or_else(|| "Message not found".to_string())?;
+
+        tx.execute(
+            "DELETE FROM messages WHERE id = ?1",
+            params![id],
+        ).map_err(|e| format!("Failed to delete message: {}", e))?;
+
+        if recompute_tokens {
+            recompute_conversation_tokens(tx, &conversation_id)?;
+        }
 
-    get_settings(app).await
+        Ok(())
+    })
 }
 
 #[tauri::command]
-pub async fn get_decrypted_api_key(app: tauri::AppHandle) -> Result<Option<String>, String> {
-    let settings = get_settings(app.clone()).await?;
-
-    if let Some(encrypted) = settings.api_key_encrypted {
-        let key = get_encryption_key(&app)?;
-        Ok(Some(decrypt_string(&encrypted, &key)?))
-    } else {
-        Ok(None)
-    }
+pub async fn delete_message(
+    id: String,
+    recompute_tokens: Option<bool>,
+    pool: State<'_, DbPool>,
+) -> Result<(), String> {
+    let conn = get_db_connection(&pool)?;
+    remove_message(&conn, &id, recompute_tokens.unwrap_or(false))
 }
 
 #[tauri::command]
-pub async fn delete_api_key(app: tauri::AppHandle) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
+pub async fn update_conversation_stats(
+    id: String,
+    tokens: i32,
+    cost: f64,
+    pool: State<'_, DbPool>,
+) -> Result<(), String> {
+    let conn = get_db_connection(&pool)?;
     let now = Utc::now().timestamp();
 
     conn.execute(
-        "UPDATE settings SET api_key_encrypted = NULL, updated_at = ?1 WHERE id = ?2",
-        params![&now, "default"],
-    ).map_err(|e| format!("Failed to delete API key: {}", e))?;
+        "UPDATE conversations
+         SET total_tokens = total_tokens + ?1,
+             total_cost = total_cost + ?2,
+             updated_at = ?3
+         WHERE id = ?4",
+        params![&tokens, &cost, &now, &id],
+    ).map_err(|e| format!("Failed to update conversation stats: {}", e))?;
 
     Ok(())
 }
 
-// Folder commands
+// Cost of a conversation's token_usage rows, including those prune_token_usage folded into the rollup
+const RECORDED_CONVERSATION_COST_SQL: &str =
+    "((SELECT COALESCE(SUM(t.cost), 0.0) FROM token_usage t WHERE t.conversation_id = conversations.id)
+      + COALESCE((SELECT r.cost FROM conversation_cost_rollup r WHERE r.conversation_id = conversations.id), 0.0))";
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Folder {
-    pub id: String,
-    pub project_id: String,
-    pub parent_id: Option<String>,
-    pub name: String,
-    pub color: Option<String>,
-    pub sort_order: i32,
-    pub created_at: i64,
-    pub updated_at: i64,
+// Token totals come from the messages themselves and cost from the recorded token_usage rows,
+// so drift from missed or doubled update_conversation_stats calls is corrected
+fn recalculate_conversation_stats_sql() -> String {
+    format!(
+        "UPDATE conversations
+         SET total_tokens = (SELECT COALESCE(SUM(m.tokens), 0) FROM messages m WHERE m.conversation_id = conversations.id),
+             total_cost = {}",
+        RECORDED_CONVERSATION_COST_SQL
+    )
 }
 
 #[tauri::command]
-pub async fn create_folder(
-    project_id: String,
-    name: String,
-    parent_id: Option<String>,
-    color: Option<String>,
-    app: tauri::AppHandle,
-) -> Result<Folder, String> {
-    let conn = get_db_connection(&app)?;
-    let id = Uuid::new_v4().to_string();
-    let now = Utc::now().timestamp();
-
-    let folder = Folder {
-        id: id.clone(),
-        project_id: project_id.clone(),
-        parent_id: parent_id.clone(),
-        name: name.clone(),
-        color: color.clone(),
-        sort_order: 0,
-        created_at: now,
-        updated_at: now,
-    };
-
-    conn.execute(
-        "INSERT INTO folders (id, project_id, parent_id, name, color, sort_order, created_at, updated_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-        params![&id, &project_id, &parent_id, &name, &color, &0, &now, &now],
-    ).map_err(|e| format!("Failed to create folder: {}", e))?;
+pub async fn recalculate_conversation_stats(
+    conversation_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Conversation, String> {
+    {
+        let conn = get_db_connection(&pool)?;
+        let rows = conn.execute(
+            &format!("{} WHERE id = ?1", recalculate_conversation_stats_sql()),
+            params![&conversation_id],
+        ).map_err(|e| format!("Failed to recalculate conversation stats: {}", e))?;
+        if rows == 0 {
+            return Err("Conversation not found".to_string());
+        }
+    }
 
-    Ok(folder)
+    get_conversation(conversation_id, pool).await?
+        .ok_or_else(|| "Conversation not found".to_string())
 }
 
+// Returns how many conversations had totals that drifted and were corrected
 #[tauri::command]
-pub async fn list_folders(
-    project_id: String,
-    app: tauri::AppHandle,
-) -> Result<Vec<Folder>, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn recalculate_all_conversation_stats(pool: State<'_, DbPool>) -> Result<usize, String> {
+    let conn = get_db_connection(&pool)?;
 
-    let mut stmt = conn.prepare(
-        "SELECT id, project_id, parent_id, name, color, sort_order, created_at, updated_at
-         FROM folders
-         WHERE project_id = ?1
-         ORDER BY sort_order ASC, name ASC"
-    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    conn.execute(
+        &format!(
+            "{} WHERE total_tokens != (SELECT COALESCE(SUM(m.tokens), 0) FROM messages m WHERE m.conversation_id = conversations.id)
+                OR ABS(total_cost - {}) > 1e-9",
+            recalculate_conversation_stats_sql(),
+            RECORDED_CONVERSATION_COST_SQL
+        ),
+        [],
+    ).map_err(|e| format!("Failed to recalculate conversation stats: {}", e))
+}
 
-    let folders = stmt.query_map(params![&project_id], |row| {
-        Ok(Folder {
-            id: row.get(0)?,
-            project_id: row.get(1)?,
-            parent_id: row.get(2)?,
-            name: row.get(3)?,
-            color: row.get(4)?,
-            sort_order: row.get(5)?,
-            created_at: row.get(6)?,
-            updated_at: row.get(7)?,
-        })
-    }).map_err(|e| format!("Failed to query folders: {}", e))?;
+#[tauri::command]
+pub async fn delete_conversation(
+    id: String,
+    pool: State<'_, DbPool>,
+) -> Result<usize, String> {
+    let conn = get_db_connection(&pool)?;
 
-    let result: Result<Vec<Folder>, _> = folders.collect();
-    result.map_err(|e| format!("Failed to collect folders: {}", e))
+    conn.execute(
+        "DELETE FROM conversations WHERE id = ?1",
+        params![&id],
+    ).map_err(|e| format!("Failed to delete conversation: {}", e))
 }
 
+// Messages don't record cost, so the fork starts at zero cost and counts only the copied tokens
 #[tauri::command]
-pub async fn get_folder(
-    id: String,
-    app: tauri::AppHandle,
-) -> Result<Option<Folder>, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn fork_conversation(
+    conversation_id: String,
+    from_message_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Conversation, String> {
+    let original = get_conversation(conversation_id.clone(), pool.clone()).await?
+        .ok_or_else(|| "Conversation not found".to_string())?;
 
-    let mut stmt = conn.prepare(
-        "SELECT id, project_id, parent_id, name, color, sort_order, created_at, updated_at
-         FROM folders WHERE id = ?1"
-    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let conn = get_db_connection(&pool)?;
+    let messages = load_conversation_messages(&conn, &conversation_id)?;
+    let cut = messages.iter().position(|m| m.id == from_message_id)
+        .ok_or_else(|| "Message not found in conversation".to_string())?;
+    let copied = &messages[..=cut];
 
-    let folder = stmt.query_row(params![&id], |row| {
-        Ok(Folder {
-            id: row.get(0)?,
-            project_id: row.get(1)?,
-            parent_id: row.get(2)?,
-            name: row.get(3)?,
-            color: row.get(4)?,
-            sort_order: row.get(5)?,
-            created_at: row.get(6)?,
-            updated_at: row.get(7)?,
-        })
-    }).optional()
-        .map_err(|e| format!("Failed to get folder: {}", e))?;
+    let new_id = Uuid::new_v4().to_string();
+    let now = Utc::now().timestamp();
+    let title = format!("Fork of {}", original.title.as_deref().unwrap_or("Untitled conversation"));
+    let total_tokens: i32 = copied.iter().map(|m| m.tokens).sum();
+
+    with_transaction(&conn, |tx| {
+        tx.execute(
+            "INSERT INTO conversations (id, project_id, title, model, total_tokens, total_cost, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![&new_id, &original.project_id, &title, &original.model, &total_tokens, &0.0, &now, &now],
+        ).map_err(|e| format!("Failed to create forked conversation: {}", e))?;
+
+        // Original timestamps are kept; inserting in order keeps seq ties stable
+        for message in copied {
+            tx.execute(
+                "INSERT INTO messages (id, conversation_id, role, content, tokens, created_at, seq)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, (SELECT COALESCE(MAX(seq), 0) + 1 FROM messages WHERE conversation_id = ?2))",
+                params![Uuid::new_v4().to_string(), &new_id, &message.role, &message.content, &message.tokens, &message.created_at],
+            ).map_err(|e| format!("Failed to copy message: {}", e))?;
+        }
+        Ok::<_, String>(())
+    })?;
+    drop(conn);
 
-    Ok(folder)
+    get_conversation(new_id, pool).await?
+        .ok_or_else(|| "Conversation not found after fork".to_string())
 }
 
+// Copies the conversation under a fresh id with cost reset. Without messages it is a clean restart
+// on the same project and model; with them, tokens are recounted from the copied messages.
 #[tauri::command]
-pub async fn update_folder(
+pub async fn duplicate_conversation(
     id: String,
-    name: Option<String>,
-    parent_id: Option<String>,
-    color: Option<String>,
-    sort_order: Option<i32>,
-    app: tauri::AppHandle,
-) -> Result<Folder, String> {
-    let conn = get_db_connection(&app)?;
-    let now = Utc::now().timestamp();
+    new_title: Option<String>,
+    include_messages: bool,
+    pool: State<'_, DbPool>,
+) -> Result<Conversation, String> {
+    let original = get_conversation(id.clone(), pool.clone()).await?
+        .ok_or_else(|| "Conversation not found".to_string())?;
 
-    conn.execute(
-        "UPDATE folders
-         SET name = COALESCE(?1, name),
-             parent_id = CASE WHEN ?2 = '__null__' THEN NULL WHEN ?2 IS NOT NULL THEN ?2 ELSE parent_id END,
-             color = COALESCE(?3, color),
-             sort_order = COALESCE(?4, sort_order),
-             updated_at = ?5
-         WHERE id = ?6",
-        params![&name, &parent_id, &color, &sort_order, &now, &id],
-    ).map_err(|e| format!("Failed to update folder: {}", e))?;
+    let conn = get_db_connection(&pool)?;
+    let messages = if include_messages {
+        load_conversation_messages(&conn, &id)?
+    } else {
+        Vec::new()
+    };
 
-    get_folder(id, app).await?
-        .ok_or_else(|| "Folder not found after update".to_string())
+    let new_id = Uuid::new_v4().to_string();
+    let now = Utc::now().timestamp();
+    let title = new_title
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or_else(|| format!("Copy of {}", original.title.as_deref().unwrap_or("Untitled conversation")));
+    let total_tokens: i32 = messages.iter().map(|m| m.tokens).sum();
+
+    with_transaction(&conn, |tx| {
+        tx.execute(
+            "INSERT INTO conversations (id, project_id, title, model, total_tokens, total_cost, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![&new_id, &original.project_id, &title, &original.model, &total_tokens, &0.0, &now, &now],
+        ).map_err(|e| format!("Failed to create duplicate conversation: {}", e))?;
+
+        for message in &messages {
+            tx.execute(
+                "INSERT INTO messages (id, conversation_id, role, content, tokens, created_at, seq)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, (SELECT COALESCE(MAX(seq), 0) + 1 FROM messages WHERE conversation_id = ?2))",
+                params![Uuid::new_v4().to_string(), &new_id, &message.role, &message.content, &message.tokens, &message.created_at],
+            ).map_err(|e| format!("Failed to copy message: {}", e))?;
+        }
+        Ok::<_, String>(())
+    })?;
+    drop(conn);
+
+    get_conversation(new_id, pool).await?
+        .ok_or_else(|| "Conversation not found after duplicate".to_string())
+}
+
+fn role_heading(role: &str) -> String {
+    match role {
+        "user" => "User".to_string(),
+        "assistant" => "Assistant".to_string(),
+        "system" => "System".to_string(),
+        other => {
+            let mut chars = other.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => "Message".to_string(),
+            }
+        }
+    }
 }
 
 #[tauri::command]
-pub async fn delete_folder(
-    id: String,
-    app: tauri::AppHandle,
-) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
+pub async fn export_conversation_markdown(
+    conversation_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<String, String> {
+    let conversation = get_conversation(conversation_id.clone(), pool.clone()).await?
+        .ok_or_else(|| "Conversation not found".to_string())?;
+
+    let conn = get_db_connection(&pool)?;
+    let messages = load_conversation_messages(&conn, &conversation_id)?;
+
+    let title = conversation.title.as_deref().unwrap_or("Untitled conversation");
+    let mut md = format!("# {}\n\n", title);
+    md.push_str(&format!("*Model: {}*\n", conversation.model));
+
+    // Message content is already Markdown; rules around each role heading keep turns apart
+    for message in &messages {
+        md.push_str("\n---\n\n");
+        md.push_str(&format!("## {}\n\n", role_heading(&message.role)));
+        md.push_str(message.content.trim_end());
+        md.push('\n');
+    }
 
-    // Set folder_id to NULL on items in this folder before deleting
-    conn.execute(
-        "UPDATE context_documents SET folder_id = NULL WHERE folder_id = ?1",
-        params![&id],
-    ).map_err(|e| format!("Failed to unlink context documents: {}", e))?;
+    md.push_str("\n---\n\n");
+    md.push_str(&format!(
+        "*Total tokens: {} · Cost: ${:.4}*\n",
+        conversation.total_tokens, conversation.total_cost
+    ));
 
-    conn.execute(
-        "UPDATE framework_outputs SET folder_id = NULL WHERE folder_id = ?1",
-        params![&id],
-    ).map_err(|e| format!("Failed to unlink framework outputs: {}", e))?;
+    Ok(md)
+}
 
-    conn.execute(
-        "DELETE FROM folders WHERE id = ?1",
-        params![&id],
-    ).map_err(|e| format!("Failed to delete folder: {}", e))?;
+// Token usage tracking commands
 
-    Ok(())
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub id: String,
+    pub conversation_id: String,
+    pub model: String,
+    pub input_tokens: i32,
+    pub output_tokens: i32,
+    pub total_tokens: i32,
+    pub cost: f64,
+    pub created_at: i64,
+    pub date: String,
 }
 
-#[tauri::command]
-pub async fn move_item_to_folder(
-    item_id: String,
-    item_type: String,
-    folder_id: Option<String>,
-    app: tauri::AppHandle,
-) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
-
-    match item_type.as_str() {
-        "context_doc" => {
-            conn.execute(
-                "UPDATE context_documents SET folder_id = ?1 WHERE id = ?2",
-                params![&folder_id, &item_id],
-            ).map_err(|e| format!("Failed to move context document: {}", e))?;
-        },
-        "framework_output" => {
-            conn.execute(
-                "UPDATE framework_outputs SET folder_id = ?1 WHERE id = ?2",
-                params![&folder_id, &item_id],
-            ).map_err(|e| format!("Failed to move framework output: {}", e))?;
-        },
-        _ => return Err(format!("Unknown item type: {}", item_type)),
-    }
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenUsageAggregate {
+    pub date: String,
+    pub total_tokens: i32,
+    pub input_tokens: i32,
+    pub output_tokens: i32,
+    pub cost: f64,
+    pub conversation_count: i32,
+}
 
-    Ok(())
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenUsageGroup {
+    pub key: String,
+    pub label: Option<String>,
+    pub total_tokens: i32,
+    pub input_tokens: i32,
+    pub output_tokens: i32,
+    pub cost: f64,
+    pub conversation_count: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct SearchResult {
+pub struct ModelPricing {
     pub id: String,
-    pub name: String,
-    pub item_type: String,
-    pub folder_id: Option<String>,
-    pub category: Option<String>,
-    pub doc_type: Option<String>,
-    pub is_favorite: bool,
+    pub model: String,
+    pub input_price_per_mtok: f64,
+    pub output_price_per_mtok: f64,
+    pub effective_date: String,
     pub created_at: i64,
+    pub updated_at: i64,
+}
+
+const MODEL_PRICING_SEED_DATE: &str = "2026-01-01";
+
+// (model, input USD per million tokens, output USD per million tokens)
+const DEFAULT_MODEL_PRICING: &[(&str, f64, f64)] = &[
+    ("gpt-5", 1.25, 10.0),
+    ("gpt-5-mini", 0.25, 1.0),
+    ("gpt-5-nano", 0.05, 0.40),
+    ("gpt-4-turbo-preview", 10.0, 30.0),
+    ("gpt-4-turbo", 10.0, 30.0),
+    ("gpt-4", 30.0, 60.0),
+    ("gpt-3.5-turbo", 0.5, 1.5),
+    ("gpt-4o", 5.0, 15.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+];
+
+fn seed_model_pricing(conn: &Connection) -> Result<(), String> {
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM model_pricing", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to count model_pricing: {}", e))?;
+
+    if count > 0 {
+        return Ok(());
+    }
+
+    let now = Utc::now().timestamp();
+    for (model, input_price, output_price) in DEFAULT_MODEL_PRICING {
+        conn.execute(
+            "INSERT OR IGNORE INTO model_pricing (id, model, input_price_per_mtok, output_price_per_mtok, effective_date, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![&Uuid::new_v4().to_string(), model, input_price, output_price, MODEL_PRICING_SEED_DATE, &now, &now],
+        ).map_err(|e| format!("Failed to seed model pricing: {}", e))?;
+    }
+
+    Ok(())
+}
+
+// Uses the most recent rate that is already in effect; None when the model has no pricing
+fn lookup_token_cost(conn: &Connection, model: &str, input_tokens: i32, output_tokens: i32) -> Result<Option<f64>, String> {
+    let today = Utc::now().format("%Y-%m-%d").to_string();
+
+    let rates: Option<(f64, f64)> = conn.query_row(
+        "SELECT input_price_per_mtok, output_price_per_mtok FROM model_pricing
+         WHERE model = ?1 AND effective_date <= ?2
+         ORDER BY effective_date DESC LIMIT 1",
+        params![model, &today],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional().map_err(|e| format!("Failed to look up model pricing: {}", e))?;
+
+    Ok(rates.map(|(input_price, output_price)| {
+        (input_tokens as f64 * input_price + output_tokens as f64 * output_price) / 1_000_000.0
+    }))
 }
 
 #[tauri::command]
-pub async fn search_project_items(
-    project_id: String,
-    query: String,
-    app: tauri::AppHandle,
-) -> Result<Vec<SearchResult>, String> {
-    let conn = get_db_connection(&app)?;
-    let search = format!("%{}%", query);
+pub async fn compute_token_cost(
+    model: String,
+    input_tokens: i32,
+    output_tokens: i32,
+    pool: State<'_, DbPool>,
+) -> Result<f64, String> {
+    let conn = get_db_connection(&pool)?;
+
+    lookup_token_cost(&conn, &model, input_tokens, output_tokens)?
+        .ok_or_else(|| format!("No pricing configured for model '{}'", model))
+}
+
+#[tauri::command]
+pub async fn list_model_pricing(pool: State<'_, DbPool>) -> Result<Vec<ModelPricing>, String> {
+    let conn = get_db_connection(&pool)?;
 
     let mut stmt = conn.prepare(
-        "SELECT id, name, 'context_doc' as item_type, folder_id, NULL as category, type as doc_type, is_favorite, created_at
-         FROM context_documents WHERE project_id = ?1 AND (name LIKE ?2 OR tags LIKE ?2)
-         UNION ALL
-         SELECT id, name, 'framework_output' as item_type, folder_id, category, NULL as doc_type, is_favorite, created_at
-         FROM framework_outputs WHERE project_id = ?1 AND (name LIKE ?2 OR tags LIKE ?2)
-         ORDER BY name ASC"
-    ).map_err(|e| format!("Failed to prepare search: {}", e))?;
+        "SELECT id, model, input_price_per_mtok, output_price_per_mtok, effective_date, created_at, updated_at
+         FROM model_pricing
+         ORDER BY model ASC, effective_date DESC"
+    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
-    let results = stmt.query_map(params![&project_id, &search], |row| {
-        Ok(SearchResult {
+    let pricing = stmt.query_map([], |row| {
+        Ok(ModelPricing {
             id: row.get(0)?,
-            name: row.get(1)?,
-            item_type: row.get(2)?,
-            folder_id: row.get(3)?,
-            category: row.get(4)?,
-            doc_type: row.get(5)?,
-            is_favorite: row.get::<_, i32>(6)? != 0,
-            created_at: row.get(7)?,
+            model: row.get(1)?,
+            input_price_per_mtok: row.get(2)?,
+            output_price_per_mtok: row.get(3)?,
+            effective_date: row.get(4)?,
+            created_at: row.get(5)?,
+            updated_at: row.get(6)?,
         })
-    }).map_err(|e| format!("Failed to search: {}", e))?;
+    }).map_err(|e| format!("Failed to query model pricing: {}", e))?;
 
-    let result: Result<Vec<SearchResult>, _> = results.collect();
-    result.map_err(|e| format!("Failed to collect search results: {}", e))
+    let result: Result<Vec<ModelPricing>, _> = pricing.collect();
+    result.map_err(|e| format!("Failed to collect model pricing: {}", e))
 }
 
+// Creates or replaces the rate for a model on the given effective date (defaults to today)
 #[tauri::command]
-pub async fn toggle_item_favorite(
-    item_id: String,
-    item_type: String,
-    is_favorite: bool,
-    app: tauri::AppHandle,
-) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
-    let fav_val = if is_favorite { 1 } else { 0 };
+pub async fn set_model_pricing(
+    model: String,
+    input_price_per_mtok: f64,
+    output_price_per_mtok: f64,
+    effective_date: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<ModelPricing, String> {
+    if model.trim().is_empty() {
+        return Err("Model name cannot be empty".to_string());
+    }
+    if input_price_per_mtok < 0.0 || output_price_per_mtok < 0.0 {
+        return Err("Prices cannot be negative".to_string());
+    }
 
-    let table = match item_type.as_str() {
-        "context_doc" => "context_documents",
-        "framework_output" => "framework_outputs",
-        _ => return Err(format!("Invalid item type: {}", item_type)),
+    let effective_date = match effective_date {
+        Some(date) => chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+            .map_err(|_| format!("Invalid effective date '{}', expected YYYY-MM-DD", date))?
+            .format("%Y-%m-%d")
+            .to_string(),
+        None => Utc::now().format("%Y-%m-%d").to_string(),
     };
 
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
+
     conn.execute(
-        &format!("UPDATE {} SET is_favorite = ?1 WHERE id = ?2", table),
-        params![&fav_val, &item_id],
-    ).map_err(|e| format!("Failed to toggle favorite: {}", e))?;
+        "INSERT INTO model_pricing (id, model, input_price_per_mtok, output_price_per_mtok, effective_date, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+         ON CONFLICT(model, effective_date) DO UPDATE SET
+             input_price_per_mtok = excluded.input_price_per_mtok,
+             output_price_per_mtok = excluded.output_price_per_mtok,
+             updated_at = excluded.updated_at",
+        params![&Uuid::new_v4().to_string(), &model, &input_price_per_mtok, &output_price_per_mtok, &effective_date, &now],
+    ).map_err(|e| format!("Failed to save model pricing: {}", e))?;
 
-    Ok(())
+    conn.query_row(
+        "SELECT id, model, input_price_per_mtok, output_price_per_mtok, effective_date, created_at, updated_at
+         FROM model_pricing WHERE model = ?1 AND effective_date = ?2",
+        params![&model, &effective_date],
+        |row| Ok(ModelPricing {
+            id: row.get(0)?,
+            model: row.get(1)?,
+            input_price_per_mtok: row.get(2)?,
+            output_price_per_mtok: row.get(3)?,
+            effective_date: row.get(4)?,
+            created_at: row.get(5)?,
+            updated_at: row.get(6)?,
+        }),
+    ).map_err(|e| format!("Failed to load model pricing: {}", e))
 }
 
 #[tauri::command]
-pub async fn set_folder_color(
-    id: String,
-    color: Option<String>,
-    app: tauri::AppHandle,
-) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
-    let now = Utc::now().timestamp();
+pub async fn delete_model_pricing(id: String, pool: State<'_, DbPool>) -> Result<(), String> {
+    let conn = get_db_connection(&pool)?;
 
     conn.execute(
-        "UPDATE folders SET color = ?1, updated_at = ?2 WHERE id = ?3",
-        params![&color, &now, &id],
-    ).map_err(|e| format!("Failed to set folder color: {}", e))?;
+        "DELETE FROM model_pricing WHERE id = ?1",
+        params![&id],
+    ).map_err(|e| format!("Failed to delete model pricing: {}", e))?;
 
     Ok(())
 }
 
-// Context Document commands
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct ContextDocument {
-    pub id: String,
-    pub project_id: String,
-    pub name: String,
-    #[serde(rename = "type")]
-    pub doc_type: String,
-    pub content: String,
-    pub url: Option<String>,
-    pub is_global: bool,
-    pub size_bytes: i64,
-    pub created_at: i64,
-    pub folder_id: Option<String>,
-    pub tags: String,
-    pub is_favorite: bool,
-    pub sort_order: i32,
-}
-
 #[tauri::command]
-pub async fn create_context_document(
-    project_id: String,
-    name: String,
-    doc_type: String,
-    content: String,
-    url: Option<String>,
-    is_global: bool,
-    app: tauri::AppHandle,
-) -> Result<ContextDocument, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn record_token_usage(
+    conversation_id: String,
+    model: String,
+    input_tokens: i32,
+    output_tokens: i32,
+    cost: Option<f64>,
+    pool: State<'_, DbPool>,
+) -> Result<String, String> {
+    let conn = get_db_connection(&pool)?;
     let id = Uuid::new_v4().to_string();
-    let now = Utc::now().timestamp();
-    let size_bytes = content.len() as i64;
+    let now = Utc::now();
+    let timestamp = now.timestamp();
+    let date = now.format("%Y-%m-%d").to_string();
+    let total_tokens = input_tokens + output_tokens;
 
-    let document = ContextDocument {
-        id: id.clone(),
-        project_id: project_id.clone(),
-        name: name.clone(),
-        doc_type: doc_type.clone(),
-        content: content.clone(),
-        url: url.clone(),
-        is_global,
-        size_bytes,
-        created_at: now,
-        folder_id: None,
-        tags: "[]".to_string(),
-        is_favorite: false,
-        sort_order: 0,
+    // Models without a pricing row are recorded at zero cost rather than dropped
+    let cost = match cost {
+        Some(cost) => cost,
+        None => lookup_token_cost(&conn, &model, input_tokens, output_tokens)?.unwrap_or(0.0),
     };
 
     conn.execute(
-        "INSERT INTO context_documents (id, project_id, name, type, content, url, is_global, size_bytes, created_at)
+        "INSERT INTO token_usage (id, conversation_id, model, input_tokens, output_tokens, total_tokens, cost, created_at, date)
          VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        params![&id, &project_id, &name, &doc_type, &content, &url, &is_global, &size_bytes, &now],
-    ).map_err(|e| format!("Failed to create context document: {}", e))?;
+        params![&id, &conversation_id, &model, &input_tokens, &output_tokens, &total_tokens, &cost, &timestamp, &date],
+    ).map_err(|e| format!("Failed to record token usage: {}", e))?;
 
-    Ok(document)
+    Ok(id)
 }
 
 #[tauri::command]
-pub async fn list_context_documents(
-    project_id: String,
-    app: tauri::AppHandle,
-) -> Result<Vec<ContextDocument>, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn get_token_usage_by_date_range(
+    start_date: String,
+    end_date: String,
+    view_type: String, // "daily" or "monthly"
+    pool: State<'_, DbPool>,
+) -> Result<Vec<TokenUsageAggregate>, String> {
+    let conn = get_db_connection(&pool)?;
 
-    let mut stmt = conn.prepare(
-        "SELECT id, project_id, name, type, content, url, is_global, size_bytes, created_at, folder_id, tags, is_favorite, sort_order
-         FROM context_documents
-         WHERE project_id = ?1
-         ORDER BY sort_order ASC, created_at DESC"
-    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let date_format = if view_type == "monthly" {
+        "%Y-%m"
+    } else {
+        "%Y-%m-%d"
+    };
 
-    let documents = stmt.query_map(params![&project_id], |row| {
-        Ok(ContextDocument {
-            id: row.get(0)?,
-            project_id: row.get(1)?,
-            name: row.get(2)?,
-            doc_type: row.get(3)?,
-            content: row.get(4)?,
-            url: row.get(5)?,
-            is_global: row.get::<_, i32>(6)? != 0,
-            size_bytes: row.get(7)?,
-            created_at: row.get(8)?,
-            folder_id: row.get(9)?,
-            tags: row.get::<_, Option<String>>(10)?.unwrap_or_else(|| "[]".to_string()),
-            is_favorite: row.get::<_, Option<i32>>(11)?.unwrap_or(0) != 0,
-            sort_order: row.get::<_, Option<i32>>(12)?.unwrap_or(0),
+    let query = format!(
+        "SELECT
+            strftime('{}', date) as period,
+            SUM(total_tokens) as total_tokens,
+            SUM(input_tokens) as input_tokens,
+            SUM(output_tokens) as output_tokens,
+            SUM(cost) as cost,
+            COUNT(DISTINCT conversation_id) as conversation_count
+         FROM token_usage
+         WHERE date >= ?1 AND date <= ?2
+         GROUP BY period
+         ORDER BY period ASC",
+        date_format
+    );
+
+    let mut stmt = conn.prepare(&query)
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let aggregates = stmt.query_map(params![&start_date, &end_date], |row| {
+        Ok(TokenUsageAggregate {
+            date: row.get(0)?,
+            total_tokens: row.get(1)?,
+            input_tokens: row.get(2)?,
+            output_tokens: row.get(3)?,
+            cost: row.get(4)?,
+            conversation_count: row.get(5)?,
         })
-    }).map_err(|e| format!("Failed to query context documents: {}", e))?;
+    }).map_err(|e| format!("Failed to query token usage: {}", e))?;
 
-    let result: Result<Vec<ContextDocument>, _> = documents.collect();
-    result.map_err(|e| format!("Failed to collect context documents: {}", e))
+    let result: Result<Vec<TokenUsageAggregate>, _> = aggregates.collect();
+    result.map_err(|e| format!("Failed to collect token usage: {}", e))
 }
 
-#[tauri::command]
-pub async fn get_context_document(
-    id: String,
-    app: tauri::AppHandle,
-) -> Result<Option<ContextDocument>, String> {
-    let conn = get_db_connection(&app)?;
-
-    let mut stmt = conn.prepare(
-        "SELECT id, project_id, name, type, content, url, is_global, size_bytes, created_at, folder_id, tags, is_favorite, sort_order
-         FROM context_documents
-         WHERE id = ?1"
-    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+// Runs a grouped token_usage query whose columns are: key, label, total, input, output, cost, conversation count
+fn query_token_usage_groups(
+    conn: &Connection,
+    query: &str,
+    start_date: &str,
+    end_date: &str,
+) -> Result<Vec<TokenUsageGroup>, String> {
+    let mut stmt = conn.prepare(query)
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
 
-    let document = stmt.query_row(params![&id], |row| {
-        Ok(ContextDocument {
-            id: row.get(0)?,
-            project_id: row.get(1)?,
-            name: row.get(2)?,
-            doc_type: row.get(3)?,
-            content: row.get(4)?,
-            url: row.get(5)?,
-            is_global: row.get::<_, i32>(6)? != 0,
-            size_bytes: row.get(7)?,
-            created_at: row.get(8)?,
-            folder_id: row.get(9)?,
-            tags: row.get::<_, Option<String>>(10)?.unwrap_or_else(|| "[]".to_string()),
-            is_favorite: row.get::<_, Option<i32>>(11)?.unwrap_or(0) != 0,
-            sort_order: row.get::<_, Option<i32>>(12)?.unwrap_or(0),
+    let groups = stmt.query_map(params![start_date, end_date], |row| {
+        Ok(TokenUsageGroup {
+            key: row.get(0)?,
+            label: row.get(1)?,
+            total_tokens: row.get(2)?,
+            input_tokens: row.get(3)?,
+            output_tokens: row.get(4)?,
+            cost: row.get(5)?,
+            conversation_count: row.get(6)?,
         })
-    }).optional()
-        .map_err(|e| format!("Failed to get context document: {}", e))?;
+    }).map_err(|e| format!("Failed to query token usage: {}", e))?;
 
-    Ok(document)
+    let result: Result<Vec<TokenUsageGroup>, _> = groups.collect();
+    result.map_err(|e| format!("Failed to collect token usage: {}", e))
 }
 
 #[tauri::command]
-pub async fn update_context_document(
-    id: String,
-    name: String,
-    is_global: bool,
-    app: tauri::AppHandle,
-) -> Result<ContextDocument, String> {
-    let conn = get_db_connection(&app)?;
-
-    conn.execute(
-        "UPDATE context_documents
-         SET name = ?1, is_global = ?2
-         WHERE id = ?3",
-        params![&name, &is_global, &id],
-    ).map_err(|e| format!("Failed to update context document: {}", e))?;
+pub async fn get_token_usage_by_project(
+    start_date: String,
+    end_date: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<TokenUsageGroup>, String> {
+    let conn = get_db_connection(&pool)?;
 
-    // Fetch the updated document
-    get_context_document(id, app).await?
-        .ok_or_else(|| "Context document not found after update".to_string())
+    query_token_usage_groups(
+        &conn,
+        "SELECT
+            c.project_id,
+            p.name,
+            SUM(t.total_tokens),
+            SUM(t.input_tokens),
+            SUM(t.output_tokens),
+            SUM(t.cost),
+            COUNT(DISTINCT t.conversation_id)
+         FROM token_usage t
+         JOIN conversations c ON c.id = t.conversation_id
+         LEFT JOIN projects p ON p.id = c.project_id
+         WHERE t.date >= ?1 AND t.date <= ?2
+         GROUP BY c.project_id
+         ORDER BY SUM(t.cost) DESC",
+        &start_date,
+        &end_date,
+    )
 }
 
 #[tauri::command]
-pub async fn delete_context_document(
-    id: String,
-    app: tauri::AppHandle,
-) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
+pub async fn get_token_usage_by_model(
+    start_date: String,
+    end_date: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<TokenUsageGroup>, String> {
+    let conn = get_db_connection(&pool)?;
 
-    conn.execute(
-        "DELETE FROM context_documents WHERE id = ?1",
-        params![&id],
-    ).map_err(|e| format!("Failed to delete context document: {}", e))?;
+    query_token_usage_groups(
+        &conn,
+        "SELECT
+            model,
+            NULL,
+            SUM(total_tokens),
+            SUM(input_tokens),
+            SUM(output_tokens),
+            SUM(cost),
+            COUNT(DISTINCT conversation_id)
+         FROM token_usage
+         WHERE date >= ?1 AND date <= ?2
+         GROUP BY model
+         ORDER BY SUM(cost) DESC",
+        &start_date,
+        &end_date,
+    )
+}
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenUsagePage {
+    pub records: Vec<TokenUsage>,
+    // Rows matching the filters, ignoring limit/offset
+    pub total: i64,
+}
+
+// token_usage.date is stored as YYYY-MM-DD, so date filters compare as plain strings
+// and must be in exactly that shape.
+fn validate_usage_date(label: &str, value: &str) -> Result<(), String> {
+    let valid = value.len() == 10
+        && chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok();
+    if !valid {
+        return Err(format!("Invalid {} '{}', expected YYYY-MM-DD", label, value));
+    }
     Ok(())
 }
 
-// Framework Output commands
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct FrameworkOutput {
-    pub id: String,
-    pub project_id: String,
-    pub framework_id: String,
-    pub category: String,
-    pub name: String,
-    pub user_prompt: String,
-    pub context_doc_ids: String,
-    pub generated_content: String,
-    pub format: String,
-    pub created_at: i64,
-    pub updated_at: i64,
-    pub folder_id: Option<String>,
-    pub tags: String,
-    pub is_favorite: bool,
-    pub sort_order: i32,
+fn validate_usage_date_range(start_date: Option<&str>, end_date: Option<&str>) -> Result<(), String> {
+    for (label, value) in [("start date", start_date), ("end date", end_date)] {
+        if let Some(value) = value {
+            validate_usage_date(label, value)?;
+        }
+    }
+    if let (Some(start), Some(end)) = (start_date, end_date) {
+        if start > end {
+            return Err(format!("Start date {} is after end date {}", start, end));
+        }
+    }
+    Ok(())
 }
 
+// Newest first. Without a limit every matching row is returned, as before pagination existed.
 #[tauri::command]
-pub async fn create_framework_output(
-    project_id: String,
-    framework_id: String,
-    category: String,
-    name: String,
-    user_prompt: String,
-    context_doc_ids: String,
-    generated_content: String,
-    format: String,
-    app: tauri::AppHandle,
-) -> Result<FrameworkOutput, String> {
-    let conn = get_db_connection(&app)?;
-    let id = Uuid::new_v4().to_string();
-    let now = Utc::now().timestamp();
+pub async fn get_all_token_usage(
+    limit: Option<i64>,
+    offset: Option<i64>,
+    conversation_id: Option<String>,
+    model: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<TokenUsagePage, String> {
+    validate_usage_date_range(start_date.as_deref(), end_date.as_deref())?;
+    let conn = get_db_connection(&pool)?;
+
+    let total: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM token_usage
+         WHERE (?1 IS NULL OR conversation_id = ?1) AND (?2 IS NULL OR model = ?2)
+           AND (?3 IS NULL OR date >= ?3) AND (?4 IS NULL OR date <= ?4)",
+        params![&conversation_id, &model, &start_date, &end_date],
+        |row| row.get(0),
+    ).map_err(|e| format!("Failed to count token usage: {}", e))?;
 
-    let output = FrameworkOutput {
-        id: id.clone(),
-        project_id: project_id.clone(),
-        framework_id: framework_id.clone(),
-        category: category.clone(),
-        name: name.clone(),
-        user_prompt: user_prompt.clone(),
-        context_doc_ids: context_doc_ids.clone(),
-        generated_content: generated_content.clone(),
-        format: format.clone(),
-        created_at: now,
-        updated_at: now,
-        folder_id: None,
-        tags: "[]".to_string(),
-        is_favorite: false,
-        sort_order: 0,
-    };
+    // SQLite treats a negative LIMIT as no limit
+    let mut stmt = conn.prepare(
+        "SELECT id, conversation_id, model, input_tokens, output_tokens, total_tokens, cost, created_at, date
+         FROM token_usage
+         WHERE (?1 IS NULL OR conversation_id = ?1) AND (?2 IS NULL OR model = ?2)
+           AND (?3 IS NULL OR date >= ?3) AND (?4 IS NULL OR date <= ?4)
+         ORDER BY created_at DESC, rowid DESC
+         LIMIT ?5 OFFSET ?6"
+    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
 
-    conn.execute(
-        "INSERT INTO framework_outputs (id, project_id, framework_id, category, name, user_prompt, context_doc_ids, generated_content, format, created_at, updated_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
-        params![&id, &project_id, &framework_id, &category, &name, &user_prompt, &context_doc_ids, &generated_content, &format, &now, &now],
-    ).map_err(|e| format!("Failed to create framework output: {}", e))?;
+    let usage_records = stmt.query_map(
+        params![
+            &conversation_id,
+            &model,
+            &start_date,
+            &end_date,
+            &limit.unwrap_or(-1),
+            &offset.unwrap_or(0).max(0)
+        ],
+        |row| {
+            Ok(TokenUsage {
+                id: row.get(0)?,
+                conversation_id: row.get(1)?,
+                model: row.get(2)?,
+                input_tokens: row.get(3)?,
+                output_tokens: row.get(4)?,
+                total_tokens: row.get(5)?,
+                cost: row.get(6)?,
+                created_at: row.get(7)?,
+                date: row.get(8)?,
+            })
+        },
+    ).map_err(|e| format!("Failed to query token usage: {}", e))?;
 
-    let _ = commit_output(project_id.clone(), id.clone(), name.clone(), generated_content.clone(), format!("Create: {}", name), app).await;
+    let records = usage_records.collect::<Result<Vec<TokenUsage>, _>>()
+        .map_err(|e| format!("Failed to collect token usage: {}", e))?;
 
-    Ok(output)
+    Ok(TokenUsagePage { records, total })
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains(',') || field.contains('"') || field.contains('\n') || field.contains('\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
 }
 
 #[tauri::command]
-pub async fn list_framework_outputs(
-    project_id: String,
-    app: tauri::AppHandle,
-) -> Result<Vec<FrameworkOutput>, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn export_token_usage_csv(
+    start_date: String,
+    end_date: String,
+    model: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<String, String> {
+    validate_usage_date_range(Some(&start_date), Some(&end_date))?;
+    let conn = get_db_connection(&pool)?;
 
     let mut stmt = conn.prepare(
-        "SELECT id, project_id, framework_id, category, name, user_prompt, context_doc_ids, generated_content, format, created_at, updated_at, folder_id, tags, is_favorite, sort_order
-         FROM framework_outputs
-         WHERE project_id = ?1
-         ORDER BY sort_order ASC, updated_at DESC"
-    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        "SELECT id, conversation_id, model, input_tokens, output_tokens, total_tokens, cost, created_at, date
+         FROM token_usage
+         WHERE date >= ?1 AND date <= ?2 AND (?3 IS NULL OR model = ?3)
+         ORDER BY created_at ASC"
+    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
 
-    let outputs = stmt.query_map(params![&project_id], |row| {
-        Ok(FrameworkOutput {
+    let records = stmt.query_map(params![&start_date, &end_date, &model], |row| {
+        Ok(TokenUsage {
             id: row.get(0)?,
-            project_id: row.get(1)?,
-            framework_id: row.get(2)?,
-            category: row.get(3)?,
-            name: row.get(4)?,
-            user_prompt: row.get(5)?,
-            context_doc_ids: row.get(6)?,
-            generated_content: row.get(7)?,
-            format: row.get(8)?,
-            created_at: row.get(9)?,
-            updated_at: row.get(10)?,
-            folder_id: row.get(11)?,
-            tags: row.get::<_, Option<String>>(12)?.unwrap_or_else(|| "[]".to_string()),
-            is_favorite: row.get::<_, Option<i32>>(13)?.unwrap_or(0) != 0,
-            sort_order: row.get::<_, Option<i32>>(14)?.unwrap_or(0),
+            conversation_id: row.get(1)?,
+            model: row.get(2)?,
+            input_tokens: row.get(3)?,
+            output_tokens: row.get(4)?,
+            total_tokens: row.get(5)?,
+            cost: row.get(6)?,
+            created_at: row.get(7)?,
+            date: row.get(8)?,
         })
-    }).map_err(|e| format!("Failed to query framework outputs: {}", e))?;
+    }).map_err(|e| format!("Failed to query token usage: {}", e))?;
 
-    let result: Result<Vec<FrameworkOutput>, _> = outputs.collect();
-    result.map_err(|e| format!("Failed to collect framework outputs: {}", e))
+    let mut csv = String::from("date,model,input_tokens,output_tokens,total_tokens,cost,conversation_id\n");
+    for record in records {
+        let record = record.map_err(|e| format!("Failed to read token usage: {}", e))?;
+        csv.push_str(&format!(
+            "{},{},{},{},{},{:.6},{}\n",
+            csv_escape(&record.date),
+            csv_escape(&record.model),
+            record.input_tokens,
+            record.output_tokens,
+            record.total_tokens,
+            record.cost,
+            csv_escape(&record.conversation_id),
+        ));
+    }
+
+    Ok(csv)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenUsageSummary {
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub total_tokens: i64,
+    pub cost: f64,
+    pub record_count: i64,
+    pub pruned_record_count: i64,
 }
 
+// Deletes detail rows dated before the cutoff, folding their totals into token_usage_rollup first
 #[tauri::command]
-pub async fn get_framework_output(
-    id: String,
-    app: tauri::AppHandle,
-) -> Result<Option<FrameworkOutput>, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn prune_token_usage(
+    before_date: String,
+    vacuum: Option<bool>,
+    pool: State<'_, DbPool>,
+) -> Result<i64, String> {
+    // A malformed cutoff would compare greater than every stored date and prune the whole table
+    validate_usage_date("cutoff date", &before_date)?;
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
+
+    let removed = with_transaction(&conn, |tx| {
+        tx.execute(
+            "INSERT INTO token_usage_rollup (id, input_tokens, output_tokens, total_tokens, cost, record_count, updated_at)
+             SELECT 'lifetime', COALESCE(SUM(input_tokens), 0), COALESCE(SUM(output_tokens), 0),
+                    COALESCE(SUM(total_tokens), 0), COALESCE(SUM(cost), 0.0), COUNT(*), ?2
+             FROM token_usage WHERE date < ?1
+             ON CONFLICT(id) DO UPDATE SET
+                 input_tokens = input_tokens + excluded.input_tokens,
+                 output_tokens = output_tokens + excluded.output_tokens,
+                 total_tokens = total_tokens + excluded.total_tokens,
+                 cost = cost + excluded.cost,
+                 record_count = record_count + excluded.record_count,
+                 updated_at = excluded.updated_at",
+            params![&before_date, &now],
+        ).map_err(|e| format!("Failed to roll up token usage: {}", e))?;
+
+        // Keeps recalculate_conversation_stats from dropping the cost of the pruned rows
+        tx.execute(
+            "INSERT INTO conversation_cost_rollup (conversation_id, cost, updated_at)
+             SELECT conversation_id, SUM(cost), ?2
+             FROM token_usage
+             WHERE date < ?1 AND conversation_id IN (SELECT id FROM conversations)
+             GROUP BY conversation_id
+             ON CONFLICT(conversation_id) DO UPDATE SET
+                 cost = cost + excluded.cost,
+                 updated_at = excluded.updated_at",
+            params![&before_date, &now],
+        ).map_err(|e| format!("Failed to roll up conversation costs: {}", e))?;
+
+        tx.execute(
+            "DELETE FROM token_usage WHERE date < ?1",
+            params![&before_date],
+        ).map_err(|e| format!("Failed to prune token usage: {}", e))
+    })?;
+
+    if vacuum.unwrap_or(false) && removed > 0 {
+        conn.execute_batch("VACUUM")
+            .map_err(|e| format!("Failed to vacuum database: {}", e))?;
+        rebuild_message_search_index(&conn)?;
+    }
+
+    Ok(removed as i64)
+}
+
+#[tauri::command]
+pub async fn get_token_usage_summary(pool: State<'_, DbPool>) -> Result<TokenUsageSummary, String> {
+    let conn = get_db_connection(&pool)?;
+
+    conn.query_row(
+        "SELECT
+            COALESCE((SELECT SUM(input_tokens) FROM token_usage), 0) + COALESCE(r.input_tokens, 0),
+            COALESCE((SELECT SUM(output_tokens) FROM token_usage), 0) + COALESCE(r.output_tokens, 0),
+            COALESCE((SELECT SUM(total_tokens) FROM token_usage), 0) + COALESCE(r.total_tokens, 0),
+            COALESCE((SELECT SUM(cost) FROM token_usage), 0.0) + COALESCE(r.cost, 0.0),
+            (SELECT COUNT(*) FROM token_usage),
+            COALESCE(r.record_count, 0)
+         FROM (SELECT 1) LEFT JOIN token_usage_rollup r ON r.id = 'lifetime'",
+        [],
+        |row| Ok(TokenUsageSummary {
+            input_tokens: row.get(0)?,
+            output_tokens: row.get(1)?,
+            total_tokens: row.get(2)?,
+            cost: row.get(3)?,
+            record_count: row.get(4)?,
+            pruned_record_count: row.get(5)?,
+        }),
+    ).map_err(|e| format!("Failed to get token usage summary: {}", e))
+}
+
+// Settings commands
+
+#[tauri::command]
+pub async fn get_settings(pool: State<'_, DbPool>) -> Result<Settings, String> {
+    let conn = get_db_connection(&pool)?;
+    let settings = load_settings(&conn)?;
+
+    let in_keychain = matches!(keyring_get_api_key(), Ok(Some(_)));
+    let has_api_key = in_keychain || settings.api_key_encrypted.is_some();
+
+    Ok(Settings { has_api_key, ..settings })
+}
+
+// Reads the default settings row, recreating it rather than failing if it was deleted out from under us
+fn load_settings(conn: &Connection) -> Result<Settings, String> {
+    ensure_default_settings(conn)?;
 
     let mut stmt = conn.prepare(
-        "SELECT id, project_id, framework_id, category, name, user_prompt, context_doc_ids, generated_content, format, created_at, updated_at, folder_id, tags, is_favorite, sort_order
-         FROM framework_outputs
-         WHERE id = ?1"
+        "SELECT id, api_key_encrypted, username, name, surname, job_title, company, company_url,
+                profile_pic, about_me, about_role, jira_url, jira_email, jira_api_token_encrypted,
+                jira_project_key, notion_api_token_encrypted, notion_parent_page_id,
+                created_at, updated_at, default_model
+         FROM settings WHERE id = ?1"
     ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
-    let output = stmt.query_row(params![&id], |row| {
-        Ok(FrameworkOutput {
+    let settings = stmt.query_row(params!["default"], |row| {
+        Ok(Settings {
             id: row.get(0)?,
-            project_id: row.get(1)?,
-            framework_id: row.get(2)?,
-            category: row.get(3)?,
-            name: row.get(4)?,
-            user_prompt: row.get(5)?,
-            context_doc_ids: row.get(6)?,
-            generated_content: row.get(7)?,
-            format: row.get(8)?,
-            created_at: row.get(9)?,
-            updated_at: row.get(10)?,
-            folder_id: row.get(11)?,
-            tags: row.get::<_, Option<String>>(12)?.unwrap_or_else(|| "[]".to_string()),
-            is_favorite: row.get::<_, Option<i32>>(13)?.unwrap_or(0) != 0,
-            sort_order: row.get::<_, Option<i32>>(14)?.unwrap_or(0),
+            api_key_encrypted: row.get(1)?,
+            username: row.get(2)?,
+            name: row.get(3)?,
+            surname: row.get(4)?,
+            job_title: row.get(5)?,
+            company: row.get(6)?,
+            company_url: row.get(7)?,
+            profile_pic: row.get(8)?,
+            about_me: row.get(9)?,
+            about_role: row.get(10)?,
+            jira_url: row.get(11)?,
+            jira_email: row.get(12)?,
+            jira_api_token_encrypted: row.get(13)?,
+            jira_project_key: row.get(14)?,
+            notion_api_token_encrypted: row.get(15)?,
+            notion_parent_page_id: row.get(16)?,
+            default_model: row.get(19)?,
+            has_api_key: false,
+            created_at: row.get(17)?,
+            updated_at: row.get(18)?,
         })
-    }).optional()
-        .map_err(|e| format!("Failed to get framework output: {}", e))?;
+    }).map_err(|e| format!("Failed to get settings: {}", e))?;
 
-    Ok(output)
+    Ok(settings)
 }
 
 #[tauri::command]
-pub async fn update_framework_output(
-    id: String,
-    name: String,
-    generated_content: String,
-    app: tauri::AppHandle,
-) -> Result<FrameworkOutput, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn update_settings(
+    settings: SettingsUpdate,
+    pool: State<'_, DbPool>,
+) -> Result<Settings, String> {
+    let conn = get_db_connection(&pool)?;
     let now = Utc::now().timestamp();
 
-    // Get project_id before update for git commit
-    let project_id: String = conn.query_row(
-        "SELECT project_id FROM framework_outputs WHERE id = ?1",
-        params![&id],
-        |row| row.get(0),
-    ).map_err(|e| format!("Output not found: {}", e))?;
+    let enc_key = get_encryption_key()?;
+
+    // Prefer the OS keychain; only fall back to the encrypted column when it is unavailable
+    let mut stored_in_keychain = false;
+    let api_key_encrypted = match settings.api_key {
+        Some(ref api_key) if !api_key.is_empty() => {
+            if keyring_set_api_key(api_key).is_ok() {
+                stored_in_keychain = true;
+                None
+            } else {
+                Some(encrypt_string(api_key, &enc_key)?)
+            }
+        }
+        _ => None,
+    };
+
+    let jira_token_encrypted = if let Some(ref token) = settings.jira_api_token {
+        if token.is_empty() { None } else { Some(encrypt_string(token, &enc_key)?) }
+    } else {
+        None
+    };
+
+    let notion_token_encrypted = if let Some(ref token) = settings.notion_api_token {
+        if token.is_empty() { None } else { Some(encrypt_string(token, &enc_key)?) }
+    } else {
+        None
+    };
+
+    with_transaction(&conn, |tx| {
+        tx.execute(
+            "UPDATE settings
+             SET api_key_encrypted = COALESCE(?1, api_key_encrypted),
+                 username = COALESCE(?2, username),
+                 name = COALESCE(?3, name),
+                 surname = COALESCE(?4, surname),
+                 job_title = COALESCE(?5, job_title),
+                 company = COALESCE(?6, company),
+                 company_url = COALESCE(?7, company_url),
+                 profile_pic = COALESCE(?8, profile_pic),
+                 about_me = COALESCE(?9, about_me),
+                 about_role = COALESCE(?10, about_role),
+                 jira_url = COALESCE(?11, jira_url),
+                 jira_email = COALESCE(?12, jira_email),
+                 jira_api_token_encrypted = COALESCE(?13, jira_api_token_encrypted),
+                 jira_project_key = COALESCE(?14, jira_project_key),
+                 notion_api_token_encrypted = COALESCE(?15, notion_api_token_encrypted),
+                 notion_parent_page_id = COALESCE(?16, notion_parent_page_id),
+                 updated_at = ?17,
+                 default_model = COALESCE(?19, default_model)
+             WHERE id = ?18",
+            params![
+                &api_key_encrypted,
+                &settings.username,
+                &settings.name,
+                &settings.surname,
+                &settings.job_title,
+                &settings.company,
+                &settings.company_url,
+                &settings.profile_pic,
+                &settings.about_me,
+                &settings.about_role,
+                &settings.jira_url,
+                &settings.jira_email,
+                &jira_token_encrypted,
+                &settings.jira_project_key,
+                &notion_token_encrypted,
+                &settings.notion_parent_page_id,
+                &now,
+                "default",
+                &settings.default_model,
+            ],
+        ).map_err(|e| format!("Failed to update settings: {}", e))?;
+
+        if stored_in_keychain {
+            tx.execute(
+                "UPDATE settings SET api_key_encrypted = NULL WHERE id = ?1",
+                params!["default"],
+            ).map_err(|e| format!("Failed to clear legacy API key: {}", e))?;
+        }
+        Ok::<_, String>(())
+    })?;
+
+    get_settings(pool).await
+}
+
+const SETTINGS_EXPORT_VERSION: i32 = 1;
+
+// Profile and integration fields that are safe to carry to another machine. The API key and
+// integration tokens are left out on purpose; encrypted values are machine-bound anyway.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SettingsExport {
+    pub export_version: i32,
+    pub username: Option<String>,
+    pub name: Option<String>,
+    pub surname: Option<String>,
+    pub job_title: Option<String>,
+    pub company: Option<String>,
+    pub company_url: Option<String>,
+    pub profile_pic: Option<String>,
+    pub about_me: Option<String>,
+    pub about_role: Option<String>,
+    pub jira_url: Option<String>,
+    pub jira_email: Option<String>,
+    pub jira_project_key: Option<String>,
+    pub notion_parent_page_id: Option<String>,
+    #[serde(default)]
+    pub default_model: Option<String>,
+}
+
+#[tauri::command]
+pub async fn export_settings(pool: State<'_, DbPool>) -> Result<String, String> {
+    let settings = get_settings(pool).await?;
+    let export = SettingsExport {
+        export_version: SETTINGS_EXPORT_VERSION,
+        username: settings.username,
+        name: settings.name,
+        surname: settings.surname,
+        job_title: settings.job_title,
+        company: settings.company,
+        company_url: settings.company_url,
+        profile_pic: settings.profile_pic,
+        about_me: settings.about_me,
+        about_role: settings.about_role,
+        jira_url: settings.jira_url,
+        jira_email: settings.jira_email,
+        jira_project_key: settings.jira_project_key,
+        notion_parent_page_id: settings.notion_parent_page_id,
+        default_model: settings.default_model,
+    };
+    serde_json::to_string_pretty(&export).map_err(|e| format!("Failed to serialize settings: {}", e))
+}
+
+// Fields missing from the file keep their current values; secrets in the file, if any, are ignored
+#[tauri::command]
+pub async fn import_settings(json: String, pool: State<'_, DbPool>) -> Result<Settings, String> {
+    let export: SettingsExport = serde_json::from_str(&json)
+        .map_err(|e| format!("Invalid settings file: {}", e))?;
+    if export.export_version != SETTINGS_EXPORT_VERSION {
+        return Err(format!(
+            "Unsupported settings export version {} (expected {})",
+            export.export_version, SETTINGS_EXPORT_VERSION
+        ));
+    }
+
+    update_settings(SettingsUpdate {
+        api_key: None,
+        username: export.username,
+        name: export.name,
+        surname: export.surname,
+        job_title: export.job_title,
+        company: export.company,
+        company_url: export.company_url,
+        profile_pic: export.profile_pic,
+        about_me: export.about_me,
+        about_role: export.about_role,
+        jira_url: export.jira_url,
+        jira_email: export.jira_email,
+        jira_api_token: None,
+        jira_project_key: export.jira_project_key,
+        notion_api_token: None,
+        notion_parent_page_id: export.notion_parent_page_id,
+        default_model: export.default_model,
+    }, pool).await
+}
+
+#[tauri::command]
+pub async fn get_decrypted_api_key(pool: State<'_, DbPool>) -> Result<Option<String>, String> {
+    let keychain_available = match keyring_get_api_key() {
+        Ok(Some(key)) => return Ok(Some(key)),
+        Ok(None) => true,
+        Err(_) => false,
+    };
+
+    let settings = get_settings(pool.clone()).await?;
+
+    if let Some(encrypted) = settings.api_key_encrypted {
+        let key = get_encryption_key()?;
+        let api_key = decrypt_string(&encrypted, &key)?;
+
+        // Migrate the legacy encrypted column into the keychain on first read
+        if keychain_available && keyring_set_api_key(&api_key).is_ok() {
+            let conn = get_db_connection(&pool)?;
+            conn.execute(
+                "UPDATE settings SET api_key_encrypted = NULL WHERE id = ?1",
+                params!["default"],
+            ).map_err(|e| format!("Failed to clear legacy API key: {}", e))?;
+        }
+
+        Ok(Some(api_key))
+    } else {
+        Ok(None)
+    }
+}
+
+#[tauri::command]
+pub async fn delete_api_key(pool: State<'_, DbPool>) -> Result<(), String> {
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
+
+    // Ignore keychain errors so the DB fallback can still be cleared
+    let _ = keyring_delete_api_key();
 
     conn.execute(
-        "UPDATE framework_outputs
-         SET name = ?1, generated_content = ?2, updated_at = ?3
-         WHERE id = ?4",
-        params![&name, &generated_content, &now, &id],
-    ).map_err(|e| format!("Failed to update framework output: {}", e))?;
+        "UPDATE settings SET api_key_encrypted = NULL, updated_at = ?1 WHERE id = ?2",
+        params![&now, "default"],
+    ).map_err(|e| format!("Failed to delete API key: {}", e))?;
 
-    let _ = commit_output(project_id, id.clone(), name.clone(), generated_content.clone(), format!("Update: {}", name), app.clone()).await;
+    Ok(())
+}
 
-    // Fetch the updated output
-    get_framework_output(id, app).await?
-        .ok_or_else(|| "Framework output not found after update".to_string())
+// Provider API keys. The single-key settings commands own the default provider's key
+// (keychain, else the encrypted settings column); other providers live in `api_keys`.
+// The single key is the one chat and embeddings send to OpenAI, so that is the provider it maps to.
+
+const DEFAULT_API_KEY_PROVIDER: &str = "openai";
+
+fn normalize_provider(provider: &str) -> Result<String, String> {
+    let provider = provider.trim().to_lowercase();
+    if provider.is_empty() {
+        return Err("Provider name cannot be empty".to_string());
+    }
+    Ok(provider)
 }
 
 #[tauri::command]
-pub async fn delete_framework_output(
-    id: String,
-    app: tauri::AppHandle,
+pub async fn set_provider_api_key(
+    provider: String,
+    key: String,
+    pool: State<'_, DbPool>,
 ) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
+    let provider = normalize_provider(&provider)?;
+    let key = key.trim().to_string();
+    if key.is_empty() {
+        return Err("API key cannot be empty".to_string());
+    }
+
+    let conn = get_db_connection(&pool)?;
+    let enc_key = get_encryption_key()?;
+    let now = Utc::now().timestamp();
+
+    if provider == DEFAULT_API_KEY_PROVIDER {
+        let column_value = if keyring_set_api_key(&key).is_ok() {
+            None
+        } else {
+            Some(encrypt_string(&key, &enc_key)?)
+        };
+        conn.execute(
+            "UPDATE settings SET api_key_encrypted = ?1, updated_at = ?2 WHERE id = ?3",
+            params![&column_value, &now, "default"],
+        ).map_err(|e| format!("Failed to save API key: {}", e))?;
+        return Ok(());
+    }
 
     conn.execute(
-        "DELETE FROM framework_outputs WHERE id = ?1",
-        params![&id],
-    ).map_err(|e| format!("Failed to delete framework output: {}", e))?;
+        "INSERT INTO api_keys (provider, key_encrypted, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?3)
+         ON CONFLICT(provider) DO UPDATE SET key_encrypted = excluded.key_encrypted, updated_at = excluded.updated_at",
+        params![&provider, &encrypt_string(&key, &enc_key)?, &now],
+    ).map_err(|e| format!("Failed to save API key: {}", e))?;
 
     Ok(())
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct CommandHistoryEntry {
-    pub id: String,
-    pub project_id: String,
-    pub command: String,
-    pub output: String,
-    pub exit_code: i32,
-    pub created_at: i64,
+#[tauri::command]
+pub async fn get_provider_api_key(provider: String, pool: State<'_, DbPool>) -> Result<Option<String>, String> {
+    let provider = normalize_provider(&provider)?;
+    if provider == DEFAULT_API_KEY_PROVIDER {
+        if let Some(key) = get_decrypted_api_key(pool.clone()).await? {
+            return Ok(Some(key));
+        }
+        // Falls through to a key saved under this provider before it became the default
+    }
+
+    let conn = get_db_connection(&pool)?;
+    let encrypted: Option<String> = conn.query_row(
+        "SELECT key_encrypted FROM api_keys WHERE provider = ?1",
+        params![&provider],
+        |row| row.get(0),
+    ).optional().map_err(|e| format!("Failed to read API key: {}", e))?;
+
+    match encrypted {
+        Some(encrypted) => {
+            let enc_key = get_encryption_key()?;
+            decrypt_string(&encrypted, &enc_key).map(Some)
+        }
+        None => Ok(None),
+    }
+}
+
+#[tauri::command]
+pub async fn list_configured_providers(pool: State<'_, DbPool>) -> Result<Vec<String>, String> {
+    let has_default = get_settings(pool.clone()).await?.has_api_key;
+
+    let conn = get_db_connection(&pool)?;
+    let mut stmt = conn.prepare("SELECT provider FROM api_keys ORDER BY provider ASC")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to list providers: {}", e))?;
+
+    let mut providers: Vec<String> = rows.collect::<Result<_, _>>()
+        .map_err(|e| format!("Failed to collect providers: {}", e))?;
+    if has_default && !providers.iter().any(|p| p == DEFAULT_API_KEY_PROVIDER) {
+        providers.push(DEFAULT_API_KEY_PROVIDER.to_string());
+        providers.sort();
+    }
+
+    Ok(providers)
+}
+
+#[tauri::command]
+pub async fn delete_provider_api_key(provider: String, pool: State<'_, DbPool>) -> Result<(), String> {
+    let provider = normalize_provider(&provider)?;
+    if provider == DEFAULT_API_KEY_PROVIDER {
+        delete_api_key(pool.clone()).await?;
+    }
+
+    let conn = get_db_connection(&pool)?;
+    conn.execute(
+        "DELETE FROM api_keys WHERE provider = ?1",
+        params![&provider],
+    ).map_err(|e| format!("Failed to delete API key: {}", e))?;
+
+    Ok(())
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct CommandResult {
-    pub output: String,
-    pub exit_code: i32,
-    pub cwd: String,
+pub struct ApiKeyValidation {
+    // "valid", "invalid" (rejected by the provider), "network_error", or "error" (unexpected response)
+    pub status: String,
+    pub http_status: Option<u16>,
+    pub message: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
-pub struct FileEntry {
-    pub name: String,
-    pub path: String,
-    pub is_dir: bool,
-    pub size: u64,
-    pub modified: i64,
-    pub extension: String,
+const API_KEY_VALIDATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Lists models with the key, which is free and needs only authentication. The key is never stored.
+#[tauri::command]
+pub async fn validate_api_key(provider: String, key: String) -> Result<ApiKeyValidation, String> {
+    let provider = normalize_provider(&provider)?;
+    let key = key.trim().to_string();
+    if key.is_empty() {
+        return Err("API key cannot be empty".to_string());
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(API_KEY_VALIDATION_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+
+    let request = match provider.as_str() {
+        "openai" => client.get("https://api.openai.com/v1/models").bearer_auth(&key),
+        "anthropic" => client.get("https://api.anthropic.com/v1/models")
+            .header("x-api-key", &key)
+            .header("anthropic-version", "2023-06-01"),
+        other => return Err(format!("Key validation is not supported for provider '{}'", other)),
+    };
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            return Ok(ApiKeyValidation {
+                status: "network_error".to_string(),
+                http_status: None,
+                message: Some(e.to_string()),
+            });
+        }
+    };
+
+    let http_status = response.status();
+    let status = if http_status.is_success() {
+        "valid"
+    } else if http_status == reqwest::StatusCode::UNAUTHORIZED || http_status == reqwest::StatusCode::FORBIDDEN {
+        "invalid"
+    } else {
+        "error"
+    };
+    let message = if http_status.is_success() {
+        None
+    } else {
+        Some(response.text().await.unwrap_or_else(|_| http_status.to_string()))
+    };
+
+    Ok(ApiKeyValidation {
+        status: status.to_string(),
+        http_status: Some(http_status.as_u16()),
+        message,
+    })
 }
 
+// Folder commands
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct FrameworkCategoryRow {
+pub struct Folder {
     pub id: String,
+    pub project_id: String,
+    pub parent_id: Option<String>,
     pub name: String,
-    pub description: String,
-    pub icon: String,
-    pub is_builtin: bool,
+    pub color: Option<String>,
     pub sort_order: i32,
     pub created_at: i64,
     pub updated_at: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct FrameworkDefRow {
-    pub id: String,
-    pub category: String,
-    pub name: String,
-    pub description: String,
-    pub icon: String,
-    pub example_output: String,
-    pub system_prompt: String,
-    pub guiding_questions: String,
-    pub supports_visuals: bool,
-    pub visual_instructions: Option<String>,
-    pub is_builtin: bool,
-    pub sort_order: i32,
-    pub created_at: i64,
-    pub updated_at: i64,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct SavedPromptRow {
-    pub id: String,
-    pub name: String,
-    pub description: String,
-    pub category: String,
-    pub prompt_text: String,
-    pub variables: String,
-    pub framework_id: Option<String>,
-    pub is_builtin: bool,
-    pub is_favorite: bool,
-    pub usage_count: i32,
-    pub sort_order: i32,
-    pub created_at: i64,
-    pub updated_at: i64,
+#[derive(Debug, Default, Deserialize)]
+pub struct FolderUpdate {
+    pub name: Option<String>,
+    // A missing field leaves the parent alone; an explicit null moves the folder to the root
+    #[serde(default, deserialize_with = "deserialize_present_option")]
+    pub parent_id: Option<Option<String>>,
+    pub color: Option<String>,
+    pub sort_order: Option<i32>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct FrameworkExportMeta {
-    pub r#type: String,
-    pub id: String,
-    pub name: String,
-    pub category: String,
-    pub description: String,
-    pub icon: String,
-    pub supports_visuals: bool,
-    pub visual_instructions: Option<String>,
-    pub exported_at: String,
-    pub export_version: i32,
+// Only called when the field is present, so null becomes Some(None) rather than None
+fn deserialize_present_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct PromptVariableExport {
-    pub name: String,
-    pub r#type: String,
-    pub label: Option<String>,
-    pub placeholder: Option<String>,
-    pub options: Option<Vec<String>>,
-    pub required: bool,
-    pub default_value: Option<String>,
-}
+#[tauri::command]
+pub async fn create_folder(
+    project_id: String,
+    name: String,
+    parent_id: Option<String>,
+    color: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<Folder, AppError> {
+    let conn = get_db_connection(&pool)?;
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().timestamp();
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct PromptExportMeta {
-    pub r#type: String,
-    pub id: String,
-    pub name: String,
-    pub description: String,
-    pub category: String,
-    pub framework_id: Option<String>,
-    pub variables: Vec<PromptVariableExport>,
-    pub exported_at: String,
-    pub export_version: i32,
-}
+    let folder = Folder {
+        id: id.clone(),
+        project_id: project_id.clone(),
+        parent_id: parent_id.clone(),
+        name: name.clone(),
+        color: color.clone(),
+        sort_order: 0,
+        created_at: now,
+        updated_at: now,
+    };
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct ImportPreview {
-    pub item_type: String,
-    pub id: String,
-    pub name: String,
-    pub category: String,
-    pub description: String,
-    pub already_exists: bool,
-    pub is_builtin_conflict: bool,
-}
+    conn.execute(
+        "INSERT INTO folders (id, project_id, parent_id, name, color, sort_order, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![&id, &project_id, &parent_id, &name, &color, &0, &now, &now],
+    ).map_err(|e| AppError::database("Failed to create folder", e))?;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct ImportResult {
-    pub success: bool,
-    pub item_type: String,
-    pub id: String,
-    pub name: String,
-    pub action: String,
-    pub error: Option<String>,
+    Ok(folder)
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct BatchExportResult {
-    pub filename: String,
-    pub content: String,
-}
+#[tauri::command]
+pub async fn list_folders(
+    project_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<Folder>, AppError> {
+    let conn = get_db_connection(&pool)?;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct WorkflowStepDef {
-    pub framework_id: String,
-    pub label: String,
-    pub prompt_template: String,
-    pub context_doc_ids: Vec<String>,
-    pub model: String,
-}
+    let mut stmt = conn.prepare(
+        "SELECT id, project_id, parent_id, name, color, sort_order, created_at, updated_at
+         FROM folders
+         WHERE project_id = ?1
+         ORDER BY sort_order ASC, name ASC"
+    ).map_err(|e| AppError::database("Failed to prepare statement", e))?;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Workflow {
-    pub id: String,
-    pub project_id: String,
-    pub name: String,
-    pub description: String,
-    pub steps: String,
-    pub is_template: bool,
-    pub created_at: i64,
-    pub updated_at: i64,
-}
+    let folders = stmt.query_map(params![&project_id], |row| {
+        Ok(Folder {
+            id: row.get(0)?,
+            project_id: row.get(1)?,
+            parent_id: row.get(2)?,
+            name: row.get(3)?,
+            color: row.get(4)?,
+            sort_order: row.get(5)?,
+            created_at: row.get(6)?,
+            updated_at: row.get(7)?,
+        })
+    }).map_err(|e| AppError::database("Failed to query folders", e))?;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct WorkflowRun {
-    pub id: String,
-    pub workflow_id: String,
-    pub project_id: String,
-    pub status: String,
-    pub started_at: Option<i64>,
-    pub completed_at: Option<i64>,
-    pub created_at: i64,
+    let result: Result<Vec<Folder>, _> = folders.collect();
+    result.map_err(|e| AppError::database("Failed to collect folders", e))
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct WorkflowRunStep {
-    pub id: String,
-    pub run_id: String,
-    pub step_index: i32,
-    pub framework_id: String,
-    pub status: String,
-    pub input_prompt: Option<String>,
-    pub output_content: Option<String>,
-    pub output_id: Option<String>,
-    pub error: Option<String>,
-    pub started_at: Option<i64>,
-    pub completed_at: Option<i64>,
+pub struct FolderNode {
+    #[serde(flatten)]
+    pub folder: Folder,
+    pub item_count: i64,
+    pub children: Vec<FolderNode>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct ProjectInsight {
-    pub id: String,
-    pub project_id: String,
-    pub insight_type: String,
-    pub title: String,
-    pub description: String,
-    pub priority: String,
-    pub framework_id: Option<String>,
-    pub is_dismissed: bool,
-    pub created_at: i64,
-}
+pub struct FolderTree {
+    pub folders: Vec<FolderNode>,
+    pub unfiled_count: i64,
+}
+
+fn build_folder_nodes(
+    parent_id: Option<&String>,
+    children_by_parent: &mut HashMap<Option<String>, Vec<Folder>>,
+    item_counts: &HashMap<Option<String>, i64>,
+) -> Vec<FolderNode> {
+    let children = children_by_parent.remove(&parent_id.cloned()).unwrap_or_default();
+    children.into_iter().map(|folder| {
+        let nested = build_folder_nodes(Some(&folder.id), children_by_parent, item_counts);
+        let item_count = item_counts.get(&Some(folder.id.clone())).copied().unwrap_or(0);
+        FolderNode { folder, item_count, children: nested }
+    }).collect()
+}
+
+// Every folder ends up in the tree: ones pointing at a missing parent, and ones caught in a
+// parent loop, are surfaced at the root rather than dropped
+fn assemble_folder_tree(folders: Vec<Folder>, item_counts: &HashMap<Option<String>, i64>) -> Vec<FolderNode> {
+    let known: std::collections::HashSet<String> = folders.iter().map(|f| f.id.clone()).collect();
+    let order: Vec<String> = folders.iter().map(|f| f.id.clone()).collect();
+    let mut children_by_parent: HashMap<Option<String>, Vec<Folder>> = HashMap::new();
+    for folder in folders {
+        let parent = folder.parent_id.clone().filter(|p| known.contains(p));
+        children_by_parent.entry(parent).or_default().push(folder);
+    }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct CommitInfo {
-    pub oid: String,
-    pub message: String,
-    pub author: String,
-    pub timestamp: i64,
+    let mut roots = build_folder_nodes(None, &mut children_by_parent, item_counts);
+
+    // Anything left is unreachable from the root; each loop is broken at its first folder in list order
+    for id in order {
+        let stranded = children_by_parent.values_mut().find_map(|siblings| {
+            siblings.iter().position(|f| f.id == id).map(|i| siblings.remove(i))
+        });
+        if let Some(folder) = stranded {
+            let children = build_folder_nodes(Some(&folder.id), &mut children_by_parent, item_counts);
+            let item_count = item_counts.get(&Some(folder.id.clone())).copied().unwrap_or(0);
+            roots.push(FolderNode { folder, item_count, children });
+        }
+    }
+    roots
 }
 
 #[tauri::command]
-pub async fn execute_shell_command(
+pub async fn get_folder_tree(
     project_id: String,
-    command: String,
-    app: tauri::AppHandle,
-) -> Result<CommandResult, String> {
-    use std::process::Command as StdCommand;
+    pool: State<'_, DbPool>,
+) -> Result<FolderTree, AppError> {
+    let folders = list_folders(project_id.clone(), pool.clone()).await?;
+    let conn = get_db_connection(&pool)?;
 
-    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
-    let cwd = {
-        let map = terminal_cwds().lock().map_err(|e| format!("Lock error: {}", e))?;
-        map.get(&project_id).cloned().unwrap_or_else(|| home_dir.clone())
-    };
-
-    let user_shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string());
-
-    let cwd_marker = "__PM_IDE_CWD_MARKER__";
-    let wrapped = format!(
-        "cd {} 2>/dev/null && {{ {}; }}; __ec=$?; printf '\\n{}'; pwd; exit $__ec",
-        shell_escape(&cwd),
-        &command,
-        cwd_marker
-    );
-
-    let output = StdCommand::new(&user_shell)
-        .arg("-l")
-        .arg("-i")
-        .arg("-c")
-        .arg(&wrapped)
-        .env("TERM", "xterm-256color")
-        .env("CLICOLOR", "1")
-        .env("CLICOLOR_FORCE", "1")
-        .env("LSCOLORS", "Gxfxcxdxbxegedabagacad")
-        .current_dir(&cwd)
-        .output()
-        .map_err(|e| format!("Failed to execute command: {}", e))?;
-
-    let stdout_raw = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-
-    let (user_output, new_cwd) = if let Some(idx) = stdout_raw.rfind(cwd_marker) {
-        let before = stdout_raw[..idx].trim_end().to_string();
-        let after = stdout_raw[idx + cwd_marker.len()..].trim().to_string();
-        let resolved_cwd = if after.is_empty() { cwd.clone() } else { after };
-        (before, resolved_cwd)
-    } else {
-        (stdout_raw, cwd.clone())
-    };
-
-    {
-        let mut map = terminal_cwds().lock().map_err(|e| format!("Lock error: {}", e))?;
-        map.insert(project_id.clone(), new_cwd.clone());
+    let mut stmt = conn.prepare(
+        "SELECT folder_id, COUNT(*) FROM (
+             SELECT folder_id FROM context_documents WHERE project_id = ?1 AND deleted_at IS NULL
+             UNION ALL
+             SELECT folder_id FROM framework_outputs WHERE project_id = ?1 AND deleted_at IS NULL
+         ) GROUP BY folder_id"
+    ).map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+    let counts = stmt.query_map(params![&project_id], |row| {
+        Ok((row.get::<_, Option<String>>(0)?, row.get::<_, i64>(1)?))
+    }).map_err(|e| AppError::database("Failed to count folder items", e))?;
+
+    let mut item_counts: HashMap<Option<String>, i64> = HashMap::new();
+    for count in counts {
+        let (folder_id, n) = count.map_err(|e| AppError::database("Failed to read folder count", e))?;
+        item_counts.insert(folder_id, n);
     }
 
-    let combined = if stderr.is_empty() {
-        user_output
-    } else if user_output.is_empty() {
-        stderr
-    } else {
-        format!("{}\n{}", user_output, stderr)
-    };
-    let exit_code = output.status.code().unwrap_or(-1);
-
-    let conn = get_db_connection(&app)?;
-    let id = Uuid::new_v4().to_string();
-    let now = Utc::now().timestamp();
-
-    conn.execute(
-        "INSERT INTO command_history (id, project_id, command, output, exit_code, created_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        params![&id, &project_id, &command, &combined, &exit_code, &now],
-    ).map_err(|e| format!("Failed to save command history: {}", e))?;
-
-    Ok(CommandResult {
-        output: combined,
-        exit_code,
-        cwd: new_cwd,
+    Ok(FolderTree {
+        folders: assemble_folder_tree(folders, &item_counts),
+        unfiled_count: item_counts.get(&None).copied().unwrap_or(0),
     })
 }
 
 #[tauri::command]
-pub async fn get_command_history(
-    project_id: String,
-    limit: Option<i32>,
-    app: tauri::AppHandle,
-) -> Result<Vec<CommandHistoryEntry>, String> {
-    let conn = get_db_connection(&app)?;
-    let limit = limit.unwrap_or(50);
+pub async fn get_folder(
+    id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Option<Folder>, AppError> {
+    let conn = get_db_connection(&pool)?;
 
     let mut stmt = conn.prepare(
-        "SELECT id, project_id, command, output, exit_code, created_at
-         FROM command_history
-         WHERE project_id = ?1
-         ORDER BY created_at DESC
-         LIMIT ?2"
-    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
+        "SELECT id, project_id, parent_id, name, color, sort_order, created_at, updated_at
+         FROM folders WHERE id = ?1"
+    ).map_err(|e| AppError::database("Failed to prepare statement", e))?;
 
-    let entries = stmt.query_map(params![&project_id, &limit], |row| {
-        Ok(CommandHistoryEntry {
+    let folder = stmt.query_row(params![&id], |row| {
+        Ok(Folder {
             id: row.get(0)?,
             project_id: row.get(1)?,
-            command: row.get(2)?,
-            output: row.get(3)?,
-            exit_code: row.get(4)?,
-            created_at: row.get(5)?,
+            parent_id: row.get(2)?,
+            name: row.get(3)?,
+            color: row.get(4)?,
+            sort_order: row.get(5)?,
+            created_at: row.get(6)?,
+            updated_at: row.get(7)?,
         })
-    }).map_err(|e| format!("Failed to query command history: {}", e))?;
-
-    let mut results = Vec::new();
-    for entry in entries {
-        results.push(entry.map_err(|e| format!("Failed to read command history entry: {}", e))?);
-    }
+    }).optional()
+        .map_err(|e| AppError::database("Failed to get folder", e))?;
 
-    results.reverse();
-    Ok(results)
+    Ok(folder)
 }
 
-#[tauri::command]
-pub async fn get_terminal_cwd(
-    project_id: String,
-) -> Result<String, String> {
-    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
-    let map = terminal_cwds().lock().map_err(|e| format!("Lock error: {}", e))?;
-    Ok(map.get(&project_id).cloned().unwrap_or(home_dir))
-}
+// Defensive cap on ancestor walks in case bad data has produced a parent loop
+const MAX_FOLDER_DEPTH: usize = 64;
 
+// Breadcrumb from the root ancestor down to (and including) the given folder
 #[tauri::command]
-pub async fn set_terminal_cwd(
-    project_id: String,
-    cwd: String,
-) -> Result<(), String> {
-    let path = std::path::Path::new(&cwd);
-    if !path.exists() || !path.is_dir() {
-        return Err(format!("Directory does not exist: {}", cwd));
-    }
-    let mut map = terminal_cwds().lock().map_err(|e| format!("Lock error: {}", e))?;
-    map.insert(project_id, cwd);
-    Ok(())
-}
+pub async fn get_folder_path(
+    folder_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<Folder>, AppError> {
+    let conn = get_db_connection(&pool)?;
 
-#[tauri::command]
-pub async fn complete_path(
-    project_id: String,
-    partial: String,
-) -> Result<Vec<String>, String> {
-    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
-    let cwd = {
-        let map = terminal_cwds().lock().map_err(|e| format!("Lock error: {}", e))?;
-        map.get(&project_id).cloned().unwrap_or_else(|| home_dir.clone())
-    };
+    let mut stmt = conn.prepare(
+        "SELECT id, project_id, parent_id, name, color, sort_order, created_at, updated_at
+         FROM folders WHERE id = ?1"
+    ).map_err(|e| AppError::database("Failed to prepare statement", e))?;
 
-    let expanded = if partial.starts_with('~') {
-        format!("{}{}", home_dir, &partial[1..])
-    } else {
-        partial.clone()
-    };
+    let mut path: Vec<Folder> = Vec::new();
+    let mut current = Some(folder_id);
 
-    let full_path = if std::path::Path::new(&expanded).is_absolute() {
-        expanded.clone()
-    } else {
-        format!("{}/{}", cwd, expanded)
-    };
+    while let Some(id) = current {
+        if path.iter().any(|f| f.id == id) {
+            return Err(AppError::Conflict(format!("Folder hierarchy contains a cycle at folder '{}'", id)));
+        }
+        if path.len() >= MAX_FOLDER_DEPTH {
+            return Err(AppError::Validation(format!(
+                "Folder hierarchy is deeper than {} levels", MAX_FOLDER_DEPTH
+            )));
+        }
 
-    let (dir, prefix) = if full_path.ends_with('/') {
-        (full_path.clone(), String::new())
-    } else {
-        let p = std::path::Path::new(&full_path);
-        let dir = p.parent().map(|d| d.to_string_lossy().to_string()).unwrap_or_else(|| "/".to_string());
-        let prefix = p.file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or_default();
-        (dir, prefix)
-    };
+        let folder = stmt.query_row(params![&id], |row| {
+            Ok(Folder {
+                id: row.get(0)?,
+                project_id: row.get(1)?,
+                parent_id: row.get(2)?,
+                name: row.get(3)?,
+                color: row.get(4)?,
+                sort_order: row.get(5)?,
+                created_at: row.get(6)?,
+                updated_at: row.get(7)?,
+            })
+        }).optional()
+            .map_err(|e| AppError::database("Failed to get folder", e))?;
 
-    let mut matches = Vec::new();
-    if let Ok(entries) = std::fs::read_dir(&dir) {
-        for entry in entries.flatten() {
-            let name = entry.file_name().to_string_lossy().to_string();
-            if name.starts_with(&prefix) {
-                let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
-                let suffix = if is_dir { "/" } else { " " };
-                if partial.contains('/') {
-                    let base = &partial[..partial.rfind('/').unwrap() + 1];
-                    matches.push(format!("{}{}{}", base, name, suffix));
-                } else {
-                    matches.push(format!("{}{}", name, suffix));
-                }
+        match folder {
+            Some(folder) => {
+                current = folder.parent_id.clone();
+                path.push(folder);
             }
+            // The requested folder itself is missing; a dangling parent just ends the walk
+            None if path.is_empty() => return Err(AppError::NotFound("Folder not found".to_string())),
+            None => break,
         }
     }
 
-    matches.sort();
-    Ok(matches.into_iter().take(20).collect())
+    path.reverse();
+    Ok(path)
 }
 
-// === Saved Prompts CRUD ===
+// Walks up from `new_parent_id` and reports whether `folder_id` is one of its ancestors
+fn would_create_cycle(conn: &Connection, folder_id: &str, new_parent_id: &str) -> Result<bool, AppError> {
+    let mut visited = std::collections::HashSet::new();
+    let mut current = Some(new_parent_id.to_string());
+
+    while let Some(ancestor) = current {
+        if ancestor == folder_id {
+            return Ok(true);
+        }
+        // An existing loop in the data; refuse rather than spin forever
+        if !visited.insert(ancestor.clone()) {
+            return Ok(true);
+        }
+        current = conn.query_row(
+            "SELECT parent_id FROM folders WHERE id = ?1",
+            params![&ancestor],
+            |row| row.get::<_, Option<String>>(0),
+        ).optional()
+            .map_err(|e| AppError::database("Failed to read folder ancestry", e))?
+            .flatten();
+    }
 
-const SAVED_PROMPT_COLUMNS: &str = "id, name, description, category, prompt_text, variables, framework_id, is_builtin, is_favorite, usage_count, sort_order, created_at, updated_at";
+    Ok(false)
+}
 
 #[tauri::command]
-pub async fn list_saved_prompts(
-    category: Option<String>,
-    framework_id: Option<String>,
-    app: tauri::AppHandle,
-) -> Result<Vec<SavedPromptRow>, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn update_folder(
+    id: String,
+    update: FolderUpdate,
+    pool: State<'_, DbPool>,
+) -> Result<Folder, AppError> {
+    let conn = get_db_connection(&pool)?;
+    apply_folder_update(&conn, &id, &update)?;
 
-    let (sql, param_values): (String, Vec<Box<dyn rusqlite::types::ToSql>>) = match (&category, &framework_id) {
-        (Some(cat), Some(fid)) => (
-            format!("SELECT {} FROM saved_prompts WHERE category = ?1 AND framework_id = ?2 ORDER BY sort_order, name", SAVED_PROMPT_COLUMNS),
-            vec![Box::new(cat.clone()) as Box<dyn rusqlite::types::ToSql>, Box::new(fid.clone())],
-        ),
-        (Some(cat), None) => (
-            format!("SELECT {} FROM saved_prompts WHERE category = ?1 ORDER BY sort_order, name", SAVED_PROMPT_COLUMNS),
-            vec![Box::new(cat.clone()) as Box<dyn rusqlite::types::ToSql>],
-        ),
-        (None, Some(fid)) => (
-            format!("SELECT {} FROM saved_prompts WHERE framework_id = ?1 ORDER BY sort_order, name", SAVED_PROMPT_COLUMNS),
-            vec![Box::new(fid.clone()) as Box<dyn rusqlite::types::ToSql>],
-        ),
-        (None, None) => (
-            format!("SELECT {} FROM saved_prompts ORDER BY sort_order, name", SAVED_PROMPT_COLUMNS),
-            vec![],
-        ),
-    };
+    get_folder(id, pool).await?
+        .ok_or_else(|| AppError::NotFound("Folder not found after update".to_string()))
+}
 
-    let params_ref: Vec<&dyn rusqlite::types::ToSql> = param_values.iter().map(|p| p.as_ref()).collect();
-    let mut stmt = conn.prepare(&sql).map_err(|e| format!("Failed to prepare query: {}", e))?;
-    let rows = stmt.query_map(params_ref.as_slice(), row_to_saved_prompt)
-        .map_err(|e| format!("Failed to list saved prompts: {}", e))?;
+fn apply_folder_update(conn: &Connection, id: &str, update: &FolderUpdate) -> Result<(), AppError> {
+    let now = Utc::now().timestamp();
 
-    let mut results = Vec::new();
-    for row in rows {
-        results.push(row.map_err(|e| format!("Failed to read saved prompt: {}", e))?);
+    if let Some(Some(ref new_parent)) = update.parent_id {
+        if new_parent == id {
+            return Err(AppError::Validation("A folder cannot be its own parent".to_string()));
+        }
+        if would_create_cycle(conn, id, new_parent)? {
+            return Err(AppError::Validation("Cannot move a folder into one of its own subfolders".to_string()));
+        }
     }
-    Ok(results)
-}
 
-#[tauri::command]
-pub async fn get_saved_prompt(id: String, app: tauri::AppHandle) -> Result<Option<SavedPromptRow>, String> {
-    let conn = get_db_connection(&app)?;
-    let result = conn.query_row(
-        &format!("SELECT {} FROM saved_prompts WHERE id = ?1", SAVED_PROMPT_COLUMNS),
-        params![&id],
-        row_to_saved_prompt,
-    ).optional().map_err(|e| format!("Failed to get saved prompt: {}", e))?;
-    Ok(result)
+    with_transaction(conn, |tx| {
+        tx.execute(
+            "UPDATE folders
+             SET name = COALESCE(?1, name),
+                 color = COALESCE(?2, color),
+                 sort_order = COALESCE(?3, sort_order),
+                 updated_at = ?4
+             WHERE id = ?5",
+            params![&update.name, &update.color, &update.sort_order, &now, id],
+        ).map_err(|e| AppError::database("Failed to update folder", e))?;
+
+        if let Some(ref parent_id) = update.parent_id {
+            tx.execute(
+                "UPDATE folders SET parent_id = ?1 WHERE id = ?2",
+                params![parent_id, id],
+            ).map_err(|e| AppError::database("Failed to update folder parent", e))?;
+        }
+        Ok::<_, AppError>(())
+    })
 }
 
-#[tauri::command]
-pub async fn create_saved_prompt(
-    name: String,
-    description: String,
-    category: String,
-    prompt_text: String,
-    variables: String,
-    framework_id: Option<String>,
-    app: tauri::AppHandle,
-) -> Result<SavedPromptRow, String> {
-    let conn = get_db_connection(&app)?;
-    let id = Uuid::new_v4().to_string();
-    let now = Utc::now().timestamp();
+// Breadth-first walk returning the folder and every folder beneath it; the visited set
+// keeps bad parent links from looping forever
+fn collect_folder_subtree(conn: &Connection, root_id: &str) -> Result<Vec<String>, AppError> {
+    let mut stmt = conn.prepare("SELECT id FROM folders WHERE parent_id = ?1")
+        .map_err(|e| AppError::database("Failed to prepare statement", e))?;
 
-    let max_sort: i32 = conn.query_row(
-        "SELECT COALESCE(MAX(sort_order), -1) FROM saved_prompts WHERE category = ?1",
-        params![&category],
-        |row| row.get(0),
-    ).unwrap_or(-1);
+    let mut visited: std::collections::HashSet<String> = std::collections::HashSet::new();
+    let mut ordered = Vec::new();
+    let mut queue = std::collections::VecDeque::from([root_id.to_string()]);
 
-    conn.execute(
-        "INSERT INTO saved_prompts (id, name, description, category, prompt_text, variables, framework_id, is_builtin, is_favorite, usage_count, sort_order, created_at, updated_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, 0, 0, ?8, ?9, ?10)",
-        params![&id, &name, &description, &category, &prompt_text, &variables, &framework_id, max_sort + 1, &now, &now],
-    ).map_err(|e| format!("Failed to create saved prompt: {}", e))?;
+    while let Some(id) = queue.pop_front() {
+        if !visited.insert(id.clone()) {
+            continue;
+        }
+        let children = stmt.query_map(params![&id], |row| row.get::<_, String>(0))
+            .map_err(|e| AppError::database("Failed to query child folders", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::database("Failed to collect child folders", e))?;
+        queue.extend(children);
+        ordered.push(id);
+    }
 
-    get_saved_prompt(id, app).await?.ok_or_else(|| "Failed to retrieve created prompt".to_string())
+    Ok(ordered)
 }
 
+// mode "promote" (default) moves the folder's items and subfolders up to its parent;
+// mode "cascade" deletes the whole subtree including every item in it
 #[tauri::command]
-pub async fn update_saved_prompt(
+pub async fn delete_folder(
     id: String,
-    name: Option<String>,
-    description: Option<String>,
-    category: Option<String>,
-    prompt_text: Option<String>,
-    variables: Option<String>,
-    framework_id: Option<Option<String>>,
-    is_favorite: Option<bool>,
-    app: tauri::AppHandle,
-) -> Result<SavedPromptRow, String> {
-    let conn = get_db_connection(&app)?;
-    let now = Utc::now().timestamp();
-
-    conn.execute(
-        "UPDATE saved_prompts SET
-            name = COALESCE(?1, name),
-            description = COALESCE(?2, description),
-            category = COALESCE(?3, category),
-            prompt_text = COALESCE(?4, prompt_text),
-            variables = COALESCE(?5, variables),
-            updated_at = ?6
-         WHERE id = ?7",
-        params![&name, &description, &category, &prompt_text, &variables, &now, &id],
-    ).map_err(|e| format!("Failed to update saved prompt: {}", e))?;
-
-    if let Some(fid) = framework_id {
-        conn.execute(
-            "UPDATE saved_prompts SET framework_id = ?1 WHERE id = ?2",
-            params![&fid, &id],
-        ).map_err(|e| format!("Failed to update prompt framework_id: {}", e))?;
+    mode: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<(), AppError> {
+    let mode = mode.unwrap_or_else(|| "promote".to_string());
+    if mode != "promote" && mode != "cascade" {
+        return Err(AppError::Validation(format!("Unknown delete mode: {}", mode)));
     }
 
-    if let Some(fav) = is_favorite {
-        conn.execute(
-            "UPDATE saved_prompts SET is_favorite = ?1 WHERE id = ?2",
-            params![fav as i32, &id],
-        ).map_err(|e| format!("Failed to update prompt favorite: {}", e))?;
-    }
+    let conn = get_db_connection(&pool)?;
+    remove_folder(&conn, &id, &mode)
+}
+
+fn remove_folder(conn: &Connection, id: &str, mode: &str) -> Result<(), AppError> {
+    with_transaction(conn, |tx| {
+        let parent_id: Option<String> = tx.query_row(
+            "SELECT parent_id FROM folders WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        ).optional().map_err(|e| AppError::database("Failed to find folder", e))?
+            .ok_or_else(|| AppError::NotFound("Folder not found".to_string()))?;
+
+        if mode == "promote" {
+            tx.execute(
+                "UPDATE context_documents SET folder_id = ?1 WHERE folder_id = ?2",
+                params![&parent_id, id],
+            ).map_err(|e| AppError::database("Failed to move context documents", e))?;
+
+            tx.execute(
+                "UPDATE framework_outputs SET folder_id = ?1 WHERE folder_id = ?2",
+                params![&parent_id, id],
+            ).map_err(|e| AppError::database("Failed to move framework outputs", e))?;
+
+            tx.execute(
+                "UPDATE folders SET parent_id = ?1 WHERE parent_id = ?2",
+                params![&parent_id, id],
+            ).map_err(|e| AppError::database("Failed to move subfolders", e))?;
+
+            tx.execute(
+                "DELETE FROM folders WHERE id = ?1",
+                params![id],
+            ).map_err(|e| AppError::database("Failed to delete folder", e))?;
+        } else {
+            let subtree = collect_folder_subtree(tx, id)?;
+            let now = Utc::now().timestamp();
+
+            // Contents go to the trash; restore_item unfiles them since their folder is gone
+            for folder_id in &subtree {
+                tx.execute(
+                    "UPDATE context_documents SET deleted_at = ?1 WHERE folder_id = ?2 AND deleted_at IS NULL",
+                    params![&now, folder_id],
+                ).map_err(|e| AppError::database("Failed to trash context documents", e))?;
+
+                tx.execute(
+                    "UPDATE framework_outputs SET deleted_at = ?1 WHERE folder_id = ?2 AND deleted_at IS NULL",
+                    params![&now, folder_id],
+                ).map_err(|e| AppError::database("Failed to trash framework outputs", e))?;
+            }
+
+            // Deepest folders first so nothing relies on the parent_id cascade
+            for folder_id in subtree.iter().rev() {
+                tx.execute(
+                    "DELETE FROM folders WHERE id = ?1",
+                    params![folder_id],
+                ).map_err(|e| AppError::database("Failed to delete folder", e))?;
+            }
+        }
 
-    get_saved_prompt(id, app).await?.ok_or_else(|| "Prompt not found after update".to_string())
+        Ok(())
+    })
 }
 
+// Total row count behind a list view, so the UI can render "showing X of N" without loading everything.
+// Messages are scoped by conversation rather than project and require conversation_id.
 #[tauri::command]
-pub async fn delete_saved_prompt(id: String, app: tauri::AppHandle) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
+pub async fn count_items(
+    project_id: String,
+    item_type: String,
+    conversation_id: Option<String>,
+    include_archived: Option<bool>,
+    pool: State<'_, DbPool>,
+) -> Result<i64, String> {
+    let conn = get_db_connection(&pool)?;
+
+    let count = match item_type.as_str() {
+        "conversations" => conn.query_row(
+            "SELECT COUNT(*) FROM conversations WHERE project_id = ?1 AND (?2 OR is_archived = 0)",
+            params![&project_id, &include_archived.unwrap_or(false)],
+            |row| row.get(0),
+        ),
+        "context_doc" => conn.query_row(
+            "SELECT COUNT(*) FROM context_documents WHERE project_id = ?1 AND deleted_at IS NULL",
+            params![&project_id],
+            |row| row.get(0),
+        ),
+        "framework_output" => conn.query_row(
+            "SELECT COUNT(*) FROM framework_outputs WHERE project_id = ?1 AND deleted_at IS NULL",
+            params![&project_id],
+            |row| row.get(0),
+        ),
+        "message" => {
+            let conversation_id = conversation_id
+                .ok_or_else(|| "conversation_id is required to count messages".to_string())?;
+            conn.query_row(
+                "SELECT COUNT(*) FROM messages WHERE conversation_id = ?1",
+                params![&conversation_id],
+                |row| row.get(0),
+            )
+        },
+        _ => return Err(format!("Unknown item type: {}", item_type)),
+    };
 
-    let is_builtin: i32 = conn.query_row(
-        "SELECT is_builtin FROM saved_prompts WHERE id = ?1", params![&id], |row| row.get(0)
-    ).map_err(|e| format!("Prompt not found: {}", e))?;
+    count.map_err(|e| format!("Failed to count {}: {}", item_type, e))
+}
 
-    if is_builtin != 0 {
-        return Err("Cannot delete built-in prompts".to_string());
+#[tauri::command]
+pub async fn move_item_to_folder(
+    item_id: String,
+    item_type: String,
+    folder_id: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<(), AppError> {
+    let conn = get_db_connection(&pool)?;
+
+    match item_type.as_str() {
+        "context_doc" => {
+            conn.execute(
+                "UPDATE context_documents SET folder_id = ?1 WHERE id = ?2",
+                params![&folder_id, &item_id],
+            ).map_err(|e| AppError::database("Failed to move context document", e))?;
+        },
+        "framework_output" => {
+            conn.execute(
+                "UPDATE framework_outputs SET folder_id = ?1 WHERE id = ?2",
+                params![&folder_id, &item_id],
+            ).map_err(|e| AppError::database("Failed to move framework output", e))?;
+        },
+        _ => return Err(AppError::Validation(format!("Unknown item type: {}", item_type))),
     }
 
-    conn.execute("DELETE FROM saved_prompts WHERE id = ?1", params![&id])
-        .map_err(|e| format!("Failed to delete saved prompt: {}", e))?;
     Ok(())
 }
 
-#[tauri::command]
-pub async fn search_saved_prompts(query: String, app: tauri::AppHandle) -> Result<Vec<SavedPromptRow>, String> {
-    let conn = get_db_connection(&app)?;
-    let search = format!("%{}%", query);
+// Maps a library item_type to its table, rejecting anything outside the known set
+fn library_item_table(item_type: &str) -> Result<&'static str, AppError> {
+    match item_type {
+        "context_doc" => Ok("context_documents"),
+        "framework_output" => Ok("framework_outputs"),
+        _ => Err(AppError::Validation(format!("Unknown item type: {}", item_type))),
+    }
+}
 
-    let mut stmt = conn.prepare(
-        &format!("SELECT {} FROM saved_prompts WHERE name LIKE ?1 OR description LIKE ?1 OR prompt_text LIKE ?1 ORDER BY usage_count DESC, name", SAVED_PROMPT_COLUMNS)
-    ).map_err(|e| format!("Failed to prepare search: {}", e))?;
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
 
-    let rows = stmt.query_map(params![&search], row_to_saved_prompt)
-        .map_err(|e| format!("Failed to search saved prompts: {}", e))?;
+// Checks an item's stored tags JSON against a filter, comparing normalized tags. An empty filter
+// matches everything; malformed tags JSON counts as no tags.
+fn tags_match(tags_json: &str, wanted: &[String], match_all: bool) -> bool {
+    if wanted.is_empty() {
+        return true;
+    }
+    let tags = normalize_tags(serde_json::from_str(tags_json).unwrap_or_default());
+    if match_all {
+        wanted.iter().all(|tag| tags.contains(tag))
+    } else {
+        wanted.iter().any(|tag| tags.contains(tag))
+    }
+}
 
-    let mut results = Vec::new();
-    for row in rows {
-        results.push(row.map_err(|e| format!("Failed to read prompt: {}", e))?);
+#[tauri::command]
+pub async fn set_item_tags(
+    item_id: String,
+    item_type: String,
+    tags: Vec<String>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<String>, String> {
+    let table = library_item_table(&item_type)?;
+    let tags = normalize_tags(tags);
+    let tags_json = serde_json::to_string(&tags)
+        .map_err(|e| format!("Failed to serialize tags: {}", e))?;
+
+    let conn = get_db_connection(&pool)?;
+    let rows = conn.execute(
+        &format!("UPDATE {} SET tags = ?1 WHERE id = ?2", table),
+        params![&tags_json, &item_id],
+    ).map_err(|e| format!("Failed to set tags: {}", e))?;
+
+    if rows == 0 {
+        return Err("Item not found".to_string());
     }
-    Ok(results)
+    Ok(tags)
 }
 
 #[tauri::command]
-pub async fn duplicate_saved_prompt(id: String, new_name: String, app: tauri::AppHandle) -> Result<SavedPromptRow, String> {
-    let original = get_saved_prompt(id, app.clone()).await?
-        .ok_or_else(|| "Prompt not found".to_string())?;
+pub async fn list_all_tags(project_id: String, pool: State<'_, DbPool>) -> Result<Vec<String>, String> {
+    let conn = get_db_connection(&pool)?;
 
-    create_saved_prompt(
-        new_name,
-        original.description,
-        original.category,
-        original.prompt_text,
-        original.variables,
-        original.framework_id,
-        app,
-    ).await
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT tag FROM (
+             SELECT j.value AS tag
+             FROM context_documents d, json_each(CASE WHEN json_valid(d.tags) THEN d.tags ELSE '[]' END) j
+             WHERE d.project_id = ?1 AND d.deleted_at IS NULL
+             UNION
+             SELECT j.value AS tag
+             FROM framework_outputs o, json_each(CASE WHEN json_valid(o.tags) THEN o.tags ELSE '[]' END) j
+             WHERE o.project_id = ?1 AND o.deleted_at IS NULL
+         )
+         WHERE typeof(tag) = 'text' AND tag != ''
+         ORDER BY tag ASC"
+    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let tags = stmt.query_map(params![&project_id], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to list tags: {}", e))?;
+
+    let result: Result<Vec<String>, _> = tags.collect();
+    result.map_err(|e| format!("Failed to collect tags: {}", e))
 }
 
 #[tauri::command]
-pub async fn increment_prompt_usage(id: String, app: tauri::AppHandle) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
-    let now = Utc::now().timestamp();
+pub async fn reorder_folders(
+    parent_id: Option<String>,
+    ordered_ids: Vec<String>,
+    pool: State<'_, DbPool>,
+) -> Result<(), AppError> {
+    let conn = get_db_connection(&pool)?;
+    with_transaction(&conn, |tx| {
+        let mut project: Option<String> = None;
+        for (index, id) in ordered_ids.iter().enumerate() {
+            let folder_project: String = tx.query_row(
+                "SELECT project_id FROM folders WHERE id = ?1 AND parent_id IS ?2",
+                params![id, &parent_id],
+                |row| row.get(0),
+            ).optional().map_err(|e| AppError::database("Failed to look up folder", e))?
+                .ok_or_else(|| AppError::Validation(format!("Folder '{}' is not in the given parent", id)))?;
+
+            // Root folders of different projects share a NULL parent, so also pin the project
+            match &project {
+                Some(p) if *p != folder_project => {
+                    return Err(AppError::Validation("Folders to reorder belong to different projects".to_string()));
+                }
+                _ => project = Some(folder_project),
+            }
 
-    conn.execute(
-        "UPDATE saved_prompts SET usage_count = usage_count + 1, updated_at = ?1 WHERE id = ?2",
-        params![&now, &id],
-    ).map_err(|e| format!("Failed to increment prompt usage: {}", e))?;
-    Ok(())
+            tx.execute(
+                "UPDATE folders SET sort_order = ?1 WHERE id = ?2",
+                params![&(index as i32), id],
+            ).map_err(|e| AppError::database("Failed to reorder folders", e))?;
+        }
+
+        Ok(())
+    })
 }
 
-// === Phase 6: Import/Export Helpers ===
+#[tauri::command]
+pub async fn reorder_items(
+    folder_id: Option<String>,
+    ordered: Vec<(String, String)>,
+    pool: State<'_, DbPool>,
+) -> Result<(), String> {
+    let targets = ordered.iter()
+        .map(|(id, item_type)| library_item_table(item_type).map(|table| (id, table)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let conn = get_db_connection(&pool)?;
+    with_transaction(&conn, |tx| {
+        let mut project: Option<String> = None;
+        for (index, (id, table)) in targets.into_iter().enumerate() {
+            let item_project: String = tx.query_row(
+                &format!("SELECT project_id FROM {} WHERE id = ?1 AND folder_id IS ?2", table),
+                params![id, &folder_id],
+                |row| row.get(0),
+            ).optional().map_err(|e| format!("Failed to look up item: {}", e))?
+                .ok_or_else(|| format!("Item '{}' is not in the given folder", id))?;
+
+            match &project {
+                Some(p) if *p != item_project => {
+                    return Err("Items to reorder belong to different projects".to_string());
+                }
+                _ => project = Some(item_project),
+            }
 
-fn parse_yaml_frontmatter(md: &str) -> Result<(String, String), String> {
-    let trimmed = md.trim();
-    if !trimmed.starts_with("---") {
-        return Err("File must start with YAML front matter (---)".to_string());
-    }
-    let after_first = &trimmed[3..];
-    let end_idx = after_first.find("\n---")
-        .ok_or("Missing closing --- for YAML front matter")?;
-    let yaml_str = after_first[..end_idx].trim().to_string();
-    let body = after_first[end_idx + 4..].trim().to_string();
-    Ok((yaml_str, body))
+            tx.execute(
+                &format!("UPDATE {} SET sort_order = ?1 WHERE id = ?2", table),
+                params![&(index as i32), id],
+            ).map_err(|e| format!("Failed to reorder items: {}", e))?;
+        }
+
+        Ok(())
+    })
 }
 
-fn framework_to_markdown(fw: &FrameworkDefRow) -> Result<String, String> {
-    let meta = FrameworkExportMeta {
-        r#type: "framework".to_string(),
-        id: fw.id.clone(),
-        name: fw.name.clone(),
-        category: fw.category.clone(),
-        description: fw.description.clone(),
-        icon: fw.icon.clone(),
-        supports_visuals: fw.supports_visuals,
-        visual_instructions: fw.visual_instructions.clone(),
-        exported_at: Utc::now().to_rfc3339(),
-        export_version: 1,
-    };
-    let yaml = serde_yaml::to_string(&meta)
-        .map_err(|e| format!("Failed to serialize YAML: {}", e))?;
+#[tauri::command]
+pub async fn move_items_to_folder(
+    items: Vec<(String, String)>,
+    folder_id: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<usize, AppError> {
+    // Validate the whole batch up front so an unknown type fails before anything is written
+    let targets = items.iter()
+        .map(|(id, item_type)| library_item_table(item_type).map(|table| (id, table)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let conn = get_db_connection(&pool)?;
+    with_transaction(&conn, |tx| {
+        let mut affected = 0;
+        for (id, table) in targets {
+            affected += tx.execute(
+                &format!("UPDATE {} SET folder_id = ?1 WHERE id = ?2", table),
+                params![&folder_id, id],
+            ).map_err(|e| AppError::database("Failed to move item", e))?;
+        }
 
-    let questions: Vec<String> = serde_json::from_str(&fw.guiding_questions)
-        .unwrap_or_default();
-    let questions_md: String = questions.iter()
-        .enumerate()
-        .map(|(i, q)| format!("{}. {}", i + 1, q))
-        .collect::<Vec<_>>()
-        .join("\n");
+        Ok(affected)
+    })
+}
 
-    Ok(format!(
-        "---\n{}---\n\n# System Prompt\n\n{}\n\n# Guiding Questions\n\n{}\n\n# Example Output\n\n{}",
-        yaml,
-        fw.system_prompt,
-        questions_md,
-        fw.example_output,
-    ))
+#[tauri::command]
+pub async fn delete_items(
+    items: Vec<(String, String)>,
+    pool: State<'_, DbPool>,
+) -> Result<usize, String> {
+    let targets = items.iter()
+        .map(|(id, item_type)| library_item_table(item_type).map(|table| (id, table)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
+    with_transaction(&conn, |tx| {
+        // Moves items to the trash, same as the single-item delete commands
+        let mut affected = 0;
+        for (id, table) in targets {
+            affected += tx.execute(
+                &format!("UPDATE {} SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL", table),
+                params![&now, id],
+            ).map_err(|e| format!("Failed to delete item: {}", e))?;
+        }
+
+        Ok(affected)
+    })
 }
 
-fn markdown_to_framework_parts(body: &str) -> Result<(String, Vec<String>, String), String> {
-    let system_prompt_start = body.find("# System Prompt")
-        .ok_or("Missing '# System Prompt' section")?;
-    let questions_start = body.find("# Guiding Questions")
-        .ok_or("Missing '# Guiding Questions' section")?;
-    let example_start = body.find("# Example Output")
-        .ok_or("Missing '# Example Output' section")?;
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub id: String,
+    pub name: String,
+    pub item_type: String,
+    pub folder_id: Option<String>,
+    pub category: Option<String>,
+    pub doc_type: Option<String>,
+    pub is_favorite: bool,
+    pub created_at: i64,
+}
 
-    let system_prompt = body[system_prompt_start + 15..questions_start].trim().to_string();
-    let questions_section = body[questions_start + 19..example_start].trim();
-    let example_output = body[example_start + 16..].trim().to_string();
+#[tauri::command]
+pub async fn search_project_items(
+    project_id: String,
+    query: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<SearchResult>, String> {
+    let conn = get_db_connection(&pool)?;
+    let search = format!("%{}%", query);
 
-    let questions: Vec<String> = questions_section
-        .lines()
-        .filter(|l| !l.trim().is_empty())
-        .map(|l| {
-            let trimmed = l.trim();
-            if let Some(pos) = trimmed.find(". ") {
-                if trimmed[..pos].chars().all(|c| c.is_ascii_digit()) {
-                    return trimmed[pos + 2..].to_string();
-                }
-            }
-            if trimmed.starts_with("- ") {
-                return trimmed[2..].to_string();
-            }
-            trimmed.to_string()
+    let mut stmt = conn.prepare(
+        "SELECT id, name, 'context_doc' as item_type, folder_id, NULL as category, type as doc_type, is_favorite, created_at
+         FROM context_documents WHERE project_id = ?1 AND deleted_at IS NULL AND (name LIKE ?2 OR tags LIKE ?2)
+         UNION ALL
+         SELECT id, name, 'framework_output' as item_type, folder_id, category, NULL as doc_type, is_favorite, created_at
+         FROM framework_outputs WHERE project_id = ?1 AND deleted_at IS NULL AND (name LIKE ?2 OR tags LIKE ?2)
+         ORDER BY name ASC"
+    ).map_err(|e| format!("Failed to prepare search: {}", e))?;
+
+    let results = stmt.query_map(params![&project_id, &search], |row| {
+        Ok(SearchResult {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            item_type: row.get(2)?,
+            folder_id: row.get(3)?,
+            category: row.get(4)?,
+            doc_type: row.get(5)?,
+            is_favorite: row.get::<_, i32>(6)? != 0,
+            created_at: row.get(7)?,
         })
-        .collect();
+    }).map_err(|e| format!("Failed to search: {}", e))?;
 
-    Ok((system_prompt, questions, example_output))
+    let result: Result<Vec<SearchResult>, _> = results.collect();
+    result.map_err(|e| format!("Failed to collect search results: {}", e))
 }
 
-fn prompt_to_markdown(prompt: &SavedPromptRow) -> Result<String, String> {
-    let variables: Vec<PromptVariableExport> = serde_json::from_str(&prompt.variables)
-        .unwrap_or_default();
-    let meta = PromptExportMeta {
-        r#type: "prompt".to_string(),
-        id: prompt.id.clone(),
-        name: prompt.name.clone(),
-        description: prompt.description.clone(),
-        category: prompt.category.clone(),
-        framework_id: prompt.framework_id.clone(),
-        variables,
-        exported_at: Utc::now().to_rfc3339(),
-        export_version: 1,
-    };
-    let yaml = serde_yaml::to_string(&meta)
-        .map_err(|e| format!("Failed to serialize YAML: {}", e))?;
+// Starred sidebar view. Documents have no updated_at, so their created_at stands in for ordering.
+#[tauri::command]
+pub async fn list_favorites(
+    project_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<SearchResult>, String> {
+    let conn = get_db_connection(&pool)?;
 
-    Ok(format!(
-        "---\n{}---\n\n# Prompt Text\n\n{}",
-        yaml,
-        prompt.prompt_text,
-    ))
-}
+    let mut stmt = conn.prepare(
+        "SELECT id, name, 'context_doc' as item_type, folder_id, NULL as category, type as doc_type, is_favorite, created_at, created_at as updated_at
+         FROM context_documents WHERE project_id = ?1 AND is_favorite = 1 AND deleted_at IS NULL
+         UNION ALL
+         SELECT id, name, 'framework_output' as item_type, folder_id, category, NULL as doc_type, is_favorite, created_at, updated_at
+         FROM framework_outputs WHERE project_id = ?1 AND is_favorite = 1 AND deleted_at IS NULL
+         ORDER BY updated_at DESC, name ASC"
+    ).map_err(|e| format!("Failed to prepare favorites query: {}", e))?;
 
-fn sanitize_filename(name: &str) -> String {
-    name.to_lowercase()
-        .chars()
-        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else if c == ' ' { '-' } else { '-' })
-        .collect::<String>()
-        .replace("--", "-")
-        .trim_matches('-')
-        .to_string()
+    let results = stmt.query_map(params![&project_id], |row| {
+        Ok(SearchResult {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            item_type: row.get(2)?,
+            folder_id: row.get(3)?,
+            category: row.get(4)?,
+            doc_type: row.get(5)?,
+            is_favorite: row.get::<_, i32>(6)? != 0,
+            created_at: row.get(7)?,
+        })
+    }).map_err(|e| format!("Failed to list favorites: {}", e))?;
+
+    let result: Result<Vec<SearchResult>, _> = results.collect();
+    result.map_err(|e| format!("Failed to collect favorites: {}", e))
 }
 
-// === Phase 6: Export Commands ===
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MessageSearchResult {
+    pub message_id: String,
+    pub conversation_id: String,
+    pub conversation_title: Option<String>,
+    pub role: String,
+    pub snippet: String,
+    pub rank: f64,
+    pub created_at: i64,
+}
 
-#[tauri::command]
-pub async fn export_framework(id: String, app: tauri::AppHandle) -> Result<String, String> {
-    let conn = get_db_connection(&app)?;
-    let fw = conn.query_row(
-        "SELECT id, category, name, description, icon, example_output, system_prompt, guiding_questions, supports_visuals, visual_instructions, is_builtin, sort_order, created_at, updated_at FROM framework_defs WHERE id = ?1",
-        params![&id],
-        |row| {
-            Ok(FrameworkDefRow {
-                id: row.get(0)?,
-                category: row.get(1)?,
-                name: row.get(2)?,
-                description: row.get(3)?,
-                icon: row.get(4)?,
-                example_output: row.get(5)?,
-                system_prompt: row.get(6)?,
-                guiding_questions: row.get(7)?,
-                supports_visuals: row.get(8)?,
-                visual_instructions: row.get(9)?,
-                is_builtin: row.get(10)?,
-                sort_order: row.get(11)?,
-                created_at: row.get(12)?,
-                updated_at: row.get(13)?,
-            })
-        },
-    ).map_err(|e| format!("Framework not found: {}", e))?;
-    framework_to_markdown(&fw)
+// Quote each term so user input can't trip FTS5 query syntax (hyphens, colons, quotes)
+fn fts_match_query(query: &str) -> String {
+    query.split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 #[tauri::command]
-pub async fn export_frameworks_batch(ids: Vec<String>, app: tauri::AppHandle) -> Result<Vec<BatchExportResult>, String> {
-    let conn = get_db_connection(&app)?;
-    let mut results = Vec::new();
-    for id in &ids {
-        let fw = conn.query_row(
-            "SELECT id, category, name, description, icon, example_output, system_prompt, guiding_questions, supports_visuals, visual_instructions, is_builtin, sort_order, created_at, updated_at FROM framework_defs WHERE id = ?1",
-            params![id],
-            |row| {
-                Ok(FrameworkDefRow {
-                    id: row.get(0)?,
-                    category: row.get(1)?,
-                    name: row.get(2)?,
-                    description: row.get(3)?,
-                    icon: row.get(4)?,
-                    example_output: row.get(5)?,
-                    system_prompt: row.get(6)?,
-                    guiding_questions: row.get(7)?,
-                    supports_visuals: row.get(8)?,
-                    visual_instructions: row.get(9)?,
-                    is_builtin: row.get(10)?,
-                    sort_order: row.get(11)?,
-                    created_at: row.get(12)?,
-                    updated_at: row.get(13)?,
-                })
-            },
-        ).map_err(|e| format!("Framework {} not found: {}", id, e))?;
-        let content = framework_to_markdown(&fw)?;
-        let filename = format!("{}.md", sanitize_filename(&fw.name));
-        results.push(BatchExportResult { filename, content });
+pub async fn search_messages(
+    project_id: String,
+    query: String,
+    limit: Option<i32>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<MessageSearchResult>, String> {
+    let match_query = fts_match_query(&query);
+    if match_query.is_empty() {
+        return Ok(Vec::new());
     }
-    Ok(results)
-}
 
-#[tauri::command]
-pub async fn export_all_frameworks(app: tauri::AppHandle) -> Result<Vec<BatchExportResult>, String> {
-    let conn = get_db_connection(&app)?;
-    let mut stmt = conn.prepare(
-        "SELECT id, category, name, description, icon, example_output, system_prompt, guiding_questions, supports_visuals, visual_instructions, is_builtin, sort_order, created_at, updated_at FROM framework_defs ORDER BY sort_order"
-    ).map_err(|e| format!("Failed to query frameworks: {}", e))?;
+    let conn = get_db_connection(&pool)?;
+    let limit = limit.unwrap_or(50);
 
-    let frameworks: Vec<FrameworkDefRow> = stmt.query_map([], |row| {
-        Ok(FrameworkDefRow {
-            id: row.get(0)?,
-            category: row.get(1)?,
-            name: row.get(2)?,
-            description: row.get(3)?,
-            icon: row.get(4)?,
-            example_output: row.get(5)?,
-            system_prompt: row.get(6)?,
-            guiding_questions: row.get(7)?,
-            supports_visuals: row.get(8)?,
-            visual_instructions: row.get(9)?,
-            is_builtin: row.get(10)?,
-            sort_order: row.get(11)?,
-            created_at: row.get(12)?,
-            updated_at: row.get(13)?,
+    let mut stmt = conn.prepare(
+        "SELECT m.id, m.conversation_id, c.title, m.role,
+                snippet(messages_fts, 0, '<mark>', '</mark>', '…', 16),
+                bm25(messages_fts), m.created_at
+         FROM messages_fts
+         JOIN messages m ON m.rowid = messages_fts.rowid
+         JOIN conversations c ON c.id = m.conversation_id
+         WHERE messages_fts MATCH ?1 AND c.project_id = ?2
+         ORDER BY bm25(messages_fts) ASC
+         LIMIT ?3"
+    ).map_err(|e| format!("Failed to prepare message search: {}", e))?;
+
+    let results = stmt.query_map(params![&match_query, &project_id, &limit], |row| {
+        Ok(MessageSearchResult {
+            message_id: row.get(0)?,
+            conversation_id: row.get(1)?,
+            conversation_title: {
+                let title: Option<String> = row.get(2)?;
+                title.filter(|t| !t.is_empty())
+            },
+            role: row.get(3)?,
+            snippet: row.get(4)?,
+            rank: row.get(5)?,
+            created_at: row.get(6)?,
         })
-    }).map_err(|e| format!("Query error: {}", e))?
-    .filter_map(|r| r.ok())
-    .collect();
+    }).map_err(|e| format!("Failed to search messages: {}", e))?;
 
-    let mut results = Vec::new();
-    for fw in &frameworks {
-        let content = framework_to_markdown(fw)?;
-        let filename = format!("{}.md", sanitize_filename(&fw.name));
-        results.push(BatchExportResult { filename, content });
+    let result: Result<Vec<MessageSearchResult>, _> = results.collect();
+    result.map_err(|e| format!("Failed to collect message search results: {}", e))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GlobalSearchResult {
+    // One of: conversation, message, context_doc, framework_output, saved_prompt, framework_def
+    pub kind: String,
+    pub id: String,
+    pub title: String,
+    pub snippet: Option<String>,
+    pub project_id: Option<String>,
+    // Conversation id for messages, category for framework outputs/definitions and prompts
+    pub parent_id: Option<String>,
+    pub score: f64,
+    pub created_at: i64,
+}
+
+const GLOBAL_SEARCH_DEFAULT_LIMIT: usize = 50;
+const SEARCH_SNIPPET_RADIUS: usize = 60;
+
+// Title hits outrank body-only hits; exact and prefix title matches rank highest
+fn search_score(query_lower: &str, title: &str) -> f64 {
+    let title_lower = title.to_lowercase();
+    if title_lower == query_lower {
+        100.0
+    } else if title_lower.starts_with(query_lower) {
+        75.0
+    } else if title_lower.contains(query_lower) {
+        50.0
+    } else {
+        25.0
     }
-    Ok(results)
 }
 
-#[tauri::command]
-pub async fn preview_import_framework(md_content: String, app: tauri::AppHandle) -> Result<ImportPreview, String> {
-    let (yaml_str, _body) = parse_yaml_frontmatter(&md_content)?;
-    let meta: FrameworkExportMeta = serde_yaml::from_str(&yaml_str)
-        .map_err(|e| format!("Invalid YAML front matter: {}", e))?;
+fn text_snippet(text: &str, query_lower: &str) -> Option<String> {
+    let lower = text.to_lowercase();
+    // Lowercasing can change byte lengths for some scripts; only trust the offset when it didn't
+    let pos = if lower.len() == text.len() { lower.find(query_lower)? } else { 0 };
 
-    if meta.r#type != "framework" {
-        return Err(format!("Expected type 'framework', got '{}'", meta.r#type));
+    let mut start = pos.saturating_sub(SEARCH_SNIPPET_RADIUS);
+    while !text.is_char_boundary(start) {
+        start -= 1;
     }
-    if meta.export_version != 1 {
-        return Err(format!("Unsupported export version: {}", meta.export_version));
+    let mut end = (pos + query_lower.len() + SEARCH_SNIPPET_RADIUS).min(text.len());
+    while !text.is_char_boundary(end) {
+        end += 1;
     }
-    if meta.name.is_empty() { return Err("Missing required field: name".to_string()); }
-    if meta.category.is_empty() { return Err("Missing required field: category".to_string()); }
-    if meta.id.is_empty() { return Err("Missing required field: id".to_string()); }
-
-    let conn = get_db_connection(&app)?;
-    let existing: Option<(String, bool)> = conn.query_row(
-        "SELECT id, is_builtin FROM framework_defs WHERE id = ?1",
-        params![&meta.id],
-        |row| Ok((row.get(0)?, row.get(1)?)),
-    ).optional().map_err(|e| format!("DB error: {}", e))?;
 
-    let (already_exists, is_builtin_conflict) = match existing {
-        Some((_, is_builtin)) => (true, is_builtin),
-        None => (false, false),
-    };
+    let mut snippet = text[start..end].replace('\n', " ");
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if end < text.len() {
+        snippet.push('…');
+    }
+    Some(snippet)
+}
+
+// Runs a LIKE search whose columns are: id, title, body, project_id, parent_id, created_at
+fn collect_like_hits(
+    conn: &Connection,
+    kind: &str,
+    query: &str,
+    project_id: &Option<String>,
+    pattern: &str,
+    query_lower: &str,
+    limit: usize,
+) -> Result<Vec<GlobalSearchResult>, String> {
+    let mut stmt = conn.prepare(query)
+        .map_err(|e| format!("Failed to prepare {} search: {}", kind, e))?;
+
+    let rows = stmt.query_map(params![project_id, pattern, &(limit as i64)], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            row.get::<_, Option<String>>(3)?,
+            row.get::<_, Option<String>>(4)?,
+            row.get::<_, i64>(5)?,
+        ))
+    }).map_err(|e| format!("Failed to search {}: {}", kind, e))?;
+
+    let mut hits = Vec::new();
+    for row in rows {
+        let (id, title, body, project_id, parent_id, created_at) =
+            row.map_err(|e| format!("Failed to read {} search result: {}", kind, e))?;
+        hits.push(GlobalSearchResult {
+            kind: kind.to_string(),
+            score: search_score(query_lower, &title),
+            snippet: text_snippet(&body, query_lower),
+            id,
+            title,
+            project_id,
+            parent_id,
+            created_at,
+        });
+    }
 
-    Ok(ImportPreview {
-        item_type: "framework".to_string(),
-        id: meta.id,
-        name: meta.name,
-        category: meta.category,
-        description: meta.description,
-        already_exists,
-        is_builtin_conflict,
-    })
+    Ok(hits)
 }
 
 #[tauri::command]
-pub async fn confirm_import_framework(md_content: String, conflict_action: String, app: tauri::AppHandle) -> Result<ImportResult, String> {
-    let (yaml_str, body) = parse_yaml_frontmatter(&md_content)?;
-    let meta: FrameworkExportMeta = serde_yaml::from_str(&yaml_str)
-        .map_err(|e| format!("Invalid YAML: {}", e))?;
-    let (system_prompt, questions, example_output) = markdown_to_framework_parts(&body)?;
-    let questions_json = serde_json::to_string(&questions)
-        .map_err(|e| format!("Failed to serialize questions: {}", e))?;
+pub async fn global_search(
+    query: String,
+    project_id: Option<String>,
+    limit: Option<usize>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<GlobalSearchResult>, String> {
+    let query = query.trim().to_string();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
 
-    let conn = get_db_connection(&app)?;
-    let now = Utc::now().timestamp();
+    let conn = get_db_connection(&pool)?;
+    let limit = limit.unwrap_or(GLOBAL_SEARCH_DEFAULT_LIMIT).max(1);
+    let query_lower = query.to_lowercase();
+    let pattern = format!("%{}%", query);
+    let mut results = Vec::new();
 
-    let existing: Option<bool> = conn.query_row(
-        "SELECT is_builtin FROM framework_defs WHERE id = ?1",
-        params![&meta.id],
-        |row| row.get(0),
-    ).optional().map_err(|e| format!("DB error: {}", e))?;
+    // Project-scoped sources skip trashed projects; ?1 narrows to a single project when given.
+    // Prompts and framework definitions are global, so they ignore ?1.
+    let like_sources: [(&str, &str); 5] = [
+        ("conversation",
+         "SELECT c.id, c.title, NULL, c.project_id, NULL, c.created_at
+          FROM conversations c JOIN projects p ON p.id = c.project_id
+          WHERE p.deleted_at IS NULL AND (?1 IS NULL OR c.project_id = ?1) AND c.title LIKE ?2
+          LIMIT ?3"),
+        ("context_doc",
+         "SELECT d.id, d.name, CASE WHEN d.is_encrypted = 1 THEN NULL ELSE d.content END, d.project_id, d.folder_id, d.created_at
+          FROM context_documents d JOIN projects p ON p.id = d.project_id
+          WHERE p.deleted_at IS NULL AND d.deleted_at IS NULL AND (?1 IS NULL OR d.project_id = ?1 OR d.is_global = 1)
+            AND (d.name LIKE ?2 OR d.tags LIKE ?2 OR (d.is_encrypted = 0 AND d.content LIKE ?2))
+          LIMIT ?3"),
+        ("framework_output",
+         "SELECT o.id, o.name, o.generated_content, o.project_id, o.category, o.created_at
+          FROM framework_outputs o JOIN projects p ON p.id = o.project_id
+          WHERE p.deleted_at IS NULL AND o.deleted_at IS NULL AND (?1 IS NULL OR o.project_id = ?1)
+            AND (o.name LIKE ?2 OR o.tags LIKE ?2 OR o.generated_content LIKE ?2)
+          LIMIT ?3"),
+        ("saved_prompt",
+         "SELECT id, name, prompt_text, NULL, category, created_at
+          FROM saved_prompts
+          WHERE name LIKE ?2 OR description LIKE ?2 OR prompt_text LIKE ?2
+          LIMIT ?3"),
+        ("framework_def",
+         "SELECT id, name, description, NULL, category, created_at
+          FROM framework_definitions
+          WHERE name LIKE ?2 OR description LIKE ?2
+          LIMIT ?3"),
+    ];
 
-    let final_id: String;
-    let action: String;
+    for (kind, sql) in like_sources {
+        results.extend(collect_like_hits(&conn, kind, sql, &project_id, &pattern, &query_lower, limit)?);
+    }
 
-    match (existing, conflict_action.as_str()) {
-        (None, _) => {
-            final_id = meta.id.clone();
-            action = "created".to_string();
-            ensure_category_exists(&conn, &meta.category)?;
-            conn.execute(
-                "INSERT INTO framework_defs (id, category, name, description, icon, example_output, system_prompt, guiding_questions, supports_visuals, visual_instructions, is_builtin, sort_order, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, 0, 999, ?11, ?12)",
-                params![&meta.id, &meta.category, &meta.name, &meta.description, &meta.icon, &example_output, &system_prompt, &questions_json, &meta.supports_visuals, &meta.visual_instructions, &now, &now],
-            ).map_err(|e| format!("Failed to insert framework: {}", e))?;
-        },
-        (Some(_), "skip") => {
-            return Ok(ImportResult {
-                success: true,
-                item_type: "framework".to_string(),
-                id: meta.id,
-                name: meta.name,
-                action: "skipped".to_string(),
-                error: None,
-            });
-        },
-        (Some(_), "overwrite") => {
-            final_id = meta.id.clone();
-            action = "overwritten".to_string();
-            ensure_category_exists(&conn, &meta.category)?;
-            conn.execute(
-                "UPDATE framework_defs SET category=?1, name=?2, description=?3, icon=?4, example_output=?5, system_prompt=?6, guiding_questions=?7, supports_visuals=?8, visual_instructions=?9, updated_at=?10 WHERE id=?11",
-                params![&meta.category, &meta.name, &meta.description, &meta.icon, &example_output, &system_prompt, &questions_json, &meta.supports_visuals, &meta.visual_instructions, &now, &meta.id],
-            ).map_err(|e| format!("Failed to update framework: {}", e))?;
-        },
-        (Some(_), "copy") | (Some(_), _) => {
-            final_id = format!("{}-imported-{}", meta.id, &Uuid::new_v4().to_string()[..8]);
-            action = "copied".to_string();
-            ensure_category_exists(&conn, &meta.category)?;
-            conn.execute(
-                "INSERT INTO framework_defs (id, category, name, description, icon, example_output, system_prompt, guiding_questions, supports_visuals, visual_instructions, is_builtin, sort_order, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, 0, 999, ?11, ?12)",
-                params![&final_id, &meta.category, &format!("{} (Imported)", meta.name), &meta.description, &meta.icon, &example_output, &system_prompt, &questions_json, &meta.supports_visuals, &meta.visual_instructions, &now, &now],
-            ).map_err(|e| format!("Failed to insert framework copy: {}", e))?;
-        },
+    let match_query = fts_match_query(&query);
+    if !match_query.is_empty() {
+        let mut stmt = conn.prepare(
+            "SELECT m.id, c.title, snippet(messages_fts, 0, '<mark>', '</mark>', '…', 16),
+                    c.project_id, m.conversation_id, m.created_at
+             FROM messages_fts
+             JOIN messages m ON m.rowid = messages_fts.rowid
+             JOIN conversations c ON c.id = m.conversation_id
+             JOIN projects p ON p.id = c.project_id
+             WHERE messages_fts MATCH ?1 AND p.deleted_at IS NULL AND (?2 IS NULL OR c.project_id = ?2)
+             ORDER BY bm25(messages_fts) ASC
+             LIMIT ?3"
+        ).map_err(|e| format!("Failed to prepare message search: {}", e))?;
+
+        let rows = stmt.query_map(params![&match_query, &project_id, &(limit as i64)], |row| {
+            Ok(GlobalSearchResult {
+                kind: "message".to_string(),
+                id: row.get(0)?,
+                title: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                snippet: row.get(2)?,
+                project_id: row.get(3)?,
+                parent_id: row.get(4)?,
+                score: 0.0,
+                created_at: row.get(5)?,
+            })
+        }).map_err(|e| format!("Failed to search messages: {}", e))?;
+
+        // Rows arrive best-first; keep that order within the body-match tier
+        for (rank, row) in rows.enumerate() {
+            let mut hit = row.map_err(|e| format!("Failed to read message search result: {}", e))?;
+            hit.score = 25.0 - (rank as f64 / limit as f64);
+            results.push(hit);
+        }
     }
 
-    Ok(ImportResult {
-        success: true,
-        item_type: "framework".to_string(),
-        id: final_id,
-        name: meta.name,
-        action,
-        error: None,
-    })
+    results.sort_by(|a, b| {
+        b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| b.created_at.cmp(&a.created_at))
+    });
+    results.truncate(limit);
+
+    Ok(results)
 }
 
-fn ensure_category_exists(conn: &Connection, category_id: &str) -> Result<(), String> {
-    let exists: bool = conn.query_row(
-        "SELECT COUNT(*) > 0 FROM framework_categories WHERE id = ?1",
-        params![category_id],
-        |row| row.get(0),
-    ).map_err(|e| format!("DB error checking category: {}", e))?;
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OutputSearchResult {
+    pub output_id: String,
+    pub name: String,
+    pub project_id: String,
+    pub project_name: String,
+    pub framework_id: String,
+    // None when the framework definition has since been deleted
+    pub framework_name: Option<String>,
+    pub snippet: Option<String>,
+    pub score: f64,
+    pub updated_at: i64,
+}
 
-    if !exists {
-        let now = Utc::now().timestamp();
-        let name = category_id.replace('-', " ")
-            .split_whitespace()
-            .map(|w| {
-                let mut c = w.chars();
-                match c.next() {
-                    None => String::new(),
-                    Some(f) => f.to_uppercase().collect::<String>() + c.as_str(),
-                }
-            })
-            .collect::<Vec<_>>()
-            .join(" ");
-        conn.execute(
-            "INSERT INTO framework_categories (id, name, description, icon, is_builtin, sort_order, created_at, updated_at) VALUES (?1, ?2, ?3, '📁', 0, 999, ?4, ?5)",
-            params![category_id, &name, &format!("Imported category: {}", name), &now, &now],
-        ).map_err(|e| format!("Failed to create category: {}", e))?;
+// Finds outputs across every live project so a PM can reuse deliverables from related products.
+// SQL pre-ranks name, then tag, then content hits so the LIMIT keeps the best candidates.
+#[tauri::command]
+pub async fn search_framework_outputs_global(
+    query: String,
+    limit: Option<usize>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<OutputSearchResult>, String> {
+    let query = query.trim().to_string();
+    if query.is_empty() {
+        return Ok(Vec::new());
     }
-    Ok(())
-}
 
-// === Phase 6: Prompt Export Commands ===
+    let conn = get_db_connection(&pool)?;
+    let limit = limit.unwrap_or(GLOBAL_SEARCH_DEFAULT_LIMIT).max(1);
+    let query_lower = query.to_lowercase();
+    let pattern = format!("%{}%", query);
 
-#[tauri::command]
-pub async fn export_prompt(id: String, app: tauri::AppHandle) -> Result<String, String> {
-    let conn = get_db_connection(&app)?;
-    let prompt = conn.query_row(
-        "SELECT id, name, description, category, prompt_text, variables, framework_id, is_builtin, is_favorite, usage_count, sort_order, created_at, updated_at FROM saved_prompts WHERE id = ?1",
-        params![&id],
-        |row| {
-            Ok(SavedPromptRow {
-                id: row.get(0)?,
+    let mut stmt = conn.prepare(
+        "SELECT o.id, o.name, o.project_id, p.name, o.framework_id, f.name, o.generated_content, o.tags, o.updated_at
+         FROM framework_outputs o
+         JOIN projects p ON p.id = o.project_id
+         LEFT JOIN framework_definitions f ON f.id = o.framework_id
+         WHERE p.deleted_at IS NULL AND o.deleted_at IS NULL
+           AND (o.name LIKE ?1 OR o.tags LIKE ?1 OR o.generated_content LIKE ?1)
+         ORDER BY CASE WHEN o.name LIKE ?1 THEN 0 WHEN o.tags LIKE ?1 THEN 1 ELSE 2 END, o.updated_at DESC
+         LIMIT ?2"
+    ).map_err(|e| format!("Failed to prepare output search: {}", e))?;
+
+    let rows = stmt.query_map(params![&pattern, &(limit as i64)], |row| {
+        Ok((
+            OutputSearchResult {
+                output_id: row.get(0)?,
                 name: row.get(1)?,
-                description: row.get(2)?,
-                category: row.get(3)?,
-                prompt_text: row.get(4)?,
-                variables: row.get(5)?,
-                framework_id: row.get(6)?,
-                is_builtin: row.get(7)?,
-                is_favorite: row.get(8)?,
-                usage_count: row.get(9)?,
-                sort_order: row.get(10)?,
-                created_at: row.get(11)?,
-                updated_at: row.get(12)?,
-            })
-        },
-    ).map_err(|e| format!("Prompt not found: {}", e))?;
-    prompt_to_markdown(&prompt)
-}
+                project_id: row.get(2)?,
+                project_name: row.get(3)?,
+                framework_id: row.get(4)?,
+                framework_name: row.get(5)?,
+                snippet: None,
+                score: 0.0,
+                updated_at: row.get(8)?,
+            },
+            row.get::<_, String>(6)?,
+            row.get::<_, Option<String>>(7)?.unwrap_or_default(),
+        ))
+    }).map_err(|e| format!("Failed to search framework outputs: {}", e))?;
 
-#[tauri::command]
-pub async fn export_prompts_batch(ids: Vec<String>, app: tauri::AppHandle) -> Result<Vec<BatchExportResult>, String> {
-    let conn = get_db_connection(&app)?;
     let mut results = Vec::new();
-    for id in &ids {
-        let prompt = conn.query_row(
-            "SELECT id, name, description, category, prompt_text, variables, framework_id, is_builtin, is_favorite, usage_count, sort_order, created_at, updated_at FROM saved_prompts WHERE id = ?1",
-            params![id],
-            |row| {
-                Ok(SavedPromptRow {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    description: row.get(2)?,
-                    category: row.get(3)?,
-                    prompt_text: row.get(4)?,
-                    variables: row.get(5)?,
-                    framework_id: row.get(6)?,
-                    is_builtin: row.get(7)?,
-                    is_favorite: row.get(8)?,
-                    usage_count: row.get(9)?,
-                    sort_order: row.get(10)?,
-                    created_at: row.get(11)?,
-                    updated_at: row.get(12)?,
-                })
-            },
-        ).map_err(|e| format!("Prompt {} not found: {}", id, e))?;
-        let content = prompt_to_markdown(&prompt)?;
-        let filename = format!("{}.md", sanitize_filename(&prompt.name));
-        results.push(BatchExportResult { filename, content });
+    for row in rows {
+        let (mut hit, content, tags) = row.map_err(|e| format!("Failed to read output search result: {}", e))?;
+        hit.score = search_score(&query_lower, &hit.name);
+        // Tag matches sit between title and body-only matches
+        if hit.score < 50.0 && tags.to_lowercase().contains(&query_lower) {
+            hit.score = 40.0;
+        }
+        hit.snippet = text_snippet(&content, &query_lower);
+        results.push(hit);
     }
+
+    results.sort_by(|a, b| {
+        b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| b.updated_at.cmp(&a.updated_at))
+    });
     Ok(results)
 }
 
 #[tauri::command]
-pub async fn export_all_prompts(app: tauri::AppHandle) -> Result<Vec<BatchExportResult>, String> {
-    let conn = get_db_connection(&app)?;
-    let mut stmt = conn.prepare(
-        "SELECT id, name, description, category, prompt_text, variables, framework_id, is_builtin, is_favorite, usage_count, sort_order, created_at, updated_at FROM saved_prompts ORDER BY sort_order"
-    ).map_err(|e| format!("Failed to query prompts: {}", e))?;
+pub async fn toggle_item_favorite(
+    item_id: String,
+    item_type: String,
+    is_favorite: bool,
+    pool: State<'_, DbPool>,
+) -> Result<(), String> {
+    let conn = get_db_connection(&pool)?;
+    let fav_val = if is_favorite { 1 } else { 0 };
 
-    let prompts: Vec<SavedPromptRow> = stmt.query_map([], |row| {
-        Ok(SavedPromptRow {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            description: row.get(2)?,
-            category: row.get(3)?,
-            prompt_text: row.get(4)?,
-            variables: row.get(5)?,
-            framework_id: row.get(6)?,
-            is_builtin: row.get(7)?,
-            is_favorite: row.get(8)?,
-            usage_count: row.get(9)?,
-            sort_order: row.get(10)?,
-            created_at: row.get(11)?,
-            updated_at: row.get(12)?,
-        })
-    }).map_err(|e| format!("Query error: {}", e))?
-    .filter_map(|r| r.ok())
-    .collect();
+    let table = match item_type.as_str() {
+        "context_doc" => "context_documents",
+        "framework_output" => "framework_outputs",
+        _ => return Err(format!("Invalid item type: {}", item_type)),
+    };
 
-    let mut results = Vec::new();
-    for prompt in &prompts {
-        let content = prompt_to_markdown(prompt)?;
-        let filename = format!("{}.md", sanitize_filename(&prompt.name));
-        results.push(BatchExportResult { filename, content });
-    }
-    Ok(results)
+    conn.execute(
+        &format!("UPDATE {} SET is_favorite = ?1 WHERE id = ?2", table),
+        params![&fav_val, &item_id],
+    ).map_err(|e| format!("Failed to toggle favorite: {}", e))?;
+
+    Ok(())
 }
 
-// === Phase 6: Prompt Import Commands ===
+#[tauri::command]
+pub async fn set_items_favorite(
+    items: Vec<(String, String)>,
+    is_favorite: bool,
+    pool: State<'_, DbPool>,
+) -> Result<usize, String> {
+    let targets = items.iter()
+        .map(|(id, item_type)| library_item_table(item_type).map(|table| (id, table)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let conn = get_db_connection(&pool)?;
+    with_transaction(&conn, |tx| {
+        let mut affected = 0;
+        for (id, table) in targets {
+            affected += tx.execute(
+                &format!("UPDATE {} SET is_favorite = ?1 WHERE id = ?2", table),
+                params![&is_favorite, id],
+            ).map_err(|e| format!("Failed to update favorite: {}", e))?;
+        }
+
+        Ok(affected)
+    })
+}
 
 #[tauri::command]
-pub async fn preview_import_prompt(md_content: String, app: tauri::AppHandle) -> Result<ImportPreview, String> {
-    let (yaml_str, _body) = parse_yaml_frontmatter(&md_content)?;
-    let meta: PromptExportMeta = serde_yaml::from_str(&yaml_str)
-        .map_err(|e| format!("Invalid YAML front matter: {}", e))?;
+pub async fn set_folder_color(
+    id: String,
+    color: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<(), AppError> {
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
 
-    if meta.r#type != "prompt" {
-        return Err(format!("Expected type 'prompt', got '{}'", meta.r#type));
-    }
-    if meta.export_version != 1 {
-        return Err(format!("Unsupported export version: {}", meta.export_version));
-    }
-    if meta.name.is_empty() { return Err("Missing required field: name".to_string()); }
-    if meta.category.is_empty() { return Err("Missing required field: category".to_string()); }
-    if meta.id.is_empty() { return Err("Missing required field: id".to_string()); }
+    conn.execute(
+        "UPDATE folders SET color = ?1, updated_at = ?2 WHERE id = ?3",
+        params![&color, &now, &id],
+    ).map_err(|e| AppError::database("Failed to set folder color", e))?;
 
-    let conn = get_db_connection(&app)?;
-    let existing: Option<(String, bool)> = conn.query_row(
-        "SELECT id, is_builtin FROM saved_prompts WHERE id = ?1",
-        params![&meta.id],
-        |row| Ok((row.get(0)?, row.get(1)?)),
-    ).optional().map_err(|e| format!("DB error: {}", e))?;
+    Ok(())
+}
 
-    let (already_exists, is_builtin_conflict) = match existing {
-        Some((_, is_builtin)) => (true, is_builtin),
-        None => (false, false),
-    };
+// Context Document commands
 
-    Ok(ImportPreview {
-        item_type: "prompt".to_string(),
-        id: meta.id,
-        name: meta.name,
-        category: meta.category,
-        description: meta.description,
-        already_exists,
-        is_builtin_conflict,
-    })
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContextDocument {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub doc_type: String,
+    pub content: String,
+    pub url: Option<String>,
+    pub is_global: bool,
+    pub size_bytes: i64,
+    pub created_at: i64,
+    pub folder_id: Option<String>,
+    pub tags: String,
+    pub is_favorite: bool,
+    pub sort_order: i32,
+    #[serde(default)]
+    pub is_encrypted: bool,
 }
 
-#[tauri::command]
-pub async fn confirm_import_prompt(md_content: String, conflict_action: String, app: tauri::AppHandle) -> Result<ImportResult, String> {
-    let (yaml_str, body) = parse_yaml_frontmatter(&md_content)?;
-    let meta: PromptExportMeta = serde_yaml::from_str(&yaml_str)
-        .map_err(|e| format!("Invalid YAML: {}", e))?;
+// List-view shape of a context document: the body is replaced by a short preview
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContextDocumentSummary {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub doc_type: String,
+    pub preview: String,
+    pub url: Option<String>,
+    pub is_global: bool,
+    pub size_bytes: i64,
+    pub created_at: i64,
+    pub folder_id: Option<String>,
+    pub tags: String,
+    pub is_favorite: bool,
+    pub sort_order: i32,
+    pub is_encrypted: bool,
+}
 
-    let prompt_text_start = body.find("# Prompt Text")
-        .ok_or("Missing '# Prompt Text' section")?;
-    let prompt_text = body[prompt_text_start + 13..].trim().to_string();
+const CONTEXT_DOC_PREVIEW_CHARS: i64 = 200;
 
-    let variables_json = serde_json::to_string(&meta.variables)
-        .map_err(|e| format!("Failed to serialize variables: {}", e))?;
+fn content_hash(content: &str) -> String {
+    let digest = Sha256::digest(content.as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
 
-    let conn = get_db_connection(&app)?;
-    let now = Utc::now().timestamp();
+// Encrypted documents get no hash: an unkeyed hash of the plaintext would reveal which of them
+// share content, so duplicate detection only covers unencrypted documents
+fn stored_content_hash(content: &str, is_encrypted: bool) -> Option<String> {
+    (!is_encrypted).then(|| content_hash(content))
+}
 
-    let existing: Option<bool> = conn.query_row(
-        "SELECT is_builtin FROM saved_prompts WHERE id = ?1",
-        params![&meta.id],
-        |row| row.get(0),
-    ).optional().map_err(|e| format!("DB error: {}", e))?;
+// Documents are sealed with the keychain data key. Older ones were sealed with the
+// machine-derived key, which stays around so they can still be opened and resealed.
+struct DocumentKeys {
+    data: Result<[u8; 32], String>,
+    legacy: [u8; 32],
+}
 
-    let final_id: String;
-    let action: String;
+fn get_document_keys() -> Result<DocumentKeys, AppError> {
+    Ok(DocumentKeys {
+        data: keyring_document_key(),
+        legacy: get_encryption_key().map_err(AppError::Internal)?,
+    })
+}
 
-    match (existing, conflict_action.as_str()) {
-        (None, _) => {
-            final_id = meta.id.clone();
-            action = "created".to_string();
-            conn.execute(
-                "INSERT INTO saved_prompts (id, name, description, category, prompt_text, variables, framework_id, is_builtin, is_favorite, usage_count, sort_order, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, 0, 0, 999, ?8, ?9)",
-                params![&meta.id, &meta.name, &meta.description, &meta.category, &prompt_text, &variables_json, &meta.framework_id, &now, &now],
-            ).map_err(|e| format!("Failed to insert prompt: {}", e))?;
-        },
-        (Some(_), "skip") => {
-            return Ok(ImportResult {
-                success: true,
-                item_type: "prompt".to_string(),
-                id: meta.id,
-                name: meta.name,
-                action: "skipped".to_string(),
-                error: None,
-            });
-        },
-        (Some(_), "overwrite") => {
-            final_id = meta.id.clone();
-            action = "overwritten".to_string();
-            conn.execute(
-                "UPDATE saved_prompts SET name=?1, description=?2, category=?3, prompt_text=?4, variables=?5, framework_id=?6, updated_at=?7 WHERE id=?8",
-                params![&meta.name, &meta.description, &meta.category, &prompt_text, &variables_json, &meta.framework_id, &now, &meta.id],
-            ).map_err(|e| format!("Failed to update prompt: {}", e))?;
-        },
-        (Some(_), "copy") | (Some(_), _) => {
-            final_id = format!("{}-imported-{}", meta.id, &Uuid::new_v4().to_string()[..8]);
-            action = "copied".to_string();
-            conn.execute(
-                "INSERT INTO saved_prompts (id, name, description, category, prompt_text, variables, framework_id, is_builtin, is_favorite, usage_count, sort_order, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, 0, 0, 999, ?8, ?9)",
-                params![&final_id, &format!("{} (Imported)", meta.name), &meta.description, &meta.category, &prompt_text, &variables_json, &meta.framework_id, &now, &now],
-            ).map_err(|e| format!("Failed to insert prompt copy: {}", e))?;
-        },
+// Encrypted documents store ciphertext in `content`; size_bytes still describes the plaintext
+fn seal_document_content(content: &str, is_encrypted: bool, keys: &DocumentKeys) -> Result<String, String> {
+    if is_encrypted {
+        let key = keys.data.as_ref()
+            .map_err(|e| format!("Cannot encrypt document without the keychain: {}", e))?;
+        encrypt_string(content, key)
+    } else {
+        Ok(content.to_string())
     }
-
-    Ok(ImportResult {
-        success: true,
-        item_type: "prompt".to_string(),
-        id: final_id,
-        name: meta.name,
-        action,
-        error: None,
-    })
 }
 
-fn seed_workflows(conn: &Connection) -> Result<(), String> {
-    let count: i64 = conn.query_row("SELECT COUNT(*) FROM workflows", [], |row| row.get(0))
-        .map_err(|e| format!("Failed to count workflows: {}", e))?;
+fn open_document_content(content: &str, keys: &DocumentKeys) -> Result<String, String> {
+    let opened = keys.data.as_ref().ok()
+        .and_then(|key| decrypt_string(content, key).ok());
+    match opened {
+        Some(plaintext) => Ok(plaintext),
+        None => decrypt_string(content, &keys.legacy),
+    }
+}
 
-    if count > 0 {
-        return Ok(());
+fn open_context_document(mut document: ContextDocument, keys: &DocumentKeys) -> Result<ContextDocument, String> {
+    if document.is_encrypted {
+        document.content = open_document_content(&document.content, keys)
+            .map_err(|e| format!("Failed to decrypt document '{}': {}", document.name, e))?;
     }
+    Ok(document)
+}
 
-    let now = Utc::now().timestamp();
+// Re-encrypts documents still sealed with the machine key under the data key; returns how many moved
+fn reseal_legacy_documents(conn: &Connection, keys: &DocumentKeys) -> Result<usize, String> {
+    let data_key = keys.data.as_ref().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare("SELECT id, content FROM context_documents WHERE is_encrypted = 1")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("Failed to query encrypted documents: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect encrypted documents: {}", e))?;
+
+    with_transaction(conn, |tx| {
+        let mut resealed = 0;
+        for (id, content) in rows {
+            if decrypt_string(&content, data_key).is_ok() {
+                continue;
+            }
+            // Rows neither key can open are left alone rather than failing the whole pass
+            let Ok(plaintext) = decrypt_string(&content, &keys.legacy) else { continue };
+            tx.execute(
+                "UPDATE context_documents SET content = ?1 WHERE id = ?2",
+                params![&encrypt_string(&plaintext, data_key)?, &id],
+            ).map_err(|e| format!("Failed to reseal document: {}", e))?;
+            resealed += 1;
+        }
+        Ok(resealed)
+    })
+}
 
-    let templates: Vec<(&str, &str, &str, Vec<WorkflowStepDef>)> = vec![
-        (
-            "complete-product-brief",
-            "Complete Product Brief",
-            "End-to-end product brief: research, competitive analysis, PRD, and go-to-market plan",
-            vec![
-                WorkflowStepDef {
-                    framework_id: "jtbd".to_string(),
-                    label: "Jobs-to-be-Done Analysis".to_string(),
-                    prompt_template: "Analyze the jobs-to-be-done for this product. Focus on the core user needs, desired outcomes, and pain points that drive adoption.".to_string(),
-                    context_doc_ids: vec![],
-                    model: "gpt-5".to_string(),
-                },
-                WorkflowStepDef {
-                    framework_id: "competitive-analysis".to_string(),
-                    label: "Competitive Analysis".to_string(),
-                    prompt_template: "Based on the following user research:\n\n{prev_output}\n\nConduct a competitive analysis identifying key competitors, their strengths and weaknesses, and market opportunities.".to_string(),
-                    context_doc_ids: vec![],
-                    model: "gpt-5".to_string(),
-                },
-                WorkflowStepDef {
-                    framework_id: "prd-template".to_string(),
-                    label: "PRD Generation".to_string(),
-                    prompt_template: "Using the research and competitive analysis below, generate a comprehensive PRD:\n\n{prev_output}".to_string(),
-                    context_doc_ids: vec![],
-                    model: "gpt-5".to_string(),
-                },
-                WorkflowStepDef {
-                    framework_id: "go-to-market".to_string(),
-                    label: "Go-to-Market Plan".to_string(),
-                    prompt_template: "Based on this PRD, create a go-to-market plan covering positioning, channels, pricing strategy, and launch timeline:\n\n{prev_output}".to_string(),
-                    context_doc_ids: vec![],
-                    model: "gpt-5".to_string(),
-                },
-            ],
-        ),
-        (
-            "feature-validation",
-            "Feature Validation",
-            "Validate and plan a feature: scoring, user stories, and sprint planning",
-            vec![
-                WorkflowStepDef {
-                    framework_id: "rice".to_string(),
-                    label: "RICE Scoring".to_string(),
-                    prompt_template: "Score this feature using the RICE framework (Reach, Impact, Confidence, Effort). Provide detailed justification for each score.".to_string(),
-                    context_doc_ids: vec![],
-                    model: "gpt-5".to_string(),
-                },
-                WorkflowStepDef {
-                    framework_id: "user-story-map".to_string(),
-                    label: "User Story Mapping".to_string(),
-                    prompt_template: "Based on this RICE analysis, create a user story map breaking down the feature into epics and stories:\n\n{prev_output}".to_string(),
-                    context_doc_ids: vec![],
-                    model: "gpt-5".to_string(),
-                },
-                WorkflowStepDef {
-                    framework_id: "sprint-planning".to_string(),
-                    label: "Sprint Planning".to_string(),
-                    prompt_template: "Using these user stories, create a sprint plan with prioritized backlog items, effort estimates, and sprint goals:\n\n{prev_output}".to_string(),
-                    context_doc_ids: vec![],
-                    model: "gpt-5".to_string(),
-                },
-            ],
-        ),
-        (
-            "strategic-review",
-            "Strategic Review",
-            "Full strategic analysis: SWOT, competitive forces, and value proposition",
-            vec![
-                WorkflowStepDef {
-                    framework_id: "swot".to_string(),
-                    label: "SWOT Analysis".to_string(),
-                    prompt_template: "Conduct a thorough SWOT analysis covering internal strengths and weaknesses, and external opportunities and threats.".to_string(),
-                    context_doc_ids: vec![],
-                    model: "gpt-5".to_string(),
-                },
-                WorkflowStepDef {
-                    framework_id: "porters-five-forces".to_string(),
-                    label: "Porter's Five Forces".to_string(),
-                    prompt_template: "Building on this SWOT analysis, analyze the competitive landscape using Porter's Five Forces:\n\n{prev_output}".to_string(),
-                    context_doc_ids: vec![],
-                    model: "gpt-5".to_string(),
-                },
-                WorkflowStepDef {
-                    framework_id: "value-proposition-canvas".to_string(),
-                    label: "Value Proposition Canvas".to_string(),
-                    prompt_template: "Using the strategic insights from SWOT and Five Forces analysis, develop a value proposition canvas:\n\n{prev_output}".to_string(),
-                    context_doc_ids: vec![],
-                    model: "gpt-5".to_string(),
-                },
-            ],
-        ),
-    ];
+// Rows written before content_hash existed (or by older code paths) get hashed on startup
+fn backfill_content_hashes(conn: &Connection) -> Result<(), String> {
+    let mut stmt = conn.prepare("SELECT id, content FROM context_documents WHERE content_hash IS NULL AND is_encrypted = 0")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("Failed to query unhashed documents: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect unhashed documents: {}", e))?;
 
-    for (id, name, description, steps) in templates {
-        let steps_json = serde_json::to_string(&steps)
-            .map_err(|e| format!("Failed to serialize workflow steps: {}", e))?;
+    for (id, content) in rows {
         conn.execute(
-            "INSERT OR IGNORE INTO workflows (id, project_id, name, description, steps, is_template, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, 1, ?6, ?7)",
-            params![id, "__templates__", name, description, &steps_json, &now, &now],
-        ).map_err(|e| format!("Failed to seed workflow: {}", e))?;
+            "UPDATE context_documents SET content_hash = ?1 WHERE id = ?2",
+            params![content_hash(&content), &id],
+        ).map_err(|e| format!("Failed to backfill content hash: {}", e))?;
     }
-
     Ok(())
 }
 
 #[tauri::command]
-pub async fn create_workflow(
+pub async fn create_context_document(
     project_id: String,
     name: String,
-    description: String,
-    steps_json: String,
-    app: tauri::AppHandle,
-) -> Result<Workflow, String> {
-    let conn = get_db_connection(&app)?;
+    doc_type: String,
+    content: String,
+    url: Option<String>,
+    is_global: bool,
+    is_encrypted: Option<bool>,
+    pool: State<'_, DbPool>,
+) -> Result<ContextDocument, AppError> {
+    let conn = get_db_connection(&pool)?;
+    ensure_project_exists(&conn, &project_id)?;
+    insert_context_document(
+        &conn, &project_id, name, doc_type, content, url, is_global, is_encrypted.unwrap_or(false), &get_document_keys()?,
+    )
+}
+
+fn insert_context_document(
+    conn: &Connection,
+    project_id: &str,
+    name: String,
+    doc_type: String,
+    content: String,
+    url: Option<String>,
+    is_global: bool,
+    is_encrypted: bool,
+    keys: &DocumentKeys,
+) -> Result<ContextDocument, AppError> {
     let id = Uuid::new_v4().to_string();
     let now = Utc::now().timestamp();
+    let size_bytes = content.len() as i64;
+    let stored_content = seal_document_content(&content, is_encrypted, keys)
+        .map_err(AppError::Internal)?;
 
     conn.execute(
-        "INSERT INTO workflows (id, project_id, name, description, steps, is_template, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, 0, ?6, ?7)",
-        params![&id, &project_id, &name, &description, &steps_json, &now, &now],
-    ).map_err(|e| format!("Failed to create workflow: {}", e))?;
+        "INSERT INTO context_documents (id, project_id, name, type, content, url, is_global, size_bytes, created_at, content_hash, is_encrypted)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![&id, &project_id, &name, &doc_type, &stored_content, &url, &is_global, &size_bytes, &now, &stored_content_hash(&content, is_encrypted), &is_encrypted],
+    ).map_err(|e| AppError::database("Failed to create context document", e))?;
 
-    Ok(Workflow {
+    Ok(ContextDocument {
         id,
-        project_id,
+        project_id: project_id.to_string(),
         name,
-        description,
-        steps: steps_json,
-        is_template: false,
+        doc_type,
+        content,
+        url,
+        is_global,
+        size_bytes,
         created_at: now,
-        updated_at: now,
+        folder_id: None,
+        tags: "[]".to_string(),
+        is_favorite: false,
+        sort_order: 0,
+        is_encrypted,
     })
 }
 
-#[tauri::command]
-pub async fn list_workflows(
-    project_id: String,
-    app: tauri::AppHandle,
-) -> Result<Vec<Workflow>, String> {
-    let conn = get_db_connection(&app)?;
+const URL_FETCH_TIMEOUT: Duration = Duration::from_secs(20);
+const URL_FETCH_MAX_BYTES: usize = 5 * 1024 * 1024;
+
+// Tags whose contents are never readable text, so they are dropped entirely
+const HTML_SKIPPED_ELEMENTS: &[&str] = &["script", "style", "noscript", "template", "svg", "head"];
+const HTML_BLOCK_ELEMENTS: &[&str] = &[
+    "p", "div", "br", "li", "ul", "ol", "tr", "table", "section", "article", "header", "footer",
+    "h1", "h2", "h3", "h4", "h5", "h6", "blockquote", "pre", "hr", "title",
+];
+
+fn decode_html_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let code = if let Some(hex) = entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                u32::from_str_radix(hex, 16).ok()?
+            } else {
+                entity.strip_prefix('#')?.parse().ok()?
+            };
+            char::from_u32(code)
+        }
+    }
+}
 
-    let mut stmt = conn.prepare(
-        "SELECT id, project_id, name, description, steps, is_template, created_at, updated_at FROM workflows WHERE project_id = ?1 OR is_template = 1 ORDER BY is_template DESC, updated_at DESC"
-    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
+// Strips tags to plain text: skips non-content elements, turns block elements into line breaks,
+// decodes common entities and collapses runs of whitespace
+fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len() / 2);
+    let mut rest = html;
+    let mut skip_until: Option<String> = None;
 
-    let workflows = stmt.query_map(params![&project_id], |row| {
-        Ok(Workflow {
-            id: row.get(0)?,
-            project_id: row.get(1)?,
-            name: row.get(2)?,
-            description: row.get(3)?,
-            steps: row.get(4)?,
-            is_template: row.get(5)?,
-            created_at: row.get(6)?,
-            updated_at: row.get(7)?,
-        })
-    }).map_err(|e| format!("Failed to query workflows: {}", e))?;
+    while !rest.is_empty() {
+        if let Some(tag_start) = rest.find('<') {
+            if skip_until.is_none() {
+                push_html_text(&mut text, &rest[..tag_start]);
+            }
+            rest = &rest[tag_start..];
+            if rest.starts_with("<!--") {
+                rest = rest.find("-->").map(|end| &rest[end + 3..]).unwrap_or("");
+                continue;
+            }
+            let Some(tag_end) = rest.find('>') else { break };
+            let tag = &rest[1..tag_end];
+            rest = &rest[tag_end + 1..];
+
+            let closing = tag.starts_with('/');
+            let tag_name = tag.trim_start_matches('/')
+                .split(|c: char| c.is_whitespace() || c == '/')
+                .next()
+                .unwrap_or("")
+                .to_ascii_lowercase();
+
+            if let Some(skipped) = &skip_until {
+                if closing && &tag_name == skipped {
+                    skip_until = None;
+                }
+                continue;
+            }
+            if !closing && !tag.ends_with('/') && HTML_SKIPPED_ELEMENTS.contains(&tag_name.as_str()) {
+                skip_until = Some(tag_name);
+            } else if HTML_BLOCK_ELEMENTS.contains(&tag_name.as_str()) {
+                text.push('\n');
+            }
+        } else {
+            if skip_until.is_none() {
+                push_html_text(&mut text, rest);
+            }
+            break;
+        }
+    }
 
-    let mut results = Vec::new();
-    for wf in workflows {
-        results.push(wf.map_err(|e| format!("Failed to read workflow: {}", e))?);
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn push_html_text(out: &mut String, raw: &str) {
+    let mut rest = raw;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest[1..].find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| decode_html_entity(&rest[1..end + 1]).map(|c| (c, end + 2)));
+        match decoded {
+            Some((c, consumed)) => {
+                out.push(c);
+                rest = &rest[consumed..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
     }
-    Ok(results)
+    out.push_str(rest);
 }
 
-#[tauri::command]
-pub async fn get_workflow(
-    id: String,
-    app: tauri::AppHandle,
-) -> Result<Workflow, String> {
-    let conn = get_db_connection(&app)?;
+async fn fetch_url_text(url: &str) -> Result<String, String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        return Err(format!("Unsupported URL scheme '{}': only http and https are allowed", parsed.scheme()));
+    }
 
-    conn.query_row(
-        "SELECT id, project_id, name, description, steps, is_template, created_at, updated_at FROM workflows WHERE id = ?1",
-        params![&id],
-        |row| {
-            Ok(Workflow {
-                id: row.get(0)?,
-                project_id: row.get(1)?,
-                name: row.get(2)?,
-                description: row.get(3)?,
-                steps: row.get(4)?,
-                is_template: row.get(5)?,
-                created_at: row.get(6)?,
-                updated_at: row.get(7)?,
-            })
-        },
-    ).map_err(|e| format!("Workflow not found: {}", e))
+    let client = reqwest::Client::builder()
+        .timeout(URL_FETCH_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+
+    let mut response = client.get(parsed).send().await
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+
+    let status = response.status();
+    if status != reqwest::StatusCode::OK {
+        return Err(format!("Failed to fetch {}: server responded with {}", url, status));
+    }
+    if let Some(length) = response.content_length() {
+        if length > URL_FETCH_MAX_BYTES as u64 {
+            return Err(format!("Page is too large ({} bytes, limit is {} bytes)", length, URL_FETCH_MAX_BYTES));
+        }
+    }
+
+    let is_html = response.headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_ascii_lowercase().contains("html"))
+        .unwrap_or(true);
+
+    // Content-Length can be absent or wrong, so the limit is also enforced while streaming
+    let mut body: Vec<u8> = Vec::new();
+    while let Some(chunk) = response.chunk().await
+        .map_err(|e| format!("Failed to read response from {}: {}", url, e))?
+    {
+        if body.len() + chunk.len() > URL_FETCH_MAX_BYTES {
+            return Err(format!("Page is too large (exceeds limit of {} bytes)", URL_FETCH_MAX_BYTES));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    let raw = String::from_utf8_lossy(&body);
+    Ok(if is_html { html_to_text(&raw) } else { raw.trim().to_string() })
 }
 
 #[tauri::command]
-pub async fn update_workflow(
-    id: String,
+pub async fn create_context_document_from_url(
+    project_id: String,
+    url: String,
     name: String,
-    description: String,
+    pool: State<'_, DbPool>,
+) -> Result<ContextDocument, String> {
+    let url = url.trim().to_string();
+    let content = fetch_url_text(&url).await?;
+    if content.is_empty() {
+        return Err(format!("No readable text found at {}", url));
+    }
+
+    let name = if name.trim().is_empty() { url.clone() } else { name };
+    create_context_document(project_id, name, "url".to_string(), content, Some(url), false, None, pool).await
+        .map_err(String::from)
+}
+
+const DIRECTORY_IMPORT_MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SkippedImportFile {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DirectoryImportResult {
+    pub created: Vec<ContextDocument>,
+    pub skipped: Vec<SkippedImportFile>,
+}
+
+// Reads a file as text, or explains why it can't be imported
+fn read_importable_text(path: &std::path::Path, size: u64) -> Result<String, String> {
+    if size > DIRECTORY_IMPORT_MAX_FILE_BYTES {
+        return Err(format!("file is too large ({} bytes, limit is {} bytes)", size, DIRECTORY_IMPORT_MAX_FILE_BYTES));
+    }
+    let bytes = std::fs::read(path).map_err(|e| format!("failed to read file: {}", e))?;
+    // A NUL byte near the start is the usual signature of a binary format
+    if bytes.iter().take(8192).any(|b| *b == 0) {
+        return Err("binary file".to_string());
+    }
+    String::from_utf8(bytes).map_err(|_| "not valid UTF-8 text".to_string())
+}
+
+// Creates one text context document per matching file, all in one transaction so a failed insert
+// leaves nothing half-imported. An empty extension list accepts every file; hidden files and
+// directories are ignored, as in the file explorer.
+#[tauri::command]
+pub async fn import_documents_from_directory(
+    project_id: String,
+    dir_path: String,
+    extensions: Vec<String>,
+    recursive: Option<bool>,
+    pool: State<'_, DbPool>,
+) -> Result<DirectoryImportResult, String> {
+    let expanded = expand_home(&dir_path);
+    let root = std::path::Path::new(&expanded);
+    if !root.is_dir() {
+        return Err(format!("Not a valid directory: {}", expanded));
+    }
+
+    let extensions: Vec<String> = extensions.iter()
+        .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+        .filter(|e| !e.is_empty())
+        .collect();
+    let recursive = recursive.unwrap_or(false);
+
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in read_dir_entries(&dir, false)? {
+            if entry.is_dir {
+                if recursive {
+                    pending.push(PathBuf::from(&entry.path));
+                }
+                continue;
+            }
+            if !extensions.is_empty() && !extensions.contains(&entry.extension.to_lowercase()) {
+                continue;
+            }
+
+            match read_importable_text(std::path::Path::new(&entry.path), entry.size) {
+                Ok(content) => files.push((entry.name, content)),
+                Err(reason) => skipped.push(SkippedImportFile { path: entry.path, reason }),
+            }
+        }
+    }
+
+    let conn = get_db_connection(&pool)?;
+    ensure_project_exists(&conn, &project_id)?;
+    let keys = get_document_keys()?;
+    let created = with_transaction(&conn, |tx| {
+        files.into_iter()
+            .map(|(name, content)| {
+                insert_context_document(tx, &project_id, name, "text".to_string(), content, None, false, false, &keys)
+            })
+            .collect::<Result<Vec<_>, AppError>>()
+    })?;
+
+    Ok(DirectoryImportResult { created, skipped })
+}
+
+const CONTEXT_DOC_COLUMNS: &str = "id, project_id, name, type, content, url, is_global, size_bytes, created_at, folder_id, tags, is_favorite, sort_order, is_encrypted";
+
+fn row_to_context_document(row: &rusqlite::Row) -> rusqlite::Result<ContextDocument> {
+    Ok(ContextDocument {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        name: row.get(2)?,
+        doc_type: row.get(3)?,
+        content: row.get(4)?,
+        url: row.get(5)?,
+        is_global: row.get::<_, i32>(6)? != 0,
+        size_bytes: row.get(7)?,
+        created_at: row.get(8)?,
+        folder_id: row.get(9)?,
+        tags: row.get::<_, Option<String>>(10)?.unwrap_or_else(|| "[]".to_string()),
+        is_favorite: row.get::<_, Option<i32>>(11)?.unwrap_or(0) != 0,
+        sort_order: row.get::<_, Option<i32>>(12)?.unwrap_or(0),
+        is_encrypted: row.get::<_, i32>(13)? != 0,
+    })
+}
+
+// Sort columns callers may request; anything else is rejected rather than spliced into SQL
+const LIST_SORT_COLUMNS: &[&str] = &["created_at", "updated_at", "name"];
+
+// Builds an ORDER BY body from whitelisted parts, falling back to `default` when no sort is given.
+// Dates default to newest first and names to A-Z; id breaks ties so paging stays stable.
+fn list_order_clause(sort_by: Option<&str>, sort_dir: Option<&str>, default: &str) -> Result<String, String> {
+    let Some(column) = sort_by else {
+        return Ok(default.to_string());
+    };
+    if !LIST_SORT_COLUMNS.contains(&column) {
+        return Err(format!("Invalid sort_by '{}': expected one of {}", column, LIST_SORT_COLUMNS.join(", ")));
+    }
+    let direction = match sort_dir.map(|d| d.to_ascii_lowercase()).as_deref() {
+        None if column == "name" => "ASC",
+        None => "DESC",
+        Some("asc") => "ASC",
+        Some("desc") => "DESC",
+        Some(other) => return Err(format!("Invalid sort_dir '{}': expected asc or desc", other)),
+    };
+    let column = if column == "name" { "name COLLATE NOCASE" } else { column };
+    Ok(format!("{} {}, id ASC", column, direction))
+}
+
+#[tauri::command]
+pub async fn list_context_documents(
+    project_id: String,
+    sort_by: Option<String>,
+    sort_dir: Option<String>,
+    created_after: Option<i64>,
+    created_before: Option<i64>,
+    tags: Option<Vec<String>>,
+    match_all: Option<bool>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<ContextDocument>, String> {
+    // Documents carry no updated_at column; their only timestamp is created_at
+    let sort_by = sort_by.map(|s| if s == "updated_at" { "created_at".to_string() } else { s });
+    let order = list_order_clause(sort_by.as_deref(), sort_dir.as_deref(), "sort_order ASC, created_at DESC")?;
+    let conn = get_db_connection(&pool)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM context_documents
+         WHERE project_id = ?1 AND deleted_at IS NULL
+           AND (?2 IS NULL OR created_at > ?2)
+           AND (?3 IS NULL OR created_at < ?3)
+         ORDER BY {}",
+        CONTEXT_DOC_COLUMNS, order
+    )).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let documents = stmt.query_map(params![&project_id, &created_after, &created_before], row_to_context_document)
+        .map_err(|e| format!("Failed to query context documents: {}", e))?;
+
+    let documents = documents.collect::<Result<Vec<ContextDocument>, _>>()
+        .map_err(|e| format!("Failed to collect context documents: {}", e))?;
+
+    // Tags live in a JSON column, so the tag filter runs after the query
+    let wanted = normalize_tags(tags.unwrap_or_default());
+    let match_all = match_all.unwrap_or(true);
+    let keys = get_document_keys()?;
+    documents.into_iter()
+        .filter(|d| tags_match(&d.tags, &wanted, match_all))
+        .map(|d| open_context_document(d, &keys))
+        .collect()
+}
+
+// Same rows as list_context_documents but without the full content, so list views stay light
+#[tauri::command]
+pub async fn list_context_documents_summary(
+    project_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<ContextDocumentSummary>, String> {
+    let conn = get_db_connection(&pool)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, project_id, name, type,
+                CASE WHEN is_encrypted = 1 THEN '' ELSE substr(content, 1, ?2) END,
+                url, is_global, size_bytes, created_at, folder_id, tags, is_favorite, sort_order, is_encrypted
+         FROM context_documents
+         WHERE project_id = ?1 AND deleted_at IS NULL
+         ORDER BY sort_order ASC, created_at DESC"
+    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let documents = stmt.query_map(params![&project_id, &CONTEXT_DOC_PREVIEW_CHARS], |row| {
+        Ok(ContextDocumentSummary {
+            id: row.get(0)?,
+            project_id: row.get(1)?,
+            name: row.get(2)?,
+            doc_type: row.get(3)?,
+            preview: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+            url: row.get(5)?,
+            is_global: row.get::<_, i32>(6)? != 0,
+            size_bytes: row.get(7)?,
+            created_at: row.get(8)?,
+            folder_id: row.get(9)?,
+            tags: row.get::<_, Option<String>>(10)?.unwrap_or_else(|| "[]".to_string()),
+            is_favorite: row.get::<_, Option<i32>>(11)?.unwrap_or(0) != 0,
+            sort_order: row.get::<_, Option<i32>>(12)?.unwrap_or(0),
+            is_encrypted: row.get::<_, i32>(13)? != 0,
+        })
+    }).map_err(|e| format!("Failed to query context documents: {}", e))?;
+
+    let result: Result<Vec<ContextDocumentSummary>, _> = documents.collect();
+    result.map_err(|e| format!("Failed to collect context documents: {}", e))
+}
+
+// Org-wide docs (brand guidelines, glossary) from every live project, regardless of the active one
+#[tauri::command]
+pub async fn list_global_context_documents(
+    pool: State<'_, DbPool>,
+) -> Result<Vec<ContextDocument>, String> {
+    let conn = get_db_connection(&pool)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM context_documents
+         WHERE is_global = 1 AND deleted_at IS NULL
+           AND project_id IN (SELECT id FROM projects WHERE deleted_at IS NULL)
+         ORDER BY name COLLATE NOCASE ASC",
+        CONTEXT_DOC_COLUMNS
+    )).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let documents = stmt.query_map([], row_to_context_document)
+        .map_err(|e| format!("Failed to query global context documents: {}", e))?;
+
+    let documents = documents.collect::<Result<Vec<ContextDocument>, _>>()
+        .map_err(|e| format!("Failed to collect global context documents: {}", e))?;
+
+    let keys = get_document_keys()?;
+    documents.into_iter().map(|d| open_context_document(d, &keys)).collect()
+}
+
+#[tauri::command]
+pub async fn get_context_document(
+    id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Option<ContextDocument>, String> {
+    let conn = get_db_connection(&pool)?;
+
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM context_documents WHERE id = ?1", CONTEXT_DOC_COLUMNS))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let document = stmt.query_row(params![&id], row_to_context_document).optional()
+        .map_err(|e| format!("Failed to get context document: {}", e))?;
+
+    let keys = get_document_keys()?;
+    document.map(|d| open_context_document(d, &keys)).transpose()
+}
+
+#[tauri::command]
+pub async fn update_context_document(
+    id: String,
+    name: Option<String>,
+    is_global: Option<bool>,
+    content: Option<String>,
+    url: Option<String>,
+    folder_id: Option<String>,
+    tags: Option<Vec<String>>,
+    is_encrypted: Option<bool>,
+    pool: State<'_, DbPool>,
+) -> Result<ContextDocument, String> {
+    let current = get_context_document(id.clone(), pool.clone()).await?
+        .ok_or_else(|| "Context document not found".to_string())?;
+    let tags = tags
+        .map(|tags| serde_json::to_string(&normalize_tags(tags)))
+        .transpose()
+        .map_err(|e| format!("Failed to serialize tags: {}", e))?;
+    let encrypt = is_encrypted.unwrap_or(current.is_encrypted);
+
+    // Switching encryption on or off rewrites the existing body even when no new content is given
+    let content = match content {
+        Some(c) => Some(c),
+        None if encrypt != current.is_encrypted => Some(current.content),
+        None => None,
+    };
+    let size_bytes = content.as_ref().map(|c| c.len() as i64);
+    let new_hash = content.as_deref().and_then(|c| stored_content_hash(c, encrypt));
+    let keys = get_document_keys()?;
+    let stored_content = content.as_deref()
+        .map(|c| seal_document_content(c, encrypt, &keys))
+        .transpose()?;
+
+    let conn = get_db_connection(&pool)?;
+    with_transaction(&conn, |tx| {
+        // Stale vectors would keep matching the old text, so drop them when the body changes.
+        // Encrypted documents are kept out of semantic search entirely.
+        if encrypt {
+            tx.execute("DELETE FROM document_embeddings WHERE document_id = ?1", params![&id])
+                .map_err(|e| format!("Failed to invalidate embeddings: {}", e))?;
+        } else if let Some(ref hash) = new_hash {
+            tx.execute(
+                "DELETE FROM document_embeddings
+                 WHERE document_id = ?1
+                   AND EXISTS (SELECT 1 FROM context_documents WHERE id = ?1 AND content_hash IS NOT ?2)",
+                params![&id, hash],
+            ).map_err(|e| format!("Failed to invalidate embeddings: {}", e))?;
+        }
+
+        tx.execute(
+            "UPDATE context_documents
+             SET name = COALESCE(?1, name),
+                 is_global = COALESCE(?2, is_global),
+                 content = COALESCE(?3, content),
+                 size_bytes = COALESCE(?4, size_bytes),
+                 url = COALESCE(?5, url),
+                 folder_id = COALESCE(?6, folder_id),
+                 tags = COALESCE(?7, tags),
+                 content_hash = CASE WHEN ?10 THEN NULL ELSE COALESCE(?9, content_hash) END,
+                 is_encrypted = ?10
+             WHERE id = ?8",
+            params![&name, &is_global, &stored_content, &size_bytes, &url, &folder_id, &tags, &id, &new_hash, &encrypt],
+        ).map_err(|e| format!("Failed to update context document: {}", e))?;
+        Ok::<_, String>(())
+    })?;
+
+    // Fetch the updated document
+    get_context_document(id, pool).await?
+        .ok_or_else(|| "Context document not found after update".to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DuplicateDocumentGroup {
+    pub content_hash: String,
+    // Oldest first; dedupe_documents keeps the first entry
+    pub documents: Vec<ContextDocument>,
+}
+
+fn load_duplicate_groups(conn: &Connection, project_id: &str, keys: &DocumentKeys) -> Result<Vec<DuplicateDocumentGroup>, String> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, content_hash FROM context_documents
+         WHERE project_id = ?1 AND deleted_at IS NULL AND content_hash IN (
+             SELECT content_hash FROM context_documents
+             WHERE project_id = ?1 AND deleted_at IS NULL AND content_hash IS NOT NULL
+             GROUP BY content_hash HAVING COUNT(*) > 1
+         )
+         ORDER BY content_hash ASC, created_at ASC, id ASC",
+        CONTEXT_DOC_COLUMNS
+    )).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let rows = stmt.query_map(params![project_id], |row| {
+        Ok((row.get::<_, String>(14)?, row_to_context_document(row)?))
+    }).map_err(|e| format!("Failed to query duplicate documents: {}", e))?;
+
+    let mut groups: Vec<DuplicateDocumentGroup> = Vec::new();
+    for row in rows {
+        let (hash, document) = row.map_err(|e| format!("Failed to read duplicate document: {}", e))?;
+        let document = open_context_document(document, keys)?;
+        match groups.last_mut() {
+            Some(group) if group.content_hash == hash => group.documents.push(document),
+            _ => groups.push(DuplicateDocumentGroup { content_hash: hash, documents: vec![document] }),
+        }
+    }
+    Ok(groups)
+}
+
+#[tauri::command]
+pub async fn find_duplicate_documents(
+    project_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<DuplicateDocumentGroup>, String> {
+    let conn = get_db_connection(&pool)?;
+    load_duplicate_groups(&conn, &project_id, &get_document_keys()?)
+}
+
+// Keeps the oldest document of each duplicate group and trashes the rest; returns how many were removed
+#[tauri::command]
+pub async fn dedupe_documents(project_id: String, pool: State<'_, DbPool>) -> Result<usize, String> {
+    let conn = get_db_connection(&pool)?;
+    let groups = load_duplicate_groups(&conn, &project_id, &get_document_keys()?)?;
+
+    let now = Utc::now().timestamp();
+    with_transaction(&conn, |tx| {
+        let mut removed = 0;
+        for group in &groups {
+            for document in group.documents.iter().skip(1) {
+                removed += tx.execute(
+                    "UPDATE context_documents SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+                    params![&now, &document.id],
+                ).map_err(|e| format!("Failed to delete duplicate document: {}", e))?;
+            }
+        }
+
+        Ok(removed)
+    })
+}
+
+// Moves the document to the trash; empty_trash or the startup purge removes it for good
+#[tauri::command]
+pub async fn delete_context_document(
+    id: String,
+    pool: State<'_, DbPool>,
+) -> Result<usize, String> {
+    let conn = get_db_connection(&pool)?;
+    trash_row(&conn, "context_documents", &id)
+        .map_err(|e| format!("Failed to delete context document: {}", e))
+}
+
+// Embedding commands
+
+const DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-3-small";
+const DEFAULT_CHUNK_SIZE: usize = 800;
+const DEFAULT_CHUNK_OVERLAP: usize = 100;
+const EMBEDDING_BATCH_SIZE: usize = 100;
+
+// Splits text into windows of `size` chars that overlap by `overlap` chars
+fn chunk_text(text: &str, size: usize, overlap: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.is_empty() || size == 0 {
+        return Vec::new();
+    }
+
+    let step = size.saturating_sub(overlap).max(1);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let end = (start + size).min(chars.len());
+        let chunk: String = chars[start..end].iter().collect();
+        if !chunk.trim().is_empty() {
+            chunks.push(chunk);
+        }
+        if end == chars.len() {
+            break;
+        }
+        start += step;
+    }
+    chunks
+}
+
+fn embedding_to_blob(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn blob_to_embedding(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+async fn request_embeddings(api_key: &str, model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>, String> {
+    let client = reqwest::Client::new();
+    let resp = client
+        .post("https://api.openai.com/v1/embeddings")
+        .header("Authorization", format!("Bearer {}", api_key))
+        .header("Content-Type", "application/json")
+        .json(&serde_json::json!({ "model": model, "input": inputs }))
+        .send()
+        .await
+        .map_err(|e| format!("Embedding request failed: {}", e))?;
+
+    if !resp.status().is_success() {
+        let err_text = resp.text().await.unwrap_or_default();
+        return Err(format!("Embedding request failed: {}", err_text));
+    }
+
+    let body: serde_json::Value = resp.json().await
+        .map_err(|e| format!("Parse error: {}", e))?;
+
+    let data = body.get("data")
+        .and_then(|d| d.as_array())
+        .ok_or("Embedding response missing data")?;
+
+    let mut embeddings = vec![Vec::new(); inputs.len()];
+    for item in data {
+        let index = item.get("index").and_then(|i| i.as_u64()).unwrap_or(0) as usize;
+        let vector: Vec<f32> = item.get("embedding")
+            .and_then(|e| e.as_array())
+            .ok_or("Embedding response missing vector")?
+            .iter()
+            .map(|v| v.as_f64().unwrap_or(0.0) as f32)
+            .collect();
+        if index < embeddings.len() {
+            embeddings[index] = vector;
+        }
+    }
+
+    if embeddings.iter().any(|e| e.is_empty()) {
+        return Err("Embedding response did not cover every input".to_string());
+    }
+    Ok(embeddings)
+}
+
+#[tauri::command]
+pub async fn generate_document_embeddings(
+    document_id: String,
+    model: Option<String>,
+    chunk_size: Option<usize>,
+    chunk_overlap: Option<usize>,
+    pool: State<'_, DbPool>,
+) -> Result<i32, String> {
+    let model = model.unwrap_or_else(|| DEFAULT_EMBEDDING_MODEL.to_string());
+    let chunk_size = chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+    let chunk_overlap = chunk_overlap.unwrap_or(DEFAULT_CHUNK_OVERLAP);
+    if chunk_overlap >= chunk_size {
+        return Err("Chunk overlap must be smaller than chunk size".to_string());
+    }
+
+    let (content, is_encrypted): (String, bool) = {
+        let conn = get_db_connection(&pool)?;
+        conn.query_row(
+            "SELECT content, is_encrypted FROM context_documents WHERE id = ?1",
+            params![&document_id],
+            |row| Ok((row.get(0)?, row.get::<_, i32>(1)? != 0)),
+        ).map_err(|e| format!("Context document not found: {}", e))?
+    };
+    if is_encrypted {
+        return Err("Encrypted documents are excluded from semantic search".to_string());
+    }
+
+    let api_key = get_decrypted_api_key(pool.clone()).await?
+        .ok_or("API key not configured")?;
+
+    let chunks = chunk_text(&content, chunk_size, chunk_overlap);
+    let mut vectors = Vec::with_capacity(chunks.len());
+    for batch in chunks.chunks(EMBEDDING_BATCH_SIZE) {
+        vectors.extend(request_embeddings(&api_key, &model, batch).await?);
+    }
+
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
+    with_transaction(&conn, |tx| {
+        tx.execute("DELETE FROM document_embeddings WHERE document_id = ?1", params![&document_id])
+            .map_err(|e| format!("Failed to clear existing embeddings: {}", e))?;
+
+        for (i, (chunk, vector)) in chunks.iter().zip(vectors.iter()).enumerate() {
+            tx.execute(
+                "INSERT INTO document_embeddings (id, document_id, chunk_text, chunk_index, embedding, model, dimension, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![Uuid::new_v4().to_string(), &document_id, chunk, i as i32, embedding_to_blob(vector), &model, vector.len() as i64, &now],
+            ).map_err(|e| format!("Failed to store embedding: {}", e))?;
+        }
+        Ok::<_, String>(())
+    })?;
+
+    Ok(chunks.len() as i32)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReindexProgressEvent {
+    pub project_id: String,
+    pub document_id: String,
+    pub document_name: String,
+    pub chunks: i32,
+    pub skipped: bool,
+    pub completed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReindexSummary {
+    pub documents_processed: usize,
+    pub documents_skipped: usize,
+    pub chunks_generated: i32,
+}
+
+// A document is current when it has vectors and every one came from `model` at a single dimension
+fn embeddings_current(conn: &Connection, document_id: &str, model: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0
+                AND COALESCE(SUM(model = ?2), 0) = COUNT(*)
+                AND COUNT(DISTINCT dimension) = 1
+         FROM document_embeddings WHERE document_id = ?1",
+        params![document_id, model],
+        |row| row.get(0),
+    ).map_err(|e| format!("Failed to inspect embeddings: {}", e))
+}
+
+// Regenerates vectors for every unencrypted document in the project, emitting `reindex-progress`
+// per document. Documents already embedded with `model` are skipped so an interrupted run can be
+// restarted cheaply; `force` re-embeds them anyway (e.g. after changing chunk sizes).
+#[tauri::command]
+pub async fn reindex_project_embeddings(
+    project_id: String,
+    model: Option<String>,
+    chunk_size: Option<usize>,
+    chunk_overlap: Option<usize>,
+    force: Option<bool>,
+    app: tauri::AppHandle,
+    pool: State<'_, DbPool>,
+) -> Result<ReindexSummary, String> {
+    let model = model.unwrap_or_else(|| DEFAULT_EMBEDDING_MODEL.to_string());
+    let force = force.unwrap_or(false);
+
+    let documents: Vec<(String, String)> = {
+        let conn = get_db_connection(&pool)?;
+        let mut stmt = conn.prepare(
+            "SELECT id, name FROM context_documents
+             WHERE project_id = ?1 AND is_encrypted = 0 AND deleted_at IS NULL
+             ORDER BY created_at ASC, id ASC"
+        ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let rows = stmt.query_map(params![&project_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("Failed to query documents: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect documents: {}", e))?
+    };
+
+    let total = documents.len();
+    let mut summary = ReindexSummary { documents_processed: 0, documents_skipped: 0, chunks_generated: 0 };
+
+    for (index, (document_id, document_name)) in documents.into_iter().enumerate() {
+        let skipped = !force && {
+            let conn = get_db_connection(&pool)?;
+            embeddings_current(&conn, &document_id, &model)?
+        };
+
+        let chunks = if skipped {
+            summary.documents_skipped += 1;
+            0
+        } else {
+            let chunks = generate_document_embeddings(
+                document_id.clone(), Some(model.clone()), chunk_size, chunk_overlap, pool.clone(),
+            ).await?;
+            summary.documents_processed += 1;
+            summary.chunks_generated += chunks;
+            chunks
+        };
+
+        let _ = app.emit("reindex-progress", ReindexProgressEvent {
+            project_id: project_id.clone(),
+            document_id,
+            document_name,
+            chunks,
+            skipped,
+            completed: index + 1,
+            total,
+        });
+    }
+
+    Ok(summary)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SimilarChunk {
+    pub document_id: String,
+    pub document_name: String,
+    pub chunk_index: i32,
+    pub chunk_text: String,
+    pub score: f32,
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let mut dot = 0.0f32;
+    let mut norm_a = 0.0f32;
+    let mut norm_b = 0.0f32;
+    for (x, y) in a.iter().zip(b.iter()) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DocumentEmbeddingStatus {
+    pub document_id: String,
+    // "indexed", "not_indexed" or "outdated"
+    pub status: String,
+    pub chunk_count: i64,
+    pub model: Option<String>,
+    pub dimension: Option<i64>,
+    pub indexed_at: Option<i64>,
+}
+
+// A document is outdated when its chunks came from another model, mix models or dimensions,
+// or have a different dimension than the model's most recent vectors (e.g. after a model change).
+#[tauri::command]
+pub async fn get_document_embedding_status(
+    document_id: String,
+    model: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<DocumentEmbeddingStatus, String> {
+    let model = model.unwrap_or_else(|| DEFAULT_EMBEDDING_MODEL.to_string());
+    let conn = get_db_connection(&pool)?;
+
+    let (chunk_count, distinct_models, distinct_dimensions, stored_model, dimension, indexed_at): (i64, i64, i64, Option<String>, Option<i64>, Option<i64>) =
+        conn.query_row(
+            "SELECT COUNT(*), COUNT(DISTINCT model), COUNT(DISTINCT dimension), MAX(model), MAX(dimension), MAX(created_at)
+             FROM document_embeddings WHERE document_id = ?1",
+            params![&document_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)),
+        ).map_err(|e| format!("Failed to read embedding status: {}", e))?;
+
+    let status = if chunk_count == 0 {
+        "not_indexed"
+    } else {
+        let current_dimension: Option<i64> = conn.query_row(
+            "SELECT dimension FROM document_embeddings WHERE model = ?1 AND dimension IS NOT NULL
+             ORDER BY created_at DESC LIMIT 1",
+            params![&model],
+            |row| row.get(0),
+        ).optional().map_err(|e| format!("Failed to read embedding dimension: {}", e))?;
+
+        let stale = distinct_models > 1
+            || distinct_dimensions > 1
+            || stored_model.as_deref() != Some(model.as_str())
+            || dimension.is_none()
+            || current_dimension.is_some_and(|d| Some(d) != dimension);
+        if stale { "outdated" } else { "indexed" }
+    };
+
+    Ok(DocumentEmbeddingStatus {
+        document_id,
+        status: status.to_string(),
+        chunk_count,
+        model: stored_model,
+        dimension,
+        indexed_at,
+    })
+}
+
+#[tauri::command]
+pub async fn search_similar_chunks(
+    project_id: String,
+    query_text: String,
+    top_k: Option<usize>,
+    model: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<SimilarChunk>, String> {
+    let model = model.unwrap_or_else(|| DEFAULT_EMBEDDING_MODEL.to_string());
+    let top_k = top_k.unwrap_or(5);
+    if query_text.trim().is_empty() || top_k == 0 {
+        return Ok(Vec::new());
+    }
+
+    let api_key = get_decrypted_api_key(pool.clone()).await?
+        .ok_or("API key not configured")?;
+    let query_vector = request_embeddings(&api_key, &model, &[query_text]).await?
+        .pop()
+        .ok_or("Embedding response was empty")?;
+
+    let conn = get_db_connection(&pool)?;
+    let mut stmt = conn.prepare(
+        "SELECT e.document_id, d.name, e.chunk_index, e.chunk_text, e.embedding
+         FROM document_embeddings e
+         JOIN context_documents d ON d.id = e.document_id
+         WHERE d.project_id = ?1 AND d.deleted_at IS NULL AND e.model = ?2 AND e.embedding IS NOT NULL"
+    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let rows = stmt.query_map(params![&project_id, &model], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, i32>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, Vec<u8>>(4)?,
+        ))
+    }).map_err(|e| format!("Failed to query embeddings: {}", e))?;
+
+    let mut scored = Vec::new();
+    for row in rows {
+        let (document_id, document_name, chunk_index, chunk_text, blob) =
+            row.map_err(|e| format!("Failed to read embedding: {}", e))?;
+        let vector = blob_to_embedding(&blob);
+        // Vectors from a different model/dimension can't be compared meaningfully
+        if vector.len() != query_vector.len() {
+            continue;
+        }
+        scored.push(SimilarChunk {
+            document_id,
+            document_name,
+            chunk_index,
+            chunk_text,
+            score: cosine_similarity(&query_vector, &vector),
+        });
+    }
+
+    scored.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    scored.truncate(top_k);
+    Ok(scored)
+}
+
+// Roughly 12k tokens at ~4 characters per token
+const DEFAULT_CONTEXT_CHAR_BUDGET: usize = 48_000;
+const DEFAULT_CONTEXT_TOP_K: usize = 5;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IncludedContextDocument {
+    pub id: String,
+    pub name: String,
+    pub chars: usize,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IncludedContextChunk {
+    pub document_id: String,
+    pub document_name: String,
+    pub chunk_index: i32,
+    pub score: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GenerationContext {
+    pub context: String,
+    pub documents: Vec<IncludedContextDocument>,
+    pub chunks: Vec<IncludedContextChunk>,
+    pub total_chars: usize,
+    // Non-fatal problems, e.g. a selected document that no longer exists or a failed embedding lookup
+    pub warnings: Vec<String>,
+}
+
+// Appends `body` under `header` if any budget is left, cutting the body to fit; returns the
+// number of body characters kept and whether it was cut
+fn push_context_section(context: &mut String, remaining: &mut usize, header: &str, body: &str) -> Option<(usize, bool)> {
+    let overhead = header.chars().count() + 4;
+    if *remaining <= overhead {
+        return None;
+    }
+    let available = *remaining - overhead;
+    let body_chars = body.chars().count();
+    let kept: String = body.chars().take(available).collect();
+    let kept_chars = body_chars.min(available);
+
+    context.push_str(header);
+    context.push_str("\n\n");
+    context.push_str(&kept);
+    context.push_str("\n\n");
+    *remaining -= overhead + kept_chars;
+    Some((kept_chars, kept_chars < body_chars))
+}
+
+// Selected documents go in first, in the order given, then the chunks most similar to `query`
+// (or to the framework itself when no query is given) from the project's other indexed
+// documents, all within `max_chars`
+#[tauri::command]
+pub async fn build_generation_context(
+    project_id: String,
+    framework_id: String,
+    context_doc_ids: Vec<String>,
+    query: Option<String>,
+    max_chars: Option<usize>,
+    top_k: Option<usize>,
+    pool: State<'_, DbPool>,
+) -> Result<GenerationContext, String> {
+    let budget = max_chars.unwrap_or(DEFAULT_CONTEXT_CHAR_BUDGET);
+    let top_k = top_k.unwrap_or(DEFAULT_CONTEXT_TOP_K);
+
+    let (framework_query, trashed, has_embeddings) = {
+        let conn = get_db_connection(&pool)?;
+        ensure_project_exists(&conn, &project_id).map_err(String::from)?;
+        let framework_query: Option<String> = conn.query_row(
+            "SELECT name || '\n' || description || '\n' || guiding_questions FROM framework_definitions WHERE id = ?1",
+            params![&framework_id],
+            |row| row.get(0),
+        ).optional().map_err(|e| format!("Failed to look up framework: {}", e))?;
+        let Some(framework_query) = framework_query else {
+            return Err(format!("Framework '{}' not found", framework_id));
+        };
+        let mut stmt = conn.prepare("SELECT deleted_at IS NOT NULL FROM context_documents WHERE id = ?1")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let mut trashed = std::collections::HashSet::new();
+        for doc_id in &context_doc_ids {
+            let is_trashed: Option<bool> = stmt.query_row(params![doc_id], |row| row.get(0))
+                .optional().map_err(|e| format!("Failed to look up context document: {}", e))?;
+            if is_trashed == Some(true) {
+                trashed.insert(doc_id.clone());
+            }
+        }
+        let has_embeddings: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM document_embeddings e
+                           JOIN context_documents d ON d.id = e.document_id
+                           WHERE d.project_id = ?1 AND d.deleted_at IS NULL)",
+            params![&project_id],
+            |row| row.get(0),
+        ).map_err(|e| format!("Failed to check embeddings: {}", e))?;
+        (framework_query, trashed, has_embeddings)
+    };
+
+    let mut context = String::new();
+    let mut remaining = budget;
+    let mut documents = Vec::new();
+    let mut warnings = Vec::new();
+
+    for doc_id in &context_doc_ids {
+        let Some(doc) = get_context_document(doc_id.clone(), pool.clone()).await? else {
+            warnings.push(format!("Context document '{}' not found", doc_id));
+            continue;
+        };
+        if trashed.contains(doc_id) {
+            warnings.push(format!("Context document '{}' is in the trash", doc.name));
+            continue;
+        }
+        let header = format!("## Context Document: {}", doc.name);
+        match push_context_section(&mut context, &mut remaining, &header, &doc.content) {
+            Some((chars, truncated)) => documents.push(IncludedContextDocument { id: doc.id, name: doc.name, chars, truncated }),
+            None => warnings.push(format!("'{}' left out: context budget exhausted", doc.name)),
+        }
+    }
+
+    let mut chunks = Vec::new();
+    // Without a query of its own, the framework's description and guiding questions pick the excerpts
+    let query = query.filter(|q| !q.trim().is_empty()).unwrap_or(framework_query);
+    if has_embeddings && !query.trim().is_empty() && top_k > 0 && remaining > 0 {
+        match search_similar_chunks(project_id.clone(), query, Some(top_k), None, pool.clone()).await {
+            Ok(similar) => {
+                for chunk in similar {
+                    // Documents included in full already cover their own chunks
+                    if context_doc_ids.contains(&chunk.document_id) {
+                        continue;
+                    }
+                    let header = format!("## Relevant Excerpt: {} (part {})", chunk.document_name, chunk.chunk_index + 1);
+                    if push_context_section(&mut context, &mut remaining, &header, &chunk.chunk_text).is_none() {
+                        break;
+                    }
+                    chunks.push(IncludedContextChunk {
+                        document_id: chunk.document_id,
+                        document_name: chunk.document_name,
+                        chunk_index: chunk.chunk_index,
+                        score: chunk.score,
+                    });
+                }
+            }
+            Err(e) => warnings.push(format!("Skipped relevant excerpts: {}", e)),
+        }
+    }
+
+    let context = context.trim_end().to_string();
+    Ok(GenerationContext {
+        total_chars: context.chars().count(),
+        context,
+        documents,
+        chunks,
+        warnings,
+    })
+}
+
+// Framework Output commands
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FrameworkOutput {
+    pub id: String,
+    pub project_id: String,
+    pub framework_id: String,
+    pub category: String,
+    pub name: String,
+    pub user_prompt: String,
+    pub context_doc_ids: String,
+    pub generated_content: String,
+    pub format: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub folder_id: Option<String>,
+    pub tags: String,
+    pub is_favorite: bool,
+    pub sort_order: i32,
+    // The chat that produced the output; cleared rather than cascaded when that chat is deleted
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    #[serde(default)]
+    pub word_count: i64,
+    // Derived from word_count, never stored
+    #[serde(default)]
+    pub reading_time_minutes: i64,
+    // Who generated the output, taken from settings at creation time
+    #[serde(default)]
+    pub author: Option<String>,
+    // Wall-clock generation time as measured by the frontend
+    #[serde(default)]
+    pub generation_ms: Option<i64>,
+}
+
+const FRAMEWORK_OUTPUT_COLUMNS: &str = "id, project_id, framework_id, category, name, user_prompt, context_doc_ids, generated_content, format, created_at, updated_at, folder_id, tags, is_favorite, sort_order, conversation_id, word_count, author, generation_ms";
+
+const READING_WORDS_PER_MINUTE: u64 = 200;
+
+fn word_count(text: &str) -> i64 {
+    text.split_whitespace().count() as i64
+}
+
+// Rounds up so any non-empty output reads as at least a minute
+fn reading_time_minutes(word_count: i64) -> i64 {
+    (word_count.max(0) as u64).div_ceil(READING_WORDS_PER_MINUTE) as i64
+}
+
+fn row_to_framework_output(row: &rusqlite::Row) -> rusqlite::Result<FrameworkOutput> {
+    let words = row.get::<_, Option<i64>>(16)?.unwrap_or(0);
+    Ok(FrameworkOutput {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        framework_id: row.get(2)?,
+        category: row.get(3)?,
+        name: row.get(4)?,
+        user_prompt: row.get(5)?,
+        context_doc_ids: row.get(6)?,
+        generated_content: row.get(7)?,
+        format: row.get(8)?,
+        created_at: row.get(9)?,
+        updated_at: row.get(10)?,
+        folder_id: row.get(11)?,
+        tags: row.get::<_, Option<String>>(12)?.unwrap_or_else(|| "[]".to_string()),
+        is_favorite: row.get::<_, Option<i32>>(13)?.unwrap_or(0) != 0,
+        sort_order: row.get::<_, Option<i32>>(14)?.unwrap_or(0),
+        conversation_id: row.get(15)?,
+        word_count: words,
+        reading_time_minutes: reading_time_minutes(words),
+        author: row.get(17)?,
+        generation_ms: row.get(18)?,
+    })
+}
+
+const UNKNOWN_AUTHOR: &str = "Unknown";
+
+// Full name from settings, falling back to the username, then to "Unknown" when both are blank
+fn current_author(conn: &Connection) -> String {
+    let names: Option<(Option<String>, Option<String>, Option<String>)> = conn.query_row(
+        "SELECT name, surname, username FROM settings WHERE id = 'default'",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    ).ok();
+    let Some((name, surname, username)) = names else {
+        return UNKNOWN_AUTHOR.to_string();
+    };
+
+    let full_name = [name, surname].into_iter()
+        .flatten()
+        .map(|part| part.trim().to_string())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if !full_name.is_empty() {
+        return full_name;
+    }
+    username.map(|u| u.trim().to_string())
+        .filter(|u| !u.is_empty())
+        .unwrap_or_else(|| UNKNOWN_AUTHOR.to_string())
+}
+
+#[tauri::command]
+pub async fn create_framework_output(
+    project_id: String,
+    framework_id: String,
+    category: String,
+    name: String,
+    user_prompt: String,
+    context_doc_ids: String,
+    generated_content: String,
+    format: String,
+    conversation_id: Option<String>,
+    generation_ms: Option<i64>,
+    app: tauri::AppHandle,
+    pool: State<'_, DbPool>,
+) -> Result<FrameworkOutput, AppError> {
+    let conn = get_db_connection(&pool)?;
+    ensure_project_exists(&conn, &project_id)?;
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().timestamp();
+    let words = word_count(&generated_content);
+    let author = current_author(&conn);
+
+    let output = FrameworkOutput {
+        id: id.clone(),
+        project_id: project_id.clone(),
+        framework_id: framework_id.clone(),
+        category: category.clone(),
+        name: name.clone(),
+        user_prompt: user_prompt.clone(),
+        context_doc_ids: context_doc_ids.clone(),
+        generated_content: generated_content.clone(),
+        format: format.clone(),
+        created_at: now,
+        updated_at: now,
+        folder_id: None,
+        tags: "[]".to_string(),
+        is_favorite: false,
+        sort_order: 0,
+        conversation_id: conversation_id.clone(),
+        word_count: words,
+        reading_time_minutes: reading_time_minutes(words),
+        author: Some(author.clone()),
+        generation_ms,
+    };
+
+    conn.execute(
+        "INSERT INTO framework_outputs (id, project_id, framework_id, category, name, user_prompt, context_doc_ids, generated_content, format, created_at, updated_at, conversation_id, word_count, author, generation_ms)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+        params![&id, &project_id, &framework_id, &category, &name, &user_prompt, &context_doc_ids, &generated_content, &format, &now, &now, &conversation_id, &words, &author, &generation_ms],
+    ).map_err(|e| AppError::database("Failed to create framework output", e))?;
+
+    let _ = commit_output(project_id.clone(), id.clone(), name.clone(), generated_content.clone(), format!("Create: {}", name), app).await;
+
+    Ok(output)
+}
+
+#[tauri::command]
+pub async fn list_framework_outputs(
+    project_id: String,
+    sort_by: Option<String>,
+    sort_dir: Option<String>,
+    created_after: Option<i64>,
+    created_before: Option<i64>,
+    tags: Option<Vec<String>>,
+    match_all: Option<bool>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<FrameworkOutput>, String> {
+    let order = list_order_clause(sort_by.as_deref(), sort_dir.as_deref(), "sort_order ASC, updated_at DESC")?;
+    let conn = get_db_connection(&pool)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM framework_outputs
+         WHERE project_id = ?1 AND deleted_at IS NULL
+           AND (?2 IS NULL OR created_at > ?2)
+           AND (?3 IS NULL OR created_at < ?3)
+         ORDER BY {}",
+        FRAMEWORK_OUTPUT_COLUMNS, order
+    )).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let outputs = stmt.query_map(params![&project_id, &created_after, &created_before], row_to_framework_output).map_err(|e| format!("Failed to query framework outputs: {}", e))?;
+
+    let outputs = outputs.collect::<Result<Vec<FrameworkOutput>, _>>()
+        .map_err(|e| format!("Failed to collect framework outputs: {}", e))?;
+
+    let wanted = normalize_tags(tags.unwrap_or_default());
+    let match_all = match_all.unwrap_or(true);
+    Ok(outputs.into_iter().filter(|o| tags_match(&o.tags, &wanted, match_all)).collect())
+}
+
+#[tauri::command]
+pub async fn get_framework_output(
+    id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Option<FrameworkOutput>, String> {
+    let conn = get_db_connection(&pool)?;
+
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM framework_outputs WHERE id = ?1", FRAMEWORK_OUTPUT_COLUMNS)).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let output = stmt.query_row(params![&id], row_to_framework_output).optional()
+        .map_err(|e| format!("Failed to get framework output: {}", e))?;
+
+    Ok(output)
+}
+
+#[tauri::command]
+pub async fn list_outputs_for_conversation(
+    conversation_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<FrameworkOutput>, String> {
+    let conn = get_db_connection(&pool)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM framework_outputs WHERE conversation_id = ?1 AND deleted_at IS NULL ORDER BY created_at DESC",
+        FRAMEWORK_OUTPUT_COLUMNS
+    )).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let outputs = stmt.query_map(params![&conversation_id], row_to_framework_output)
+        .map_err(|e| format!("Failed to query framework outputs: {}", e))?;
+
+    let result: Result<Vec<FrameworkOutput>, _> = outputs.collect();
+    result.map_err(|e| format!("Failed to collect framework outputs: {}", e))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FrameworkOutputVersion {
+    pub id: String,
+    pub output_id: String,
+    pub version_no: i32,
+    pub content: String,
+    pub created_at: i64,
+}
+
+const DEFAULT_MAX_OUTPUT_VERSIONS: u32 = 20;
+
+// Saves the output's current content as the next version, then prunes the oldest
+// versions beyond `max_versions`
+fn snapshot_output_version(conn: &Connection, output_id: &str, max_versions: u32) -> Result<(), String> {
+    let now = Utc::now().timestamp();
+
+    conn.execute(
+        "INSERT INTO framework_output_versions (id, output_id, version_no, content, created_at)
+         SELECT ?1, id, (SELECT COALESCE(MAX(version_no), 0) + 1 FROM framework_output_versions WHERE output_id = ?2),
+                generated_content, ?3
+         FROM framework_outputs WHERE id = ?2",
+        params![Uuid::new_v4().to_string(), output_id, &now],
+    ).map_err(|e| format!("Failed to snapshot framework output: {}", e))?;
+
+    conn.execute(
+        "DELETE FROM framework_output_versions
+         WHERE output_id = ?1 AND version_no NOT IN (
+             SELECT version_no FROM framework_output_versions
+             WHERE output_id = ?1 ORDER BY version_no DESC LIMIT ?2
+         )",
+        params![output_id, max_versions],
+    ).map_err(|e| format!("Failed to prune framework output versions: {}", e))?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn update_framework_output(
+    id: String,
+    name: String,
+    generated_content: String,
+    max_versions: Option<u32>,
+    app: tauri::AppHandle,
+    pool: State<'_, DbPool>,
+) -> Result<FrameworkOutput, String> {
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
+
+    // Get project_id before update for git commit
+    let (project_id, previous_content): (String, String) = conn.query_row(
+        "SELECT project_id, generated_content FROM framework_outputs WHERE id = ?1",
+        params![&id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).map_err(|e| format!("Output not found: {}", e))?;
+
+    with_transaction(&conn, |tx| {
+        if previous_content != generated_content {
+            snapshot_output_version(tx, &id, max_versions.unwrap_or(DEFAULT_MAX_OUTPUT_VERSIONS))?;
+        }
+
+        tx.execute(
+            "UPDATE framework_outputs
+             SET name = ?1, generated_content = ?2, updated_at = ?3, word_count = ?5
+             WHERE id = ?4",
+            params![&name, &generated_content, &now, &id, word_count(&generated_content)],
+        ).map_err(|e| format!("Failed to update framework output: {}", e))?;
+        Ok::<_, String>(())
+    })?;
+
+    let _ = commit_output(project_id, id.clone(), name.clone(), generated_content.clone(), format!("Update: {}", name), app.clone()).await;
+
+    // Fetch the updated output
+    get_framework_output(id, pool).await?
+        .ok_or_else(|| "Framework output not found after update".to_string())
+}
+
+#[tauri::command]
+pub async fn list_framework_output_versions(
+    output_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<FrameworkOutputVersion>, String> {
+    let conn = get_db_connection(&pool)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, output_id, version_no, content, created_at
+         FROM framework_output_versions
+         WHERE output_id = ?1
+         ORDER BY version_no DESC"
+    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let versions = stmt.query_map(params![&output_id], |row| {
+        Ok(FrameworkOutputVersion {
+            id: row.get(0)?,
+            output_id: row.get(1)?,
+            version_no: row.get(2)?,
+            content: row.get(3)?,
+            created_at: row.get(4)?,
+        })
+    }).map_err(|e| format!("Failed to query framework output versions: {}", e))?;
+
+    let result: Result<Vec<FrameworkOutputVersion>, _> = versions.collect();
+    result.map_err(|e| format!("Failed to collect framework output versions: {}", e))
+}
+
+#[tauri::command]
+pub async fn restore_framework_output_version(
+    output_id: String,
+    version_no: i32,
+    app: tauri::AppHandle,
+    pool: State<'_, DbPool>,
+) -> Result<FrameworkOutput, String> {
+    let (project_id, name, content) = {
+        let conn = get_db_connection(&pool)?;
+        let now = Utc::now().timestamp();
+
+        let content: String = conn.query_row(
+            "SELECT content FROM framework_output_versions WHERE output_id = ?1 AND version_no = ?2",
+            params![&output_id, &version_no],
+            |row| row.get(0),
+        ).map_err(|e| format!("Version {} not found: {}", version_no, e))?;
+
+        let (project_id, name): (String, String) = conn.query_row(
+            "SELECT project_id, name FROM framework_outputs WHERE id = ?1",
+            params![&output_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).map_err(|e| format!("Output not found: {}", e))?;
+
+        // Keep the content being replaced so the restore itself can be undone
+        with_transaction(&conn, |tx| {
+            snapshot_output_version(tx, &output_id, DEFAULT_MAX_OUTPUT_VERSIONS)?;
+
+            tx.execute(
+                "UPDATE framework_outputs SET generated_content = ?1, updated_at = ?2, word_count = ?4 WHERE id = ?3",
+                params![&content, &now, &output_id, word_count(&content)],
+            ).map_err(|e| format!("Failed to restore framework output: {}", e))?;
+            Ok::<_, String>(())
+        })?;
+
+        (project_id, name, content)
+    };
+
+    let _ = commit_output(project_id, output_id.clone(), name.clone(), content, format!("Restore version {}: {}", version_no, name), app.clone()).await;
+
+    get_framework_output(output_id, pool).await?
+        .ok_or_else(|| "Framework output not found after restore".to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Unchanged,
+}
+
+// A run of consecutive lines sharing a kind; line numbers are 1-based and absent on the side
+// the run does not appear in
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiffSegment {
+    pub kind: DiffKind,
+    pub text: String,
+    pub old_start: Option<usize>,
+    pub new_start: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OutputDiff {
+    pub segments: Vec<DiffSegment>,
+    pub added_lines: usize,
+    pub removed_lines: usize,
+}
+
+fn diff_lines(old: &str, new: &str) -> OutputDiff {
+    let diff = similar::TextDiff::from_lines(old, new);
+    let mut segments: Vec<DiffSegment> = Vec::new();
+    let mut added_lines = 0;
+    let mut removed_lines = 0;
+
+    for change in diff.iter_all_changes() {
+        let kind = match change.tag() {
+            similar::ChangeTag::Insert => {
+                added_lines += 1;
+                DiffKind::Added
+            }
+            similar::ChangeTag::Delete => {
+                removed_lines += 1;
+                DiffKind::Removed
+            }
+            similar::ChangeTag::Equal => DiffKind::Unchanged,
+        };
+        match segments.last_mut() {
+            Some(last) if last.kind == kind => last.text.push_str(change.value()),
+            _ => segments.push(DiffSegment {
+                kind,
+                text: change.value().to_string(),
+                old_start: change.old_index().map(|i| i + 1),
+                new_start: change.new_index().map(|i| i + 1),
+            }),
+        }
+    }
+
+    OutputDiff { segments, added_lines, removed_lines }
+}
+
+fn output_content(conn: &Connection, output_id: &str) -> Result<String, String> {
+    conn.query_row(
+        "SELECT generated_content FROM framework_outputs WHERE id = ?1",
+        params![output_id],
+        |row| row.get(0),
+    ).optional()
+        .map_err(|e| format!("Failed to load framework output: {}", e))?
+        .ok_or_else(|| format!("Framework output '{}' not found", output_id))
+}
+
+fn output_version_content(conn: &Connection, output_id: &str, version_no: i32) -> Result<String, String> {
+    conn.query_row(
+        "SELECT content FROM framework_output_versions WHERE output_id = ?1 AND version_no = ?2",
+        params![output_id, &version_no],
+        |row| row.get(0),
+    ).optional()
+        .map_err(|e| format!("Failed to load version: {}", e))?
+        .ok_or_else(|| format!("Version {} not found", version_no))
+}
+
+// Line diff from output A to output B
+#[tauri::command]
+pub async fn diff_framework_outputs(
+    id_a: String,
+    id_b: String,
+    pool: State<'_, DbPool>,
+) -> Result<OutputDiff, String> {
+    let conn = get_db_connection(&pool)?;
+    let old = output_content(&conn, &id_a)?;
+    let new = output_content(&conn, &id_b)?;
+    Ok(diff_lines(&old, &new))
+}
+
+// Line diff between two saved versions of one output; a missing version_b means the current content
+#[tauri::command]
+pub async fn diff_framework_output_versions(
+    output_id: String,
+    version_a: i32,
+    version_b: Option<i32>,
+    pool: State<'_, DbPool>,
+) -> Result<OutputDiff, String> {
+    let conn = get_db_connection(&pool)?;
+    let old = output_version_content(&conn, &output_id, version_a)?;
+    let new = match version_b {
+        Some(version_no) => output_version_content(&conn, &output_id, version_no)?,
+        None => output_content(&conn, &output_id)?,
+    };
+    Ok(diff_lines(&old, &new))
+}
+
+// Moves the output to the trash; empty_trash or the startup purge removes it for good
+#[tauri::command]
+pub async fn delete_framework_output(
+    id: String,
+    pool: State<'_, DbPool>,
+) -> Result<usize, String> {
+    let conn = get_db_connection(&pool)?;
+    trash_row(&conn, "framework_outputs", &id)
+        .map_err(|e| format!("Failed to delete framework output: {}", e))
+}
+
+// Trashed library items older than this are purged on startup
+const TRASH_RETENTION_DAYS: i64 = 30;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrashedItem {
+    pub id: String,
+    pub name: String,
+    pub item_type: String,
+    pub folder_id: Option<String>,
+    pub deleted_at: i64,
+}
+
+#[tauri::command]
+pub async fn list_trash(project_id: String, pool: State<'_, DbPool>) -> Result<Vec<TrashedItem>, String> {
+    let conn = get_db_connection(&pool)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, name, 'context_doc' as item_type, folder_id, deleted_at
+         FROM context_documents WHERE project_id = ?1 AND deleted_at IS NOT NULL
+         UNION ALL
+         SELECT id, name, 'framework_output' as item_type, folder_id, deleted_at
+         FROM framework_outputs WHERE project_id = ?1 AND deleted_at IS NOT NULL
+         ORDER BY deleted_at DESC"
+    ).map_err(|e| format!("Failed to prepare trash query: {}", e))?;
+
+    let items = stmt.query_map(params![&project_id], |row| {
+        Ok(TrashedItem {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            item_type: row.get(2)?,
+            folder_id: row.get(3)?,
+            deleted_at: row.get(4)?,
+        })
+    }).map_err(|e| format!("Failed to query trash: {}", e))?;
+
+    let result: Result<Vec<TrashedItem>, _> = items.collect();
+    result.map_err(|e| format!("Failed to collect trash: {}", e))
+}
+
+// Restored items whose folder no longer exists come back unfiled
+#[tauri::command]
+pub async fn restore_item(id: String, item_type: String, pool: State<'_, DbPool>) -> Result<(), String> {
+    let table = library_item_table(&item_type)?;
+    let conn = get_db_connection(&pool)?;
+
+    let restored = conn.execute(
+        &format!(
+            "UPDATE {} SET deleted_at = NULL,
+                 folder_id = CASE WHEN folder_id IN (SELECT id FROM folders) THEN folder_id ELSE NULL END
+             WHERE id = ?1 AND deleted_at IS NOT NULL",
+            table
+        ),
+        params![&id],
+    ).map_err(|e| format!("Failed to restore item: {}", e))?;
+
+    if restored == 0 {
+        return Err(format!("No trashed {} with id '{}'", item_type, id));
+    }
+    Ok(())
+}
+
+// Permanently deletes every trashed item in the project; returns how many were removed
+#[tauri::command]
+pub async fn empty_trash(project_id: String, pool: State<'_, DbPool>) -> Result<usize, String> {
+    let conn = get_db_connection(&pool)?;
+    with_transaction(&conn, |tx| {
+        let documents = tx.execute(
+            "DELETE FROM context_documents WHERE project_id = ?1 AND deleted_at IS NOT NULL",
+            params![&project_id],
+        ).map_err(|e| format!("Failed to empty trashed documents: {}", e))?;
+        let outputs = tx.execute(
+            "DELETE FROM framework_outputs WHERE project_id = ?1 AND deleted_at IS NOT NULL",
+            params![&project_id],
+        ).map_err(|e| format!("Failed to empty trashed outputs: {}", e))?;
+        Ok(documents + outputs)
+    })
+}
+
+fn purge_expired_trash(conn: &Connection, retention_days: i64) -> Result<(), String> {
+    let cutoff = Utc::now().timestamp() - retention_days * 24 * 60 * 60;
+    for table in ["context_documents", "framework_outputs"] {
+        conn.execute(
+            &format!("DELETE FROM {} WHERE deleted_at IS NOT NULL AND deleted_at < ?1", table),
+            params![&cutoff],
+        ).map_err(|e| format!("Failed to purge trashed {}: {}", table, e))?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommandHistoryEntry {
+    pub id: String,
+    pub project_id: String,
+    pub command: String,
+    pub output: String,
+    pub exit_code: i32,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommandResult {
+    pub id: String,
+    pub output: String,
+    pub exit_code: i32,
+    pub cwd: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileEntry {
+    pub name: String,
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: i64,
+    pub extension: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FrameworkCategoryRow {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub icon: String,
+    pub is_builtin: bool,
+    pub sort_order: i32,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FrameworkDefRow {
+    pub id: String,
+    pub category: String,
+    pub name: String,
+    pub description: String,
+    pub icon: String,
+    pub example_output: String,
+    pub system_prompt: String,
+    pub guiding_questions: String,
+    pub supports_visuals: bool,
+    pub visual_instructions: Option<String>,
+    pub is_builtin: bool,
+    pub sort_order: i32,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SavedPromptRow {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub category: String,
+    pub prompt_text: String,
+    pub variables: String,
+    pub framework_id: Option<String>,
+    pub is_builtin: bool,
+    pub is_favorite: bool,
+    pub usage_count: i32,
+    pub sort_order: i32,
+    pub created_at: i64,
+    pub updated_at: i64,
+    #[serde(default)]
+    pub last_used_at: Option<i64>,
+    #[serde(default)]
+    pub project_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FrameworkExportMeta {
+    pub r#type: String,
+    pub id: String,
+    pub name: String,
+    pub category: String,
+    pub description: String,
+    pub icon: String,
+    pub supports_visuals: bool,
+    pub visual_instructions: Option<String>,
+    pub exported_at: String,
+    pub export_version: i32,
+}
+
+// JSON/YAML export shape: the front matter fields plus the sections the Markdown body carries
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FrameworkExportDocument {
+    #[serde(flatten)]
+    pub meta: FrameworkExportMeta,
+    pub system_prompt: String,
+    pub guiding_questions: Vec<String>,
+    pub example_output: String,
+}
+
+// Wire names match what the prompt picker renders; the aliases accept the descriptive spellings
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PromptVariableType {
+    #[default]
+    #[serde(rename = "text", alias = "string")]
+    Text,
+    #[serde(rename = "textarea", alias = "multiline")]
+    Multiline,
+    #[serde(rename = "number")]
+    Number,
+    #[serde(rename = "select", alias = "enum")]
+    Enum,
+}
+
+// Untyped or unrecognised types fall back to plain text so older freeform schemas still load
+fn deserialize_variable_type<'de, D>(deserializer: D) -> Result<PromptVariableType, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let raw: Option<serde_json::Value> = Option::deserialize(deserializer)?;
+    Ok(raw
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PromptVariable {
+    pub name: String,
+    #[serde(rename = "type", default, deserialize_with = "deserialize_variable_type")]
+    pub var_type: PromptVariableType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placeholder: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<Vec<String>>,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default, alias = "default", skip_serializing_if = "Option::is_none")]
+    pub default_value: Option<String>,
+}
+
+// A bare string entry is a variable name from the original freeform schema
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PromptVariableEntry {
+    Name(String),
+    Declared(PromptVariable),
+}
+
+fn parse_prompt_variables(json: &str) -> Result<Vec<PromptVariable>, String> {
+    if json.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let entries: Vec<PromptVariableEntry> = serde_json::from_str(json)
+        .map_err(|e| format!("Invalid variables schema: {}", e))?;
+
+    let mut variables: Vec<PromptVariable> = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let variable = match entry {
+            PromptVariableEntry::Name(name) => PromptVariable {
+                name,
+                var_type: PromptVariableType::Text,
+                label: None,
+                placeholder: None,
+                options: None,
+                required: false,
+                default_value: None,
+            },
+            PromptVariableEntry::Declared(variable) => variable,
+        };
+        let name = variable.name.trim().to_string();
+        if name.is_empty() {
+            return Err("Invalid variables schema: variable name cannot be empty".to_string());
+        }
+        if variables.iter().any(|v| v.name == name) {
+            return Err(format!("Invalid variables schema: duplicate variable '{}'", name));
+        }
+        variables.push(PromptVariable { name, ..variable });
+    }
+    Ok(variables)
+}
+
+fn normalize_prompt_variables(json: &str) -> Result<String, String> {
+    serde_json::to_string(&parse_prompt_variables(json)?)
+        .map_err(|e| format!("Failed to serialize variables: {}", e))
+}
+
+// Explains why a supplied value doesn't fit its declared type, or None when it does
+fn prompt_value_error(variable: &PromptVariable, value: &str) -> Option<String> {
+    match variable.var_type {
+        PromptVariableType::Number => match value.trim().parse::<f64>() {
+            Ok(n) if n.is_finite() => None,
+            _ => Some(format!("{}: '{}' is not a number", variable.name, value)),
+        },
+        PromptVariableType::Enum => {
+            let options = variable.options.as_deref().unwrap_or_default();
+            if options.is_empty() || options.iter().any(|o| o == value) {
+                None
+            } else {
+                Some(format!("{}: '{}' is not one of {}", variable.name, value, options.join(", ")))
+            }
+        }
+        PromptVariableType::Text | PromptVariableType::Multiline => None,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PromptExportMeta {
+    pub r#type: String,
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub category: String,
+    pub framework_id: Option<String>,
+    pub variables: Vec<PromptVariable>,
+    pub exported_at: String,
+    pub export_version: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportPreview {
+    pub item_type: String,
+    pub id: String,
+    pub name: String,
+    pub category: String,
+    pub description: String,
+    pub already_exists: bool,
+    pub is_builtin_conflict: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportResult {
+    pub success: bool,
+    pub item_type: String,
+    pub id: String,
+    pub name: String,
+    pub action: String,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchExportResult {
+    pub filename: String,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WorkflowStepDef {
+    pub framework_id: String,
+    pub label: String,
+    pub prompt_template: String,
+    pub context_doc_ids: Vec<String>,
+    pub model: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Workflow {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    pub description: String,
+    pub steps: String,
+    pub is_template: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WorkflowRun {
+    pub id: String,
+    pub workflow_id: String,
+    pub project_id: String,
+    pub status: String,
+    pub started_at: Option<i64>,
+    pub completed_at: Option<i64>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WorkflowRunStep {
+    pub id: String,
+    pub run_id: String,
+    pub step_index: i32,
+    pub framework_id: String,
+    pub status: String,
+    pub input_prompt: Option<String>,
+    pub output_content: Option<String>,
+    pub output_id: Option<String>,
+    pub error: Option<String>,
+    pub started_at: Option<i64>,
+    pub completed_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectInsight {
+    pub id: String,
+    pub project_id: String,
+    pub insight_type: String,
+    pub title: String,
+    pub description: String,
+    pub priority: String,
+    pub framework_id: Option<String>,
+    pub is_dismissed: bool,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommitInfo {
+    pub oid: String,
+    pub message: String,
+    pub author: String,
+    pub timestamp: i64,
+}
+
+// --- Command Policy ---
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommandPolicyRule {
+    pub id: String,
+    pub kind: String,
+    pub pattern: String,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommandPolicy {
+    pub allowlist_enabled: bool,
+    pub rules: Vec<CommandPolicyRule>,
+}
+
+fn normalize_command(command: &str) -> String {
+    command.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Word-ish characters that would make a match part of a longer token ("rm -rf /tmp" is not "rm -rf /")
+fn is_token_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '/' | '~')
+}
+
+// A '/' just before the match is the directory of the executable ("/bin/rm -rf /"), so it
+// still counts as a boundary there
+fn contains_pattern(command: &str, pattern: &str) -> bool {
+    if pattern.is_empty() {
+        return false;
+    }
+    command.match_indices(pattern).any(|(start, matched)| {
+        let end = start + matched.len();
+        let before_ok = command[..start].chars().next_back()
+            .map(|c| c == '/' || !is_token_char(c) || !pattern.starts_with(is_token_char))
+            .unwrap_or(true);
+        let after_ok = command[end..].chars().next()
+            .map(|c| !is_token_char(c) || !pattern.ends_with(is_token_char))
+            .unwrap_or(true);
+        before_ok && after_ok
+    })
+}
+
+// Substitution and redirection run or write something an allowed prefix says nothing about
+// ("git log $(rm -rf ~)", "git log > ~/.bashrc"), so allowlist mode refuses them outright
+const ALLOWLIST_FORBIDDEN_SYNTAX: &[&str] = &["$(", "`", "<(", ">(", ">", "<"];
+
+// Splits the raw command on shell control operators, newlines included, so every chained command
+// is checked against the allowlist; segments are normalized only after splitting
+fn command_segments(command: &str) -> Vec<String> {
+    command.split(['\n', '\r', ';', '|', '&'])
+        .map(normalize_command)
+        .filter(|segment| !segment.is_empty())
+        .collect()
+}
+
+fn evaluate_command_policy(
+    command: &str,
+    allowlist_enabled: bool,
+    rules: &[CommandPolicyRule],
+) -> Result<(), AppError> {
+    let normalized = normalize_command(command);
+
+    for rule in rules.iter().filter(|r| r.kind == "deny") {
+        if contains_pattern(&normalized, &normalize_command(&rule.pattern)) {
+            return Err(AppError::PolicyViolation(
+                format!("Command blocked by policy: matches denied pattern '{}'", rule.pattern),
+            ));
+        }
+    }
+
+    if allowlist_enabled {
+        if let Some(syntax) = ALLOWLIST_FORBIDDEN_SYNTAX.iter().find(|syntax| normalized.contains(**syntax)) {
+            return Err(AppError::PolicyViolation(format!(
+                "Command blocked by policy: '{}' is not allowed while the allowlist is enabled",
+                syntax
+            )));
+        }
+
+        let prefixes: Vec<String> = rules.iter()
+            .filter(|r| r.kind == "allow")
+            .map(|r| normalize_command(&r.pattern))
+            .collect();
+
+        for segment in command_segments(command) {
+            let allowed = prefixes.iter().any(|prefix| {
+                segment == *prefix
+                    || (segment.starts_with(prefix.as_str()) && segment[prefix.len()..].starts_with(' '))
+            });
+            if !allowed {
+                return Err(AppError::PolicyViolation(
+                    format!("Command blocked by policy: '{}' is not on the allowlist", segment),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn load_command_policy(conn: &Connection) -> Result<CommandPolicy, AppError> {
+    let allowlist_enabled: bool = conn.query_row(
+        "SELECT command_allowlist_enabled FROM settings WHERE id = 'default'",
+        [],
+        |row| row.get::<_, i32>(0),
+    ).optional().map_err(|e| AppError::database("Failed to read command policy mode", e))?
+        .map(|v| v != 0)
+        .unwrap_or(false);
+
+    let mut stmt = conn.prepare(
+        "SELECT id, kind, pattern, created_at FROM command_policy ORDER BY kind ASC, pattern ASC"
+    ).map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+    let rules = stmt.query_map([], |row| {
+        Ok(CommandPolicyRule {
+            id: row.get(0)?,
+            kind: row.get(1)?,
+            pattern: row.get(2)?,
+            created_at: row.get(3)?,
+        })
+    }).map_err(|e| AppError::database("Failed to query command policy", e))?;
+
+    let rules: Result<Vec<CommandPolicyRule>, _> = rules.collect();
+    Ok(CommandPolicy {
+        allowlist_enabled,
+        rules: rules.map_err(|e| AppError::database("Failed to collect command policy", e))?,
+    })
+}
+
+fn check_command_policy(pool: &DbPool, command: &str) -> Result<(), AppError> {
+    let conn = get_db_connection(pool)?;
+    let policy = load_command_policy(&conn)?;
+    evaluate_command_policy(command, policy.allowlist_enabled, &policy.rules)
+}
+
+#[tauri::command]
+pub async fn check_command_allowed(command: String, pool: State<'_, DbPool>) -> Result<(), AppError> {
+    check_command_policy(&pool, &command)
+}
+
+#[tauri::command]
+pub async fn get_command_policy(pool: State<'_, DbPool>) -> Result<CommandPolicy, String> {
+    let conn = get_db_connection(&pool)?;
+    Ok(load_command_policy(&conn)?)
+}
+
+#[tauri::command]
+pub async fn add_command_policy_rule(
+    kind: String,
+    pattern: String,
+    pool: State<'_, DbPool>,
+) -> Result<CommandPolicyRule, String> {
+    if kind != "deny" && kind != "allow" {
+        return Err(format!("Unknown policy rule kind: {}", kind));
+    }
+    let pattern = normalize_command(&pattern);
+    if pattern.is_empty() {
+        return Err("Policy pattern cannot be empty".to_string());
+    }
+
+    let conn = get_db_connection(&pool)?;
+    let rule = CommandPolicyRule {
+        id: Uuid::new_v4().to_string(),
+        kind,
+        pattern,
+        created_at: Utc::now().timestamp(),
+    };
+
+    conn.execute(
+        "INSERT INTO command_policy (id, kind, pattern, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![&rule.id, &rule.kind, &rule.pattern, &rule.created_at],
+    ).map_err(|e| format!("Failed to add policy rule: {}", e))?;
+
+    Ok(rule)
+}
+
+#[tauri::command]
+pub async fn delete_command_policy_rule(id: String, pool: State<'_, DbPool>) -> Result<(), String> {
+    let conn = get_db_connection(&pool)?;
+
+    conn.execute(
+        "DELETE FROM command_policy WHERE id = ?1",
+        params![&id],
+    ).map_err(|e| format!("Failed to delete policy rule: {}", e))?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_command_allowlist_enabled(enabled: bool, pool: State<'_, DbPool>) -> Result<(), String> {
+    let conn = get_db_connection(&pool)?;
+
+    conn.execute(
+        "UPDATE settings SET command_allowlist_enabled = ?1 WHERE id = 'default'",
+        params![&(enabled as i32)],
+    ).map_err(|e| format!("Failed to update command policy mode: {}", e))?;
+
+    Ok(())
+}
+
+const CWD_MARKER: &str = "__PM_IDE_CWD_MARKER__";
+
+// Explicit cwd, else the terminal's tracked cwd for the project, else its default
+fn resolve_command_cwd(pool: &DbPool, project_id: &str, cwd: Option<String>) -> Result<String, AppError> {
+    let cwd = match cwd {
+        Some(dir) => expand_home(&dir),
+        None => {
+            let tracked = {
+                let map = terminal_cwds().lock().map_err(|e| AppError::Internal(format!("Lock error: {}", e)))?;
+                map.get(project_id).cloned()
+            };
+            match tracked {
+                Some(dir) => dir,
+                None => default_terminal_cwd(&get_db_connection(pool)?, project_id)?,
+            }
+        }
+    };
+
+    if !std::path::Path::new(&cwd).is_dir() {
+        return Err(AppError::Validation(format!("Working directory does not exist: {}", cwd)));
+    }
+
+    Ok(cwd)
+}
+
+#[tauri::command]
+pub async fn execute_shell_command(
+    project_id: String,
+    command: String,
+    cwd: Option<String>,
+    timeout_secs: Option<u64>,
+    command_id: Option<String>,
+    max_output_bytes: Option<usize>,
+    pool: State<'_, DbPool>,
+) -> Result<CommandResult, AppError> {
+    let cwd = resolve_command_cwd(&pool, &project_id, cwd)?;
+    check_command_policy(&pool, &command)?;
+
+    let cwd_marker = CWD_MARKER;
+    let id = command_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let mut child = build_shell_command(&cwd, &command, cwd_marker)
+        .current_dir(&cwd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| AppError::Io(format!("Failed to execute command: {}", e)))?;
+
+    // Drain the pipes on background threads so a chatty child can't fill them and stall
+    let stdout_rx = spawn_pipe_reader(child.stdout.take());
+    let stderr_rx = spawn_pipe_reader(child.stderr.take());
+
+    {
+        let mut running = running_commands().lock().map_err(|e| AppError::Internal(format!("Lock error: {}", e)))?;
+        running.insert(id.clone(), child);
+    }
+
+    let deadline = timeout_secs.map(|secs| Instant::now() + Duration::from_secs(secs));
+    let mut timed_out = false;
+
+    let status = loop {
+        {
+            let mut running = running_commands().lock().map_err(|e| AppError::Internal(format!("Lock error: {}", e)))?;
+            let child = match running.get_mut(&id) {
+                Some(child) => child,
+                // Removed by kill_running_command
+                None => break None,
+            };
+
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    running.remove(&id);
+                    break Some(status);
+                }
+                Ok(None) => {
+                    if deadline.map(|d| Instant::now() >= d).unwrap_or(false) {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                        running.remove(&id);
+                        timed_out = true;
+                        break None;
+                    }
+                }
+                Err(e) => {
+                    running.remove(&id);
+                    return Err(AppError::Io(format!("Failed to wait for command: {}", e)));
+                }
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    };
+
+    let stdout_raw = String::from_utf8_lossy(&stdout_rx.recv_timeout(PIPE_DRAIN_TIMEOUT).unwrap_or_default())
+        .replace("\r\n", "\n");
+    let stderr = String::from_utf8_lossy(&stderr_rx.recv_timeout(PIPE_DRAIN_TIMEOUT).unwrap_or_default())
+        .replace("\r\n", "\n");
+
+    let (user_output, new_cwd) = if let Some(idx) = stdout_raw.rfind(cwd_marker) {
+        let before = stdout_raw[..idx].trim_end().to_string();
+        let after = stdout_raw[idx + cwd_marker.len()..].trim().to_string();
+        let resolved_cwd = if after.is_empty() { cwd.clone() } else { after };
+        (before, resolved_cwd)
+    } else {
+        (stdout_raw, cwd.clone())
+    };
+
+    {
+        let mut map = terminal_cwds().lock().map_err(|e| AppError::Internal(format!("Lock error: {}", e)))?;
+        map.insert(project_id.clone(), new_cwd.clone());
+    }
+
+    let mut combined = if stderr.is_empty() {
+        user_output
+    } else if user_output.is_empty() {
+        stderr
+    } else {
+        format!("{}\n{}", user_output, stderr)
+    };
+
+    let (exit_code, history_exit_code) = match status {
+        Some(status) => {
+            let code = status.code().unwrap_or(-1);
+            (code, code)
+        }
+        None if timed_out => {
+            if !combined.is_empty() { combined.push('\n'); }
+            combined.push_str(&format!("[Command timed out after {}s and was killed]", timeout_secs.unwrap_or(0)));
+            (-1, EXIT_CODE_TIMED_OUT)
+        }
+        None => {
+            if !combined.is_empty() { combined.push('\n'); }
+            combined.push_str("[Command was killed]");
+            (-1, EXIT_CODE_KILLED)
+        }
+    };
+
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
+    let stored_output = truncate_output(&combined, max_output_bytes.unwrap_or(DEFAULT_MAX_OUTPUT_BYTES));
+
+    conn.execute(
+        "INSERT INTO command_history (id, project_id, command, output, exit_code, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![&id, &project_id, &command, &stored_output, &history_exit_code, &now],
+    ).map_err(|e| AppError::database("Failed to save command history", e))?;
+
+    Ok(CommandResult {
+        id,
+        output: combined,
+        exit_code,
+        cwd: new_cwd,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommandOutputEvent {
+    pub command_id: String,
+    pub stream: String,
+    pub chunk: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommandFinishedEvent {
+    pub command_id: String,
+    pub exit_code: i32,
+    pub cwd: String,
+}
+
+// Emits each line of a pipe as a `command-output` event and hands back everything it read.
+// On stdout the trailing cwd marker and the path after it are swallowed and returned separately.
+fn spawn_line_emitter<R: Read + Send + 'static>(
+    app: tauri::AppHandle,
+    command_id: String,
+    stream: &'static str,
+    pipe: Option<R>,
+) -> mpsc::Receiver<(String, Option<String>)> {
+    use std::io::BufRead;
+
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut collected = String::new();
+        let mut new_cwd = None;
+        // Held back one line so the blank line printed just before the marker can be dropped
+        let mut pending: Option<String> = None;
+        let mut awaiting_cwd = false;
+
+        let emit = |line: &str| {
+            let _ = app.emit("command-output", CommandOutputEvent {
+                command_id: command_id.clone(),
+                stream: stream.to_string(),
+                chunk: format!("{}\n", line),
+            });
+        };
+
+        if let Some(pipe) = pipe {
+            let mut reader = std::io::BufReader::new(pipe);
+            let mut buf = Vec::new();
+            while reader.read_until(b'\n', &mut buf).map(|n| n > 0).unwrap_or(false) {
+                let line = String::from_utf8_lossy(&buf).trim_end_matches(['\r', '\n']).to_string();
+                buf.clear();
+
+                if awaiting_cwd {
+                    if !line.trim().is_empty() {
+                        new_cwd = Some(line.trim().to_string());
+                        awaiting_cwd = false;
+                    }
+                    continue;
+                }
+                if stream == "stdout" {
+                    if let Some(rest) = line.strip_prefix(CWD_MARKER) {
+                        if pending.as_deref().map(str::is_empty).unwrap_or(false) {
+                            pending = None;
+                        }
+                        if rest.trim().is_empty() {
+                            awaiting_cwd = true;
+                        } else {
+                            new_cwd = Some(rest.trim().to_string());
+                        }
+                        continue;
+                    }
+                }
+
+                if let Some(previous) = pending.replace(line) {
+                    emit(&previous);
+                    collected.push_str(&previous);
+                    collected.push('\n');
+                }
+            }
+        }
+
+        if let Some(last) = pending {
+            emit(&last);
+            collected.push_str(&last);
+            collected.push('\n');
+        }
+        let _ = tx.send((collected, new_cwd));
+    });
+    rx
+}
+
+// Starts a command and returns its id right away; output arrives as `command-output` events
+// and completion as a single `command-finished` event once history has been written.
+#[tauri::command]
+pub async fn execute_shell_command_streaming(
+    project_id: String,
+    command: String,
+    cwd: Option<String>,
+    app: tauri::AppHandle,
+    pool: State<'_, DbPool>,
+) -> Result<String, AppError> {
+    let cwd = resolve_command_cwd(&pool, &project_id, cwd)?;
+    check_command_policy(&pool, &command)?;
+
+    let id = Uuid::new_v4().to_string();
+
+    let mut child = build_shell_command(&cwd, &command, CWD_MARKER)
+        .current_dir(&cwd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| AppError::Io(format!("Failed to execute command: {}", e)))?;
+
+    let stdout_rx = spawn_line_emitter(app.clone(), id.clone(), "stdout", child.stdout.take());
+    let stderr_rx = spawn_line_emitter(app.clone(), id.clone(), "stderr", child.stderr.take());
+
+    {
+        let mut running = running_commands().lock().map_err(|e| AppError::Internal(format!("Lock error: {}", e)))?;
+        running.insert(id.clone(), child);
+    }
+
+    // The finishing task outlives this call, so it needs its own handle to the pool
+    let pool = pool.inner().clone();
+    let command_id = id.clone();
+    tauri::async_runtime::spawn(async move {
+        let status = loop {
+            {
+                let mut running = match running_commands().lock() {
+                    Ok(running) => running,
+                    Err(_) => break None,
+                };
+                let child = match running.get_mut(&command_id) {
+                    Some(child) => child,
+                    // Removed by kill_running_command
+                    None => break None,
+                };
+                match child.try_wait() {
+                    Ok(Some(status)) => {
+                        running.remove(&command_id);
+                        break Some(status);
+                    }
+                    Ok(None) => {}
+                    Err(_) => {
+                        running.remove(&command_id);
+                        break None;
+                    }
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        };
+
+        let (stdout, new_cwd) = stdout_rx.recv_timeout(PIPE_DRAIN_TIMEOUT).unwrap_or_default();
+        let (stderr, _) = stderr_rx.recv_timeout(PIPE_DRAIN_TIMEOUT).unwrap_or_default();
+        let new_cwd = new_cwd.unwrap_or_else(|| cwd.clone());
+
+        let mut combined = format!("{}{}", stdout, stderr).trim_end().to_string();
+        let (exit_code, history_exit_code) = match status {
+            Some(status) => {
+                let code = status.code().unwrap_or(-1);
+                (code, code)
+            }
+            None => {
+                if !combined.is_empty() { combined.push('\n'); }
+                combined.push_str("[Command was killed]");
+                (-1, EXIT_CODE_KILLED)
+            }
+        };
+
+        if let Ok(mut map) = terminal_cwds().lock() {
+            map.insert(project_id.clone(), new_cwd.clone());
+        }
+
+        if let Ok(conn) = get_db_connection(&pool) {
+            let _ = conn.execute(
+                "INSERT INTO command_history (id, project_id, command, output, exit_code, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    &command_id, &project_id, &command,
+                    &truncate_output(&combined, DEFAULT_MAX_OUTPUT_BYTES),
+                    &history_exit_code, &Utc::now().timestamp(),
+                ],
+            );
+        }
+
+        let _ = app.emit("command-finished", CommandFinishedEvent {
+            command_id,
+            exit_code,
+            cwd: new_cwd,
+        });
+    });
+
+    Ok(id)
+}
+
+#[tauri::command]
+pub async fn kill_running_command(command_id: String) -> Result<bool, String> {
+    let mut running = running_commands().lock().map_err(|e| format!("Lock error: {}", e))?;
+    match running.remove(&command_id) {
+        Some(mut child) => {
+            child.kill().map_err(|e| format!("Failed to kill command: {}", e))?;
+            let _ = child.wait();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+#[tauri::command]
+pub async fn get_command_history(
+    project_id: String,
+    limit: Option<i32>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<CommandHistoryEntry>, String> {
+    let conn = get_db_connection(&pool)?;
+    let limit = limit.unwrap_or(50);
+
+    let mut stmt = conn.prepare(
+        "SELECT id, project_id, command, output, exit_code, created_at
+         FROM command_history
+         WHERE project_id = ?1
+         ORDER BY created_at DESC
+         LIMIT ?2"
+    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let entries = stmt.query_map(params![&project_id, &limit], |row| {
+        Ok(CommandHistoryEntry {
+            id: row.get(0)?,
+            project_id: row.get(1)?,
+            command: row.get(2)?,
+            output: row.get(3)?,
+            exit_code: row.get(4)?,
+            created_at: row.get(5)?,
+        })
+    }).map_err(|e| format!("Failed to query command history: {}", e))?;
+
+    let mut results = Vec::new();
+    for entry in entries {
+        results.push(entry.map_err(|e| format!("Failed to read command history entry: {}", e))?);
+    }
+
+    results.reverse();
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn search_command_history(
+    project_id: String,
+    query: String,
+    limit: Option<i32>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<CommandHistoryEntry>, String> {
+    let conn = get_db_connection(&pool)?;
+    let limit = limit.unwrap_or(50);
+    let search = format!("%{}%", query);
+
+    let mut stmt = conn.prepare(
+        "SELECT id, project_id, command, output, exit_code, created_at
+         FROM command_history
+         WHERE project_id = ?1 AND command LIKE ?2
+         ORDER BY created_at DESC
+         LIMIT ?3"
+    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let entries = stmt.query_map(params![&project_id, &search, &limit], |row| {
+        Ok(CommandHistoryEntry {
+            id: row.get(0)?,
+            project_id: row.get(1)?,
+            command: row.get(2)?,
+            output: row.get(3)?,
+            exit_code: row.get(4)?,
+            created_at: row.get(5)?,
+        })
+    }).map_err(|e| format!("Failed to search command history: {}", e))?;
+
+    let mut results = Vec::new();
+    for entry in entries {
+        results.push(entry.map_err(|e| format!("Failed to read command history entry: {}", e))?);
+    }
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn clear_command_history(project_id: String, pool: State<'_, DbPool>) -> Result<usize, String> {
+    let conn = get_db_connection(&pool)?;
+
+    conn.execute(
+        "DELETE FROM command_history WHERE project_id = ?1",
+        params![&project_id],
+    ).map_err(|e| format!("Failed to clear command history: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_terminal_cwd(
+    project_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<String, String> {
+    let tracked = {
+        let map = terminal_cwds().lock().map_err(|e| format!("Lock error: {}", e))?;
+        map.get(&project_id).cloned()
+    };
+    match tracked {
+        Some(dir) => Ok(dir),
+        None => Ok(default_terminal_cwd(&get_db_connection(&pool)?, &project_id)?),
+    }
+}
+
+#[tauri::command]
+pub async fn set_terminal_cwd(
+    project_id: String,
+    cwd: String,
+) -> Result<(), String> {
+    let path = std::path::Path::new(&cwd);
+    if !path.exists() || !path.is_dir() {
+        return Err(format!("Directory does not exist: {}", cwd));
+    }
+    let mut map = terminal_cwds().lock().map_err(|e| format!("Lock error: {}", e))?;
+    map.insert(project_id, cwd);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn complete_path(
+    project_id: String,
+    partial: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<String>, String> {
+    let home_dir = home_dir();
+    let tracked = {
+        let map = terminal_cwds().lock().map_err(|e| format!("Lock error: {}", e))?;
+        map.get(&project_id).cloned()
+    };
+    let cwd = match tracked {
+        Some(dir) => dir,
+        None => default_terminal_cwd(&get_db_connection(&pool)?, &project_id)?,
+    };
+
+    let expanded = if partial.starts_with('~') {
+        format!("{}{}", home_dir, &partial[1..])
+    } else {
+        partial.clone()
+    };
+
+    let full_path = if std::path::Path::new(&expanded).is_absolute() {
+        expanded.clone()
+    } else {
+        format!("{}/{}", cwd, expanded)
+    };
+
+    let (dir, prefix) = if full_path.ends_with('/') {
+        (full_path.clone(), String::new())
+    } else {
+        let p = std::path::Path::new(&full_path);
+        let dir = p.parent().map(|d| d.to_string_lossy().to_string()).unwrap_or_else(|| "/".to_string());
+        let prefix = p.file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or_default();
+        (dir, prefix)
+    };
+
+    let mut matches = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(&dir) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with(&prefix) {
+                let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+                let suffix = if is_dir { "/" } else { " " };
+                if partial.contains('/') {
+                    let base = &partial[..partial.rfind('/').unwrap() + 1];
+                    matches.push(format!("{}{}{}", base, name, suffix));
+                } else {
+                    matches.push(format!("{}{}", name, suffix));
+                }
+            }
+        }
+    }
+
+    matches.sort();
+    Ok(matches.into_iter().take(20).collect())
+}
+
+// === Saved Prompts CRUD ===
+
+const SAVED_PROMPT_COLUMNS: &str = "id, name, description, category, prompt_text, variables, framework_id, is_builtin, is_favorite, usage_count, sort_order, created_at, updated_at, last_used_at, project_id";
+
+// Global prompts are always included; `project_id` adds that project's own prompts on top
+#[tauri::command]
+pub async fn list_saved_prompts(
+    category: Option<String>,
+    framework_id: Option<String>,
+    project_id: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<SavedPromptRow>, AppError> {
+    let conn = get_db_connection(&pool)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM saved_prompts
+         WHERE (?1 IS NULL OR category = ?1)
+           AND (?2 IS NULL OR framework_id = ?2)
+           AND (project_id IS NULL OR project_id = ?3)
+         ORDER BY is_favorite DESC, sort_order, name",
+        SAVED_PROMPT_COLUMNS
+    )).map_err(|e| AppError::database("Failed to prepare query", e))?;
+    let rows = stmt.query_map(params![&category, &framework_id, &project_id], row_to_saved_prompt)
+        .map_err(|e| AppError::database("Failed to list saved prompts", e))?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row.map_err(|e| AppError::database("Failed to read saved prompt", e))?);
+    }
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn list_favorite_prompts(project_id: Option<String>, pool: State<'_, DbPool>) -> Result<Vec<SavedPromptRow>, AppError> {
+    let conn = get_db_connection(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM saved_prompts
+         WHERE is_favorite = 1 AND (project_id IS NULL OR project_id = ?1)
+         ORDER BY updated_at DESC, name",
+        SAVED_PROMPT_COLUMNS
+    )).map_err(|e| AppError::database("Failed to prepare query", e))?;
+    let rows = stmt.query_map(params![&project_id], row_to_saved_prompt)
+        .map_err(|e| AppError::database("Failed to list favorite prompts", e))?;
+    let result: Result<Vec<_>, _> = rows.collect();
+    result.map_err(|e| AppError::database("Failed to collect favorite prompts", e))
+}
+
+#[tauri::command]
+pub async fn get_saved_prompt(id: String, pool: State<'_, DbPool>) -> Result<Option<SavedPromptRow>, AppError> {
+    let conn = get_db_connection(&pool)?;
+    let result = conn.query_row(
+        &format!("SELECT {} FROM saved_prompts WHERE id = ?1", SAVED_PROMPT_COLUMNS),
+        params![&id],
+        row_to_saved_prompt,
+    ).optional().map_err(|e| AppError::database("Failed to get saved prompt", e))?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn create_saved_prompt(
+    name: String,
+    description: String,
+    category: String,
+    prompt_text: String,
+    variables: String,
+    framework_id: Option<String>,
+    project_id: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<SavedPromptRow, AppError> {
+    let variables = normalize_prompt_variables(&variables).map_err(AppError::Validation)?;
+    let conn = get_db_connection(&pool)?;
+    if let Some(ref pid) = project_id {
+        ensure_project_exists(&conn, pid)?;
+    }
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().timestamp();
+
+    let max_sort: i32 = conn.query_row(
+        "SELECT COALESCE(MAX(sort_order), -1) FROM saved_prompts WHERE category = ?1",
+        params![&category],
+        |row| row.get(0),
+    ).unwrap_or(-1);
+
+    conn.execute(
+        "INSERT INTO saved_prompts (id, name, description, category, prompt_text, variables, framework_id, is_builtin, is_favorite, usage_count, sort_order, created_at, updated_at, project_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, 0, 0, ?8, ?9, ?10, ?11)",
+        params![&id, &name, &description, &category, &prompt_text, &variables, &framework_id, max_sort + 1, &now, &now, &project_id],
+    ).map_err(|e| AppError::database("Failed to create saved prompt", e))?;
+
+    get_saved_prompt(id, pool).await?.ok_or_else(|| AppError::NotFound("Failed to retrieve created prompt".to_string()))
+}
+
+#[tauri::command]
+pub async fn update_saved_prompt(
+    id: String,
+    name: Option<String>,
+    description: Option<String>,
+    category: Option<String>,
+    prompt_text: Option<String>,
+    variables: Option<String>,
+    framework_id: Option<Option<String>>,
+    is_favorite: Option<bool>,
+    pool: State<'_, DbPool>,
+) -> Result<SavedPromptRow, AppError> {
+    let variables = variables.as_deref().map(normalize_prompt_variables).transpose().map_err(AppError::Validation)?;
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
+
+    with_transaction(&conn, |tx| {
+        tx.execute(
+            "UPDATE saved_prompts SET
+                name = COALESCE(?1, name),
+                description = COALESCE(?2, description),
+                category = COALESCE(?3, category),
+                prompt_text = COALESCE(?4, prompt_text),
+                variables = COALESCE(?5, variables),
+                updated_at = ?6
+             WHERE id = ?7",
+            params![&name, &description, &category, &prompt_text, &variables, &now, &id],
+        ).map_err(|e| AppError::database("Failed to update saved prompt", e))?;
+
+        if let Some(fid) = framework_id {
+            tx.execute(
+                "UPDATE saved_prompts SET framework_id = ?1 WHERE id = ?2",
+                params![&fid, &id],
+            ).map_err(|e| AppError::database("Failed to update prompt framework_id", e))?;
+        }
+
+        if let Some(fav) = is_favorite {
+            tx.execute(
+                "UPDATE saved_prompts SET is_favorite = ?1 WHERE id = ?2",
+                params![fav as i32, &id],
+            ).map_err(|e| AppError::database("Failed to update prompt favorite", e))?;
+        }
+        Ok::<_, AppError>(())
+    })?;
+
+    get_saved_prompt(id, pool).await?.ok_or_else(|| AppError::NotFound("Prompt not found after update".to_string()))
+}
+
+#[tauri::command]
+pub async fn delete_saved_prompt(id: String, pool: State<'_, DbPool>) -> Result<usize, AppError> {
+    let conn = get_db_connection(&pool)?;
+    remove_saved_prompt(&conn, &id)
+}
+
+// Like the other delete commands, a missing id is reported as 0 rows rather than an error
+fn remove_saved_prompt(conn: &Connection, id: &str) -> Result<usize, AppError> {
+    let is_builtin: Option<bool> = conn.query_row(
+        "SELECT is_builtin FROM saved_prompts WHERE id = ?1", params![id], |row| row.get(0)
+    ).optional().map_err(|e| AppError::database("Failed to look up prompt", e))?;
+
+    match is_builtin {
+        None => Ok(0),
+        Some(true) => Err(AppError::Validation("Cannot delete built-in prompts".to_string())),
+        Some(false) => conn.execute("DELETE FROM saved_prompts WHERE id = ?1", params![id])
+            .map_err(|e| AppError::database("Failed to delete saved prompt", e)),
+    }
+}
+
+// Name hits outweigh description hits, which outweigh body hits; an exact or leading
+// name match ranks above a name that merely contains the query
+fn prompt_search_score(prompt: &SavedPromptRow, query: &str) -> u32 {
+    let name = prompt.name.to_lowercase();
+    let mut score = if name == query {
+        100
+    } else if name.starts_with(query) {
+        60
+    } else if name.contains(query) {
+        40
+    } else {
+        0
+    };
+    if prompt.description.to_lowercase().contains(query) {
+        score += 20;
+    }
+    if prompt.prompt_text.to_lowercase().contains(query) {
+        score += 10;
+    }
+    score
+}
+
+#[tauri::command]
+pub async fn search_saved_prompts(
+    query: String,
+    project_id: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<SavedPromptRow>, AppError> {
+    let conn = get_db_connection(&pool)?;
+    let search = format!("%{}%", query);
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM saved_prompts
+         WHERE (name LIKE ?1 OR description LIKE ?1 OR prompt_text LIKE ?1)
+           AND (project_id IS NULL OR project_id = ?2)",
+        SAVED_PROMPT_COLUMNS
+    )).map_err(|e| AppError::database("Failed to prepare search", e))?;
+
+    let rows = stmt.query_map(params![&search, &project_id], row_to_saved_prompt)
+        .map_err(|e| AppError::database("Failed to search saved prompts", e))?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row.map_err(|e| AppError::database("Failed to read prompt", e))?);
+    }
+
+    Ok(rank_prompt_search_results(results, &query))
+}
+
+// Relevance first, then popularity, then name for a stable order
+fn rank_prompt_search_results(results: Vec<SavedPromptRow>, query: &str) -> Vec<SavedPromptRow> {
+    let needle = query.trim().to_lowercase();
+    let mut scored: Vec<(u32, SavedPromptRow)> = results.into_iter()
+        .map(|p| (prompt_search_score(&p, &needle), p))
+        .collect();
+    scored.sort_by(|(sa, a), (sb, b)| {
+        sb.cmp(sa)
+            .then(b.usage_count.cmp(&a.usage_count))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    scored.into_iter().map(|(_, p)| p).collect()
+}
+
+#[tauri::command]
+pub async fn duplicate_saved_prompt(id: String, new_name: String, pool: State<'_, DbPool>) -> Result<SavedPromptRow, AppError> {
+    let original = get_saved_prompt(id, pool.clone()).await?
+        .ok_or_else(|| AppError::NotFound("Prompt not found".to_string()))?;
+
+    create_saved_prompt(
+        new_name,
+        original.description,
+        original.category,
+        original.prompt_text,
+        original.variables,
+        original.framework_id,
+        original.project_id,
+        pool,
+    ).await
+}
+
+#[tauri::command]
+pub async fn increment_prompt_usage(id: String, pool: State<'_, DbPool>) -> Result<(), AppError> {
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
+
+    conn.execute(
+        "UPDATE saved_prompts SET usage_count = usage_count + 1, last_used_at = ?1, updated_at = ?1 WHERE id = ?2",
+        params![&now, &id],
+    ).map_err(|e| AppError::database("Failed to increment prompt usage", e))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_recently_used_prompts(
+    limit: Option<i32>,
+    project_id: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<SavedPromptRow>, AppError> {
+    let conn = get_db_connection(&pool)?;
+    let limit = limit.unwrap_or(10);
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM saved_prompts
+         WHERE project_id IS NULL OR project_id = ?2
+         ORDER BY last_used_at DESC NULLS LAST, usage_count DESC, name LIMIT ?1",
+        SAVED_PROMPT_COLUMNS
+    )).map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+    let rows = stmt.query_map(params![&limit, &project_id], row_to_saved_prompt)
+        .map_err(|e| AppError::database("Failed to query recent prompts", e))?;
+
+    let result: Result<Vec<_>, _> = rows.collect();
+    result.map_err(|e| AppError::database("Failed to collect recent prompts", e))
+}
+
+// Substitutes declared variables into `{{name}}` and `{name}` placeholders; undeclared braces are left alone
+fn render_prompt_text(
+    prompt_text: &str,
+    variables: &[PromptVariable],
+    values: &HashMap<String, String>,
+) -> Result<String, String> {
+    let missing: Vec<String> = variables.iter()
+        .filter(|v| v.required)
+        .filter(|v| {
+            let supplied = values.get(&v.name).map(|s| !s.trim().is_empty()).unwrap_or(false);
+            let defaulted = v.default_value.as_deref().map(|s| !s.trim().is_empty()).unwrap_or(false);
+            !supplied && !defaulted
+        })
+        .map(|v| v.name.clone())
+        .collect();
+
+    let mut unknown: Vec<String> = values.keys()
+        .filter(|key| !variables.iter().any(|v| &v.name == *key))
+        .cloned()
+        .collect();
+    unknown.sort();
+
+    let invalid: Vec<String> = variables.iter()
+        .filter_map(|v| {
+            let value = values.get(&v.name).filter(|s| !s.trim().is_empty())?;
+            prompt_value_error(v, value)
+        })
+        .collect();
+
+    if !missing.is_empty() || !unknown.is_empty() || !invalid.is_empty() {
+        let mut parts = Vec::new();
+        if !missing.is_empty() {
+            parts.push(format!("missing required variables: {}", missing.join(", ")));
+        }
+        if !unknown.is_empty() {
+            parts.push(format!("unknown variables: {}", unknown.join(", ")));
+        }
+        if !invalid.is_empty() {
+            parts.push(format!("invalid values: {}", invalid.join("; ")));
+        }
+        return Err(format!("Cannot render prompt: {}", parts.join("; ")));
+    }
+
+    let resolved: HashMap<&str, String> = variables.iter()
+        .map(|variable| {
+            let value = values.get(&variable.name)
+                .filter(|s| !s.trim().is_empty())
+                .cloned()
+                .or_else(|| variable.default_value.clone())
+                .unwrap_or_default();
+            (variable.name.as_str(), value)
+        })
+        .collect();
+
+    Ok(substitute_placeholders(prompt_text, &resolved))
+}
+
+// One left-to-right pass: substituted values are never rescanned, so a value containing
+// `{{other}}` comes out literally instead of being expanded as template text
+fn substitute_placeholders(text: &str, values: &HashMap<&str, String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let tail = &rest[open..];
+        let (name_start, closer) = if tail.starts_with("{{") { (2, "}}") } else { (1, "}") };
+
+        let replacement = tail[name_start..].find('}').and_then(|close| {
+            let name = &tail[name_start..name_start + close];
+            if !tail[name_start + close..].starts_with(closer) {
+                return None;
+            }
+            values.get(name).map(|value| (value, name_start + close + closer.len()))
+        });
+
+        match replacement {
+            Some((value, end)) => {
+                out.push_str(value);
+                rest = &tail[end..];
+            }
+            None => {
+                out.push('{');
+                rest = &tail[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+#[tauri::command]
+pub async fn render_prompt(
+    id: String,
+    values: HashMap<String, String>,
+    pool: State<'_, DbPool>,
+) -> Result<String, AppError> {
+    let prompt = get_saved_prompt(id, pool).await?
+        .ok_or_else(|| AppError::NotFound("Prompt not found".to_string()))?;
+
+    let variables = parse_prompt_variables(&prompt.variables).map_err(AppError::Validation)?;
+
+    render_prompt_text(&prompt.prompt_text, &variables, &values)
+        .map_err(AppError::Validation)
+}
+
+// === Phase 6: Import/Export Helpers ===
+
+fn parse_yaml_frontmatter(md: &str) -> Result<(String, String), String> {
+    let trimmed = md.trim();
+    if !trimmed.starts_with("---") {
+        return Err("File must start with YAML front matter (---)".to_string());
+    }
+    let after_first = &trimmed[3..];
+    let end_idx = after_first.find("\n---")
+        .ok_or("Missing closing --- for YAML front matter")?;
+    let yaml_str = after_first[..end_idx].trim().to_string();
+    let body = after_first[end_idx + 4..].trim().to_string();
+    Ok((yaml_str, body))
+}
+
+fn framework_export_meta(fw: &FrameworkDefRow) -> FrameworkExportMeta {
+    FrameworkExportMeta {
+        r#type: "framework".to_string(),
+        id: fw.id.clone(),
+        name: fw.name.clone(),
+        category: fw.category.clone(),
+        description: fw.description.clone(),
+        icon: fw.icon.clone(),
+        supports_visuals: fw.supports_visuals,
+        visual_instructions: fw.visual_instructions.clone(),
+        exported_at: Utc::now().to_rfc3339(),
+        export_version: 1,
+    }
+}
+
+fn framework_to_markdown(fw: &FrameworkDefRow) -> Result<String, String> {
+    let yaml = serde_yaml::to_string(&framework_export_meta(fw))
+        .map_err(|e| format!("Failed to serialize YAML: {}", e))?;
+
+    let questions: Vec<String> = serde_json::from_str(&fw.guiding_questions)
+        .unwrap_or_default();
+    let questions_md: String = questions.iter()
+        .enumerate()
+        .map(|(i, q)| format!("{}. {}", i + 1, q))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(format!(
+        "---\n{}---\n\n# System Prompt\n\n{}\n\n# Guiding Questions\n\n{}\n\n# Example Output\n\n{}",
+        yaml,
+        fw.system_prompt,
+        questions_md,
+        fw.example_output,
+    ))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameworkExportFormat {
+    Markdown,
+    Json,
+    Yaml,
+}
+
+impl FrameworkExportFormat {
+    // Markdown with YAML front matter stays the default so existing callers are unaffected
+    fn parse(format: Option<&str>) -> Result<Self, String> {
+        match format.map(|f| f.trim().to_lowercase()).as_deref() {
+            None | Some("markdown") | Some("md") => Ok(FrameworkExportFormat::Markdown),
+            Some("json") => Ok(FrameworkExportFormat::Json),
+            Some("yaml") | Some("yml") => Ok(FrameworkExportFormat::Yaml),
+            Some(other) => Err(format!("Unsupported export format: {} (expected json, yaml or markdown)", other)),
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            FrameworkExportFormat::Markdown => "md",
+            FrameworkExportFormat::Json => "json",
+            FrameworkExportFormat::Yaml => "yaml",
+        }
+    }
+}
+
+fn framework_to_format(fw: &FrameworkDefRow, format: FrameworkExportFormat) -> Result<String, String> {
+    let document = || FrameworkExportDocument {
+        meta: framework_export_meta(fw),
+        system_prompt: fw.system_prompt.clone(),
+        guiding_questions: serde_json::from_str(&fw.guiding_questions).unwrap_or_default(),
+        example_output: fw.example_output.clone(),
+    };
+    match format {
+        FrameworkExportFormat::Markdown => framework_to_markdown(fw),
+        FrameworkExportFormat::Json => serde_json::to_string_pretty(&document())
+            .map_err(|e| format!("Failed to serialize JSON: {}", e)),
+        FrameworkExportFormat::Yaml => serde_yaml::to_string(&document())
+            .map_err(|e| format!("Failed to serialize YAML: {}", e)),
+    }
+}
+
+// Accepts any format framework_to_format produces: JSON objects, Markdown with front matter
+// (recognised by its section headings), and plain YAML documents
+fn parse_framework_import(content: &str) -> Result<FrameworkExportDocument, String> {
+    let trimmed = content.trim();
+    if trimmed.starts_with('{') {
+        return serde_json::from_str(trimmed).map_err(|e| format!("Invalid JSON: {}", e));
+    }
+
+    if let Ok((yaml_str, body)) = parse_yaml_frontmatter(trimmed) {
+        if body.contains("# System Prompt") {
+            let meta: FrameworkExportMeta = serde_yaml::from_str(&yaml_str)
+                .map_err(|e| format!("Invalid YAML front matter: {}", e))?;
+            let (system_prompt, guiding_questions, example_output) = markdown_to_framework_parts(&body)?;
+            return Ok(FrameworkExportDocument { meta, system_prompt, guiding_questions, example_output });
+        }
+    }
+
+    serde_yaml::from_str(trimmed).map_err(|e| format!("Invalid YAML: {}", e))
+}
+
+fn markdown_to_framework_parts(body: &str) -> Result<(String, Vec<String>, String), String> {
+    let system_prompt_start = body.find("# System Prompt")
+        .ok_or("Missing '# System Prompt' section")?;
+    let questions_start = body.find("# Guiding Questions")
+        .ok_or("Missing '# Guiding Questions' section")?;
+    let example_start = body.find("# Example Output")
+        .ok_or("Missing '# Example Output' section")?;
+
+    let system_prompt = body[system_prompt_start + 15..questions_start].trim().to_string();
+    let questions_section = body[questions_start + 19..example_start].trim();
+    let example_output = body[example_start + 16..].trim().to_string();
+
+    let questions: Vec<String> = questions_section
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| {
+            let trimmed = l.trim();
+            if let Some(pos) = trimmed.find(". ") {
+                if trimmed[..pos].chars().all(|c| c.is_ascii_digit()) {
+                    return trimmed[pos + 2..].to_string();
+                }
+            }
+            if trimmed.starts_with("- ") {
+                return trimmed[2..].to_string();
+            }
+            trimmed.to_string()
+        })
+        .collect();
+
+    Ok((system_prompt, questions, example_output))
+}
+
+fn prompt_to_markdown(prompt: &SavedPromptRow) -> Result<String, String> {
+    let variables = parse_prompt_variables(&prompt.variables)
+        .unwrap_or_default();
+    let meta = PromptExportMeta {
+        r#type: "prompt".to_string(),
+        id: prompt.id.clone(),
+        name: prompt.name.clone(),
+        description: prompt.description.clone(),
+        category: prompt.category.clone(),
+        framework_id: prompt.framework_id.clone(),
+        variables,
+        exported_at: Utc::now().to_rfc3339(),
+        export_version: 1,
+    };
+    let yaml = serde_yaml::to_string(&meta)
+        .map_err(|e| format!("Failed to serialize YAML: {}", e))?;
+
+    Ok(format!(
+        "---\n{}---\n\n# Prompt Text\n\n{}",
+        yaml,
+        prompt.prompt_text,
+    ))
+}
+
+fn sanitize_filename(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else if c == ' ' { '-' } else { '-' })
+        .collect::<String>()
+        .replace("--", "-")
+        .trim_matches('-')
+        .to_string()
+}
+
+// === Phase 6: Export Commands ===
+
+#[tauri::command]
+pub async fn export_framework(id: String, format: Option<String>, pool: State<'_, DbPool>) -> Result<String, String> {
+    let format = FrameworkExportFormat::parse(format.as_deref())?;
+    let conn = get_db_connection(&pool)?;
+    let fw = conn.query_row(
+        &format!("SELECT {} FROM framework_definitions WHERE id = ?1", FRAMEWORK_DEF_COLUMNS),
+        params![&id],
+        row_to_framework_def,
+    ).map_err(|e| format!("Framework not found: {}", e))?;
+    framework_to_format(&fw, format)
+}
+
+#[tauri::command]
+pub async fn export_frameworks_batch(ids: Vec<String>, format: Option<String>, pool: State<'_, DbPool>) -> Result<Vec<BatchExportResult>, String> {
+    let format = FrameworkExportFormat::parse(format.as_deref())?;
+    let conn = get_db_connection(&pool)?;
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM framework_definitions WHERE id = ?1", FRAMEWORK_DEF_COLUMNS))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let mut results = Vec::new();
+    for id in &ids {
+        let fw = stmt.query_row(params![id], row_to_framework_def)
+            .map_err(|e| format!("Framework {} not found: {}", id, e))?;
+        let content = framework_to_format(&fw, format)?;
+        let filename = format!("{}.{}", sanitize_filename(&fw.name), format.extension());
+        results.push(BatchExportResult { filename, content });
+    }
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn export_all_frameworks(format: Option<String>, pool: State<'_, DbPool>) -> Result<Vec<BatchExportResult>, String> {
+    let format = FrameworkExportFormat::parse(format.as_deref())?;
+    let conn = get_db_connection(&pool)?;
+    let mut stmt = conn.prepare(
+        &format!("SELECT {} FROM framework_definitions ORDER BY sort_order", FRAMEWORK_DEF_COLUMNS)
+    ).map_err(|e| format!("Failed to query frameworks: {}", e))?;
+
+    let frameworks: Vec<FrameworkDefRow> = stmt.query_map([], row_to_framework_def)
+        .map_err(|e| format!("Query error: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut results = Vec::new();
+    for fw in &frameworks {
+        let content = framework_to_format(fw, format)?;
+        let filename = format!("{}.{}", sanitize_filename(&fw.name), format.extension());
+        results.push(BatchExportResult { filename, content });
+    }
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn preview_import_framework(md_content: String, pool: State<'_, DbPool>) -> Result<ImportPreview, String> {
+    let meta = parse_framework_import(&md_content)?.meta;
+
+    if meta.r#type != "framework" {
+        return Err(format!("Expected type 'framework', got '{}'", meta.r#type));
+    }
+    if meta.export_version != 1 {
+        return Err(format!("Unsupported export version: {}", meta.export_version));
+    }
+    if meta.name.is_empty() { return Err("Missing required field: name".to_string()); }
+    if meta.category.is_empty() { return Err("Missing required field: category".to_string()); }
+    if meta.id.is_empty() { return Err("Missing required field: id".to_string()); }
+
+    let conn = get_db_connection(&pool)?;
+    let existing: Option<(String, bool)> = conn.query_row(
+        "SELECT id, is_builtin FROM framework_definitions WHERE id = ?1",
+        params![&meta.id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional().map_err(|e| format!("DB error: {}", e))?;
+
+    let (already_exists, is_builtin_conflict) = match existing {
+        Some((_, is_builtin)) => (true, is_builtin),
+        None => (false, false),
+    };
+
+    Ok(ImportPreview {
+        item_type: "framework".to_string(),
+        id: meta.id,
+        name: meta.name,
+        category: meta.category,
+        description: meta.description,
+        already_exists,
+        is_builtin_conflict,
+    })
+}
+
+#[tauri::command]
+pub async fn confirm_import_framework(md_content: String, conflict_action: String, pool: State<'_, DbPool>) -> Result<ImportResult, String> {
+    let FrameworkExportDocument { meta, system_prompt, guiding_questions: questions, example_output } =
+        parse_framework_import(&md_content)?;
+    let questions_json = serde_json::to_string(&questions)
+        .map_err(|e| format!("Failed to serialize questions: {}", e))?;
+
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
+
+    let existing: Option<bool> = conn.query_row(
+        "SELECT is_builtin FROM framework_definitions WHERE id = ?1",
+        params![&meta.id],
+        |row| row.get(0),
+    ).optional().map_err(|e| format!("DB error: {}", e))?;
+
+    let final_id: String;
+    let action: String;
+
+    match (existing, conflict_action.as_str()) {
+        (None, _) => {
+            final_id = meta.id.clone();
+            action = "created".to_string();
+            ensure_category_exists(&conn, &meta.category)?;
+            conn.execute(
+                "INSERT INTO framework_definitions (id, category, name, description, icon, example_output, system_prompt, guiding_questions, supports_visuals, visual_instructions, is_builtin, sort_order, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, 0, 999, ?11, ?12)",
+                params![&meta.id, &meta.category, &meta.name, &meta.description, &meta.icon, &example_output, &system_prompt, &questions_json, &meta.supports_visuals, &meta.visual_instructions, &now, &now],
+            ).map_err(|e| format!("Failed to insert framework: {}", e))?;
+        },
+        (Some(_), "skip") => {
+            return Ok(ImportResult {
+                success: true,
+                item_type: "framework".to_string(),
+                id: meta.id,
+                name: meta.name,
+                action: "skipped".to_string(),
+                error: None,
+            });
+        },
+        (Some(_), "overwrite") => {
+            final_id = meta.id.clone();
+            action = "overwritten".to_string();
+            ensure_category_exists(&conn, &meta.category)?;
+            conn.execute(
+                "UPDATE framework_definitions SET category=?1, name=?2, description=?3, icon=?4, example_output=?5, system_prompt=?6, guiding_questions=?7, supports_visuals=?8, visual_instructions=?9, updated_at=?10 WHERE id=?11",
+                params![&meta.category, &meta.name, &meta.description, &meta.icon, &example_output, &system_prompt, &questions_json, &meta.supports_visuals, &meta.visual_instructions, &now, &meta.id],
+            ).map_err(|e| format!("Failed to update framework: {}", e))?;
+        },
+        (Some(_), "copy") | (Some(_), _) => {
+            final_id = format!("{}-imported-{}", meta.id, &Uuid::new_v4().to_string()[..8]);
+            action = "copied".to_string();
+            ensure_category_exists(&conn, &meta.category)?;
+            conn.execute(
+                "INSERT INTO framework_definitions (id, category, name, description, icon, example_output, system_prompt, guiding_questions, supports_visuals, visual_instructions, is_builtin, sort_order, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, 0, 999, ?11, ?12)",
+                params![&final_id, &meta.category, &format!("{} (Imported)", meta.name), &meta.description, &meta.icon, &example_output, &system_prompt, &questions_json, &meta.supports_visuals, &meta.visual_instructions, &now, &now],
+            ).map_err(|e| format!("Failed to insert framework copy: {}", e))?;
+        },
+    }
+
+    Ok(ImportResult {
+        success: true,
+        item_type: "framework".to_string(),
+        id: final_id,
+        name: meta.name,
+        action,
+        error: None,
+    })
+}
+
+fn ensure_category_exists(conn: &Connection, category_id: &str) -> Result<(), String> {
+    let exists: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM framework_categories WHERE id = ?1",
+        params![category_id],
+        |row| row.get(0),
+    ).map_err(|e| format!("DB error checking category: {}", e))?;
+
+    if !exists {
+        let now = Utc::now().timestamp();
+        let name = category_id.replace('-', " ")
+            .split_whitespace()
+            .map(|w| {
+                let mut c = w.chars();
+                match c.next() {
+                    None => String::new(),
+                    Some(f) => f.to_uppercase().collect::<String>() + c.as_str(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        conn.execute(
+            "INSERT INTO framework_categories (id, name, description, icon, is_builtin, sort_order, created_at, updated_at) VALUES (?1, ?2, ?3, '📁', 0, 999, ?4, ?5)",
+            params![category_id, &name, &format!("Imported category: {}", name), &now, &now],
+        ).map_err(|e| format!("Failed to create category: {}", e))?;
+    }
+    Ok(())
+}
+
+// === Phase 6: Prompt Export Commands ===
+
+#[tauri::command]
+pub async fn export_prompt(id: String, pool: State<'_, DbPool>) -> Result<String, String> {
+    let conn = get_db_connection(&pool)?;
+    let prompt = conn.query_row(
+        &format!("SELECT {} FROM saved_prompts WHERE id = ?1", SAVED_PROMPT_COLUMNS),
+        params![&id],
+        row_to_saved_prompt,
+    ).map_err(|e| format!("Prompt not found: {}", e))?;
+    prompt_to_markdown(&prompt)
+}
+
+#[tauri::command]
+pub async fn export_prompts_batch(ids: Vec<String>, pool: State<'_, DbPool>) -> Result<Vec<BatchExportResult>, String> {
+    let conn = get_db_connection(&pool)?;
+    let mut results = Vec::new();
+    for id in &ids {
+        let prompt = conn.query_row(
+            &format!("SELECT {} FROM saved_prompts WHERE id = ?1", SAVED_PROMPT_COLUMNS),
+            params![id],
+            row_to_saved_prompt,
+        ).map_err(|e| format!("Prompt {} not found: {}", id, e))?;
+        let content = prompt_to_markdown(&prompt)?;
+        let filename = format!("{}.md", sanitize_filename(&prompt.name));
+        results.push(BatchExportResult { filename, content });
+    }
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn export_all_prompts(pool: State<'_, DbPool>) -> Result<Vec<BatchExportResult>, String> {
+    let conn = get_db_connection(&pool)?;
+    let mut stmt = conn.prepare(
+        &format!("SELECT {} FROM saved_prompts ORDER BY sort_order", SAVED_PROMPT_COLUMNS)
+    ).map_err(|e| format!("Failed to query prompts: {}", e))?;
+
+    let prompts: Vec<SavedPromptRow> = stmt.query_map([], row_to_saved_prompt).map_err(|e| format!("Query error: {}", e))?
+    .filter_map(|r| r.ok())
+    .collect();
+
+    let mut results = Vec::new();
+    for prompt in &prompts {
+        let content = prompt_to_markdown(prompt)?;
+        let filename = format!("{}.md", sanitize_filename(&prompt.name));
+        results.push(BatchExportResult { filename, content });
+    }
+    Ok(results)
+}
+
+const PROMPT_LIBRARY_VERSION: i32 = 1;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PromptLibraryEntry {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub category: String,
+    #[serde(default)]
+    pub framework_id: Option<String>,
+    #[serde(default)]
+    pub variables: Vec<PromptVariable>,
+    pub prompt_text: String,
+}
+
+// Single-file prompt library meant to be kept under version control
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PromptLibraryFile {
+    pub r#type: String,
+    pub export_version: i32,
+    pub exported_at: String,
+    pub count: usize,
+    pub prompts: Vec<PromptLibraryEntry>,
+}
+
+#[tauri::command]
+pub async fn export_prompt_library_file(
+    format: Option<String>,
+    category: Option<String>,
+    project_id: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<String, String> {
+    let conn = get_db_connection(&pool)?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM saved_prompts
+         WHERE (?1 IS NULL OR category = ?1) AND (project_id IS NULL OR project_id = ?2)
+         ORDER BY category, sort_order, name",
+        SAVED_PROMPT_COLUMNS
+    )).map_err(|e| format!("Failed to query prompts: {}", e))?;
+
+    let prompts = stmt.query_map(params![&category, &project_id], row_to_saved_prompt)
+        .map_err(|e| format!("Failed to query prompts: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read prompts: {}", e))?;
+
+    let entries: Vec<PromptLibraryEntry> = prompts.into_iter().map(|p| PromptLibraryEntry {
+        variables: parse_prompt_variables(&p.variables).unwrap_or_default(),
+        id: p.id,
+        name: p.name,
+        description: p.description,
+        category: p.category,
+        framework_id: p.framework_id,
+        prompt_text: p.prompt_text,
+    }).collect();
+
+    let library = PromptLibraryFile {
+        r#type: "prompt_library".to_string(),
+        export_version: PROMPT_LIBRARY_VERSION,
+        exported_at: Utc::now().to_rfc3339(),
+        count: entries.len(),
+        prompts: entries,
+    };
+
+    match format.as_deref().map(|f| f.to_ascii_lowercase()).as_deref() {
+        None | Some("json") => serde_json::to_string_pretty(&library)
+            .map_err(|e| format!("Failed to serialize JSON: {}", e)),
+        Some("yaml") | Some("yml") => serde_yaml::to_string(&library)
+            .map_err(|e| format!("Failed to serialize YAML: {}", e)),
+        Some(other) => Err(format!("Unsupported prompt library format '{}': expected json or yaml", other)),
+    }
+}
+
+// JSON when the document opens with `{`, YAML otherwise
+fn parse_prompt_library(content: &str) -> Result<PromptLibraryFile, String> {
+    let library: PromptLibraryFile = if content.trim_start().starts_with('{') {
+        serde_json::from_str(content).map_err(|e| format!("Invalid prompt library JSON: {}", e))?
+    } else {
+        serde_yaml::from_str(content).map_err(|e| format!("Invalid prompt library YAML: {}", e))?
+    };
+
+    if library.r#type != "prompt_library" {
+        return Err(format!("Expected type 'prompt_library', got '{}'", library.r#type));
+    }
+    if library.export_version != PROMPT_LIBRARY_VERSION {
+        return Err(format!("Unsupported export version: {}", library.export_version));
+    }
+    for entry in &library.prompts {
+        if entry.id.is_empty() { return Err("Prompt library entry is missing an id".to_string()); }
+        if entry.name.is_empty() { return Err(format!("Prompt '{}' is missing a name", entry.id)); }
+        if entry.category.is_empty() { return Err(format!("Prompt '{}' is missing a category", entry.id)); }
+    }
+    Ok(library)
+}
+
+#[tauri::command]
+pub async fn preview_prompt_library_file(content: String, pool: State<'_, DbPool>) -> Result<Vec<ImportPreview>, String> {
+    let library = parse_prompt_library(&content)?;
+    let conn = get_db_connection(&pool)?;
+    let mut stmt = conn.prepare("SELECT is_builtin FROM saved_prompts WHERE id = ?1")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let mut previews = Vec::with_capacity(library.prompts.len());
+    for entry in library.prompts {
+        let existing: Option<bool> = stmt.query_row(params![&entry.id], |row| row.get(0))
+            .optional().map_err(|e| format!("DB error: {}", e))?;
+        previews.push(ImportPreview {
+            item_type: "prompt".to_string(),
+            id: entry.id,
+            name: entry.name,
+            category: entry.category,
+            description: entry.description,
+            already_exists: existing.is_some(),
+            is_builtin_conflict: existing.unwrap_or(false),
+        });
+    }
+    Ok(previews)
+}
+
+// Imports every prompt whose id is new; existing ids (and repeats within the file) are skipped.
+// With a project id the prompts are scoped to that project, otherwise they join the shared library.
+#[tauri::command]
+pub async fn import_prompt_library_file(
+    content: String,
+    project_id: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<ImportResult>, String> {
+    let library = parse_prompt_library(&content)?;
+    let conn = get_db_connection(&pool)?;
+    if let Some(ref project_id) = project_id {
+        ensure_project_exists(&conn, project_id)?;
+    }
+    let now = Utc::now().timestamp();
+
+    with_transaction(&conn, |tx| {
+        let mut seen = std::collections::HashSet::new();
+        let mut results = Vec::with_capacity(library.prompts.len());
+        for entry in library.prompts {
+            let exists: bool = tx.query_row(
+                "SELECT EXISTS(SELECT 1 FROM saved_prompts WHERE id = ?1)", params![&entry.id], |row| row.get(0)
+            ).map_err(|e| format!("DB error: {}", e))?;
+
+            let action = if exists || !seen.insert(entry.id.clone()) {
+                "skipped"
+            } else {
+                let variables_json = serde_json::to_string(&entry.variables)
+                    .map_err(|e| format!("Failed to serialize variables: {}", e))?;
+                tx.execute(
+                    "INSERT INTO saved_prompts (id, name, description, category, prompt_text, variables, framework_id, is_builtin, is_favorite, usage_count, sort_order, created_at, updated_at, project_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, 0, 0, 999, ?8, ?9, ?10)",
+                    params![&entry.id, &entry.name, &entry.description, &entry.category, &entry.prompt_text, &variables_json, &entry.framework_id, &now, &now, &project_id],
+                ).map_err(|e| format!("Failed to insert prompt '{}': {}", entry.id, e))?;
+                "created"
+            };
+
+            results.push(ImportResult {
+                success: true,
+                item_type: "prompt".to_string(),
+                id: entry.id,
+                name: entry.name,
+                action: action.to_string(),
+                error: None,
+            });
+        }
+        Ok(results)
+    })
+}
+
+// === Phase 6: Prompt Import Commands ===
+
+#[tauri::command]
+pub async fn preview_import_prompt(md_content: String, pool: State<'_, DbPool>) -> Result<ImportPreview, String> {
+    let (yaml_str, _body) = parse_yaml_frontmatter(&md_content)?;
+    let meta: PromptExportMeta = serde_yaml::from_str(&yaml_str)
+        .map_err(|e| format!("Invalid YAML front matter: {}", e))?;
+
+    if meta.r#type != "prompt" {
+        return Err(format!("Expected type 'prompt', got '{}'", meta.r#type));
+    }
+    if meta.export_version != 1 {
+        return Err(format!("Unsupported export version: {}", meta.export_version));
+    }
+    if meta.name.is_empty() { return Err("Missing required field: name".to_string()); }
+    if meta.category.is_empty() { return Err("Missing required field: category".to_string()); }
+    if meta.id.is_empty() { return Err("Missing required field: id".to_string()); }
+
+    let conn = get_db_connection(&pool)?;
+    let existing: Option<(String, bool)> = conn.query_row(
+        "SELECT id, is_builtin FROM saved_prompts WHERE id = ?1",
+        params![&meta.id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional().map_err(|e| format!("DB error: {}", e))?;
+
+    let (already_exists, is_builtin_conflict) = match existing {
+        Some((_, is_builtin)) => (true, is_builtin),
+        None => (false, false),
+    };
+
+    Ok(ImportPreview {
+        item_type: "prompt".to_string(),
+        id: meta.id,
+        name: meta.name,
+        category: meta.category,
+        description: meta.description,
+        already_exists,
+        is_builtin_conflict,
+    })
+}
+
+#[tauri::command]
+pub async fn confirm_import_prompt(md_content: String, conflict_action: String, pool: State<'_, DbPool>) -> Result<ImportResult, String> {
+    let (yaml_str, body) = parse_yaml_frontmatter(&md_content)?;
+    let meta: PromptExportMeta = serde_yaml::from_str(&yaml_str)
+        .map_err(|e| format!("Invalid YAML: {}", e))?;
+
+    let prompt_text_start = body.find("# Prompt Text")
+        .ok_or("Missing '# Prompt Text' section")?;
+    let prompt_text = body[prompt_text_start + 13..].trim().to_string();
+
+    let variables_json = serde_json::to_string(&meta.variables)
+        .map_err(|e| format!("Failed to serialize variables: {}", e))?;
+
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
+
+    let existing: Option<bool> = conn.query_row(
+        "SELECT is_builtin FROM saved_prompts WHERE id = ?1",
+        params![&meta.id],
+        |row| row.get(0),
+    ).optional().map_err(|e| format!("DB error: {}", e))?;
+
+    let final_id: String;
+    let action: String;
+
+    match (existing, conflict_action.as_str()) {
+        (None, _) => {
+            final_id = meta.id.clone();
+            action = "created".to_string();
+            conn.execute(
+                "INSERT INTO saved_prompts (id, name, description, category, prompt_text, variables, framework_id, is_builtin, is_favorite, usage_count, sort_order, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, 0, 0, 999, ?8, ?9)",
+                params![&meta.id, &meta.name, &meta.description, &meta.category, &prompt_text, &variables_json, &meta.framework_id, &now, &now],
+            ).map_err(|e| format!("Failed to insert prompt: {}", e))?;
+        },
+        (Some(_), "skip") => {
+            return Ok(ImportResult {
+                success: true,
+                item_type: "prompt".to_string(),
+                id: meta.id,
+                name: meta.name,
+                action: "skipped".to_string(),
+                error: None,
+            });
+        },
+        (Some(_), "overwrite") => {
+            final_id = meta.id.clone();
+            action = "overwritten".to_string();
+            conn.execute(
+                "UPDATE saved_prompts SET name=?1, description=?2, category=?3, prompt_text=?4, variables=?5, framework_id=?6, updated_at=?7 WHERE id=?8",
+                params![&meta.name, &meta.description, &meta.category, &prompt_text, &variables_json, &meta.framework_id, &now, &meta.id],
+            ).map_err(|e| format!("Failed to update prompt: {}", e))?;
+        },
+        (Some(_), "copy") | (Some(_), _) => {
+            final_id = format!("{}-imported-{}", meta.id, &Uuid::new_v4().to_string()[..8]);
+            action = "copied".to_string();
+            conn.execute(
+                "INSERT INTO saved_prompts (id, name, description, category, prompt_text, variables, framework_id, is_builtin, is_favorite, usage_count, sort_order, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, 0, 0, 999, ?8, ?9)",
+                params![&final_id, &format!("{} (Imported)", meta.name), &meta.description, &meta.category, &prompt_text, &variables_json, &meta.framework_id, &now, &now],
+            ).map_err(|e| format!("Failed to insert prompt copy: {}", e))?;
+        },
+    }
+
+    Ok(ImportResult {
+        success: true,
+        item_type: "prompt".to_string(),
+        id: final_id,
+        name: meta.name,
+        action,
+        error: None,
+    })
+}
+
+fn seed_workflows(conn: &Connection) -> Result<(), String> {
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM workflows", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to count workflows: {}", e))?;
+
+    if count > 0 {
+        return Ok(());
+    }
+
+    let now = Utc::now().timestamp();
+
+    let templates: Vec<(&str, &str, &str, Vec<WorkflowStepDef>)> = vec![
+        (
+            "complete-product-brief",
+            "Complete Product Brief",
+            "End-to-end product brief: research, competitive analysis, PRD, and go-to-market plan",
+            vec![
+                WorkflowStepDef {
+                    framework_id: "jtbd".to_string(),
+                    label: "Jobs-to-be-Done Analysis".to_string(),
+                    prompt_template: "Analyze the jobs-to-be-done for this product. Focus on the core user needs, desired outcomes, and pain points that drive adoption.".to_string(),
+                    context_doc_ids: vec![],
+                    model: "gpt-5".to_string(),
+                },
+                WorkflowStepDef {
+                    framework_id: "competitive-analysis".to_string(),
+                    label: "Competitive Analysis".to_string(),
+                    prompt_template: "Based on the following user research:\n\n{prev_output}\n\nConduct a competitive analysis identifying key competitors, their strengths and weaknesses, and market opportunities.".to_string(),
+                    context_doc_ids: vec![],
+                    model: "gpt-5".to_string(),
+                },
+                WorkflowStepDef {
+                    framework_id: "prd-template".to_string(),
+                    label: "PRD Generation".to_string(),
+                    prompt_template: "Using the research and competitive analysis below, generate a comprehensive PRD:\n\n{prev_output}".to_string(),
+                    context_doc_ids: vec![],
+                    model: "gpt-5".to_string(),
+                },
+                WorkflowStepDef {
+                    framework_id: "go-to-market".to_string(),
+                    label: "Go-to-Market Plan".to_string(),
+                    prompt_template: "Based on this PRD, create a go-to-market plan covering positioning, channels, pricing strategy, and launch timeline:\n\n{prev_output}".to_string(),
+                    context_doc_ids: vec![],
+                    model: "gpt-5".to_string(),
+                },
+            ],
+        ),
+        (
+            "feature-validation",
+            "Feature Validation",
+            "Validate and plan a feature: scoring, user stories, and sprint planning",
+            vec![
+                WorkflowStepDef {
+                    framework_id: "rice".to_string(),
+                    label: "RICE Scoring".to_string(),
+                    prompt_template: "Score this feature using the RICE framework (Reach, Impact, Confidence, Effort). Provide detailed justification for each score.".to_string(),
+                    context_doc_ids: vec![],
+                    model: "gpt-5".to_string(),
+                },
+                WorkflowStepDef {
+                    framework_id: "user-story-map".to_string(),
+                    label: "User Story Mapping".to_string(),
+                    prompt_template: "Based on this RICE analysis, create a user story map breaking down the feature into epics and stories:\n\n{prev_output}".to_string(),
+                    context_doc_ids: vec![],
+                    model: "gpt-5".to_string(),
+                },
+                WorkflowStepDef {
+                    framework_id: "sprint-planning".to_string(),
+                    label: "Sprint Planning".to_string(),
+                    prompt_template: "Using these user stories, create a sprint plan with prioritized backlog items, effort estimates, and sprint goals:\n\n{prev_output}".to_string(),
+                    context_doc_ids: vec![],
+                    model: "gpt-5".to_string(),
+                },
+            ],
+        ),
+        (
+            "strategic-review",
+            "Strategic Review",
+            "Full strategic analysis: SWOT, competitive forces, and value proposition",
+            vec![
+                WorkflowStepDef {
+                    framework_id: "swot".to_string(),
+                    label: "SWOT Analysis".to_string(),
+                    prompt_template: "Conduct a thorough SWOT analysis covering internal strengths and weaknesses, and external opportunities and threats.".to_string(),
+                    context_doc_ids: vec![],
+                    model: "gpt-5".to_string(),
+                },
+                WorkflowStepDef {
+                    framework_id: "porters-five-forces".to_string(),
+                    label: "Porter's Five Forces".to_string(),
+                    prompt_template: "Building on this SWOT analysis, analyze the competitive landscape using Porter's Five Forces:\n\n{prev_output}".to_string(),
+                    context_doc_ids: vec![],
+                    model: "gpt-5".to_string(),
+                },
+                WorkflowStepDef {
+                    framework_id: "value-proposition-canvas".to_string(),
+                    label: "Value Proposition Canvas".to_string(),
+                    prompt_template: "Using the strategic insights from SWOT and Five Forces analysis, develop a value proposition canvas:\n\n{prev_output}".to_string(),
+                    context_doc_ids: vec![],
+                    model: "gpt-5".to_string(),
+                },
+            ],
+        ),
+    ];
+
+    for (id, name, description, steps) in templates {
+        let steps_json = serde_json::to_string(&steps)
+            .map_err(|e| format!("Failed to serialize workflow steps: {}", e))?;
+        conn.execute(
+            "INSERT OR IGNORE INTO workflows (id, project_id, name, description, steps, is_template, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, 1, ?6, ?7)",
+            params![id, "__templates__", name, description, &steps_json, &now, &now],
+        ).map_err(|e| format!("Failed to seed workflow: {}", e))?;
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn create_workflow(
+    project_id: String,
+    name: String,
+    description: String,
+    steps_json: String,
+    pool: State<'_, DbPool>,
+) -> Result<Workflow, String> {
+    let conn = get_db_connection(&pool)?;
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().timestamp();
+
+    conn.execute(
+        "INSERT INTO workflows (id, project_id, name, description, steps, is_template, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, 0, ?6, ?7)",
+        params![&id, &project_id, &name, &description, &steps_json, &now, &now],
+    ).map_err(|e| format!("Failed to create workflow: {}", e))?;
+
+    Ok(Workflow {
+        id,
+        project_id,
+        name,
+        description,
+        steps: steps_json,
+        is_template: false,
+        created_at: now,
+        updated_at: now,
+    })
+}
+
+#[tauri::command]
+pub async fn list_workflows(
+    project_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<Workflow>, String> {
+    let conn = get_db_connection(&pool)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, project_id, name, description, steps, is_template, created_at, updated_at FROM workflows WHERE project_id = ?1 OR is_template = 1 ORDER BY is_template DESC, updated_at DESC"
+    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let workflows = stmt.query_map(params![&project_id], |row| {
+        Ok(Workflow {
+            id: row.get(0)?,
+            project_id: row.get(1)?,
+            name: row.get(2)?,
+            description: row.get(3)?,
+            steps: row.get(4)?,
+            is_template: row.get(5)?,
+            created_at: row.get(6)?,
+            updated_at: row.get(7)?,
+        })
+    }).map_err(|e| format!("Failed to query workflows: {}", e))?;
+
+    let mut results = Vec::new();
+    for wf in workflows {
+        results.push(wf.map_err(|e| format!("Failed to read workflow: {}", e))?);
+    }
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn get_workflow(
+    id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Workflow, String> {
+    let conn = get_db_connection(&pool)?;
+
+    conn.query_row(
+        "SELECT id, project_id, name, description, steps, is_template, created_at, updated_at FROM workflows WHERE id = ?1",
+        params![&id],
+        |row| {
+            Ok(Workflow {
+                id: row.get(0)?,
+                project_id: row.get(1)?,
+                name: row.get(2)?,
+                description: row.get(3)?,
+                steps: row.get(4)?,
+                is_template: row.get(5)?,
+                created_at: row.get(6)?,
+                updated_at: row.get(7)?,
+            })
+        },
+    ).map_err(|e| format!("Workflow not found: {}", e))
+}
+
+#[tauri::command]
+pub async fn update_workflow(
+    id: String,
+    name: String,
+    description: String,
     steps_json: String,
-    app: tauri::AppHandle,
+    pool: State<'_, DbPool>,
+) -> Result<Workflow, String> {
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
+
+    conn.execute(
+        "UPDATE workflows SET name = ?1, description = ?2, steps = ?3, updated_at = ?4 WHERE id = ?5",
+        params![&name, &description, &steps_json, &now, &id],
+    ).map_err(|e| format!("Failed to update workflow: {}", e))?;
+
+    get_workflow(id, pool).await
+}
+
+#[tauri::command]
+pub async fn delete_workflow(
+    id: String,
+    pool: State<'_, DbPool>,
+) -> Result<(), String> {
+    let conn = get_db_connection(&pool)?;
+    conn.execute("DELETE FROM workflows WHERE id = ?1", params![&id])
+        .map_err(|e| format!("Failed to delete workflow: {}", e))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn duplicate_workflow(
+    id: String,
+    new_name: String,
+    project_id: String,
+    pool: State<'_, DbPool>,
 ) -> Result<Workflow, String> {
-    let conn = get_db_connection(&app)?;
+    let conn = get_db_connection(&pool)?;
+    let original = conn.query_row(
+        "SELECT steps, description FROM workflows WHERE id = ?1",
+        params![&id],
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+    ).map_err(|e| format!("Workflow not found: {}", e))?;
+
+    let new_id = Uuid::new_v4().to_string();
+    let now = Utc::now().timestamp();
+
+    conn.execute(
+        "INSERT INTO workflows (id, project_id, name, description, steps, is_template, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, 0, ?6, ?7)",
+        params![&new_id, &project_id, &new_name, &original.1, &original.0, &now, &now],
+    ).map_err(|e| format!("Failed to duplicate workflow: {}", e))?;
+
+    Ok(Workflow {
+        id: new_id,
+        project_id,
+        name: new_name,
+        description: original.1,
+        steps: original.0,
+        is_template: false,
+        created_at: now,
+        updated_at: now,
+    })
+}
+
+#[tauri::command]
+pub async fn create_workflow_run(
+    workflow_id: String,
+    project_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<WorkflowRun, String> {
+    let conn = get_db_connection(&pool)?;
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().timestamp();
+
+    conn.execute(
+        "INSERT INTO workflow_runs (id, workflow_id, project_id, status, created_at) VALUES (?1, ?2, ?3, 'pending', ?4)",
+        params![&id, &workflow_id, &project_id, &now],
+    ).map_err(|e| format!("Failed to create workflow run: {}", e))?;
+
+    Ok(WorkflowRun {
+        id,
+        workflow_id,
+        project_id,
+        status: "pending".to_string(),
+        started_at: None,
+        completed_at: None,
+        created_at: now,
+    })
+}
+
+#[tauri::command]
+pub async fn get_workflow_run(
+    id: String,
+    pool: State<'_, DbPool>,
+) -> Result<WorkflowRun, String> {
+    let conn = get_db_connection(&pool)?;
+
+    conn.query_row(
+        "SELECT id, workflow_id, project_id, status, started_at, completed_at, created_at FROM workflow_runs WHERE id = ?1",
+        params![&id],
+        |row| {
+            Ok(WorkflowRun {
+                id: row.get(0)?,
+                workflow_id: row.get(1)?,
+                project_id: row.get(2)?,
+                status: row.get(3)?,
+                started_at: row.get(4)?,
+                completed_at: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        },
+    ).map_err(|e| format!("Workflow run not found: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_workflow_runs(
+    workflow_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<WorkflowRun>, String> {
+    let conn = get_db_connection(&pool)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, workflow_id, project_id, status, started_at, completed_at, created_at FROM workflow_runs WHERE workflow_id = ?1 ORDER BY created_at DESC"
+    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let runs = stmt.query_map(params![&workflow_id], |row| {
+        Ok(WorkflowRun {
+            id: row.get(0)?,
+            workflow_id: row.get(1)?,
+            project_id: row.get(2)?,
+            status: row.get(3)?,
+            started_at: row.get(4)?,
+            completed_at: row.get(5)?,
+            created_at: row.get(6)?,
+        })
+    }).map_err(|e| format!("Failed to query runs: {}", e))?;
+
+    let mut results = Vec::new();
+    for run in runs {
+        results.push(run.map_err(|e| format!("Failed to read run: {}", e))?);
+    }
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn update_workflow_run_status(
+    id: String,
+    status: String,
+    completed_at: Option<i64>,
+    pool: State<'_, DbPool>,
+) -> Result<(), String> {
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
+
+    if status == "running" {
+        conn.execute(
+            "UPDATE workflow_runs SET status = ?1, started_at = ?2 WHERE id = ?3",
+            params![&status, &now, &id],
+        ).map_err(|e| format!("Failed to update run status: {}", e))?;
+    } else {
+        let end_time = completed_at.unwrap_or(now);
+        conn.execute(
+            "UPDATE workflow_runs SET status = ?1, completed_at = ?2 WHERE id = ?3",
+            params![&status, &end_time, &id],
+        ).map_err(|e| format!("Failed to update run status: {}", e))?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_workflow_run(
+    id: String,
+    pool: State<'_, DbPool>,
+) -> Result<(), String> {
+    let conn = get_db_connection(&pool)?;
+    conn.execute("DELETE FROM workflow_runs WHERE id = ?1", params![&id])
+        .map_err(|e| format!("Failed to delete workflow run: {}", e))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn create_workflow_run_step(
+    run_id: String,
+    step_index: i32,
+    framework_id: String,
+    input_prompt: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<WorkflowRunStep, String> {
+    let conn = get_db_connection(&pool)?;
+    let id = Uuid::new_v4().to_string();
+
+    conn.execute(
+        "INSERT INTO workflow_run_steps (id, run_id, step_index, framework_id, status, input_prompt) VALUES (?1, ?2, ?3, ?4, 'pending', ?5)",
+        params![&id, &run_id, &step_index, &framework_id, &input_prompt],
+    ).map_err(|e| format!("Failed to create run step: {}", e))?;
+
+    Ok(WorkflowRunStep {
+        id,
+        run_id,
+        step_index,
+        framework_id,
+        status: "pending".to_string(),
+        input_prompt,
+        output_content: None,
+        output_id: None,
+        error: None,
+        started_at: None,
+        completed_at: None,
+    })
+}
+
+#[tauri::command]
+pub async fn update_workflow_run_step(
+    id: String,
+    status: String,
+    output_content: Option<String>,
+    output_id: Option<String>,
+    error: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<(), String> {
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
+
+    if status == "running" {
+        conn.execute(
+            "UPDATE workflow_run_steps SET status = ?1, started_at = ?2 WHERE id = ?3",
+            params![&status, &now, &id],
+        ).map_err(|e| format!("Failed to update step: {}", e))?;
+    } else {
+        conn.execute(
+            "UPDATE workflow_run_steps SET status = ?1, output_content = ?2, output_id = ?3, error = ?4, completed_at = ?5 WHERE id = ?6",
+            params![&status, &output_content, &output_id, &error, &now, &id],
+        ).map_err(|e| format!("Failed to update step: {}", e))?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_workflow_run_steps(
+    run_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<WorkflowRunStep>, String> {
+    let conn = get_db_connection(&pool)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, run_id, step_index, framework_id, status, input_prompt, output_content, output_id, error, started_at, completed_at FROM workflow_run_steps WHERE run_id = ?1 ORDER BY step_index ASC"
+    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let steps = stmt.query_map(params![&run_id], |row| {
+        Ok(WorkflowRunStep {
+            id: row.get(0)?,
+            run_id: row.get(1)?,
+            step_index: row.get(2)?,
+            framework_id: row.get(3)?,
+            status: row.get(4)?,
+            input_prompt: row.get(5)?,
+            output_content: row.get(6)?,
+            output_id: row.get(7)?,
+            error: row.get(8)?,
+            started_at: row.get(9)?,
+            completed_at: row.get(10)?,
+        })
+    }).map_err(|e| format!("Failed to query steps: {}", e))?;
+
+    let mut results = Vec::new();
+    for step in steps {
+        results.push(step.map_err(|e| format!("Failed to read step: {}", e))?);
+    }
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn get_workflow_run_step(
+    id: String,
+    pool: State<'_, DbPool>,
+) -> Result<WorkflowRunStep, String> {
+    let conn = get_db_connection(&pool)?;
+
+    conn.query_row(
+        "SELECT id, run_id, step_index, framework_id, status, input_prompt, output_content, output_id, error, started_at, completed_at FROM workflow_run_steps WHERE id = ?1",
+        params![&id],
+        |row| {
+            Ok(WorkflowRunStep {
+                id: row.get(0)?,
+                run_id: row.get(1)?,
+                step_index: row.get(2)?,
+                framework_id: row.get(3)?,
+                status: row.get(4)?,
+                input_prompt: row.get(5)?,
+                output_content: row.get(6)?,
+                output_id: row.get(7)?,
+                error: row.get(8)?,
+                started_at: row.get(9)?,
+                completed_at: row.get(10)?,
+            })
+        },
+    ).map_err(|e| format!("Run step not found: {}", e))
+}
+
+// --- AI Insights Commands ---
+
+#[tauri::command]
+pub async fn list_project_insights(
+    project_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<ProjectInsight>, String> {
+    let conn = get_db_connection(&pool)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, project_id, insight_type, title, description, priority, framework_id, is_dismissed, created_at FROM project_insights WHERE project_id = ?1 AND is_dismissed = 0 ORDER BY CASE priority WHEN 'high' THEN 0 WHEN 'medium' THEN 1 ELSE 2 END, created_at DESC"
+    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let insights = stmt.query_map(params![&project_id], |row| {
+        Ok(ProjectInsight {
+            id: row.get(0)?,
+            project_id: row.get(1)?,
+            insight_type: row.get(2)?,
+            title: row.get(3)?,
+            description: row.get(4)?,
+            priority: row.get(5)?,
+            framework_id: row.get(6)?,
+            is_dismissed: row.get(7)?,
+            created_at: row.get(8)?,
+        })
+    }).map_err(|e| format!("Failed to query insights: {}", e))?;
+
+    let mut results = Vec::new();
+    for insight in insights {
+        results.push(insight.map_err(|e| format!("Failed to read insight: {}", e))?);
+    }
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn dismiss_insight(
+    id: String,
+    pool: State<'_, DbPool>,
+) -> Result<(), String> {
+    let conn = get_db_connection(&pool)?;
+    conn.execute("UPDATE project_insights SET is_dismissed = 1 WHERE id = ?1", params![&id])
+        .map_err(|e| format!("Failed to dismiss insight: {}", e))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn save_insights(
+    project_id: String,
+    insights_json: String,
+    pool: State<'_, DbPool>,
+) -> Result<(), String> {
+    let conn = get_db_connection(&pool)?;
+    let now = Utc::now().timestamp();
+
+    let insights: Vec<serde_json::Value> = serde_json::from_str(&insights_json)
+        .map_err(|e| format!("Invalid insights JSON: {}", e))?;
+
+    for insight in insights {
+        let id = Uuid::new_v4().to_string();
+        conn.execute(
+            "INSERT INTO project_insights (id, project_id, insight_type, title, description, priority, framework_id, is_dismissed, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, ?8)",
+            params![
+                &id,
+                &project_id,
+                insight["type"].as_str().unwrap_or("suggestion"),
+                insight["title"].as_str().unwrap_or(""),
+                insight["description"].as_str().unwrap_or(""),
+                insight["priority"].as_str().unwrap_or("medium"),
+                insight.get("framework_id").and_then(|v| v.as_str()),
+                &now,
+            ],
+        ).map_err(|e| format!("Failed to save insight: {}", e))?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn clear_project_insights(
+    project_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<(), String> {
+    let conn = get_db_connection(&pool)?;
+    conn.execute("DELETE FROM project_insights WHERE project_id = ?1", params![&project_id])
+        .map_err(|e| format!("Failed to clear insights: {}", e))?;
+    Ok(())
+}
+
+// --- Project Bundle Commands ---
+
+const PROJECT_BUNDLE_VERSION: i32 = 1;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectBundle {
+    pub bundle_version: i32,
+    pub exported_at: String,
+    pub project: Project,
+    pub folders: Vec<Folder>,
+    pub conversations: Vec<Conversation>,
+    pub messages: Vec<Message>,
+    pub context_documents: Vec<ContextDocument>,
+    pub framework_outputs: Vec<FrameworkOutput>,
+    pub command_history: Vec<CommandHistoryEntry>,
+}
+
+async fn build_project_bundle(
+    project_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<ProjectBundle, String> {
+    let project = get_project(project_id.clone(), pool.clone()).await?
+        .ok_or_else(|| format!("Project '{}' not found", project_id))?;
+
+    // Sort everything by (created_at, id) so repeated exports diff cleanly
+    let mut folders = list_folders(project_id.clone(), pool.clone()).await?;
+    folders.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+
+    let mut conversations = list_conversations(project_id.clone(), Some(true), pool.clone()).await?;
+    conversations.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+
+    let mut context_documents = list_context_documents(project_id.clone(), None, None, None, None, None, None, pool.clone()).await?;
+    context_documents.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+
+    let mut framework_outputs = list_framework_outputs(project_id.clone(), None, None, None, None, None, None, pool.clone()).await?;
+    framework_outputs.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+
+    let conn = get_db_connection(&pool)?;
+
+    let messages = {
+        let mut stmt = conn.prepare(
+            "SELECT m.id, m.conversation_id, m.role, m.content, m.tokens, m.created_at
+             FROM messages m
+             JOIN conversations c ON c.id = m.conversation_id
+             WHERE c.project_id = ?1
+             ORDER BY c.created_at ASC, c.id ASC, m.created_at ASC, m.seq ASC"
+        ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let rows = stmt.query_map(params![&project_id], |row| {
+            Ok(Message {
+                id: row.get(0)?,
+                conversation_id: row.get(1)?,
+                role: row.get(2)?,
+                content: row.get(3)?,
+                tokens: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        }).map_err(|e| format!("Failed to query messages: {}", e))?;
+
+        let result: Result<Vec<Message>, _> = rows.collect();
+        result.map_err(|e| format!("Failed to collect messages: {}", e))?
+    };
+
+    let command_history = {
+        let mut stmt = conn.prepare(
+            "SELECT id, project_id, command, output, exit_code, created_at
+             FROM command_history
+             WHERE project_id = ?1
+             ORDER BY created_at ASC, id ASC"
+        ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let rows = stmt.query_map(params![&project_id], |row| {
+            Ok(CommandHistoryEntry {
+                id: row.get(0)?,
+                project_id: row.get(1)?,
+                command: row.get(2)?,
+                output: row.get(3)?,
+                exit_code: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        }).map_err(|e| format!("Failed to query command history: {}", e))?;
+
+        let result: Result<Vec<CommandHistoryEntry>, _> = rows.collect();
+        result.map_err(|e| format!("Failed to collect command history: {}", e))?
+    };
+
+    Ok(ProjectBundle {
+        bundle_version: PROJECT_BUNDLE_VERSION,
+        exported_at: Utc::now().to_rfc3339(),
+        project,
+        folders,
+        conversations,
+        messages,
+        context_documents,
+        framework_outputs,
+        command_history,
+    })
+}
+
+#[tauri::command]
+pub async fn export_project(
+    project_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<String, String> {
+    let bundle = build_project_bundle(project_id, pool).await?;
+
+    serde_json::to_string_pretty(&bundle)
+        .map_err(|e| format!("Failed to serialize project bundle: {}", e))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OutputsManifestEntry {
+    pub filename: String,
+    pub output_id: String,
+    pub name: String,
+    pub framework_id: String,
+    pub category: String,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OutputsManifest {
+    pub project_id: String,
+    pub exported_at: String,
+    pub files: Vec<OutputsManifestEntry>,
+}
+
+// Zips every framework output of the project as Markdown plus a manifest.json. With dest_path
+// the archive is written there and the path returned; otherwise the archive comes back as base64.
+#[tauri::command]
+pub async fn export_project_outputs_zip(
+    project_id: String,
+    dest_path: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<String, String> {
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    let outputs = list_framework_outputs(project_id.clone(), None, None, None, None, None, None, pool).await?;
+
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let mut used_names: std::collections::HashSet<String> = std::collections::HashSet::new();
+    let mut files = Vec::with_capacity(outputs.len());
+
+    for output in &outputs {
+        let base = match sanitize_filename(&output.name) {
+            name if name.is_empty() => "output".to_string(),
+            name => name,
+        };
+        let mut filename = format!("{}.md", base);
+        let mut suffix = 2;
+        while used_names.contains(&filename) {
+            filename = format!("{}-{}.md", base, suffix);
+            suffix += 1;
+        }
+        used_names.insert(filename.clone());
+
+        writer.start_file(filename.as_str(), options)
+            .map_err(|e| format!("Failed to add {} to zip: {}", filename, e))?;
+        writer.write_all(output.generated_content.as_bytes())
+            .map_err(|e| format!("Failed to write {} to zip: {}", filename, e))?;
+
+        files.push(OutputsManifestEntry {
+            filename,
+            output_id: output.id.clone(),
+            name: output.name.clone(),
+            framework_id: output.framework_id.clone(),
+            category: output.category.clone(),
+            created_at: output.created_at,
+        });
+    }
+
+    let manifest = OutputsManifest {
+        project_id,
+        exported_at: Utc::now().to_rfc3339(),
+        files,
+    };
+    let manifest_json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    writer.start_file("manifest.json", options)
+        .map_err(|e| format!("Failed to add manifest to zip: {}", e))?;
+    writer.write_all(manifest_json.as_bytes())
+        .map_err(|e| format!("Failed to write manifest to zip: {}", e))?;
+
+    let bytes = writer.finish()
+        .map_err(|e| format!("Failed to finish zip: {}", e))?
+        .into_inner();
+
+    match dest_path {
+        Some(path) => {
+            let dest = PathBuf::from(expand_home(&path));
+            std::fs::write(&dest, &bytes)
+                .map_err(|e| format!("Failed to write zip to {}: {}", dest.display(), e))?;
+            Ok(dest.to_string_lossy().to_string())
+        }
+        None => Ok(general_purpose::STANDARD.encode(bytes)),
+    }
+}
+
+// Inserts a bundle under freshly generated ids, remapping every internal reference.
+// Runs inside the caller's transaction.
+fn insert_project_bundle(conn: &Connection, bundle: &ProjectBundle, keys: &DocumentKeys) -> Result<String, String> {
     let now = Utc::now().timestamp();
+    let new_project_id = Uuid::new_v4().to_string();
+
+    let working_dir = bundle.project.working_dir.clone()
+        .filter(|dir| std::path::Path::new(dir).is_dir());
+
+    conn.execute(
+        "INSERT INTO projects (id, name, description, working_dir, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            &new_project_id,
+            &bundle.project.name,
+            &bundle.project.description.clone().unwrap_or_default(),
+            &working_dir,
+            &bundle.project.created_at,
+            &now,
+        ],
+    ).map_err(|e| format!("Failed to import project: {}", e))?;
+
+    let folder_ids: HashMap<&str, String> = bundle.folders.iter()
+        .map(|f| (f.id.as_str(), Uuid::new_v4().to_string()))
+        .collect();
+    let conversation_ids: HashMap<&str, String> = bundle.conversations.iter()
+        .map(|c| (c.id.as_str(), Uuid::new_v4().to_string()))
+        .collect();
+    let document_ids: HashMap<&str, String> = bundle.context_documents.iter()
+        .map(|d| (d.id.as_str(), Uuid::new_v4().to_string()))
+        .collect();
+    let remap_folder = |id: &Option<String>| id.as_deref().and_then(|f| folder_ids.get(f).cloned());
+
+    // Parents may appear after their children, so link the hierarchy in a second pass
+    for folder in &bundle.folders {
+        conn.execute(
+            "INSERT INTO folders (id, project_id, parent_id, name, color, sort_order, created_at, updated_at)
+             VALUES (?1, ?2, NULL, ?3, ?4, ?5, ?6, ?7)",
+            params![&folder_ids[folder.id.as_str()], &new_project_id, &folder.name, &folder.color, &folder.sort_order, &folder.created_at, &folder.updated_at],
+        ).map_err(|e| format!("Failed to import folder: {}", e))?;
+    }
+    for folder in &bundle.folders {
+        if let Some(parent) = remap_folder(&folder.parent_id) {
+            conn.execute(
+                "UPDATE folders SET parent_id = ?1 WHERE id = ?2",
+                params![&parent, &folder_ids[folder.id.as_str()]],
+            ).map_err(|e| format!("Failed to link folder: {}", e))?;
+        }
+    }
+
+    for conversation in &bundle.conversations {
+        conn.execute(
+            "INSERT INTO conversations (id, project_id, title, model, total_tokens, total_cost, created_at, updated_at, is_archived, is_pinned)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                &conversation_ids[conversation.id.as_str()], &new_project_id,
+                &conversation.title.clone().unwrap_or_default(), &conversation.model,
+                &conversation.total_tokens, &conversation.total_cost,
+                &conversation.created_at, &conversation.updated_at, &conversation.is_archived,
+                &conversation.is_pinned,
+            ],
+        ).map_err(|e| format!("Failed to import conversation: {}", e))?;
+    }
+
+    for message in &bundle.messages {
+        let conversation_id = conversation_ids.get(message.conversation_id.as_str())
+            .ok_or_else(|| format!("Message '{}' references an unknown conversation", message.id))?;
+        conn.execute(
+            "INSERT INTO messages (id, conversation_id, role, content, tokens, created_at, seq)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, (SELECT COALESCE(MAX(seq), 0) + 1 FROM messages WHERE conversation_id = ?2))",
+            params![Uuid::new_v4().to_string(), conversation_id, &message.role, &message.content, &message.tokens, &message.created_at],
+        ).map_err(|e| format!("Failed to import message: {}", e))?;
+    }
+
+    // Bundles carry plaintext (the key is machine-bound), so encrypted documents are sealed again here
+    for doc in &bundle.context_documents {
+        conn.execute(
+            "INSERT INTO context_documents (id, project_id, name, type, content, url, is_global, size_bytes, created_at, folder_id, tags, is_favorite, sort_order, content_hash, is_encrypted)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            params![
+                &document_ids[doc.id.as_str()], &new_project_id, &doc.name, &doc.doc_type,
+                seal_document_content(&doc.content, doc.is_encrypted, keys)?,
+                &doc.url, &doc.is_global, &doc.size_bytes, &doc.created_at,
+                remap_folder(&doc.folder_id), &doc.tags, &doc.is_favorite, &doc.sort_order,
+                stored_content_hash(&doc.content, doc.is_encrypted), &doc.is_encrypted,
+            ],
+        ).map_err(|e| format!("Failed to import context document: {}", e))?;
+    }
+
+    for output in &bundle.framework_outputs {
+        // Documents outside the bundle (e.g. global docs from another project) keep their ids
+        let doc_ids: Vec<String> = serde_json::from_str(&output.context_doc_ids).unwrap_or_default();
+        let remapped: Vec<String> = doc_ids.iter()
+            .map(|id| document_ids.get(id.as_str()).cloned().unwrap_or_else(|| id.clone()))
+            .collect();
+        let context_doc_ids = serde_json::to_string(&remapped)
+            .map_err(|e| format!("Failed to serialize context doc ids: {}", e))?;
+
+        conn.execute(
+            "INSERT INTO framework_outputs (id, project_id, framework_id, category, name, user_prompt, context_doc_ids, generated_content, format, created_at, updated_at, folder_id, tags, is_favorite, sort_order, conversation_id, word_count, author, generation_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+            params![
+                Uuid::new_v4().to_string(), &new_project_id, &output.framework_id, &output.category,
+                &output.name, &output.user_prompt, &context_doc_ids, &output.generated_content,
+                &output.format, &output.created_at, &output.updated_at,
+                remap_folder(&output.folder_id), &output.tags, &output.is_favorite, &output.sort_order,
+                // Conversations left out of the bundle leave the output without a source
+                output.conversation_id.as_deref().and_then(|c| conversation_ids.get(c).cloned()),
+                word_count(&output.generated_content),
+                &output.author,
+                &output.generation_ms,
+            ],
+        ).map_err(|e| format!("Failed to import framework output: {}", e))?;
+    }
+
+    for entry in &bundle.command_history {
+        conn.execute(
+            "INSERT INTO command_history (id, project_id, command, output, exit_code, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![Uuid::new_v4().to_string(), &new_project_id, &entry.command, &entry.output, &entry.exit_code, &entry.created_at],
+        ).map_err(|e| format!("Failed to import command history: {}", e))?;
+    }
+
+    Ok(new_project_id)
+}
+
+fn parse_project_bundle(bundle_json: &str) -> Result<ProjectBundle, String> {
+    let raw: serde_json::Value = serde_json::from_str(bundle_json)
+        .map_err(|e| format!("Invalid project bundle: {}", e))?;
+
+    // Check the version before the full parse so a newer bundle gets a clear error
+    let version = raw.get("bundle_version").and_then(|v| v.as_i64())
+        .ok_or("Project bundle is missing bundle_version")?;
+    if version != PROJECT_BUNDLE_VERSION as i64 {
+        return Err(format!(
+            "Unsupported project bundle version {} (expected {})",
+            version, PROJECT_BUNDLE_VERSION
+        ));
+    }
+
+    serde_json::from_value(raw).map_err(|e| format!("Invalid project bundle: {}", e))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectImportPreview {
+    pub project_name: String,
+    pub bundle_version: i32,
+    pub exported_at: String,
+    pub projects: usize,
+    pub folders: usize,
+    pub conversations: usize,
+    pub messages: usize,
+    pub context_documents: usize,
+    pub framework_outputs: usize,
+    pub command_history: usize,
+    pub warnings: Vec<String>,
+}
+
+// Things insert_project_bundle will quietly drop or leave dangling; anything it would
+// reject outright is returned as an error instead
+fn project_bundle_warnings(bundle: &ProjectBundle) -> Result<Vec<String>, String> {
+    let folder_ids: std::collections::HashSet<&str> = bundle.folders.iter().map(|f| f.id.as_str()).collect();
+    let conversation_ids: std::collections::HashSet<&str> = bundle.conversations.iter().map(|c| c.id.as_str()).collect();
+    let document_ids: std::collections::HashSet<&str> = bundle.context_documents.iter().map(|d| d.id.as_str()).collect();
+
+    if let Some(message) = bundle.messages.iter().find(|m| !conversation_ids.contains(m.conversation_id.as_str())) {
+        return Err(format!("Message '{}' references an unknown conversation", message.id));
+    }
+
+    let mut warnings = Vec::new();
+
+    if let Some(dir) = &bundle.project.working_dir {
+        if !std::path::Path::new(dir).is_dir() {
+            warnings.push(format!("Working directory {} does not exist here and will not be set", dir));
+        }
+    }
+
+    let is_missing = |ids: &std::collections::HashSet<&str>, id: &Option<String>| {
+        id.as_deref().is_some_and(|id| !ids.contains(id))
+    };
+    let orphan_folders = bundle.folders.iter().filter(|f| is_missing(&folder_ids, &f.parent_id)).count();
+    if orphan_folders > 0 {
+        warnings.push(format!("{} folder(s) have a parent outside the bundle and will be placed at the root", orphan_folders));
+    }
+    let unfiled_items = bundle.context_documents.iter().filter(|d| is_missing(&folder_ids, &d.folder_id)).count()
+        + bundle.framework_outputs.iter().filter(|o| is_missing(&folder_ids, &o.folder_id)).count();
+    if unfiled_items > 0 {
+        warnings.push(format!("{} item(s) are filed in folders outside the bundle and will be unfiled", unfiled_items));
+    }
+    let unlinked_outputs = bundle.framework_outputs.iter().filter(|o| is_missing(&conversation_ids, &o.conversation_id)).count();
+    if unlinked_outputs > 0 {
+        warnings.push(format!("{} output(s) came from conversations outside the bundle and will lose that link", unlinked_outputs));
+    }
+    let external_doc_outputs = bundle.framework_outputs.iter()
+        .filter(|o| {
+            serde_json::from_str::<Vec<String>>(&o.context_doc_ids).unwrap_or_default()
+                .iter()
+                .any(|id| !document_ids.contains(id.as_str()))
+        })
+        .count();
+    if external_doc_outputs > 0 {
+        warnings.push(format!("{} output(s) reference context documents outside the bundle", external_doc_outputs));
+    }
 
-    conn.execute(
-        "UPDATE workflows SET name = ?1, description = ?2, steps = ?3, updated_at = ?4 WHERE id = ?5",
-        params![&name, &description, &steps_json, &now, &id],
-    ).map_err(|e| format!("Failed to update workflow: {}", e))?;
+    Ok(warnings)
+}
 
-    get_workflow(id, app).await
+// Dry run of confirm_import_project: parses and validates the bundle without touching the database
+#[tauri::command]
+pub async fn preview_import_project(bundle_json: String) -> Result<ProjectImportPreview, String> {
+    let bundle = parse_project_bundle(&bundle_json)?;
+    let warnings = project_bundle_warnings(&bundle)?;
+
+    Ok(ProjectImportPreview {
+        project_name: bundle.project.name,
+        bundle_version: bundle.bundle_version,
+        exported_at: bundle.exported_at,
+        projects: 1,
+        folders: bundle.folders.len(),
+        conversations: bundle.conversations.len(),
+        messages: bundle.messages.len(),
+        context_documents: bundle.context_documents.len(),
+        framework_outputs: bundle.framework_outputs.len(),
+        command_history: bundle.command_history.len(),
+        warnings,
+    })
 }
 
 #[tauri::command]
-pub async fn delete_workflow(
-    id: String,
-    app: tauri::AppHandle,
-) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
-    conn.execute("DELETE FROM workflows WHERE id = ?1", params![&id])
-        .map_err(|e| format!("Failed to delete workflow: {}", e))?;
-    Ok(())
+pub async fn confirm_import_project(
+    bundle_json: String,
+    pool: State<'_, DbPool>,
+) -> Result<String, String> {
+    let bundle = parse_project_bundle(&bundle_json)?;
+
+    let conn = get_db_connection(&pool)?;
+    let keys = get_document_keys()?;
+    with_transaction(&conn, |tx| insert_project_bundle(tx, &bundle, &keys))
 }
 
+// Copies a project through the same bundle path as export/import, so every id is remapped.
+// Command history is never copied; conversations only when asked.
 #[tauri::command]
-pub async fn duplicate_workflow(
+pub async fn duplicate_project(
     id: String,
     new_name: String,
-    project_id: String,
-    app: tauri::AppHandle,
-) -> Result<Workflow, String> {
-    let conn = get_db_connection(&app)?;
-    let original = conn.query_row(
-        "SELECT steps, description FROM workflows WHERE id = ?1",
-        params![&id],
-        |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
-    ).map_err(|e| format!("Workflow not found: {}", e))?;
+    include_conversations: bool,
+    pool: State<'_, DbPool>,
+) -> Result<Project, String> {
+    if new_name.trim().is_empty() {
+        return Err("Project name cannot be empty".to_string());
+    }
 
-    let new_id = Uuid::new_v4().to_string();
-    let now = Utc::now().timestamp();
+    let mut bundle = build_project_bundle(id, pool.clone()).await?;
+    bundle.project.name = new_name;
+    bundle.project.created_at = Utc::now().timestamp();
+    bundle.command_history.clear();
+    if !include_conversations {
+        bundle.conversations.clear();
+        bundle.messages.clear();
+    }
 
-    conn.execute(
-        "INSERT INTO workflows (id, project_id, name, description, steps, is_template, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, 0, ?6, ?7)",
-        params![&new_id, &project_id, &new_name, &original.1, &original.0, &now, &now],
-    ).map_err(|e| format!("Failed to duplicate workflow: {}", e))?;
+    let conn = get_db_connection(&pool)?;
+    let keys = get_document_keys()?;
+    let new_project_id = with_transaction(&conn, |tx| insert_project_bundle(tx, &bundle, &keys))?;
+    drop(conn);
 
-    Ok(Workflow {
-        id: new_id,
-        project_id,
-        name: new_name,
-        description: original.1,
-        steps: original.0,
-        is_template: false,
-        created_at: now,
-        updated_at: now,
-    })
+    get_project(new_project_id, pool).await?
+        .ok_or_else(|| "Project not found after duplicate".to_string())
 }
 
-#[tauri::command]
-pub async fn create_workflow_run(
-    workflow_id: String,
-    project_id: String,
-    app: tauri::AppHandle,
-) -> Result<WorkflowRun, String> {
-    let conn = get_db_connection(&app)?;
-    let id = Uuid::new_v4().to_string();
-    let now = Utc::now().timestamp();
+// --- Database Backup Commands ---
 
-    conn.execute(
-        "INSERT INTO workflow_runs (id, workflow_id, project_id, status, created_at) VALUES (?1, ?2, ?3, 'pending', ?4)",
-        params![&id, &workflow_id, &project_id, &now],
-    ).map_err(|e| format!("Failed to create workflow run: {}", e))?;
-
-    Ok(WorkflowRun {
-        id,
-        workflow_id,
-        project_id,
-        status: "pending".to_string(),
-        started_at: None,
-        completed_at: None,
-        created_at: now,
-    })
-}
+// Key tables a restore source must contain to be treated as an app database
+const BACKUP_REQUIRED_TABLES: &[&str] = &["projects", "conversations", "messages", "settings", "context_documents"];
 
-#[tauri::command]
-pub async fn get_workflow_run(
-    id: String,
-    app: tauri::AppHandle,
-) -> Result<WorkflowRun, String> {
-    let conn = get_db_connection(&app)?;
+// VACUUM INTO takes a consistent snapshot even while other connections are writing
+fn vacuum_into(conn: &Connection, dest: &std::path::Path) -> Result<u64, String> {
+    conn.execute("VACUUM INTO ?1", params![dest.to_string_lossy()])
+        .map_err(|e| format!("Failed to back up database: {}", e))?;
 
-    conn.query_row(
-        "SELECT id, workflow_id, project_id, status, started_at, completed_at, created_at FROM workflow_runs WHERE id = ?1",
-        params![&id],
-        |row| {
-            Ok(WorkflowRun {
-                id: row.get(0)?,
-                workflow_id: row.get(1)?,
-                project_id: row.get(2)?,
-                status: row.get(3)?,
-                started_at: row.get(4)?,
-                completed_at: row.get(5)?,
-                created_at: row.get(6)?,
-            })
-        },
-    ).map_err(|e| format!("Workflow run not found: {}", e))
+    std::fs::metadata(dest)
+        .map(|m| m.len())
+        .map_err(|e| format!("Failed to read backup size: {}", e))
 }
 
 #[tauri::command]
-pub async fn list_workflow_runs(
-    workflow_id: String,
-    app: tauri::AppHandle,
-) -> Result<Vec<WorkflowRun>, String> {
-    let conn = get_db_connection(&app)?;
+pub async fn backup_database(dest_path: String, pool: State<'_, DbPool>) -> Result<u64, String> {
+    let dest = PathBuf::from(expand_home(&dest_path));
+    if dest.exists() {
+        return Err(format!("Backup destination already exists: {}", dest.display()));
+    }
 
-    let mut stmt = conn.prepare(
-        "SELECT id, workflow_id, project_id, status, started_at, completed_at, created_at FROM workflow_runs WHERE workflow_id = ?1 ORDER BY created_at DESC"
-    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
+    let conn = get_db_connection(&pool)?;
+    vacuum_into(&conn, &dest)
+}
 
-    let runs = stmt.query_map(params![&workflow_id], |row| {
-        Ok(WorkflowRun {
-            id: row.get(0)?,
-            workflow_id: row.get(1)?,
-            project_id: row.get(2)?,
-            status: row.get(3)?,
-            started_at: row.get(4)?,
-            completed_at: row.get(5)?,
-            created_at: row.get(6)?,
-        })
-    }).map_err(|e| format!("Failed to query runs: {}", e))?;
+fn validate_backup_source(src: &std::path::Path) -> Result<(), String> {
+    let source = Connection::open_with_flags(src, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open backup: {}", e))?;
 
-    let mut results = Vec::new();
-    for run in runs {
-        results.push(run.map_err(|e| format!("Failed to read run: {}", e))?);
+    let check: String = source.query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .map_err(|e| format!("Backup is not a valid database: {}", e))?;
+    if check != "ok" {
+        return Err(format!("Backup failed integrity check: {}", check));
     }
-    Ok(results)
-}
 
-#[tauri::command]
-pub async fn update_workflow_run_status(
-    id: String,
-    status: String,
-    completed_at: Option<i64>,
-    app: tauri::AppHandle,
-) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
-    let now = Utc::now().timestamp();
+    for table in BACKUP_REQUIRED_TABLES {
+        let exists: bool = source.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+            params![table],
+            |row| row.get(0),
+        ).map_err(|e| format!("Failed to inspect backup: {}", e))?;
+        if !exists {
+            return Err(format!("Backup is missing the '{}' table", table));
+        }
+    }
 
-    if status == "running" {
-        conn.execute(
-            "UPDATE workflow_runs SET status = ?1, started_at = ?2 WHERE id = ?3",
-            params![&status, &now, &id],
-        ).map_err(|e| format!("Failed to update run status: {}", e))?;
-    } else {
-        let end_time = completed_at.unwrap_or(now);
-        conn.execute(
-            "UPDATE workflow_runs SET status = ?1, completed_at = ?2 WHERE id = ?3",
-            params![&status, &end_time, &id],
-        ).map_err(|e| format!("Failed to update run status: {}", e))?;
+    // Backups from before versioned migrations have no schema_migrations table; init_db upgrades them
+    let has_versions: bool = source.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_migrations')",
+        [],
+        |row| row.get(0),
+    ).map_err(|e| format!("Failed to inspect backup: {}", e))?;
+    if has_versions {
+        let backup_version: u32 = source.query_row(
+            "SELECT COALESCE(MAX(version), 0) FROM schema_migrations", [], |row| row.get(0)
+        ).map_err(|e| format!("Failed to read backup schema version: {}", e))?;
+        let latest = migrations().last().map(|(v, _)| *v).unwrap_or(0);
+        if backup_version > latest {
+            return Err(format!(
+                "Backup was made by a newer version of the app (schema {} > {})",
+                backup_version, latest
+            ));
+        }
     }
-    Ok(())
-}
 
-#[tauri::command]
-pub async fn delete_workflow_run(
-    id: String,
-    app: tauri::AppHandle,
-) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
-    conn.execute("DELETE FROM workflow_runs WHERE id = ?1", params![&id])
-        .map_err(|e| format!("Failed to delete workflow run: {}", e))?;
     Ok(())
 }
 
+// Copies the backup over the live database through SQLite's backup API, keeping a
+// pre-restore snapshot next to it, then re-runs init_db so older schemas are migrated.
 #[tauri::command]
-pub async fn create_workflow_run_step(
-    run_id: String,
-    step_index: i32,
-    framework_id: String,
-    input_prompt: Option<String>,
-    app: tauri::AppHandle,
-) -> Result<WorkflowRunStep, String> {
-    let conn = get_db_connection(&app)?;
-    let id = Uuid::new_v4().to_string();
-
-    conn.execute(
-        "INSERT INTO workflow_run_steps (id, run_id, step_index, framework_id, status, input_prompt) VALUES (?1, ?2, ?3, ?4, 'pending', ?5)",
-        params![&id, &run_id, &step_index, &framework_id, &input_prompt],
-    ).map_err(|e| format!("Failed to create run step: {}", e))?;
-
-    Ok(WorkflowRunStep {
-        id,
-        run_id,
-        step_index,
-        framework_id,
-        status: "pending".to_string(),
-        input_prompt,
-        output_content: None,
-        output_id: None,
-        error: None,
-        started_at: None,
-        completed_at: None,
-    })
-}
+pub async fn restore_database(src_path: String, app: tauri::AppHandle, pool: State<'_, DbPool>) -> Result<u64, String> {
+    let src = PathBuf::from(expand_home(&src_path));
+    if !src.is_file() {
+        return Err(format!("Backup file not found: {}", src.display()));
+    }
+    validate_backup_source(&src)?;
 
-#[tauri::command]
-pub async fn update_workflow_run_step(
-    id: String,
-    status: String,
-    output_content: Option<String>,
-    output_id: Option<String>,
-    error: Option<String>,
-    app: tauri::AppHandle,
-) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
-    let now = Utc::now().timestamp();
+    let mut conn = get_db_connection(&pool)?;
 
-    if status == "running" {
-        conn.execute(
-            "UPDATE workflow_run_steps SET status = ?1, started_at = ?2 WHERE id = ?3",
-            params![&status, &now, &id],
-        ).map_err(|e| format!("Failed to update step: {}", e))?;
-    } else {
-        conn.execute(
-            "UPDATE workflow_run_steps SET status = ?1, output_content = ?2, output_id = ?3, error = ?4, completed_at = ?5 WHERE id = ?6",
-            params![&status, &output_content, &output_id, &error, &now, &id],
-        ).map_err(|e| format!("Failed to update step: {}", e))?;
+    let snapshot = db_file_path(&app)?.with_file_name("pm-ide.pre-restore.db");
+    if snapshot.exists() {
+        std::fs::remove_file(&snapshot)
+            .map_err(|e| format!("Failed to remove old pre-restore snapshot: {}", e))?;
     }
-    Ok(())
-}
+    vacuum_into(&conn, &snapshot)?;
 
-#[tauri::command]
-pub async fn list_workflow_run_steps(
-    run_id: String,
-    app: tauri::AppHandle,
-) -> Result<Vec<WorkflowRunStep>, String> {
-    let conn = get_db_connection(&app)?;
-
-    let mut stmt = conn.prepare(
-        "SELECT id, run_id, step_index, framework_id, status, input_prompt, output_content, output_id, error, started_at, completed_at FROM workflow_run_steps WHERE run_id = ?1 ORDER BY step_index ASC"
-    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
+    conn.restore(rusqlite::DatabaseName::Main, &src, None::<fn(rusqlite::backup::Progress)>)
+        .map_err(|e| format!("Failed to restore database: {}", e))?;
+    drop(conn);
 
-    let steps = stmt.query_map(params![&run_id], |row| {
-        Ok(WorkflowRunStep {
-            id: row.get(0)?,
-            run_id: row.get(1)?,
-            step_index: row.get(2)?,
-            framework_id: row.get(3)?,
-            status: row.get(4)?,
-            input_prompt: row.get(5)?,
-            output_content: row.get(6)?,
-            output_id: row.get(7)?,
-            error: row.get(8)?,
-            started_at: row.get(9)?,
-            completed_at: row.get(10)?,
-        })
-    }).map_err(|e| format!("Failed to query steps: {}", e))?;
+    init_db()?;
+    rebuild_message_search_index(&get_db_connection(&pool)?)?;
+    terminal_cwds().lock().map_err(|e| format!("Lock error: {}", e))?.clear();
 
-    let mut results = Vec::new();
-    for step in steps {
-        results.push(step.map_err(|e| format!("Failed to read step: {}", e))?);
-    }
-    Ok(results)
+    std::fs::metadata(&src)
+        .map(|m| m.len())
+        .map_err(|e| format!("Failed to read backup size: {}", e))
 }
 
-#[tauri::command]
-pub async fn get_workflow_run_step(
-    id: String,
-    app: tauri::AppHandle,
-) -> Result<WorkflowRunStep, String> {
-    let conn = get_db_connection(&app)?;
+// --- Database Integrity Commands ---
 
-    conn.query_row(
-        "SELECT id, run_id, step_index, framework_id, status, input_prompt, output_content, output_id, error, started_at, completed_at FROM workflow_run_steps WHERE id = ?1",
-        params![&id],
-        |row| {
-            Ok(WorkflowRunStep {
-                id: row.get(0)?,
-                run_id: row.get(1)?,
-                step_index: row.get(2)?,
-                framework_id: row.get(3)?,
-                status: row.get(4)?,
-                input_prompt: row.get(5)?,
-                output_content: row.get(6)?,
-                output_id: row.get(7)?,
-                error: row.get(8)?,
-                started_at: row.get(9)?,
-                completed_at: row.get(10)?,
-            })
-        },
-    ).map_err(|e| format!("Run step not found: {}", e))
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ForeignKeyViolation {
+    pub table: String,
+    pub rowid: Option<i64>,
+    pub parent_table: String,
+    pub column: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IntegrityReport {
+    pub ok: bool,
+    pub integrity_errors: Vec<String>,
+    pub foreign_key_violations: Vec<ForeignKeyViolation>,
 }
 
-// --- AI Insights Commands ---
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OrphanRepairReport {
+    pub removed: HashMap<String, usize>,
+    // Rows whose foreign key is ON DELETE SET NULL keep the row and only lose the dangling reference
+    pub detached: HashMap<String, usize>,
+}
+
+// Every row PRAGMA foreign_key_check flags, paired with the ON DELETE action of the broken key
+fn collect_foreign_key_violations(conn: &Connection) -> Result<Vec<(ForeignKeyViolation, String)>, String> {
+    let mut stmt = conn.prepare("PRAGMA foreign_key_check")
+        .map_err(|e| format!("Failed to prepare foreign key check: {}", e))?;
+    let raw = stmt.query_map([], |row| Ok((
+        row.get::<_, String>(0)?,
+        row.get::<_, Option<i64>>(1)?,
+        row.get::<_, String>(2)?,
+        row.get::<_, i64>(3)?,
+    )))
+        .map_err(|e| format!("Failed to run foreign key check: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect foreign key violations: {}", e))?;
+
+    let mut fk_cache: HashMap<(String, i64), (Option<String>, String)> = HashMap::new();
+    let mut violations = Vec::with_capacity(raw.len());
+    for (table, rowid, parent_table, fk_id) in raw {
+        let key = (table.clone(), fk_id);
+        if !fk_cache.contains_key(&key) {
+            let fk = conn.query_row(
+                "SELECT \"from\", on_delete FROM pragma_foreign_key_list(?1) WHERE id = ?2",
+                params![&table, fk_id],
+                |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, String>(1)?)),
+            ).optional()
+                .map_err(|e| format!("Failed to inspect foreign keys of '{}': {}", table, e))?
+                .unwrap_or((None, "NO ACTION".to_string()));
+            fk_cache.insert(key.clone(), fk);
+        }
+        let (column, on_delete) = fk_cache[&key].clone();
+        violations.push((ForeignKeyViolation { table, rowid, parent_table, column }, on_delete));
+    }
+    Ok(violations)
+}
 
 #[tauri::command]
-pub async fn list_project_insights(
-    project_id: String,
-    app: tauri::AppHandle,
-) -> Result<Vec<ProjectInsight>, String> {
-    let conn = get_db_connection(&app)?;
-
-    let mut stmt = conn.prepare(
-        "SELECT id, project_id, insight_type, title, description, priority, framework_id, is_dismissed, created_at FROM project_insights WHERE project_id = ?1 AND is_dismissed = 0 ORDER BY CASE priority WHEN 'high' THEN 0 WHEN 'medium' THEN 1 ELSE 2 END, created_at DESC"
-    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
+pub async fn check_database_integrity(pool: State<'_, DbPool>) -> Result<IntegrityReport, String> {
+    let conn = get_db_connection(&pool)?;
+
+    let mut stmt = conn.prepare("PRAGMA integrity_check")
+        .map_err(|e| format!("Failed to prepare integrity check: {}", e))?;
+    let integrity_errors: Vec<String> = stmt.query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to run integrity check: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect integrity check results: {}", e))?
+        .into_iter()
+        .filter(|line| line != "ok")
+        .collect();
 
-    let insights = stmt.query_map(params![&project_id], |row| {
-        Ok(ProjectInsight {
-            id: row.get(0)?,
-            project_id: row.get(1)?,
-            insight_type: row.get(2)?,
-            title: row.get(3)?,
-            description: row.get(4)?,
-            priority: row.get(5)?,
-            framework_id: row.get(6)?,
-            is_dismissed: row.get(7)?,
-            created_at: row.get(8)?,
-        })
-    }).map_err(|e| format!("Failed to query insights: {}", e))?;
+    let foreign_key_violations: Vec<ForeignKeyViolation> = collect_foreign_key_violations(&conn)?
+        .into_iter()
+        .map(|(violation, _)| violation)
+        .collect();
 
-    let mut results = Vec::new();
-    for insight in insights {
-        results.push(insight.map_err(|e| format!("Failed to read insight: {}", e))?);
-    }
-    Ok(results)
+    Ok(IntegrityReport {
+        ok: integrity_errors.is_empty() && foreign_key_violations.is_empty(),
+        integrity_errors,
+        foreign_key_violations,
+    })
 }
 
+// Deleting an orphan cascades to its own children, and those can only become orphans through
+// it, so one pass over the current violations is enough. Everything runs in one transaction.
 #[tauri::command]
-pub async fn dismiss_insight(
-    id: String,
-    app: tauri::AppHandle,
-) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
-    conn.execute("UPDATE project_insights SET is_dismissed = 1 WHERE id = ?1", params![&id])
-        .map_err(|e| format!("Failed to dismiss insight: {}", e))?;
+pub async fn repair_orphaned_rows(pool: State<'_, DbPool>) -> Result<OrphanRepairReport, String> {
+    let conn = get_db_connection(&pool)?;
+    with_transaction(&conn, |tx| {
+        let mut report = OrphanRepairReport { removed: HashMap::new(), detached: HashMap::new() };
+        for (violation, on_delete) in collect_foreign_key_violations(tx)? {
+            let Some(rowid) = violation.rowid else { continue };
+            let quoted_table = violation.table.replace('"', "\"\"");
+
+            let (sql, counts) = match (&violation.column, on_delete.as_str()) {
+                (Some(column), "SET NULL") => (
+                    format!("UPDATE \"{}\" SET \"{}\" = NULL WHERE rowid = ?1", quoted_table, column.replace('"', "\"\"")),
+                    &mut report.detached,
+                ),
+                _ => (
+                    format!("DELETE FROM \"{}\" WHERE rowid = ?1", quoted_table),
+                    &mut report.removed,
+                ),
+            };
+            let changed = tx.execute(&sql, params![rowid])
+                .map_err(|e| format!("Failed to repair orphaned row in '{}': {}", violation.table, e))?;
+            if changed > 0 {
+                *counts.entry(violation.table).or_insert(0) += changed;
+            }
+        }
+
+        Ok(report)
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OptimizeReport {
+    pub size_before: u64,
+    pub size_after: u64,
+    pub vacuumed: bool,
+}
+
+// messages_fts is keyed by the messages rowid, which VACUUM may renumber on a table without an
+// INTEGER PRIMARY KEY, so the index is rebuilt from the table after every vacuum or restore
+fn rebuild_message_search_index(conn: &Connection) -> Result<(), String> {
+    conn.execute("INSERT INTO messages_fts (messages_fts) VALUES ('rebuild')", [])
+        .map_err(|e| format!("Failed to rebuild message search index: {}", e))?;
     Ok(())
 }
 
-#[tauri::command]
-pub async fn save_insights(
-    project_id: String,
-    insights_json: String,
-    app: tauri::AppHandle,
-) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
-    let now = Utc::now().timestamp();
+const VACUUM_MAX_ATTEMPTS: u32 = 3;
+const VACUUM_RETRY_DELAY: Duration = Duration::from_millis(500);
 
-    let insights: Vec<serde_json::Value> = serde_json::from_str(&insights_json)
-        .map_err(|e| format!("Invalid insights JSON: {}", e))?;
+// In WAL mode recent writes live in the -wal file, so both count towards the on-disk size
+fn database_disk_size(db_path: &std::path::Path) -> u64 {
+    let wal_path = db_path.with_file_name(format!(
+        "{}-wal",
+        db_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+    ));
+    [db_path, wal_path.as_path()].iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|m| m.len())
+        .sum()
+}
 
-    for insight in insights {
-        let id = Uuid::new_v4().to_string();
-        conn.execute(
-            "INSERT INTO project_insights (id, project_id, insight_type, title, description, priority, framework_id, is_dismissed, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, ?8)",
-            params![
-                &id,
-                &project_id,
-                insight["type"].as_str().unwrap_or("suggestion"),
-                insight["title"].as_str().unwrap_or(""),
-                insight["description"].as_str().unwrap_or(""),
-                insight["priority"].as_str().unwrap_or("medium"),
-                insight.get("framework_id").and_then(|v| v.as_str()),
-                &now,
-            ],
-        ).map_err(|e| format!("Failed to save insight: {}", e))?;
+fn is_busy_error(error: &rusqlite::Error) -> bool {
+    matches!(
+        error,
+        rusqlite::Error::SqliteFailure(e, _)
+            if e.code == rusqlite::ErrorCode::DatabaseBusy || e.code == rusqlite::ErrorCode::DatabaseLocked
+    )
+}
+
+// VACUUM cannot run inside a transaction and needs every other connection idle. The pool's busy
+// timeout covers short waits; if another command still holds the database, VACUUM is retried a
+// few times before giving up with a "database is busy" error. PRAGMA optimize and ANALYZE always run.
+#[tauri::command]
+pub async fn optimize_database(vacuum: Option<bool>, app: tauri::AppHandle, pool: State<'_, DbPool>) -> Result<OptimizeReport, String> {
+    let db_path = db_file_path(&app)?;
+    let size_before = database_disk_size(&db_path);
+
+    let conn = get_db_connection(&pool)?;
+    conn.execute_batch("PRAGMA optimize; ANALYZE;")
+        .map_err(|e| format!("Failed to optimize database: {}", e))?;
+
+    let vacuumed = vacuum.unwrap_or(false);
+    if vacuumed {
+        let mut attempt = 1;
+        loop {
+            match conn.execute_batch("VACUUM") {
+                Ok(()) => break,
+                Err(e) if is_busy_error(&e) && attempt < VACUUM_MAX_ATTEMPTS => {
+                    attempt += 1;
+                    tokio::time::sleep(VACUUM_RETRY_DELAY).await;
+                }
+                Err(e) if is_busy_error(&e) => {
+                    return Err("Database is busy; close running operations and try vacuuming again".to_string());
+                }
+                Err(e) => return Err(format!("Failed to vacuum database: {}", e)),
+            }
+        }
+        rebuild_message_search_index(&conn)?;
+        // VACUUM rewrites through the WAL; checkpoint so the main file actually shrinks
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+            .map_err(|e| format!("Failed to checkpoint database: {}", e))?;
     }
-    Ok(())
+    drop(conn);
+
+    Ok(OptimizeReport {
+        size_before,
+        size_after: database_disk_size(&db_path),
+        vacuumed,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AppDiagnostics {
+    pub app_version: String,
+    pub schema_version: u32,
+    pub latest_schema_version: u32,
+    pub database_path: String,
+    pub database_size_bytes: u64,
+    pub table_row_counts: std::collections::BTreeMap<String, i64>,
 }
 
+// Support snapshot for issue reports: versions, where the database lives and how big each table is.
+// Only counts are read, never row contents, so no keys or tokens can leak through it.
 #[tauri::command]
-pub async fn clear_project_insights(
-    project_id: String,
-    app: tauri::AppHandle,
-) -> Result<(), String> {
-    let conn = get_db_connection(&app)?;
-    conn.execute("DELETE FROM project_insights WHERE project_id = ?1", params![&project_id])
-        .map_err(|e| format!("Failed to clear insights: {}", e))?;
-    Ok(())
+pub async fn get_app_diagnostics(app: tauri::AppHandle, pool: State<'_, DbPool>) -> Result<AppDiagnostics, String> {
+    let db_path = db_file_path(&app)?;
+    let conn = get_db_connection(&pool)?;
+
+    let schema_version: u32 = conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_migrations", [], |row| row.get(0)
+    ).map_err(|e| format!("Failed to read schema version: {}", e))?;
+
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name"
+    ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let tables = stmt.query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to list tables: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect tables: {}", e))?;
+
+    let mut table_row_counts = std::collections::BTreeMap::new();
+    for table in tables {
+        let count: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM \"{}\"", table.replace('"', "\"\"")), [], |row| row.get(0)
+        ).map_err(|e| format!("Failed to count rows in {}: {}", table, e))?;
+        table_row_counts.insert(table, count);
+    }
+
+    Ok(AppDiagnostics {
+        app_version: app.package_info().version.to_string(),
+        schema_version,
+        latest_schema_version: migrations().last().map(|(v, _)| *v).unwrap_or(0),
+        database_path: db_path.to_string_lossy().to_string(),
+        database_size_bytes: database_disk_size(&db_path),
+        table_row_counts,
+    })
 }
 
 // --- Git Integration Commands ---
@@ -4344,15 +11145,16 @@ pub async fn rollback_output(
     output_id: String,
     commit_oid: String,
     app: tauri::AppHandle,
+    pool: State<'_, DbPool>,
 ) -> Result<String, String> {
     let content = get_output_at_commit(project_id.clone(), output_id.clone(), commit_oid, app.clone()).await?;
 
     // Update the DB
-    let conn = get_db_connection(&app)?;
+    let conn = get_db_connection(&pool)?;
     let now = Utc::now().timestamp();
     conn.execute(
-        "UPDATE framework_outputs SET generated_content = ?1, updated_at = ?2 WHERE id = ?3",
-        params![&content, &now, &output_id],
+        "UPDATE framework_outputs SET generated_content = ?1, updated_at = ?2, word_count = ?4 WHERE id = ?3",
+        params![&content, &now, &output_id, word_count(&content)],
     ).map_err(|e| format!("Failed to update output: {}", e))?;
 
     // Commit the rollback
@@ -4390,9 +11192,9 @@ pub struct NotionExportResult {
     pub error: Option<String>,
 }
 
-fn get_decrypted_token(encrypted: &Option<String>, app: &tauri::AppHandle) -> Result<Option<String>, String> {
+fn get_decrypted_token(encrypted: &Option<String>) -> Result<Option<String>, String> {
     if let Some(ref enc) = encrypted {
-        let key = get_encryption_key(app)?;
+        let key = get_encryption_key()?;
         Ok(Some(decrypt_string(enc, &key)?))
     } else {
         Ok(None)
@@ -4421,11 +11223,11 @@ fn markdown_to_jira(md: &str) -> String {
 }
 
 #[tauri::command]
-pub async fn test_jira_connection(app: tauri::AppHandle) -> Result<bool, String> {
-    let settings = get_settings(app.clone()).await?;
+pub async fn test_jira_connection(pool: State<'_, DbPool>) -> Result<bool, String> {
+    let settings = get_settings(pool.clone()).await?;
     let url = settings.jira_url.ok_or("Jira URL not configured")?;
     let email = settings.jira_email.ok_or("Jira email not configured")?;
-    let token = get_decrypted_token(&settings.jira_api_token_encrypted, &app)?
+    let token = get_decrypted_token(&settings.jira_api_token_encrypted)?
         .ok_or("Jira API token not configured")?;
 
     let client = reqwest::Client::new();
@@ -4441,11 +11243,11 @@ pub async fn test_jira_connection(app: tauri::AppHandle) -> Result<bool, String>
 }
 
 #[tauri::command]
-pub async fn list_jira_projects(app: tauri::AppHandle) -> Result<Vec<JiraProject>, String> {
-    let settings = get_settings(app.clone()).await?;
+pub async fn list_jira_projects(pool: State<'_, DbPool>) -> Result<Vec<JiraProject>, String> {
+    let settings = get_settings(pool.clone()).await?;
     let url = settings.jira_url.ok_or("Jira URL not configured")?;
     let email = settings.jira_email.ok_or("Jira email not configured")?;
-    let token = get_decrypted_token(&settings.jira_api_token_encrypted, &app)?
+    let token = get_decrypted_token(&settings.jira_api_token_encrypted)?
         .ok_or("Jira API token not configured")?;
 
     let client = reqwest::Client::new();
@@ -4480,15 +11282,15 @@ pub async fn export_to_jira(
     project_key: String,
     issue_type: String,
     summary: String,
-    app: tauri::AppHandle,
+    pool: State<'_, DbPool>,
 ) -> Result<JiraExportResult, String> {
-    let settings = get_settings(app.clone()).await?;
+    let settings = get_settings(pool.clone()).await?;
     let url = settings.jira_url.ok_or("Jira URL not configured")?;
     let email = settings.jira_email.ok_or("Jira email not configured")?;
-    let token = get_decrypted_token(&settings.jira_api_token_encrypted, &app)?
+    let token = get_decrypted_token(&settings.jira_api_token_encrypted)?
         .ok_or("Jira API token not configured")?;
 
-    let conn = get_db_connection(&app)?;
+    let conn = get_db_connection(&pool)?;
     let content: String = conn.query_row(
         "SELECT generated_content FROM framework_outputs WHERE id = ?1",
         params![&output_id],
@@ -4548,9 +11350,9 @@ pub async fn export_to_jira(
 }
 
 #[tauri::command]
-pub async fn test_notion_connection(app: tauri::AppHandle) -> Result<bool, String> {
-    let settings = get_settings(app.clone()).await?;
-    let token = get_decrypted_token(&settings.notion_api_token_encrypted, &app)?
+pub async fn test_notion_connection(pool: State<'_, DbPool>) -> Result<bool, String> {
+    let settings = get_settings(pool.clone()).await?;
+    let token = get_decrypted_token(&settings.notion_api_token_encrypted)?
         .ok_or("Notion API token not configured")?;
 
     let client = reqwest::Client::new();
@@ -4568,10 +11370,10 @@ pub async fn test_notion_connection(app: tauri::AppHandle) -> Result<bool, Strin
 #[tauri::command]
 pub async fn search_notion_pages(
     query: String,
-    app: tauri::AppHandle,
+    pool: State<'_, DbPool>,
 ) -> Result<Vec<NotionPage>, String> {
-    let settings = get_settings(app.clone()).await?;
-    let token = get_decrypted_token(&settings.notion_api_token_encrypted, &app)?
+    let settings = get_settings(pool.clone()).await?;
+    let token = get_decrypted_token(&settings.notion_api_token_encrypted)?
         .ok_or("Notion API token not configured")?;
 
     let client = reqwest::Client::new();
@@ -4619,13 +11421,13 @@ pub async fn export_to_notion(
     output_id: String,
     parent_page_id: String,
     title: String,
-    app: tauri::AppHandle,
+    pool: State<'_, DbPool>,
 ) -> Result<NotionExportResult, String> {
-    let settings = get_settings(app.clone()).await?;
-    let token = get_decrypted_token(&settings.notion_api_token_encrypted, &app)?
+    let settings = get_settings(pool.clone()).await?;
+    let token = get_decrypted_token(&settings.notion_api_token_encrypted)?
         .ok_or("Notion API token not configured")?;
 
-    let conn = get_db_connection(&app)?;
+    let conn = get_db_connection(&pool)?;
     let content: String = conn.query_row(
         "SELECT generated_content FROM framework_outputs WHERE id = ?1",
         params![&output_id],
@@ -4726,8 +11528,7 @@ pub async fn export_to_notion(
 
 fn expand_home(path: &str) -> String {
     if path.starts_with('~') {
-        let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
-        format!("{}{}", home, &path[1..])
+        format!("{}{}", home_dir(), &path[1..])
     } else {
         path.to_string()
     }
@@ -4843,7 +11644,9 @@ pub async fn delete_fs_path(path: String, is_dir: bool) -> Result<(), String> {
 
 #[tauri::command]
 pub async fn get_home_directory() -> Result<String, String> {
-    std::env::var("HOME").map_err(|_| "HOME not set".to_string())
+    std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .map_err(|_| "HOME not set".to_string())
 }
 
 #[tauri::command]
@@ -4852,3 +11655,621 @@ pub async fn get_app_directory() -> Result<String, String> {
         .map(|p| p.to_string_lossy().to_string())
         .map_err(|e| format!("Failed to get current directory: {}", e))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_conn() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+        init_schema(&conn).unwrap();
+        conn
+    }
+
+    fn insert_project(conn: &Connection, id: &str) {
+        conn.execute(
+            "INSERT INTO projects (id, name, description, created_at, updated_at) VALUES (?1, ?1, NULL, 0, 0)",
+            params![id],
+        ).unwrap();
+    }
+
+    fn insert_folder(conn: &Connection, id: &str, project_id: &str, parent_id: Option<&str>) {
+        conn.execute(
+            "INSERT INTO folders (id, project_id, parent_id, name, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?1, 0, 0)",
+            params![id, project_id, parent_id],
+        ).unwrap();
+    }
+
+    fn insert_conversation(conn: &Connection, id: &str, project_id: &str) {
+        conn.execute(
+            "INSERT INTO conversations (id, project_id, title, model, created_at, updated_at)
+             VALUES (?1, ?2, NULL, 'gpt-5', 0, 0)",
+            params![id, project_id],
+        ).unwrap();
+    }
+
+    fn insert_message(conn: &Connection, id: &str, conversation_id: &str, created_at: i64, tokens: i32) {
+        conn.execute(
+            "INSERT INTO messages (id, conversation_id, role, content, tokens, created_at, seq)
+             VALUES (?1, ?2, 'user', ?1, ?3, ?4, (SELECT COALESCE(MAX(seq), 0) + 1 FROM messages WHERE conversation_id = ?2))",
+            params![id, conversation_id, tokens, created_at],
+        ).unwrap();
+    }
+
+    fn message_ids(conn: &Connection, conversation_id: &str) -> Vec<String> {
+        load_conversation_messages(conn, conversation_id).unwrap().into_iter().map(|m| m.id).collect()
+    }
+
+    fn insert_document(conn: &Connection, id: &str, project_id: &str, folder_id: Option<&str>) {
+        conn.execute(
+            "INSERT INTO context_documents (id, project_id, name, type, content, created_at, folder_id)
+             VALUES (?1, ?2, ?1, 'text', ?1, 0, ?3)",
+            params![id, project_id, folder_id],
+        ).unwrap();
+    }
+
+    fn insert_output(conn: &Connection, id: &str, project_id: &str, folder_id: Option<&str>) {
+        conn.execute(
+            "INSERT INTO framework_outputs
+                 (id, project_id, framework_id, category, name, user_prompt, context_doc_ids,
+                  generated_content, created_at, updated_at, folder_id)
+             VALUES (?1, ?2, 'swot', 'strategy', ?1, '', '[]', ?1, 0, 0, ?3)",
+            params![id, project_id, folder_id],
+        ).unwrap();
+    }
+
+    fn folder_of(conn: &Connection, table: &str, id: &str) -> Option<String> {
+        conn.query_row(&format!("SELECT folder_id FROM {} WHERE id = ?1", table), params![id], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn live_count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {} WHERE deleted_at IS NULL", table), [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn encrypt_string_uses_a_fresh_nonce_each_time() {
+        let key = [7u8; 32];
+        let first = encrypt_string("sk-test-secret", &key).unwrap();
+        let second = encrypt_string("sk-test-secret", &key).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(decrypt_string(&first, &key).unwrap(), "sk-test-secret");
+        assert_eq!(decrypt_string(&second, &key).unwrap(), "sk-test-secret");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn build_shell_command_runs_through_powershell() {
+        let cwd = std::env::temp_dir();
+        let output = build_shell_command(&cwd.to_string_lossy(), "echo hello", "__PM_IDE_CWD__")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("hello"));
+        assert!(stdout.contains("__PM_IDE_CWD__"));
+    }
+
+    #[test]
+    fn would_create_cycle_rejects_self_parent() {
+        let conn = test_conn();
+        insert_project(&conn, "p1");
+        insert_folder(&conn, "a", "p1", None);
+
+        assert!(would_create_cycle(&conn, "a", "a").unwrap());
+    }
+
+    #[test]
+    fn would_create_cycle_rejects_moving_under_a_grandchild() {
+        let conn = test_conn();
+        insert_project(&conn, "p1");
+        insert_folder(&conn, "a", "p1", None);
+        insert_folder(&conn, "b", "p1", Some("a"));
+        insert_folder(&conn, "c", "p1", Some("b"));
+
+        assert!(would_create_cycle(&conn, "a", "c").unwrap());
+    }
+
+    #[test]
+    fn would_create_cycle_allows_legal_reparent() {
+        let conn = test_conn();
+        insert_project(&conn, "p1");
+        insert_folder(&conn, "a", "p1", None);
+        insert_folder(&conn, "b", "p1", Some("a"));
+        insert_folder(&conn, "c", "p1", None);
+
+        assert!(!would_create_cycle(&conn, "b", "c").unwrap());
+        assert!(!would_create_cycle(&conn, "c", "b").unwrap());
+    }
+
+    #[test]
+    fn message_page_query_uses_the_conversation_index() {
+        let conn = test_conn();
+        let plan: Vec<String> = conn
+            .prepare(&format!("EXPLAIN QUERY PLAN {}", MESSAGE_PAGE_SQL))
+            .unwrap()
+            .query_map(params!["c1", None::<i64>, None::<i64>, 10], |row| row.get::<_, String>(3))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert!(plan.iter().any(|step| step.contains("idx_messages_conversation_page")), "{:?}", plan);
+        assert!(!plan.iter().any(|step| step.contains("TEMP B-TREE")), "{:?}", plan);
+    }
+
+    #[test]
+    fn deleting_a_middle_message_keeps_the_rest_in_order() {
+        let conn = test_conn();
+        insert_project(&conn, "p1");
+        insert_conversation(&conn, "c1", "p1");
+        // Same timestamp throughout, as a batch insert produces
+        for (id, tokens) in [("m1", 10), ("m2", 20), ("m3", 30), ("m4", 40)] {
+            insert_message(&conn, id, "c1", 100, tokens);
+        }
+
+        remove_message(&conn, "m2", true).unwrap();
+
+        assert_eq!(message_ids(&conn, "c1"), ["m1", "m3", "m4"]);
+        let total_tokens: i64 = conn
+            .query_row("SELECT total_tokens FROM conversations WHERE id = 'c1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(total_tokens, 80);
+    }
+
+    #[test]
+    fn validate_usage_date_rejects_malformed_dates() {
+        assert!(validate_usage_date("cutoff date", "2026-10-05").is_ok());
+        for bad in ["Oct 2026", "2026-1-5", "2026-13-01", "2026-10-05x", ""] {
+            assert!(validate_usage_date("cutoff date", bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn render_prompt_text_does_not_expand_placeholders_inside_values() {
+        let variables: Vec<PromptVariable> = serde_json::from_value(serde_json::json!([
+            { "name": "topic" },
+            { "name": "secret", "default_value": "hidden" },
+        ])).unwrap();
+        let values = HashMap::from([("topic".to_string(), "{{secret}} and {secret}".to_string())]);
+
+        let rendered = render_prompt_text("About {{topic}} / {topic} / {{unknown}}", &variables, &values).unwrap();
+
+        assert_eq!(rendered, "About {{secret}} and {secret} / {{secret}} and {secret} / {{unknown}}");
+    }
+
+    fn policy_rule(kind: &str, pattern: &str) -> CommandPolicyRule {
+        CommandPolicyRule {
+            id: pattern.to_string(),
+            kind: kind.to_string(),
+            pattern: pattern.to_string(),
+            created_at: 0,
+        }
+    }
+
+    fn is_policy_violation(result: Result<(), AppError>) -> bool {
+        matches!(result, Err(AppError::PolicyViolation(_)))
+    }
+
+    #[test]
+    fn deny_rules_match_whole_tokens_and_absolute_paths() {
+        let rules = [policy_rule("deny", "rm -rf /"), policy_rule("deny", "mkfs")];
+
+        assert!(is_policy_violation(evaluate_command_policy("rm -rf /", false, &rules)));
+        assert!(is_policy_violation(evaluate_command_policy("/bin/rm  -rf /", false, &rules)));
+        assert!(is_policy_violation(evaluate_command_policy("echo hi; rm -rf /", false, &rules)));
+        assert!(is_policy_violation(evaluate_command_policy("/sbin/mkfs.ext4 /dev/sdb1", false, &rules)));
+        assert!(evaluate_command_policy("rm -rf /tmp/build", false, &rules).is_ok());
+        assert!(evaluate_command_policy("git log", false, &rules).is_ok());
+    }
+
+    #[test]
+    fn allowlist_mode_requires_every_segment_to_be_allowed() {
+        let rules = [policy_rule("allow", "git"), policy_rule("allow", "ls")];
+
+        assert!(evaluate_command_policy("git status", true, &rules).is_ok());
+        assert!(evaluate_command_policy("git status && ls -la", true, &rules).is_ok());
+        assert!(is_policy_violation(evaluate_command_policy("gitk", true, &rules)));
+        assert!(is_policy_violation(evaluate_command_policy("git status; rm -rf build", true, &rules)));
+        assert!(is_policy_violation(evaluate_command_policy("git log | sh", true, &rules)));
+        assert!(is_policy_violation(evaluate_command_policy("git status\nrm -rf build", true, &rules)));
+        assert!(is_policy_violation(evaluate_command_policy("git status\r\nrm -rf build", true, &rules)));
+        // Allowlist mode off: anything not denied runs
+        assert!(evaluate_command_policy("rm -rf build", false, &rules).is_ok());
+    }
+
+    #[test]
+    fn allowlist_mode_rejects_substitution_and_redirection() {
+        let rules = [policy_rule("allow", "git")];
+
+        for command in ["git log $(rm -rf ~)", "git log `rm -rf ~`", "git log > ~/.bashrc", "git apply < patch", "git diff <(cat x)"] {
+            assert!(is_policy_violation(evaluate_command_policy(command, true, &rules)), "{}", command);
+        }
+    }
+
+    // root > middle > leaf, with a document and an output at each of the lower two levels
+    fn two_level_folders(conn: &Connection) {
+        insert_project(conn, "p1");
+        insert_folder(conn, "root", "p1", None);
+        insert_folder(conn, "middle", "p1", Some("root"));
+        insert_folder(conn, "leaf", "p1", Some("middle"));
+        insert_document(conn, "doc-middle", "p1", Some("middle"));
+        insert_document(conn, "doc-leaf", "p1", Some("leaf"));
+        insert_output(conn, "out-middle", "p1", Some("middle"));
+        insert_output(conn, "out-leaf", "p1", Some("leaf"));
+    }
+
+    #[test]
+    fn delete_folder_promote_moves_contents_up_one_level() {
+        let conn = test_conn();
+        two_level_folders(&conn);
+
+        remove_folder(&conn, "middle", "promote").unwrap();
+
+        let leaf_parent: Option<String> = conn
+            .query_row("SELECT parent_id FROM folders WHERE id = 'leaf'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(leaf_parent.as_deref(), Some("root"));
+        assert_eq!(folder_of(&conn, "context_documents", "doc-middle").as_deref(), Some("root"));
+        assert_eq!(folder_of(&conn, "framework_outputs", "out-middle").as_deref(), Some("root"));
+        assert_eq!(folder_of(&conn, "context_documents", "doc-leaf").as_deref(), Some("leaf"));
+        assert_eq!(folder_of(&conn, "framework_outputs", "out-leaf").as_deref(), Some("leaf"));
+        assert_eq!(live_count(&conn, "context_documents"), 2);
+    }
+
+    #[test]
+    fn delete_folder_cascade_removes_the_whole_subtree() {
+        let conn = test_conn();
+        two_level_folders(&conn);
+        insert_document(&conn, "doc-root", "p1", Some("root"));
+
+        remove_folder(&conn, "middle", "cascade").unwrap();
+
+        let folders: Vec<String> = conn
+            .prepare("SELECT id FROM folders ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(folders, ["root"]);
+        assert_eq!(live_count(&conn, "context_documents"), 1);
+        assert_eq!(live_count(&conn, "framework_outputs"), 0);
+        let trashed: i64 = conn
+            .query_row("SELECT COUNT(*) FROM context_documents WHERE deleted_at IS NOT NULL", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(trashed, 2);
+    }
+
+    fn parent_of(conn: &Connection, folder_id: &str) -> Option<String> {
+        conn.query_row("SELECT parent_id FROM folders WHERE id = ?1", params![folder_id], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn folder_update(json: &str) -> FolderUpdate {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn update_folder_without_parent_id_leaves_parent_unchanged() {
+        let conn = test_conn();
+        insert_project(&conn, "p1");
+        insert_folder(&conn, "a", "p1", None);
+        insert_folder(&conn, "b", "p1", Some("a"));
+
+        apply_folder_update(&conn, "b", &folder_update(r#"{"name": "Renamed"}"#)).unwrap();
+
+        assert_eq!(parent_of(&conn, "b").as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn update_folder_with_null_parent_id_moves_to_root() {
+        let conn = test_conn();
+        insert_project(&conn, "p1");
+        insert_folder(&conn, "a", "p1", None);
+        insert_folder(&conn, "b", "p1", Some("a"));
+
+        let update = folder_update(r#"{"parent_id": null}"#);
+        assert!(matches!(update.parent_id, Some(None)));
+        apply_folder_update(&conn, "b", &update).unwrap();
+
+        assert_eq!(parent_of(&conn, "b"), None);
+    }
+
+    #[test]
+    fn update_folder_with_parent_id_sets_parent() {
+        let conn = test_conn();
+        insert_project(&conn, "p1");
+        insert_folder(&conn, "a", "p1", None);
+        insert_folder(&conn, "b", "p1", None);
+
+        apply_folder_update(&conn, "b", &folder_update(r#"{"parent_id": "a"}"#)).unwrap();
+
+        assert_eq!(parent_of(&conn, "b").as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn unique_slug_id_suffixes_frameworks_with_the_same_name() {
+        let conn = test_conn();
+        let base = slugify("Opportunity Tree Review");
+
+        let first = unique_slug_id(&conn, "framework_definitions", &base).unwrap();
+        conn.execute(
+            "INSERT INTO framework_definitions (id, category, name, description, icon, created_at, updated_at)
+             VALUES (?1, 'strategy', 'Opportunity Tree Review', '', '', 0, 0)",
+            params![&first],
+        ).unwrap();
+        let second = unique_slug_id(&conn, "framework_definitions", &base).unwrap();
+
+        assert_eq!(first, "opportunity-tree-review");
+        assert_eq!(second, "opportunity-tree-review-2");
+    }
+
+    #[test]
+    fn tags_match_all_requires_every_tag_and_any_requires_one() {
+        let tags = r#"["Roadmap", "q3"]"#;
+        let both = vec!["roadmap".to_string(), "q3".to_string()];
+        let one_missing = vec!["roadmap".to_string(), "okr".to_string()];
+        let none = vec!["okr".to_string()];
+
+        assert!(tags_match(tags, &both, true));
+        assert!(!tags_match(tags, &one_missing, true));
+        assert!(tags_match(tags, &one_missing, false));
+        assert!(!tags_match(tags, &none, false));
+        assert!(tags_match(tags, &[], true));
+        assert!(!tags_match("not json", &none, false));
+    }
+
+    #[test]
+    fn with_transaction_rolls_back_earlier_writes_on_error() {
+        let conn = test_conn();
+        let result = with_transaction::<(), String, _>(&conn, |tx| {
+            tx.execute(
+                "INSERT INTO projects (id, name, created_at, updated_at) VALUES ('p1', 'Project', 0, 0)",
+                [],
+            )
+            .map_err(|e| e.to_string())?;
+            tx.execute("INSERT INTO missing_table (id) VALUES ('x')", [])
+                .map_err(|e| e.to_string())?;
+            Ok(())
+        });
+
+        assert!(result.is_err());
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM projects WHERE id = 'p1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn load_settings_recreates_deleted_row() {
+        let conn = test_conn();
+        conn.execute("DELETE FROM settings", []).unwrap();
+
+        let settings = load_settings(&conn).unwrap();
+        assert_eq!(settings.id, "default");
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM settings", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn slugify_normalizes_names() {
+        assert_eq!(slugify("  Launch Plan: Q3!  "), "launch-plan-q3");
+        assert_eq!(slugify("RICE -- Scoring"), "rice-scoring");
+    }
+
+    #[test]
+    fn slugify_falls_back_to_uuid_for_names_without_ascii_alphanumerics() {
+        for name in ["\u{1F680}\u{1F525}", "!!!", ""] {
+            let slug = slugify(name);
+            assert!(Uuid::parse_str(&slug).is_ok(), "{:?} gave {:?}", name, slug);
+        }
+    }
+
+    #[test]
+    fn document_content_opens_with_data_key_or_legacy_machine_key() {
+        let keys = DocumentKeys { data: Ok([1u8; 32]), legacy: [2u8; 32] };
+        let sealed = seal_document_content("roadmap", true, &keys).unwrap();
+        let legacy_sealed = encrypt_string("pricing", &keys.legacy).unwrap();
+
+        assert_eq!(decrypt_string(&sealed, &[1u8; 32]).unwrap(), "roadmap");
+        assert!(decrypt_string(&sealed, &keys.legacy).is_err());
+        assert_eq!(open_document_content(&sealed, &keys).unwrap(), "roadmap");
+        assert_eq!(open_document_content(&legacy_sealed, &keys).unwrap(), "pricing");
+    }
+
+    #[test]
+    fn sealing_without_keychain_fails_instead_of_using_machine_key() {
+        let keys = DocumentKeys { data: Err("Keychain unavailable".to_string()), legacy: [2u8; 32] };
+        assert!(seal_document_content("roadmap", true, &keys).is_err());
+        assert_eq!(seal_document_content("roadmap", false, &keys).unwrap(), "roadmap");
+    }
+
+    #[test]
+    fn reseal_legacy_documents_moves_content_to_data_key() {
+        let conn = test_conn();
+        insert_project(&conn, "p1");
+        insert_document(&conn, "d1", "p1", None);
+        let keys = DocumentKeys { data: Ok([1u8; 32]), legacy: [2u8; 32] };
+        conn.execute(
+            "UPDATE context_documents SET content = ?1, is_encrypted = 1 WHERE id = 'd1'",
+            params![encrypt_string("pricing", &keys.legacy).unwrap()],
+        ).unwrap();
+
+        assert_eq!(reseal_legacy_documents(&conn, &keys).unwrap(), 1);
+        let content: String = conn
+            .query_row("SELECT content FROM context_documents WHERE id = 'd1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(decrypt_string(&content, &[1u8; 32]).unwrap(), "pricing");
+        assert_eq!(reseal_legacy_documents(&conn, &keys).unwrap(), 0);
+    }
+
+    fn saved_prompt(id: &str, name: &str, prompt_text: &str, usage_count: i32) -> SavedPromptRow {
+        SavedPromptRow {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: String::new(),
+            category: "general".to_string(),
+            prompt_text: prompt_text.to_string(),
+            variables: "[]".to_string(),
+            framework_id: None,
+            is_builtin: false,
+            is_favorite: false,
+            usage_count,
+            sort_order: 0,
+            created_at: 0,
+            updated_at: 0,
+            last_used_at: None,
+            project_id: None,
+        }
+    }
+
+    #[test]
+    fn prompt_search_ranks_exact_name_above_popular_body_match() {
+        let results = vec![
+            saved_prompt("body", "Weekly update", "Summarize the roadmap for stakeholders", 500),
+            saved_prompt("exact", "Roadmap", "Draft a plan", 0),
+        ];
+
+        let ranked = rank_prompt_search_results(results, "  ROADMAP ");
+        let ids: Vec<&str> = ranked.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["exact", "body"]);
+    }
+
+    #[test]
+    fn trash_row_reports_affected_rows() {
+        let conn = test_conn();
+        insert_project(&conn, "p1");
+        insert_document(&conn, "d1", "p1", None);
+        insert_output(&conn, "o1", "p1", None);
+
+        for (table, id) in [("context_documents", "d1"), ("framework_outputs", "o1"), ("projects", "p1")] {
+            assert_eq!(trash_row(&conn, table, id).unwrap(), 1, "{}", table);
+            assert_eq!(trash_row(&conn, table, id).unwrap(), 0, "{} already trashed", table);
+            assert_eq!(trash_row(&conn, table, "missing").unwrap(), 0, "{} missing", table);
+        }
+    }
+
+    #[test]
+    fn remove_saved_prompt_returns_zero_for_missing_id() {
+        let conn = test_conn();
+        conn.execute(
+            "INSERT INTO saved_prompts (id, name, description, category, prompt_text, variables, is_builtin, created_at, updated_at)
+             VALUES ('mine', 'Mine', '', 'general', 'text', '[]', 0, 0, 0),
+                    ('shipped', 'Shipped', '', 'general', 'text', '[]', 1, 0, 0)",
+            [],
+        ).unwrap();
+
+        assert_eq!(remove_saved_prompt(&conn, "missing").unwrap(), 0);
+        assert_eq!(remove_saved_prompt(&conn, "mine").unwrap(), 1);
+        assert_eq!(remove_saved_prompt(&conn, "mine").unwrap(), 0);
+        assert!(matches!(remove_saved_prompt(&conn, "shipped"), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn migrations_skip_existing_columns_but_run_the_rest() {
+        let conn = test_conn();
+        assert_eq!(added_column("ALTER TABLE projects ADD COLUMN deleted_at INTEGER"), Some(("projects", "deleted_at")));
+        assert_eq!(added_column("UPDATE projects SET name = name"), None);
+
+        // Simulates a database that gained the column before its migration was recorded
+        conn.execute("DELETE FROM schema_migrations WHERE version >= 37", []).unwrap();
+        run_migrations(&conn).unwrap();
+
+        assert!(column_exists(&conn, "framework_outputs", "deleted_at").unwrap());
+        let latest: u32 = conn
+            .query_row("SELECT MAX(version) FROM schema_migrations", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(latest as usize, migrations().len());
+    }
+
+    #[test]
+    fn default_terminal_cwd_falls_back_only_for_unusable_working_dir() {
+        let conn = test_conn();
+        insert_project(&conn, "p1");
+        assert_eq!(default_terminal_cwd(&conn, "p1").unwrap(), home_dir());
+
+        let dir = std::env::temp_dir().to_string_lossy().to_string();
+        conn.execute("UPDATE projects SET working_dir = ?1 WHERE id = 'p1'", params![&dir]).unwrap();
+        assert_eq!(default_terminal_cwd(&conn, "p1").unwrap(), dir);
+
+        conn.execute("UPDATE projects SET working_dir = '/no/such/dir' WHERE id = 'p1'", []).unwrap();
+        assert_eq!(default_terminal_cwd(&conn, "p1").unwrap(), home_dir());
+
+        assert!(matches!(default_terminal_cwd(&conn, "missing"), Err(AppError::NotFound(_))));
+    }
+
+    fn folder(id: &str, parent_id: Option<&str>) -> Folder {
+        Folder {
+            id: id.to_string(),
+            project_id: "p1".to_string(),
+            parent_id: parent_id.map(str::to_string),
+            name: id.to_string(),
+            color: None,
+            sort_order: 0,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn folder_tree_surfaces_orphans_and_parent_loops_at_the_root() {
+        let folders = vec![
+            folder("root", None),
+            folder("orphan", Some("gone")),
+            folder("loop-a", Some("loop-b")),
+            folder("loop-b", Some("loop-a")),
+        ];
+
+        let tree = assemble_folder_tree(folders, &HashMap::new());
+
+        let roots: Vec<&str> = tree.iter().map(|n| n.folder.id.as_str()).collect();
+        assert_eq!(roots, vec!["root", "orphan", "loop-a"]);
+        let loop_children: Vec<&str> = tree[2].children.iter().map(|n| n.folder.id.as_str()).collect();
+        assert_eq!(loop_children, vec!["loop-b"]);
+        assert!(tree[2].children[0].children.is_empty());
+    }
+
+    #[test]
+    fn word_count_migration_backfills_uncounted_outputs() {
+        let conn = test_conn();
+        insert_project(&conn, "p1");
+        insert_output(&conn, "o1", "p1", None);
+        conn.execute(
+            "UPDATE framework_outputs SET generated_content = 'one  two\nthree', word_count = NULL WHERE id = 'o1'",
+            [],
+        ).unwrap();
+
+        conn.execute("DELETE FROM schema_migrations WHERE version >= 39", []).unwrap();
+        run_migrations(&conn).unwrap();
+
+        let words: Option<i64> = conn
+            .query_row("SELECT word_count FROM framework_outputs WHERE id = 'o1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(words, Some(3));
+    }
+
+    #[test]
+    fn encrypted_documents_store_no_content_hash() {
+        let conn = test_conn();
+        insert_project(&conn, "p1");
+        let keys = DocumentKeys { data: Ok([1u8; 32]), legacy: [2u8; 32] };
+        let text = || "Pricing notes".to_string();
+
+        let plain = insert_context_document(&conn, "p1", "a".to_string(), "text".to_string(), text(), None, false, false, &keys).unwrap();
+        let sealed = insert_context_document(&conn, "p1", "b".to_string(), "text".to_string(), text(), None, false, true, &keys).unwrap();
+
+        let hash_of = |id: &str| -> Option<String> {
+            conn.query_row("SELECT content_hash FROM context_documents WHERE id = ?1", params![id], |row| row.get(0))
+                .unwrap()
+        };
+        assert_eq!(hash_of(&plain.id), Some(content_hash("Pricing notes")));
+        assert_eq!(hash_of(&sealed.id), None);
+    }
+}
@@ -2,6 +2,7 @@
 mod commands;
 
 use commands::*;
+use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -11,50 +12,135 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .setup(|app| {
             // Initialize database on startup
-            init_db(&app.handle())?;
+            let pool = create_db_pool(app.handle())?;
+            init_db(&pool)?;
+            app.manage(pool);
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             create_project,
             list_projects,
             get_project,
+            touch_project,
+            list_recent_projects,
             update_project,
+            set_project_working_dir,
+            reorder_projects,
+            get_project_stats,
             delete_project,
+            list_trashed_projects,
+            restore_project,
+            permanently_delete_project,
+            count_project_children,
             create_conversation,
             list_conversations,
             get_conversation,
+            update_conversation,
+            archive_conversation,
+            unarchive_conversation,
+            toggle_conversation_pin,
+            move_conversation,
             add_message,
+            add_messages_batch,
+            begin_streaming_message,
+            append_to_message,
+            finalize_message,
             get_messages,
+            update_message,
+            delete_message,
             update_conversation_stats,
+            recalculate_conversation_stats,
+            recalculate_all_conversation_stats,
             delete_conversation,
+            fork_conversation,
+            duplicate_conversation,
+            export_conversation_markdown,
             record_token_usage,
             get_token_usage_by_date_range,
+            get_token_usage_by_project,
+            get_token_usage_by_model,
             get_all_token_usage,
+            export_token_usage_csv,
+            prune_token_usage,
+            get_token_usage_summary,
+            compute_token_cost,
+            list_model_pricing,
+            set_model_pricing,
+            delete_model_pricing,
             get_settings,
             update_settings,
+            export_settings,
+            import_settings,
             get_decrypted_api_key,
             delete_api_key,
+            set_provider_api_key,
+            get_provider_api_key,
+            list_configured_providers,
+            delete_provider_api_key,
+            validate_api_key,
             create_context_document,
+            create_context_document_from_url,
+            import_documents_from_directory,
             list_context_documents,
+            list_context_documents_summary,
+            list_global_context_documents,
             get_context_document,
             update_context_document,
             delete_context_document,
+            find_duplicate_documents,
+            dedupe_documents,
+            generate_document_embeddings,
+            reindex_project_embeddings,
+            search_similar_chunks,
+            build_generation_context,
+            get_document_embedding_status,
             create_framework_output,
             list_framework_outputs,
             get_framework_output,
+            list_outputs_for_conversation,
             update_framework_output,
+            list_framework_output_versions,
+            restore_framework_output_version,
+            diff_framework_outputs,
+            diff_framework_output_versions,
             delete_framework_output,
+            list_trash,
+            restore_item,
+            empty_trash,
             create_folder,
             list_folders,
+            get_folder_tree,
+            get_folder_path,
             get_folder,
             update_folder,
             delete_folder,
+            count_items,
             move_item_to_folder,
+            move_items_to_folder,
+            reorder_folders,
+            reorder_items,
+            delete_items,
+            set_item_tags,
+            list_all_tags,
             search_project_items,
+            list_favorites,
+            search_messages,
+            global_search,
+            search_framework_outputs_global,
             toggle_item_favorite,
+            set_items_favorite,
             set_folder_color,
             execute_shell_command,
+            execute_shell_command_streaming,
+            kill_running_command,
+            check_command_allowed,
+            get_command_policy,
+            add_command_policy_rule,
+            delete_command_policy_rule,
+            set_command_allowlist_enabled,
             get_command_history,
+            search_command_history,
+            clear_command_history,
             get_terminal_cwd,
             set_terminal_cwd,
             complete_path,
@@ -63,15 +149,20 @@ pub fn run() {
             create_framework_category,
             update_framework_category,
             delete_framework_category,
+            reassign_frameworks_category,
             list_framework_defs,
             get_framework_def,
             create_framework_def,
             update_framework_def,
             delete_framework_def,
             reset_framework_def,
+            reset_all_frameworks,
+            reset_all_prompts,
             search_framework_defs,
+            get_framework_usage_stats,
             duplicate_framework_def,
             list_saved_prompts,
+            list_favorite_prompts,
             get_saved_prompt,
             create_saved_prompt,
             update_saved_prompt,
@@ -79,6 +170,8 @@ pub fn run() {
             search_saved_prompts,
             duplicate_saved_prompt,
             increment_prompt_usage,
+            list_recently_used_prompts,
+            render_prompt,
             export_framework,
             export_frameworks_batch,
             export_all_frameworks,
@@ -89,6 +182,9 @@ pub fn run() {
             export_all_prompts,
             preview_import_prompt,
             confirm_import_prompt,
+            export_prompt_library_file,
+            preview_prompt_library_file,
+            import_prompt_library_file,
             create_workflow,
             list_workflows,
             get_workflow,
@@ -108,6 +204,17 @@ pub fn run() {
             dismiss_insight,
             save_insights,
             clear_project_insights,
+            export_project,
+            export_project_outputs_zip,
+            preview_import_project,
+            confirm_import_project,
+            duplicate_project,
+            backup_database,
+            restore_database,
+            check_database_integrity,
+            repair_orphaned_rows,
+            optimize_database,
+            get_app_diagnostics,
             init_project_repo,
             commit_output,
             list_output_commits,
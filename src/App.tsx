@@ -109,7 +109,7 @@ function App() {
     try {
       const results = await Promise.all([
         frameworkDefsAPI.search(query).catch(() => []),
-        savedPromptsAPI.search(query).catch(() => []),
+        savedPromptsAPI.search(query, currentProjectId || undefined).catch(() => []),
         currentProjectId ? frameworkOutputsAPI.list(currentProjectId).catch(() => []) : Promise.resolve([]),
         currentProjectId ? foldersAPI.searchItems(currentProjectId, query).catch(() => []) : Promise.resolve([]),
       ]);
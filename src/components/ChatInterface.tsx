@@ -1,5 +1,5 @@
 import { useState, useEffect, useRef } from 'react';
-import { Message, ChatStreamEvent, Settings, ContextDocument } from '../lib/types';
+import { Message, ChatStreamEvent, Settings, ContextDocument, errorMessage } from '../lib/types';
 import { conversationsAPI, messagesAPI, tokenUsageAPI, modelsAPI, contextDocumentsAPI } from '../lib/ipc';
 import MarkdownRenderer from './MarkdownRenderer';
 
@@ -30,6 +30,7 @@ export default function ChatInterface({
   onInitialMessageConsumed,
 }: ChatInterfaceProps) {
   const [messages, setMessages] = useState<MessageWithContext[]>([]);
+  const [hasEarlierMessages, setHasEarlierMessages] = useState(false);
   const [input, setInput] = useState('');
   const [loading, setLoading] = useState(false);
   const [conversationId, setConversationId] = useState<string | undefined>(
@@ -65,6 +66,7 @@ export default function ChatInterface({
     } else {
       // Clear messages when starting new conversation
       setMessages([]);
+      setHasEarlierMessages(false);
     }
   }, [conversationId]);
 
@@ -146,15 +148,34 @@ export default function ChatInterface({
     if (!conversationId) return;
     console.log('Loading messages for conversation:', conversationId);
     try {
-      const msgs = await messagesAPI.list(conversationId);
+      const page = await messagesAPI.list(conversationId);
+      const msgs = [...page.messages].reverse();
       console.log('Loaded messages:', msgs.length);
       setMessages(msgs);
+      setHasEarlierMessages(page.has_more);
     } catch (error) {
       console.error('Failed to load messages:', error);
       setError('Failed to load conversation messages');
     }
   };
 
+  const loadEarlierMessages = async () => {
+    const oldest = messages[0];
+    if (!conversationId || !oldest) return;
+    try {
+      const page = await messagesAPI.list(conversationId, {
+        beforeCreatedAt: oldest.created_at,
+        beforeId: oldest.id,
+      });
+      const earlier = [...page.messages].reverse();
+      setMessages((prev) => [...earlier, ...prev]);
+      setHasEarlierMessages(page.has_more);
+    } catch (error) {
+      console.error('Failed to load earlier messages:', error);
+      setError('Failed to load earlier messages');
+    }
+  };
+
   const generateSystemPrompt = (): string => {
     const parts: string[] = [
       'You are an AI assistant helping a Product Manager with their work.',
@@ -419,23 +440,20 @@ export default function ChatInterface({
       console.log('Conversation stats updated successfully');
     } catch (error) {
       console.error('Failed to send message:', error);
-      let errorMessage: string;
+      let message: string;
 
       if (error instanceof TypeError && (error.message.includes('fetch') || error.message.includes('Failed to fetch') || error.message.includes('NetworkError'))) {
-        errorMessage = 'Cannot connect to AI server. Please ensure the Python sidecar is running (cd python-sidecar && python main.py).';
-      } else if (error instanceof Error) {
-        errorMessage = error.message;
-      } else if (typeof error === 'string') {
-        errorMessage = error;
+        message = 'Cannot connect to AI server. Please ensure the Python sidecar is running (cd python-sidecar && python main.py).';
       } else {
-        errorMessage = 'Failed to connect to AI server. Check that the sidecar is running on port 8000.';
+        // Covers thrown Errors, plain-string command errors and AppError payloads alike
+        message = errorMessage(error);
       }
 
-      if (errorMessage.includes('tokens exceed') || errorMessage.includes('token limit')) {
-        errorMessage = 'Context is too large! Try removing some documents or using a shorter message. Consider using GPT-5 instead of GPT-5-nano for larger contexts.';
+      if (message.includes('tokens exceed') || message.includes('token limit')) {
+        message = 'Context is too large! Try removing some documents or using a shorter message. Consider using GPT-5 instead of GPT-5-nano for larger contexts.';
       }
 
-      setError(errorMessage);
+      setError(message);
     } finally {
       setLoading(false);
     }
@@ -490,6 +508,16 @@ export default function ChatInterface({
         )}
 
         <div className="py-4">
+          {hasEarlierMessages && (
+            <div className="max-w-3xl mx-auto px-6 pb-2 text-center">
+              <button
+                onClick={loadEarlierMessages}
+                className="text-xs text-codex-text-secondary hover:text-codex-text-primary"
+              >
+                Load earlier messages
+              </button>
+            </div>
+          )}
           {messages.map((message) => (
             <div key={message.id} className="w-full">
               <div className="max-w-3xl mx-auto px-6 py-4">
@@ -1,5 +1,5 @@
 import { useState, useEffect, useMemo } from 'react';
-import { SavedPrompt, PromptVariable } from '../lib/types';
+import { SavedPrompt, PromptVariable, errorMessage } from '../lib/types';
 import { savedPromptsAPI } from '../lib/ipc';
 import PromptEditor from './PromptEditor';
 
@@ -118,7 +118,7 @@ export default function PromptEditorModal({ prompt, onSave, onClose }: PromptEdi
       }
       onSave();
     } catch (err) {
-      setError(String(err));
+      setError(errorMessage(err));
     } finally {
       setSaving(false);
     }
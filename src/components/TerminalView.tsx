@@ -4,7 +4,7 @@ import { FitAddon } from '@xterm/addon-fit';
 import { WebLinksAddon } from '@xterm/addon-web-links';
 import '@xterm/xterm/css/xterm.css';
 import { terminalAPI } from '../lib/ipc';
-import { CommandHistoryEntry } from '../lib/types';
+import { CommandHistoryEntry, errorMessage } from '../lib/types';
 
 interface TerminalViewProps {
   projectId: string | null;
@@ -349,9 +349,10 @@ export default function TerminalView({ projectId, onError }: TerminalViewProps)
         onError(`[${new Date().toLocaleTimeString()}] Command failed (exit ${result.exit_code}): ${cmd}\n${result.output.slice(0, 500)}`);
       }
     } catch (err) {
-      term.writeln(`\x1b[31mError: ${err}\x1b[0m`);
+      const message = errorMessage(err);
+      term.writeln(`\x1b[31mError: ${message}\x1b[0m`);
       if (onError) {
-        onError(`[${new Date().toLocaleTimeString()}] Error: ${err}`);
+        onError(`[${new Date().toLocaleTimeString()}] Error: ${message}`);
       }
     } finally {
       isRunningRef.current = false;
@@ -1,5 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
-import { Project, Conversation, Message, Settings, SettingsUpdate, TokenUsage, TokenUsageAggregate, ContextDocument, FrameworkOutput, Folder, SearchResult, CommandHistoryEntry, CommandResult, FrameworkDefinition, FrameworkCategory, SavedPrompt, PromptVariable, ImportPreview, ImportResult, BatchExportResult, ConflictAction, Workflow, WorkflowRun, WorkflowRunStep, ProjectInsight, CommitInfo, JiraProject, JiraExportResult, NotionPage, NotionExportResult, FileEntry } from './types';
+import { Project, Conversation, Message, MessagePage, Settings, SettingsUpdate, TokenUsage, TokenUsagePage, TokenUsageAggregate, ContextDocument, ContextDocumentSummary, FrameworkOutput, GenerationContext, Folder, SearchResult, TrashedItem, CommandHistoryEntry, CommandResult, FrameworkDefinition, FrameworkCategory, SavedPrompt, PromptVariable, ImportPreview, ImportResult, BatchExportResult, ConflictAction, FrameworkExportFormat, Workflow, WorkflowRun, WorkflowRunStep, ProjectInsight, CommitInfo, JiraProject, JiraExportResult, NotionPage, NotionExportResult, FileEntry } from './types';
 
 interface FrameworkDefRow {
   id: string;
@@ -41,6 +41,14 @@ function parseCategoryRow(row: FrameworkCategoryRow): Omit<FrameworkCategory, 'f
   return row;
 }
 
+// Delete commands report how many rows they touched; zero means the id matched nothing
+function ensureDeleted(count: number, what: string): number {
+  if (count === 0) {
+    throw new Error(`${what} not found`);
+  }
+  return count;
+}
+
 export const projectsAPI = {
   async create(name: string, description?: string): Promise<Project> {
     return await invoke('create_project', { name, description });
@@ -58,8 +66,8 @@ export const projectsAPI = {
     return await invoke('update_project', { id, name, description });
   },
 
-  async delete(id: string): Promise<void> {
-    return await invoke('delete_project', { id });
+  async delete(id: string): Promise<number> {
+    return ensureDeleted(await invoke<number>('delete_project', { id }), 'Project');
   },
 };
 
@@ -67,7 +75,8 @@ export const conversationsAPI = {
   async create(
     projectId: string,
     title?: string,
-    model: string = 'gpt-5'
+    // Empty lets the backend use the default model from settings
+    model: string = ''
   ): Promise<Conversation> {
     return await invoke('create_conversation', {
       projectId,
@@ -96,8 +105,8 @@ export const conversationsAPI = {
     });
   },
 
-  async delete(id: string): Promise<void> {
-    return await invoke('delete_conversation', { id });
+  async delete(id: string): Promise<number> {
+    return ensureDeleted(await invoke<number>('delete_conversation', { id }), 'Conversation');
   },
 };
 
@@ -116,8 +125,16 @@ export const messagesAPI = {
     });
   },
 
-  async list(conversationId: string): Promise<Message[]> {
-    return await invoke('get_messages', { conversationId });
+  async list(
+    conversationId: string,
+    options: { limit?: number; beforeCreatedAt?: number; beforeId?: string } = {}
+  ): Promise<MessagePage> {
+    return await invoke('get_messages', {
+      conversationId,
+      limit: options.limit ?? null,
+      beforeCreatedAt: options.beforeCreatedAt ?? null,
+      beforeId: options.beforeId ?? null,
+    });
   },
 };
 
@@ -169,7 +186,19 @@ export const tokenUsageAPI = {
   },
 
   async getAll(): Promise<TokenUsage[]> {
-    return await invoke('get_all_token_usage');
+    const page: TokenUsagePage = await invoke('get_all_token_usage');
+    return page.records;
+  },
+
+  async getPage(params: {
+    limit: number;
+    offset?: number;
+    conversationId?: string;
+    model?: string;
+    startDate?: string;
+    endDate?: string;
+  }): Promise<TokenUsagePage> {
+    return await invoke('get_all_token_usage', params);
   },
 };
 
@@ -211,11 +240,10 @@ export const foldersAPI = {
   },
 
   async update(id: string, name?: string, parentId?: string | null, color?: string): Promise<Folder> {
+    // parent_id is left out when undefined (unchanged); null moves the folder to the root
     return await invoke('update_folder', {
       id,
-      name,
-      parentId: parentId === null ? '__null__' : parentId,
-      color,
+      update: { name, parent_id: parentId, color },
     });
   },
 
@@ -238,6 +266,18 @@ export const foldersAPI = {
   async setFolderColor(id: string, color: string | null): Promise<void> {
     return await invoke('set_folder_color', { id, color });
   },
+
+  async listTrash(projectId: string): Promise<TrashedItem[]> {
+    return await invoke('list_trash', { projectId });
+  },
+
+  async restoreItem(id: string, itemType: 'context_doc' | 'framework_output'): Promise<void> {
+    return await invoke('restore_item', { id, itemType });
+  },
+
+  async emptyTrash(projectId: string): Promise<number> {
+    return await invoke('empty_trash', { projectId });
+  },
 };
 
 export const terminalAPI = {
@@ -269,7 +309,8 @@ export const contextDocumentsAPI = {
     docType: 'pdf' | 'url' | 'google_doc' | 'text',
     content: string,
     url?: string,
-    isGlobal: boolean = false
+    isGlobal: boolean = false,
+    isEncrypted: boolean = false
   ): Promise<ContextDocument> {
     return await invoke('create_context_document', {
       projectId,
@@ -277,7 +318,8 @@ export const contextDocumentsAPI = {
       docType,
       content,
       url,
-      isGlobal
+      isGlobal,
+      isEncrypted
     });
   },
 
@@ -285,20 +327,25 @@ export const contextDocumentsAPI = {
     return await invoke('list_context_documents', { projectId });
   },
 
+  async listSummary(projectId: string): Promise<ContextDocumentSummary[]> {
+    return await invoke('list_context_documents_summary', { projectId });
+  },
+
   async get(id: string): Promise<ContextDocument | null> {
     return await invoke('get_context_document', { id });
   },
 
-  async update(id: string, name: string, isGlobal: boolean): Promise<ContextDocument> {
+  async update(id: string, name: string, isGlobal: boolean, isEncrypted?: boolean): Promise<ContextDocument> {
     return await invoke('update_context_document', {
       id,
       name,
-      isGlobal
+      isGlobal,
+      isEncrypted
     });
   },
 
-  async delete(id: string): Promise<void> {
-    return await invoke('delete_context_document', { id });
+  async delete(id: string): Promise<number> {
+    return ensureDeleted(await invoke<number>('delete_context_document', { id }), 'Context document');
   }
 };
 
@@ -323,8 +370,12 @@ export const frameworkCategoriesAPI = {
     return parseCategoryRow(row);
   },
 
-  async delete(id: string): Promise<void> {
-    return await invoke('delete_framework_category', { id });
+  async delete(id: string, reassignTo?: string): Promise<number> {
+    return await invoke('delete_framework_category', { id, reassignTo });
+  },
+
+  async reassignFrameworks(fromCategory: string, toCategory: string): Promise<number> {
+    return await invoke('reassign_frameworks_category', { fromCategory, toCategory });
   },
 };
 
@@ -424,6 +475,8 @@ interface SavedPromptRow {
   sort_order: number;
   created_at: number;
   updated_at: number;
+  last_used_at: number | null;
+  project_id: string | null;
 }
 
 function parseSavedPrompt(row: SavedPromptRow): SavedPrompt {
@@ -431,14 +484,16 @@ function parseSavedPrompt(row: SavedPromptRow): SavedPrompt {
     ...row,
     variables: JSON.parse(row.variables || '[]') as PromptVariable[],
     framework_id: row.framework_id || undefined,
+    project_id: row.project_id || undefined,
   };
 }
 
 export const savedPromptsAPI = {
-  async list(category?: string, frameworkId?: string): Promise<SavedPrompt[]> {
+  async list(category?: string, frameworkId?: string, projectId?: string): Promise<SavedPrompt[]> {
     const rows: SavedPromptRow[] = await invoke('list_saved_prompts', {
       category: category || null,
       frameworkId: frameworkId || null,
+      projectId: projectId || null,
     });
     return rows.map(parseSavedPrompt);
   },
@@ -455,6 +510,7 @@ export const savedPromptsAPI = {
     promptText: string;
     variables: PromptVariable[];
     frameworkId?: string;
+    projectId?: string;
   }): Promise<SavedPrompt> {
     const row: SavedPromptRow = await invoke('create_saved_prompt', {
       name: params.name,
@@ -463,6 +519,7 @@ export const savedPromptsAPI = {
       promptText: params.promptText,
       variables: JSON.stringify(params.variables),
       frameworkId: params.frameworkId || null,
+      projectId: params.projectId || null,
     });
     return parseSavedPrompt(row);
   },
@@ -489,12 +546,12 @@ export const savedPromptsAPI = {
     return parseSavedPrompt(row);
   },
 
-  async delete(id: string): Promise<void> {
-    return await invoke('delete_saved_prompt', { id });
+  async delete(id: string): Promise<number> {
+    return ensureDeleted(await invoke<number>('delete_saved_prompt', { id }), 'Prompt');
   },
 
-  async search(query: string): Promise<SavedPrompt[]> {
-    const rows: SavedPromptRow[] = await invoke('search_saved_prompts', { query });
+  async search(query: string, projectId?: string): Promise<SavedPrompt[]> {
+    const rows: SavedPromptRow[] = await invoke('search_saved_prompts', { query, projectId: projectId || null });
     return rows.map(parseSavedPrompt);
   },
 
@@ -506,9 +563,25 @@ export const savedPromptsAPI = {
   async incrementUsage(id: string): Promise<void> {
     return await invoke('increment_prompt_usage', { id });
   },
+
+  async listRecentlyUsed(limit?: number, projectId?: string): Promise<SavedPrompt[]> {
+    const rows: SavedPromptRow[] = await invoke('list_recently_used_prompts', { limit, projectId: projectId || null });
+    return rows.map(parseSavedPrompt);
+  },
 };
 
 export const frameworkOutputsAPI = {
+  async buildContext(params: {
+    projectId: string;
+    frameworkId: string;
+    contextDocIds: string[];
+    query?: string;
+    maxChars?: number;
+    topK?: number;
+  }): Promise<GenerationContext> {
+    return await invoke('build_generation_context', params);
+  },
+
   async create(
     projectId: string,
     frameworkId: string,
@@ -517,7 +590,8 @@ export const frameworkOutputsAPI = {
     userPrompt: string,
     contextDocIds: string[],
     generatedContent: string,
-    format: 'markdown' | 'html' = 'markdown'
+    format: 'markdown' | 'html' = 'markdown',
+    generationMs?: number
   ): Promise<FrameworkOutput> {
     return await invoke('create_framework_output', {
       projectId,
@@ -527,7 +601,8 @@ export const frameworkOutputsAPI = {
       userPrompt,
       contextDocIds: JSON.stringify(contextDocIds),
       generatedContent,
-      format
+      format,
+      generationMs
     });
   },
 
@@ -547,8 +622,8 @@ export const frameworkOutputsAPI = {
     });
   },
 
-  async delete(id: string): Promise<void> {
-    return await invoke('delete_framework_output', { id });
+  async delete(id: string): Promise<number> {
+    return ensureDeleted(await invoke<number>('delete_framework_output', { id }), 'Framework output');
   },
 
   async generate(
@@ -589,16 +664,16 @@ export const frameworkOutputsAPI = {
 };
 
 export const marketplaceAPI = {
-  async exportFramework(id: string): Promise<string> {
-    return await invoke('export_framework', { id });
+  async exportFramework(id: string, format?: FrameworkExportFormat): Promise<string> {
+    return await invoke('export_framework', { id, format });
   },
 
-  async exportFrameworksBatch(ids: string[]): Promise<BatchExportResult[]> {
-    return await invoke('export_frameworks_batch', { ids });
+  async exportFrameworksBatch(ids: string[], format?: FrameworkExportFormat): Promise<BatchExportResult[]> {
+    return await invoke('export_frameworks_batch', { ids, format });
   },
 
-  async exportAllFrameworks(): Promise<BatchExportResult[]> {
-    return await invoke('export_all_frameworks');
+  async exportAllFrameworks(format?: FrameworkExportFormat): Promise<BatchExportResult[]> {
+    return await invoke('export_all_frameworks', { format });
   },
 
   async previewImportFramework(mdContent: string): Promise<ImportPreview> {
@@ -628,6 +703,18 @@ export const marketplaceAPI = {
   async confirmImportPrompt(mdContent: string, conflictAction: ConflictAction): Promise<ImportResult> {
     return await invoke('confirm_import_prompt', { mdContent, conflictAction });
   },
+
+  async exportPromptLibrary(format: 'json' | 'yaml' = 'json', category?: string, projectId?: string): Promise<string> {
+    return await invoke('export_prompt_library_file', { format, category, projectId: projectId || null });
+  },
+
+  async previewPromptLibrary(content: string): Promise<ImportPreview[]> {
+    return await invoke('preview_prompt_library_file', { content });
+  },
+
+  async importPromptLibrary(content: string, projectId?: string): Promise<ImportResult[]> {
+    return await invoke('import_prompt_library_file', { content, projectId: projectId || null });
+  },
 };
 
 export const workflowsAPI = {
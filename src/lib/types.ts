@@ -44,6 +44,11 @@ export interface Message {
   created_at: number;
 }
 
+export interface MessagePage {
+  messages: Message[];
+  has_more: boolean;
+}
+
 export interface ChatStreamEvent {
   type: 'conversation_id' | 'content_block_delta' | 'message_stop' | 'error';
   conversation_id?: string;
@@ -76,6 +81,8 @@ export interface Settings {
   jira_project_key?: string;
   notion_api_token_encrypted?: string;
   notion_parent_page_id?: string;
+  default_model?: string;
+  has_api_key: boolean;
   created_at: number;
   updated_at: number;
 }
@@ -97,6 +104,7 @@ export interface SettingsUpdate {
   jira_project_key?: string;
   notion_api_token?: string;
   notion_parent_page_id?: string;
+  default_model?: string;
 }
 
 export interface TokenUsage {
@@ -111,6 +119,11 @@ export interface TokenUsage {
   date: string;
 }
 
+export interface TokenUsagePage {
+  records: TokenUsage[];
+  total: number;
+}
+
 export interface TokenUsageAggregate {
   date: string;
   total_tokens: number;
@@ -134,6 +147,11 @@ export interface ContextDocument {
   tags: string;
   is_favorite: boolean;
   sort_order: number;
+  is_encrypted: boolean;
+}
+
+export interface ContextDocumentSummary extends Omit<ContextDocument, 'content'> {
+  preview: string;
 }
 
 export interface FrameworkOutput {
@@ -193,6 +211,22 @@ export interface SearchResult {
   created_at: number;
 }
 
+export interface GenerationContext {
+  context: string;
+  documents: { id: string; name: string; chars: number; truncated: boolean }[];
+  chunks: { document_id: string; document_name: string; chunk_index: number; score: number }[];
+  total_chars: number;
+  warnings: string[];
+}
+
+export interface TrashedItem {
+  id: string;
+  name: string;
+  item_type: 'context_doc' | 'framework_output';
+  folder_id: string | null;
+  deleted_at: number;
+}
+
 export interface CommandHistoryEntry {
   id: string;
   project_id: string;
@@ -261,8 +295,12 @@ export interface SavedPrompt {
   sort_order: number;
   created_at: number;
   updated_at: number;
+  last_used_at: number | null;
+  project_id?: string;
 }
 
+export type FrameworkExportFormat = 'markdown' | 'json' | 'yaml';
+
 export interface ImportPreview {
   item_type: 'framework' | 'prompt';
   id: string;
@@ -382,3 +420,16 @@ export interface FileEntry {
   modified: number;
   extension: string;
 }
+
+// Error payload from commands that return AppError instead of a plain string
+export interface AppError {
+  kind: 'not_found' | 'validation' | 'database' | 'conflict' | 'io' | 'internal' | 'policy_violation';
+  message: string;
+}
+
+export function errorMessage(err: unknown): string {
+  if (typeof err === 'object' && err !== null && 'message' in err) {
+    return String((err as { message: unknown }).message);
+  }
+  return String(err);
+}
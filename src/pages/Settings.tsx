@@ -73,7 +73,7 @@ export default function Settings() {
       setJiraProjectKey(data.jira_project_key || '');
       setNotionParentPageId(data.notion_parent_page_id || '');
 
-      const keyExists = !!data.api_key_encrypted;
+      const keyExists = data.has_api_key;
       setHasApiKey(keyExists);
 
       if (keyExists) {